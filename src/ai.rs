@@ -0,0 +1,943 @@
+use crate::config::{AiPricing, AiProfile, AppConfig, PromptMode};
+use crate::error::DecreeError;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::{Read, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::LazyLock;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Backends with built-in profiles, in detection priority order.
+pub const BUILTIN_BACKENDS: &[&str] = &["opencode", "claude", "copilot"];
+
+/// Frontmatter field that picks the AI profile for one message.
+pub const AI_FIELD: &str = "ai";
+
+/// Env var naming the profile a routine's `decree ai` calls use.
+pub const DECREE_AI_ENV: &str = "DECREE_AI";
+
+/// Env var holding the chosen backend's command line, for routines that
+/// call the tool directly (`$DECREE_AI_CMD "prompt"`).
+pub const DECREE_AI_CMD_ENV: &str = "DECREE_AI_CMD";
+
+/// How much of a backend's output is kept to look for quota errors.
+const OUTPUT_TAIL_BYTES: usize = 64 * 1024;
+
+/// The built-in profile for a known backend.
+pub fn builtin_profile(name: &str) -> Option<AiProfile> {
+    let args: &[&str] = match name {
+        "opencode" => &["run"],
+        "claude" => &["-p"],
+        "copilot" => &["-p"],
+        _ => return None,
+    };
+    Some(AiProfile {
+        command: name.to_string(),
+        args: args.iter().map(|a| a.to_string()).collect(),
+        prompt: PromptMode::Argv,
+        model: None,
+        model_flag: "--model".to_string(),
+        env: BTreeMap::new(),
+        timeout: None,
+        pricing: None,
+    })
+}
+
+/// The interactive command for a known backend, started with the prompt.
+pub fn builtin_interactive(name: &str) -> Option<&'static str> {
+    match name {
+        "opencode" => Some("opencode --prompt {prompt}"),
+        "claude" => Some("claude {prompt}"),
+        "copilot" => Some("copilot -i {prompt}"),
+        _ => None,
+    }
+}
+
+/// The `commands.ai_interactive` template to use, and whether it takes
+/// the prompt. A bare built-in backend name (as older configs have) means
+/// that backend's interactive default.
+pub fn interactive_template(configured: &str) -> (String, bool) {
+    let template = builtin_interactive(configured.trim())
+        .map(str::to_string)
+        .unwrap_or_else(|| configured.to_string());
+    let takes_prompt = template.contains("{prompt}") || template.contains("{prompt_file}");
+    (template, takes_prompt)
+}
+
+/// Largest prompt passed inline for `{prompt}`; Linux refuses a single
+/// argument over 128KB (E2BIG), and prompts that include files get there.
+const MAX_INLINE_PROMPT: usize = 64 * 1024;
+
+/// An interactive command line, and the prompt file it reads (if any),
+/// which the caller removes once the AI exits.
+#[derive(Debug)]
+pub struct InteractiveCommand {
+    pub argv: Vec<String>,
+    pub prompt_file: Option<PathBuf>,
+}
+
+/// The argv for an interactive template: words split on whitespace, with
+/// `{prompt}` replaced by the prompt and `{prompt_file}` by the path of a
+/// file holding it. A prompt too large for one argument is written to the
+/// file too, and `{prompt}` becomes an instruction to read it.
+pub fn interactive_argv(template: &str, prompt: &str) -> Result<InteractiveCommand, DecreeError> {
+    let inline = prompt.len() <= MAX_INLINE_PROMPT;
+    let prompt_file = if template.contains("{prompt_file}") || (!inline && template.contains("{prompt}")) {
+        Some(write_prompt_file(prompt)?)
+    } else {
+        None
+    };
+    let path = prompt_file.as_ref().map(|p| p.to_string_lossy().into_owned());
+    let prompt_arg = match &path {
+        Some(path) if !inline => {
+            format!("Read {path} and follow the instructions in it.")
+        }
+        _ => prompt.to_string(),
+    };
+    let argv: Vec<String> = template
+        .split_whitespace()
+        .map(|word| {
+            let word = word.replace("{prompt}", &prompt_arg);
+            match &path {
+                Some(path) => word.replace("{prompt_file}", path),
+                None => word,
+            }
+        })
+        .collect();
+    if argv.is_empty() {
+        if let Some(path) = &prompt_file {
+            let _ = std::fs::remove_file(path);
+        }
+        return Err(DecreeError::Config("commands.ai_interactive is empty".into()));
+    }
+    Ok(InteractiveCommand { argv, prompt_file })
+}
+
+/// A resolved AI backend, ready to run prompts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Backend {
+    /// Profile name (or `commands.ai_router` for the legacy template).
+    pub name: String,
+    kind: BackendKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum BackendKind {
+    Profile(AiProfile),
+    /// A shell template with `{prompt}`, run through `bash -c`.
+    Legacy(String),
+}
+
+/// Resolve an AI backend by profile name, or the default one.
+///
+/// Profiles from the `ai` section win over built-in ones. Without an `ai`
+/// section, the default is the legacy `commands.ai_router` template.
+pub fn backend(config: &AppConfig, name: Option<&str>) -> Result<Backend, DecreeError> {
+    let ai = config.ai.as_ref();
+    let name = match (name, ai) {
+        (Some(name), _) => name,
+        (None, Some(ai)) => ai.default.as_str(),
+        (None, None) if !config.commands.ai_router.is_empty() => {
+            return Ok(Backend::legacy(&config.commands.ai_router));
+        }
+        (None, None) => BUILTIN_BACKENDS[0],
+    };
+
+    let profile = ai
+        .and_then(|ai| ai.profiles.get(name).cloned())
+        .or_else(|| builtin_profile(name))
+        .ok_or_else(|| DecreeError::Config(format!("unknown AI profile '{name}'")))?;
+    Ok(Backend {
+        name: name.to_string(),
+        kind: BackendKind::Profile(profile),
+    })
+}
+
+/// A profile followed by the `ai.fallback` profiles, in order and without
+/// repeats.
+pub fn candidates(config: &AppConfig, name: Option<&str>) -> Result<Vec<Backend>, DecreeError> {
+    let mut backends = vec![backend(config, name)?];
+    let fallback = config.ai.as_ref().map(|ai| ai.fallback.as_slice()).unwrap_or(&[]);
+    for name in fallback {
+        if !backends.iter().any(|b| &b.name == name) {
+            backends.push(backend(config, Some(name))?);
+        }
+    }
+    Ok(backends)
+}
+
+/// The backends for the AI router, or `None` if routing by AI is off.
+///
+/// With an `ai` section this is `ai.router` (or `ai.default`) plus the
+/// fallbacks; otherwise the legacy `commands.ai_router` template, if set.
+pub fn router_backends(config: &AppConfig) -> Option<Result<Vec<Backend>, DecreeError>> {
+    match &config.ai {
+        Some(ai) => Some(candidates(
+            config,
+            Some(ai.router.as_deref().unwrap_or(&ai.default)),
+        )),
+        None if config.commands.ai_router.is_empty() => None,
+        None => Some(Ok(vec![Backend::legacy(&config.commands.ai_router)])),
+    }
+}
+
+/// The profile a routine run asks for: the message's `ai` field, then the
+/// routine's registry entry. `None` means the default profile.
+pub fn requested_profile(
+    config: &AppConfig,
+    routine: &str,
+    custom_fields: &BTreeMap<String, serde_yaml::Value>,
+) -> Option<String> {
+    custom_fields
+        .get(AI_FIELD)
+        .and_then(|v| v.as_str())
+        .map(str::to_string)
+        .or_else(|| config.routine_entry(routine).and_then(|e| e.ai.clone()))
+}
+
+/// Env vars that tell a routine which AI backend to use.
+///
+/// The requested profile (see [`requested_profile`]) is replaced by the
+/// first installed fallback when its own command is missing. An unknown
+/// profile is an error.
+pub fn routine_env(
+    config: &AppConfig,
+    routine: &str,
+    custom_fields: &BTreeMap<String, serde_yaml::Value>,
+) -> Result<Vec<(&'static str, String)>, DecreeError> {
+    let requested = requested_profile(config, routine, custom_fields);
+    let backends = candidates(config, requested.as_deref())?;
+    let chosen = backends
+        .iter()
+        .find(|b| b.check().is_ok())
+        .unwrap_or(&backends[0]);
+    Ok(vec![
+        (DECREE_AI_ENV, chosen.name.clone()),
+        (DECREE_AI_CMD_ENV, chosen.command_line()),
+    ])
+}
+
+/// Run `run` against each backend in turn until one succeeds.
+///
+/// A backend is skipped when its command is not installed, or when it fails
+/// with a quota or rate-limit error; any other failure is returned as is.
+/// `on_skip` is told why each skipped backend was passed over.
+pub fn with_fallback<T>(
+    backends: &[Backend],
+    mut run: impl FnMut(&Backend) -> Result<T, DecreeError>,
+    mut on_skip: impl FnMut(&Backend, &DecreeError),
+) -> Result<T, DecreeError> {
+    let mut last_err = None;
+    for (i, backend) in backends.iter().enumerate() {
+        let result = backend.check().and_then(|()| run(backend));
+        match result {
+            Err(e) if is_fallback_error(&e) && i + 1 < backends.len() => {
+                on_skip(backend, &e);
+                last_err = Some(e);
+            }
+            result => return result,
+        }
+    }
+    Err(last_err.unwrap_or_else(|| DecreeError::Config("no AI backend configured".into())))
+}
+
+/// Errors that move [`with_fallback`] on to the next backend.
+fn is_fallback_error(e: &DecreeError) -> bool {
+    matches!(e, DecreeError::AiUnavailable(_) | DecreeError::AiQuota(_))
+}
+
+impl Backend {
+    /// A backend for a legacy `{prompt}` shell template.
+    pub fn legacy(template: &str) -> Self {
+        Self {
+            name: "commands.ai_router".to_string(),
+            kind: BackendKind::Legacy(template.to_string()),
+        }
+    }
+
+    /// Run a prompt and return its trimmed stdout.
+    pub fn run_captured(&self, prompt: &str) -> Result<String, DecreeError> {
+        let mut invocation = self.invocation(prompt)?;
+        invocation.command.stdout(Stdio::piped()).stderr(Stdio::piped());
+        let mut child = invocation.spawn(&self.name)?;
+
+        let stdout = child.stdout.take();
+        let reader = std::thread::spawn(move || {
+            let mut out = String::new();
+            if let Some(mut stdout) = stdout {
+                let _ = stdout.read_to_string(&mut out);
+            }
+            out
+        });
+        let stderr_reader = tee(child.stderr.take(), None);
+
+        let status = invocation.wait(&mut child, &self.name)?;
+        let output = reader.join().unwrap_or_default();
+        let (errors, _) = stderr_reader.join().unwrap_or_default();
+        self.check_status(status, &errors)?;
+        Ok(output.trim().to_string())
+    }
+
+    /// Run a prompt with the backend's output going straight to ours.
+    ///
+    /// Returns how many bytes the backend wrote to stdout.
+    pub fn run_inherited(&self, prompt: &str) -> Result<u64, DecreeError> {
+        let mut invocation = self.invocation(prompt)?;
+        invocation.command.stdout(Stdio::piped()).stderr(Stdio::piped());
+        let mut child = invocation.spawn(&self.name)?;
+
+        // Pass output through, keeping the stderr tail to spot quota errors
+        let stdout_reader = tee(child.stdout.take(), Some(Box::new(std::io::stdout())));
+        let stderr_reader = tee(child.stderr.take(), Some(Box::new(std::io::stderr())));
+
+        let status = invocation.wait(&mut child, &self.name)?;
+        let (_, output_len) = stdout_reader.join().unwrap_or_default();
+        let (errors, _) = stderr_reader.join().unwrap_or_default();
+        self.check_status(status, &errors)?;
+        Ok(output_len)
+    }
+
+    /// The model the backend is configured with, if any.
+    pub fn model(&self) -> Option<&str> {
+        match &self.kind {
+            BackendKind::Profile(profile) => profile.model.as_deref(),
+            BackendKind::Legacy(_) => None,
+        }
+    }
+
+    /// The backend's prices, if configured.
+    pub fn pricing(&self) -> Option<AiPricing> {
+        match &self.kind {
+            BackendKind::Profile(profile) => profile.pricing,
+            BackendKind::Legacy(_) => None,
+        }
+    }
+
+    /// The command line a shell script can run with the prompt appended.
+    ///
+    /// Profiles that take the prompt on stdin or in a file don't fit that
+    /// shape, so routines should use `decree ai` with them.
+    pub fn command_line(&self) -> String {
+        match &self.kind {
+            BackendKind::Legacy(template) => template
+                .replace("{prompt}", "")
+                .trim()
+                .to_string(),
+            BackendKind::Profile(profile) => {
+                let mut words = vec![shell_escape(&profile.command)];
+                words.extend(
+                    profile
+                        .args
+                        .iter()
+                        .filter(|a| !a.contains("{prompt}") && !a.contains("{prompt_file}"))
+                        .map(|a| shell_escape(a)),
+                );
+                if let Some(model) = &profile.model {
+                    words.push(shell_escape(&profile.model_flag));
+                    words.push(shell_escape(model));
+                }
+                words.join(" ")
+            }
+        }
+    }
+
+    /// Check that the backend's executable is installed.
+    pub fn check(&self) -> Result<(), DecreeError> {
+        let command = match &self.kind {
+            BackendKind::Profile(profile) => profile.command.clone(),
+            BackendKind::Legacy(template) => match legacy_command(template) {
+                Some(command) => command.to_string(),
+                // Leave shell syntax we can't see through to bash
+                None if !template.trim().is_empty() => return Ok(()),
+                None => String::new(),
+            },
+        };
+        if on_path(&command) {
+            Ok(())
+        } else {
+            Err(DecreeError::AiUnavailable(format!(
+                "AI backend '{}': {command} not found",
+                self.name
+            )))
+        }
+    }
+
+    /// Build the command for a prompt.
+    fn invocation(&self, prompt: &str) -> Result<Invocation, DecreeError> {
+        let profile = match &self.kind {
+            BackendKind::Legacy(template) => {
+                let cmd_str = if template.contains("{prompt}") {
+                    template.replace("{prompt}", &shell_escape(prompt))
+                } else {
+                    format!("{} {}", template, shell_escape(prompt))
+                };
+                let mut command = Command::new("bash");
+                command.arg("-c").arg(cmd_str);
+                return Ok(Invocation {
+                    command,
+                    stdin: None,
+                    prompt_file: None,
+                    timeout: None,
+                });
+            }
+            BackendKind::Profile(profile) => profile,
+        };
+
+        let mut args: Vec<String> = profile.args.clone();
+        if let Some(model) = &profile.model {
+            args.push(profile.model_flag.clone());
+            args.push(model.clone());
+        }
+
+        let mut stdin = None;
+        let mut prompt_file = None;
+        match profile.prompt {
+            PromptMode::Argv => substitute_or_append(&mut args, "{prompt}", prompt),
+            PromptMode::Stdin => stdin = Some(prompt.to_string()),
+            PromptMode::File => {
+                let path = write_prompt_file(prompt)?;
+                substitute_or_append(&mut args, "{prompt_file}", &path.to_string_lossy());
+                prompt_file = Some(path);
+            }
+        }
+
+        let mut command = Command::new(&profile.command);
+        command.args(&args).envs(&profile.env);
+        if stdin.is_some() {
+            command.stdin(Stdio::piped());
+        }
+        Ok(Invocation {
+            command,
+            stdin,
+            prompt_file,
+            timeout: profile.timeout,
+        })
+    }
+
+    /// Turn a failed exit into an error, spotting quota errors in `stderr`.
+    fn check_status(&self, status: ExitStatus, stderr: &[u8]) -> Result<(), DecreeError> {
+        if status.success() {
+            return Ok(());
+        }
+        let code = status.code().unwrap_or(1);
+        if is_quota_error(&String::from_utf8_lossy(stderr)) {
+            Err(DecreeError::AiQuota(format!(
+                "AI backend '{}' hit a quota or rate limit (exit code {code})",
+                self.name
+            )))
+        } else {
+            Err(DecreeError::Other(format!(
+                "AI backend '{}' exited with code {code}",
+                self.name
+            )))
+        }
+    }
+}
+
+/// A command built for one prompt, plus what it needs at run time.
+struct Invocation {
+    command: Command,
+    stdin: Option<String>,
+    prompt_file: Option<PathBuf>,
+    timeout: Option<u64>,
+}
+
+impl Invocation {
+    fn spawn(&mut self, name: &str) -> Result<Child, DecreeError> {
+        let mut child = self.command.spawn().map_err(|e| {
+            DecreeError::Other(format!("failed to run AI backend '{name}': {e}"))
+        })?;
+        if let (Some(prompt), Some(mut stdin)) = (self.stdin.take(), child.stdin.take()) {
+            // Write from a thread so a large prompt can't deadlock against output
+            std::thread::spawn(move || {
+                let _ = stdin.write_all(prompt.as_bytes());
+            });
+        }
+        Ok(child)
+    }
+
+    /// Wait for the command, killing it once the timeout passes.
+    fn wait(&mut self, child: &mut Child, name: &str) -> Result<ExitStatus, DecreeError> {
+        let Some(secs) = self.timeout else {
+            return Ok(child.wait()?);
+        };
+        let deadline = Instant::now() + Duration::from_secs(secs);
+        loop {
+            if let Some(status) = child.try_wait()? {
+                return Ok(status);
+            }
+            if Instant::now() >= deadline {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(DecreeError::Other(format!(
+                    "AI backend '{name}' timed out after {secs}s"
+                )));
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+    }
+}
+
+impl Drop for Invocation {
+    fn drop(&mut self) {
+        if let Some(path) = &self.prompt_file {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// Error messages that report a quota or rate-limit problem.
+///
+/// Only whole phrases count, so a bare "quota" or a line number like 429
+/// elsewhere in the output doesn't look like a quota failure.
+static QUOTA_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)\b(?:quota (?:exceeded|exhausted|reached)|exceeded (?:your |the )?(?:current )?quota|rate[ -]?limit(?:ed| exceeded| reached)|usage limit (?:reached|exceeded)|too many requests|(?:http|status|status code|error)[ :]+429\b|insufficient[ _]credits|credit balance is too low)",
+    )
+    .expect("valid regex")
+});
+
+/// Whether a backend's stderr reports a quota or rate-limit problem.
+fn is_quota_error(stderr: &str) -> bool {
+    QUOTA_RE.is_match(stderr)
+}
+
+/// Read a pipe to the end, copying it to `out` if given, and return the
+/// last [`OUTPUT_TAIL_BYTES`] of it along with its full length.
+fn tee<R: Read + Send + 'static>(
+    pipe: Option<R>,
+    mut out: Option<Box<dyn Write + Send>>,
+) -> JoinHandle<(Vec<u8>, u64)> {
+    std::thread::spawn(move || {
+        let mut tail = Vec::new();
+        let mut len = 0u64;
+        let Some(mut pipe) = pipe else {
+            return (tail, len);
+        };
+        let mut buf = [0u8; 8192];
+        loop {
+            let n = match pipe.read(&mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => n,
+            };
+            if let Some(out) = out.as_mut() {
+                let _ = out.write_all(&buf[..n]);
+                let _ = out.flush();
+            }
+            len += n as u64;
+            tail.extend_from_slice(&buf[..n]);
+            if tail.len() > OUTPUT_TAIL_BYTES {
+                tail.drain(..tail.len() - OUTPUT_TAIL_BYTES);
+            }
+        }
+        (tail, len)
+    })
+}
+
+/// Replace `placeholder` in any argument, or append `value` if none has it.
+fn substitute_or_append(args: &mut Vec<String>, placeholder: &str, value: &str) {
+    if args.iter().any(|a| a.contains(placeholder)) {
+        for arg in args.iter_mut() {
+            *arg = arg.replace(placeholder, value);
+        }
+    } else {
+        args.push(value.to_string());
+    }
+}
+
+/// Distinguishes prompt files written by this process.
+static PROMPT_FILE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Write a prompt to a fresh temporary file, readable only by the user.
+///
+/// Prompts can carry project source and secrets, so the file is created
+/// exclusively with mode 0600 (never through an existing file or symlink),
+/// and another name is tried if one is taken.
+fn write_prompt_file(prompt: &str) -> Result<PathBuf, DecreeError> {
+    for _ in 0..100 {
+        let n = PROMPT_FILE_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or(0);
+        let path = std::env::temp_dir().join(format!(
+            "decree-prompt-{}-{nanos}-{n}.md",
+            std::process::id()
+        ));
+        let mut file = match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&path)
+        {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.into()),
+        };
+        if let Err(e) = file.write_all(prompt.as_bytes()) {
+            let _ = std::fs::remove_file(&path);
+            return Err(e.into());
+        }
+        return Ok(path);
+    }
+    Err(DecreeError::Other("could not create a temporary prompt file".into()))
+}
+
+/// The executable a legacy `bash -c` template runs, skipping leading
+/// `NAME=value` assignments. `None` when the template starts with quoting or
+/// other shell syntax that a plain word split can't resolve.
+fn legacy_command(template: &str) -> Option<&str> {
+    for word in template.split_whitespace() {
+        if word.contains(['\'', '"', '\\', '$', '`', '(', ')', ';', '&', '|', '<', '>']) {
+            return None;
+        }
+        let is_assignment = word.split_once('=').is_some_and(|(name, _)| {
+            name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        });
+        if !is_assignment {
+            return Some(word);
+        }
+    }
+    None
+}
+
+/// Whether a command can be found (a path, or a name on PATH).
+fn on_path(command: &str) -> bool {
+    if command.is_empty() {
+        return false;
+    }
+    if command.contains('/') {
+        return Path::new(command).is_file();
+    }
+    std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).any(|dir| dir.join(command).is_file()))
+        .unwrap_or(false)
+}
+
+/// Shell-escape a string for safe use in bash -c.
+fn shell_escape(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AiConfig;
+
+    fn profile(command: &str, args: &[&str], prompt: PromptMode) -> AiProfile {
+        AiProfile {
+            command: command.to_string(),
+            args: args.iter().map(|a| a.to_string()).collect(),
+            prompt,
+            model: None,
+            model_flag: "--model".to_string(),
+            env: BTreeMap::new(),
+            timeout: None,
+            pricing: None,
+        }
+    }
+
+    fn run(profile: AiProfile, prompt: &str) -> Result<String, DecreeError> {
+        Backend {
+            name: "test".into(),
+            kind: BackendKind::Profile(profile),
+        }
+        .run_captured(prompt)
+    }
+
+    #[test]
+    fn test_legacy_backend() {
+        // Use printf to avoid trailing args from the prompt
+        let out = Backend::legacy("printf rust-develop").run_captured("ignored prompt");
+        assert_eq!(out.unwrap(), "rust-develop");
+
+        // The prompt is shell-escaped, so it comes through as the literal string
+        let out = Backend::legacy("echo {prompt}").run_captured("hello 'world'");
+        assert_eq!(out.unwrap(), "hello 'world'");
+
+        assert!(Backend::legacy("exit 1").run_captured("test prompt").is_err());
+    }
+
+    #[test]
+    fn test_prompt_modes() {
+        let argv = profile("echo", &["-n"], PromptMode::Argv);
+        assert_eq!(run(argv, "hi there").unwrap(), "hi there");
+
+        let placeholder = profile("printf", &["<%s>", "{prompt}"], PromptMode::Argv);
+        assert_eq!(run(placeholder, "x").unwrap(), "<x>");
+
+        let stdin = profile("cat", &[], PromptMode::Stdin);
+        assert_eq!(run(stdin, "from stdin").unwrap(), "from stdin");
+
+        let file = profile("cat", &["{prompt_file}"], PromptMode::File);
+        assert_eq!(run(file, "from file").unwrap(), "from file");
+    }
+
+    #[test]
+    fn test_model_env_and_failure() {
+        let mut p = profile("echo", &[], PromptMode::Argv);
+        p.model = Some("big".into());
+        assert_eq!(run(p, "go").unwrap(), "--model big go");
+
+        let mut p = profile("bash", &["-c", "printf %s \"$GREETING\""], PromptMode::Stdin);
+        p.env.insert("GREETING".into(), "hello".into());
+        assert_eq!(run(p, "").unwrap(), "hello");
+
+        let err = run(profile("false", &[], PromptMode::Argv), "x").unwrap_err();
+        assert!(err.to_string().contains("AI backend 'test' exited with code 1"));
+    }
+
+    #[test]
+    fn test_timeout_kills_backend() {
+        let mut p = profile("sleep", &["5"], PromptMode::Stdin);
+        p.timeout = Some(1);
+        let start = Instant::now();
+        let err = run(p, "").unwrap_err();
+        assert!(err.to_string().contains("timed out after 1s"));
+        assert!(start.elapsed() < Duration::from_secs(4));
+    }
+
+    #[test]
+    fn test_backend_resolution() {
+        let mut config = AppConfig::default();
+
+        // No `ai` section: legacy commands.ai_router template
+        let b = backend(&config, None).unwrap();
+        assert_eq!(b, Backend::legacy("opencode run {prompt}"));
+        assert_eq!(backend(&config, Some("claude")).unwrap().name, "claude");
+
+        config.ai = Some(AiConfig {
+            default: "mine".into(),
+            router: Some("claude".into()),
+            fallback: Vec::new(),
+            profiles: BTreeMap::from([(
+                "mine".to_string(),
+                profile("my-ai", &[], PromptMode::Stdin),
+            )]),
+        });
+        assert_eq!(backend(&config, None).unwrap().name, "mine");
+        assert_eq!(router_backends(&config).unwrap().unwrap()[0].name, "claude");
+        assert!(matches!(
+            backend(&config, Some("nope")),
+            Err(DecreeError::Config(_))
+        ));
+
+        config.ai = None;
+        config.commands.ai_router = String::new();
+        assert!(router_backends(&config).is_none());
+    }
+
+    fn config_with_fallback(fallback: &[&str]) -> AppConfig {
+        AppConfig {
+            ai: Some(AiConfig {
+                default: "main".into(),
+                router: None,
+                fallback: fallback.iter().map(|f| f.to_string()).collect(),
+                profiles: BTreeMap::from([
+                    ("main".to_string(), profile("no-such-ai-tool", &[], PromptMode::Argv)),
+                    (
+                        "limited".to_string(),
+                        profile("bash", &["-c", "echo 'Error: rate limit exceeded' >&2; exit 1"], PromptMode::Stdin),
+                    ),
+                    ("broken".to_string(), profile("false", &[], PromptMode::Argv)),
+                    ("works".to_string(), profile("echo", &["-n", "ok"], PromptMode::Stdin)),
+                ]),
+            }),
+            ..AppConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_candidates_follow_fallback_order() {
+        let config = config_with_fallback(&["works", "main", "claude"]);
+        let names: Vec<String> = candidates(&config, None)
+            .unwrap()
+            .into_iter()
+            .map(|b| b.name)
+            .collect();
+        assert_eq!(names, ["main", "works", "claude"]);
+
+        let config = config_with_fallback(&["nope"]);
+        assert!(matches!(candidates(&config, None), Err(DecreeError::Config(_))));
+    }
+
+    #[test]
+    fn test_fallback_skips_missing_and_quota_errors() {
+        let config = config_with_fallback(&["limited", "works"]);
+        let backends = candidates(&config, None).unwrap();
+        let mut skipped = Vec::new();
+        let out = with_fallback(
+            &backends,
+            |b| b.run_captured("x"),
+            |b, e| skipped.push((b.name.clone(), e.to_string())),
+        );
+        assert_eq!(out.unwrap(), "ok");
+        assert_eq!(skipped.len(), 2);
+        assert_eq!(skipped[0].0, "main");
+        assert!(skipped[1].1.contains("hit a quota or rate limit"));
+
+        // Other failures stop the chain
+        let config = config_with_fallback(&["broken", "works"]);
+        let backends = candidates(&config, None).unwrap();
+        let err = with_fallback(&backends, |b| b.run_captured("x"), |_, _| {}).unwrap_err();
+        assert!(err.to_string().contains("AI backend 'broken' exited with code 1"));
+
+        // The last backend's error is returned when all are skipped
+        let config = config_with_fallback(&["limited"]);
+        let backends = candidates(&config, None).unwrap();
+        let err = with_fallback(&backends, |b| b.run_captured("x"), |_, _| {}).unwrap_err();
+        assert!(matches!(err, DecreeError::AiQuota(_)));
+    }
+
+    #[test]
+    fn test_routine_env_picks_profile() {
+        let mut config = config_with_fallback(&["works"]);
+        let mut entry = crate::config::RoutineEntry::new(true);
+        entry.ai = Some("claude".into());
+        config.routines = Some(BTreeMap::from([("review".to_string(), entry)]));
+
+        let env = |routine: &str, fields: &[(&str, &str)]| {
+            let fields = fields
+                .iter()
+                .map(|(k, v)| (k.to_string(), serde_yaml::Value::String(v.to_string())))
+                .collect();
+            routine_env(&config, routine, &fields).map(|vars| {
+                vars.into_iter()
+                    .map(|(k, v)| format!("{k}={v}"))
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        // Default profile is missing, so the installed fallback is chosen
+        assert_eq!(
+            env("develop", &[]).unwrap(),
+            ["DECREE_AI=works", "DECREE_AI_CMD='echo' '-n' 'ok'"]
+        );
+        // A message field wins over the registry entry
+        assert_eq!(requested_profile(&config, "review", &BTreeMap::new()).as_deref(), Some("claude"));
+        assert_eq!(env("review", &[("ai", "works")]).unwrap()[0], "DECREE_AI=works");
+        assert!(env("develop", &[("ai", "nope")]).is_err());
+    }
+
+    #[test]
+    fn test_interactive_commands() {
+        assert_eq!(
+            interactive_template("claude"),
+            ("claude {prompt}".to_string(), true)
+        );
+        assert_eq!(
+            interactive_template("my-ai --tui"),
+            ("my-ai --tui".to_string(), false)
+        );
+
+        let cmd = interactive_argv("opencode --prompt {prompt}", "two words\nand a line").unwrap();
+        assert_eq!(cmd.argv, ["opencode", "--prompt", "two words\nand a line"]);
+        assert!(cmd.prompt_file.is_none());
+
+        let cmd = interactive_argv("tool --file={prompt_file}", "from a file").unwrap();
+        let path = cmd.argv[1].strip_prefix("--file=").unwrap();
+        assert_eq!(Some(PathBuf::from(path)), cmd.prompt_file);
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "from a file");
+        std::fs::remove_file(path).unwrap();
+
+        assert!(interactive_argv("  ", "x").is_err());
+    }
+
+    #[test]
+    fn test_large_interactive_prompt_goes_to_a_file() {
+        let prompt = "x".repeat(MAX_INLINE_PROMPT + 1);
+        let cmd = interactive_argv("claude {prompt}", &prompt).unwrap();
+        let path = cmd.prompt_file.unwrap();
+        assert_eq!(
+            cmd.argv[1],
+            format!("Read {} and follow the instructions in it.", path.display())
+        );
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), prompt);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_command_line() {
+        assert_eq!(Backend::legacy("claude -p {prompt}").command_line(), "claude -p");
+        let mut p = profile("opencode", &["run", "{prompt}"], PromptMode::Argv);
+        p.model = Some("gpt".into());
+        let b = Backend {
+            name: "x".into(),
+            kind: BackendKind::Profile(p),
+        };
+        assert_eq!(b.command_line(), "'opencode' 'run' '--model' 'gpt'");
+    }
+
+    #[test]
+    fn test_quota_detection() {
+        assert!(is_quota_error("Error: 429 Too Many Requests"));
+        assert!(is_quota_error("Claude AI usage limit reached"));
+        assert!(is_quota_error("You exceeded your current quota"));
+        assert!(!is_quota_error("error: file not found"));
+        assert!(is_quota_error("HTTP 429"));
+        assert!(is_quota_error("API error (status code: 429)"));
+        assert!(is_quota_error("Error: rate limit exceeded"));
+        // Normal output that merely contains the tokens
+        assert!(!is_quota_error("Updated the disk quota docs"));
+        assert!(!is_quota_error("src/main.rs:429: expected `;`"));
+        assert!(!is_quota_error("test result: 429 passed; 0 failed"));
+        assert!(!is_quota_error("Added a rate_limit setting"));
+    }
+
+    #[test]
+    fn test_quota_words_on_stdout_are_not_quota_errors() {
+        let b = Backend {
+            name: "chatty".into(),
+            kind: BackendKind::Profile(profile(
+                "bash",
+                &["-c", "echo 'You exceeded your current quota'; exit 1"],
+                PromptMode::Stdin,
+            )),
+        };
+        let err = b.run_captured("x").unwrap_err();
+        assert!(!matches!(err, DecreeError::AiQuota(_)), "{err}");
+    }
+
+    #[test]
+    fn test_write_prompt_file_is_private_and_unique() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let a = write_prompt_file("secret").unwrap();
+        let b = write_prompt_file("secret").unwrap();
+        assert_ne!(a, b);
+        assert_eq!(std::fs::read_to_string(&a).unwrap(), "secret");
+        let mode = std::fs::metadata(&a).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        std::fs::remove_file(a).unwrap();
+        std::fs::remove_file(b).unwrap();
+    }
+
+    #[test]
+    fn test_legacy_command() {
+        assert_eq!(legacy_command("claude -p {prompt}"), Some("claude"));
+        assert_eq!(legacy_command("FOO=1 BAR_2=x claude -p"), Some("claude"));
+        assert_eq!(legacy_command("/usr/bin/opencode run"), Some("/usr/bin/opencode"));
+        assert_eq!(legacy_command("FOO='a b' claude"), None);
+        assert_eq!(legacy_command("\"$HOME/bin/ai\" -p"), None);
+        assert_eq!(legacy_command(""), None);
+    }
+
+    #[test]
+    fn test_check() {
+        assert!(Backend::legacy("bash -c true").check().is_ok());
+        assert!(Backend::legacy("FOO=1 bash -c true").check().is_ok());
+        assert!(Backend::legacy("FOO=1 no-such-ai-tool -p").check().is_err());
+        assert!(Backend::legacy("").check().is_err());
+        let missing = Backend {
+            name: "x".into(),
+            kind: BackendKind::Profile(profile("no-such-ai-tool", &[], PromptMode::Argv)),
+        };
+        assert!(missing.check().is_err());
+    }
+}
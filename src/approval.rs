@@ -0,0 +1,319 @@
+use crate::atomic;
+use crate::config;
+use crate::error::DecreeError;
+use crate::events::{self, Event, EventKind};
+use crate::message::{self, read_messages_in, InboxMessage};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Frontmatter field that holds a message for approval before its routine runs.
+pub const REQUIRES_APPROVAL_FIELD: &str = "requires_approval";
+
+/// Field set when a message is approved (visible to routines as `$approved`).
+pub const APPROVED_FIELD: &str = "approved";
+
+/// Field set on a rejected message in `inbox/dead/`, holding the reason.
+pub const REJECTED_FIELD: &str = "rejected";
+
+/// Routine exit code meaning "stop here until a human approves" (EX_TEMPFAIL).
+pub const AWAITING_APPROVAL_EXIT_CODE: i32 = 75;
+
+/// File in a run directory recording the approval request and decision.
+pub const APPROVAL_RECORD_FILE: &str = "approval.json";
+
+/// What `approval.json` in a run directory records.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApprovalRecord {
+    pub requested_at: String,
+    /// Why the message is waiting (frontmatter field or routine exit code).
+    pub reason: String,
+    /// `approved` or `rejected`, once decided.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decision: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decided_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decided_by: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rejection_reason: Option<String>,
+}
+
+/// `.decree/inbox/pending-approval/`, where messages wait for sign-off.
+pub fn pending_approval_dir(project_root: &Path) -> PathBuf {
+    project_root
+        .join(config::DECREE_DIR)
+        .join(config::INBOX_DIR)
+        .join(config::PENDING_APPROVAL_DIR)
+}
+
+/// Whether a message must be approved before its routine runs.
+///
+/// True when `requires_approval` is set and the message has not been
+/// approved yet.
+pub fn needs_approval(msg: &InboxMessage) -> bool {
+    let required = match msg.custom_fields.get(REQUIRES_APPROVAL_FIELD) {
+        Some(serde_yaml::Value::Bool(b)) => *b,
+        Some(serde_yaml::Value::String(s)) => matches!(s.as_str(), "true" | "yes"),
+        _ => false,
+    };
+    required && !msg.custom_fields.contains_key(APPROVED_FIELD)
+}
+
+/// Move a message from the inbox to `inbox/pending-approval/` and record why.
+pub fn hold(project_root: &Path, msg: &InboxMessage, reason: &str) -> Result<(), DecreeError> {
+    let dir = pending_approval_dir(project_root);
+    std::fs::create_dir_all(&dir)?;
+    atomic::write(&dir.join(&msg.filename), msg.serialize())?;
+    remove_from_inbox(project_root, &msg.filename)?;
+
+    if let Some(id) = &msg.id {
+        write_record(
+            project_root,
+            id,
+            &ApprovalRecord {
+                requested_at: Local::now().to_rfc3339(),
+                reason: reason.to_string(),
+                decision: None,
+                decided_at: None,
+                decided_by: None,
+                rejection_reason: None,
+            },
+        )?;
+    }
+    events::record(
+        project_root,
+        Event {
+            detail: Some(reason.to_string()),
+            ..Event::for_message(EventKind::Held, msg)
+        },
+    );
+    Ok(())
+}
+
+/// Messages waiting in `inbox/pending-approval/`, sorted by filename.
+pub fn pending_messages(project_root: &Path) -> Result<Vec<InboxMessage>, DecreeError> {
+    read_messages_in(&pending_approval_dir(project_root))
+}
+
+/// Find a pending message by ID or filename.
+pub fn find_pending(project_root: &Path, id: &str) -> Result<InboxMessage, DecreeError> {
+    pending_messages(project_root)?
+        .into_iter()
+        .find(|m| {
+            m.id.as_deref() == Some(id)
+                || m.filename == id
+                || m.filename.strip_suffix(".md") == Some(id)
+        })
+        .ok_or_else(|| DecreeError::MessageNotFound(id.to_string()))
+}
+
+/// Approve a pending message: mark it approved and return it to the inbox,
+/// where the next `decree process` (or daemon poll) runs it.
+pub fn approve(project_root: &Path, mut msg: InboxMessage) -> Result<InboxMessage, DecreeError> {
+    let now = Local::now().to_rfc3339();
+    msg.custom_fields.insert(
+        APPROVED_FIELD.to_string(),
+        serde_yaml::Value::String(now.clone()),
+    );
+
+    std::fs::create_dir_all(
+        project_root
+            .join(config::DECREE_DIR)
+            .join(config::INBOX_DIR),
+    )?;
+    msg.write_to_inbox(project_root)?;
+    std::fs::remove_file(pending_approval_dir(project_root).join(&msg.filename))?;
+
+    decide(project_root, &msg, "approved", &now, None)?;
+    events::record(project_root, Event::for_message(EventKind::Approved, &msg));
+    Ok(msg)
+}
+
+/// Reject a pending message: dead-letter it with the reason.
+///
+/// A rejected migration is marked processed, like any other dead letter.
+pub fn reject(
+    project_root: &Path,
+    mut msg: InboxMessage,
+    reason: Option<&str>,
+) -> Result<InboxMessage, DecreeError> {
+    let now = Local::now().to_rfc3339();
+    msg.custom_fields.insert(
+        REJECTED_FIELD.to_string(),
+        serde_yaml::Value::String(reason.unwrap_or("rejected").to_string()),
+    );
+
+    let dead_dir = project_root
+        .join(config::DECREE_DIR)
+        .join(config::INBOX_DIR)
+        .join(config::DEAD_DIR);
+    std::fs::create_dir_all(&dead_dir)?;
+    atomic::write(&dead_dir.join(&msg.filename), msg.serialize())?;
+    std::fs::remove_file(pending_approval_dir(project_root).join(&msg.filename))?;
+
+    if let Some(migration) = &msg.migration {
+        message::mark_processed(project_root, migration)?;
+    }
+
+    decide(project_root, &msg, "rejected", &now, reason)?;
+    events::record(
+        project_root,
+        Event {
+            detail: Some(format!("rejected: {}", reason.unwrap_or("no reason given"))),
+            ..Event::for_message(EventKind::DeadLettered, &msg)
+        },
+    );
+    Ok(msg)
+}
+
+/// Read `approval.json` from a message's run directory.
+pub fn read_record(project_root: &Path, msg_id: &str) -> Option<ApprovalRecord> {
+    std::fs::read_to_string(record_path(project_root, msg_id))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+}
+
+/// Add the decision to the message's approval record.
+fn decide(
+    project_root: &Path,
+    msg: &InboxMessage,
+    decision: &str,
+    at: &str,
+    reason: Option<&str>,
+) -> Result<(), DecreeError> {
+    let Some(id) = &msg.id else {
+        return Ok(());
+    };
+    let mut record = read_record(project_root, id).unwrap_or_else(|| ApprovalRecord {
+        requested_at: at.to_string(),
+        reason: "unknown".to_string(),
+        decision: None,
+        decided_at: None,
+        decided_by: None,
+        rejection_reason: None,
+    });
+    record.decision = Some(decision.to_string());
+    record.decided_at = Some(at.to_string());
+    record.decided_by = std::env::var("USER").ok();
+    record.rejection_reason = reason.map(str::to_string);
+    write_record(project_root, id, &record)
+}
+
+fn record_path(project_root: &Path, msg_id: &str) -> PathBuf {
+    project_root
+        .join(config::DECREE_DIR)
+        .join(config::RUNS_DIR)
+        .join(msg_id)
+        .join(APPROVAL_RECORD_FILE)
+}
+
+fn write_record(
+    project_root: &Path,
+    msg_id: &str,
+    record: &ApprovalRecord,
+) -> Result<(), DecreeError> {
+    let path = record_path(project_root, msg_id);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let json = serde_json::to_string_pretty(record)
+        .map_err(|e| DecreeError::Other(format!("failed to serialize approval record: {e}")))?;
+    atomic::write(&path, json + "\n")?;
+    Ok(())
+}
+
+fn remove_from_inbox(project_root: &Path, filename: &str) -> Result<(), DecreeError> {
+    let inbox_path = project_root
+        .join(config::DECREE_DIR)
+        .join(config::INBOX_DIR)
+        .join(filename);
+    if inbox_path.exists() {
+        std::fs::remove_file(inbox_path)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn setup(dir: &TempDir) -> InboxMessage {
+        let decree = dir.path().join(".decree");
+        std::fs::create_dir_all(decree.join("inbox")).unwrap();
+        std::fs::write(decree.join("processed.md"), "").unwrap();
+        let msg = InboxMessage::parse(
+            "D0001-1200-deploy-0.md",
+            "---\nid: D0001-1200-deploy-0\nchain: D0001-1200-deploy\nseq: 0\nroutine: deploy\nmigration: 01-deploy.md\nrequires_approval: true\n---\nShip it.\n",
+        )
+        .unwrap();
+        msg.write_to_inbox(dir.path()).unwrap();
+        msg
+    }
+
+    #[test]
+    fn test_needs_approval() {
+        let parse = |fm: &str| InboxMessage::parse("a.md", &format!("---\n{fm}\n---\n")).unwrap();
+        assert!(needs_approval(&parse("requires_approval: true")));
+        assert!(needs_approval(&parse("requires_approval: \"yes\"")));
+        assert!(!needs_approval(&parse("requires_approval: false")));
+        assert!(!needs_approval(&parse("other: 1")));
+        assert!(!needs_approval(&parse(
+            "requires_approval: true\napproved: \"2026-01-01T00:00:00Z\""
+        )));
+    }
+
+    #[test]
+    fn test_hold_and_approve() {
+        let dir = TempDir::new().unwrap();
+        let msg = setup(&dir);
+        let inbox = dir.path().join(".decree/inbox");
+
+        hold(dir.path(), &msg, "requires_approval is set").unwrap();
+        assert!(!inbox.join("D0001-1200-deploy-0.md").exists());
+        assert_eq!(pending_messages(dir.path()).unwrap().len(), 1);
+        let record = read_record(dir.path(), "D0001-1200-deploy-0").unwrap();
+        assert_eq!(record.reason, "requires_approval is set");
+        assert_eq!(record.decision, None);
+
+        let pending = find_pending(dir.path(), "D0001-1200-deploy-0").unwrap();
+        let approved = approve(dir.path(), pending).unwrap();
+        assert!(!needs_approval(&approved));
+        assert!(pending_messages(dir.path()).unwrap().is_empty());
+        let content = std::fs::read_to_string(inbox.join("D0001-1200-deploy-0.md")).unwrap();
+        assert!(content.contains("approved:"));
+        let record = read_record(dir.path(), "D0001-1200-deploy-0").unwrap();
+        assert_eq!(record.decision.as_deref(), Some("approved"));
+    }
+
+    #[test]
+    fn test_reject_dead_letters_and_marks_migration() {
+        let dir = TempDir::new().unwrap();
+        let msg = setup(&dir);
+        hold(dir.path(), &msg, "requires_approval is set").unwrap();
+
+        let pending = find_pending(dir.path(), "D0001-1200-deploy-0.md").unwrap();
+        reject(dir.path(), pending, Some("not on a Friday")).unwrap();
+
+        let dead = std::fs::read_to_string(
+            dir.path().join(".decree/inbox/dead/D0001-1200-deploy-0.md"),
+        )
+        .unwrap();
+        assert!(dead.contains("rejected: not on a Friday"));
+        assert!(pending_messages(dir.path()).unwrap().is_empty());
+        let processed =
+            std::fs::read_to_string(dir.path().join(".decree/processed.md")).unwrap();
+        assert!(processed.contains("01-deploy.md"));
+        let record = read_record(dir.path(), "D0001-1200-deploy-0").unwrap();
+        assert_eq!(record.rejection_reason.as_deref(), Some("not on a Friday"));
+    }
+
+    #[test]
+    fn test_find_pending_missing() {
+        let dir = TempDir::new().unwrap();
+        setup(&dir);
+        let err = find_pending(dir.path(), "nope").unwrap_err();
+        assert!(matches!(err, DecreeError::MessageNotFound(_)));
+    }
+}
@@ -0,0 +1,173 @@
+//! Crash-safe writes for decree's state files.
+//!
+//! Content goes to a uniquely named `<name>.<pid>.<n>.tmp` sibling, is synced
+//! to disk, and then renamed over the target, so readers see the old file or
+//! the new one — never a partial write, even with several writers. Scanners
+//! skip `*.tmp` files, which is also how other tools should drop messages
+//! into the inbox or outbox. Line-oriented logs are appended in place with a
+//! single `O_APPEND` write per line.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Suffix of files that are still being written.
+pub const TMP_SUFFIX: &str = ".tmp";
+
+/// Distinguishes temp files of concurrent writes within this process.
+static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Write `contents` to `path` atomically.
+pub fn write(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let tmp = tmp_path(path);
+    let result = (|| {
+        let mut file = File::create(&tmp)?;
+        file.write_all(contents.as_ref())?;
+        file.sync_all()?;
+        std::fs::rename(&tmp, path)?;
+        sync_parent(path)
+    })();
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    result
+}
+
+/// Append `line` (plus a newline) with a single `O_APPEND` write.
+///
+/// Concurrent appenders never interleave within a line, and nothing already
+/// in the file is rewritten. Used for `processed.md` and the JSONL logs.
+pub fn append_line(path: &Path, line: &str) -> io::Result<()> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .create(true)
+        .append(true)
+        .open(path)?;
+
+    let mut buf = Vec::with_capacity(line.len() + 2);
+    let len = file.metadata()?.len();
+    if len > 0 {
+        let mut last = [0u8];
+        file.read_exact_at(&mut last, len - 1)?;
+        if last[0] != b'\n' {
+            buf.push(b'\n');
+        }
+    }
+    buf.extend_from_slice(line.as_bytes());
+    buf.push(b'\n');
+    file.write_all(&buf)?;
+    file.sync_data()
+}
+
+/// Whether a file name belongs to a write still in progress.
+pub fn is_tmp(file_name: &str) -> bool {
+    file_name.ends_with(TMP_SUFFIX)
+}
+
+/// A temp file next to `path`, unique across processes and threads.
+fn tmp_path(path: &Path) -> PathBuf {
+    let n = TMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}.{n}{TMP_SUFFIX}", std::process::id()));
+    path.with_file_name(name)
+}
+
+/// Sync the directory holding `path`, so the rename itself survives a crash.
+fn sync_parent(path: &Path) -> io::Result<()> {
+    match path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        Some(dir) => File::open(dir)?.sync_all(),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_write_replaces_without_leftovers() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("state.yml");
+
+        write(&path, "one").unwrap();
+        write(&path, "two").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "two");
+
+        let names: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        assert_eq!(names, ["state.yml"]);
+    }
+
+    #[test]
+    fn test_write_failure_keeps_original() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("missing-dir/state.yml");
+        assert!(write(&path, "x").is_err());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_append_line() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("processed.md");
+
+        append_line(&path, "01-a.md").unwrap();
+        std::fs::write(&path, "01-a.md\n02-b.md").unwrap();
+        append_line(&path, "03-c.md").unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "01-a.md\n02-b.md\n03-c.md\n"
+        );
+    }
+
+    #[test]
+    fn test_tmp_paths_are_unique() {
+        let path = Path::new("/x/.decree/cron-state.yml");
+        let (a, b) = (tmp_path(path), tmp_path(path));
+        assert_ne!(a, b);
+        assert_eq!(a.parent(), path.parent());
+        assert!(is_tmp(a.file_name().unwrap().to_str().unwrap()));
+    }
+
+    #[test]
+    fn test_concurrent_writes() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("state.yml");
+
+        std::thread::scope(|scope| {
+            for i in 0..8 {
+                let path = &path;
+                scope.spawn(move || {
+                    for _ in 0..20 {
+                        write(path, format!("writer {i}\n")).unwrap();
+                        append_line(&path.with_file_name("log.jsonl"), &format!("{{\"i\":{i}}}"))
+                            .unwrap();
+                    }
+                });
+            }
+        });
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(contents.starts_with("writer ") && contents.lines().count() == 1);
+        let log = std::fs::read_to_string(dir.path().join("log.jsonl")).unwrap();
+        assert_eq!(log.lines().count(), 160);
+        assert!(log.lines().all(|l| l.starts_with("{\"i\":") && l.ends_with('}')));
+        let mut names: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["log.jsonl", "state.yml"]);
+    }
+
+    #[test]
+    fn test_is_tmp() {
+        assert!(is_tmp("D0001-1200-x-0.md.tmp"));
+        assert!(!is_tmp("D0001-1200-x-0.md"));
+    }
+}
@@ -0,0 +1,384 @@
+//! Size-capped routine logs.
+//!
+//! Routine output is copied to the terminal and to the attempt log as it
+//! arrives. Once the log outgrows `max_log_size`, decree keeps the first
+//! `log_head_size` bytes (what was attempted) and the most recent output
+//! (the final error), with a `[decree] ... N bytes elided ...` line between
+//! them. The log is compacted while the routine runs, so a chatty AI CLI
+//! never fills the disk; with `keep_full_log`, the untruncated output is
+//! also gzipped next to it.
+
+use crate::atomic;
+use crate::config::AppConfig;
+use crate::error::{color, DecreeError};
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+
+/// How the log for one attempt is capped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Total bytes kept from the head and tail; 0 disables truncation.
+    pub max_size: u64,
+    /// Bytes kept from the start of the log.
+    pub head_size: u64,
+    /// Also gzip the untruncated output to `<log>.gz`.
+    pub keep_full: bool,
+}
+
+impl Limits {
+    pub fn from_config(config: &AppConfig) -> Self {
+        Self {
+            max_size: config.max_log_size,
+            head_size: config.log_head_size,
+            keep_full: config.keep_full_log,
+        }
+    }
+
+    /// The head never takes more than half of the cap.
+    fn head(&self) -> usize {
+        self.head_size.min(self.max_size / 2) as usize
+    }
+
+    fn tail(&self) -> usize {
+        self.max_size as usize - self.head()
+    }
+}
+
+/// Path of the gzipped full log kept beside `log_path`.
+pub fn full_log_path(log_path: &Path) -> PathBuf {
+    let mut name = log_path.file_name().unwrap_or_default().to_os_string();
+    name.push(".gz");
+    log_path.with_file_name(name)
+}
+
+/// An attempt log that keeps its head and tail once it grows too large.
+pub struct CappedLog {
+    path: PathBuf,
+    file: File,
+    limits: Limits,
+    /// The first bytes ever written, up to the head size.
+    head: Vec<u8>,
+    /// The most recent bytes after the head, up to the tail size.
+    tail: VecDeque<u8>,
+    /// Bytes written in total.
+    total: u64,
+    /// Current size of the file on disk.
+    len: u64,
+    /// Bytes appended since the file was last compacted.
+    since_compact: u64,
+    full: Option<Child>,
+}
+
+impl CappedLog {
+    /// Continue the log at `path`; what it already holds counts toward the head.
+    pub fn open(path: &Path, limits: Limits) -> Result<Self, DecreeError> {
+        let existing = match std::fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let full = if limits.keep_full && limits.max_size > 0 {
+            start_gzip(&full_log_path(path))
+        } else {
+            None
+        };
+
+        let mut log = Self {
+            path: path.to_path_buf(),
+            file,
+            limits,
+            head: Vec::new(),
+            tail: VecDeque::new(),
+            total: 0,
+            len: existing.len() as u64,
+            since_compact: existing.len() as u64,
+            full,
+        };
+        log.remember(&existing);
+        log.write_full(&existing);
+        Ok(log)
+    }
+
+    /// Append `chunk`, compacting the file once it is well past the cap.
+    pub fn write(&mut self, chunk: &[u8]) -> Result<(), DecreeError> {
+        self.file.write_all(chunk)?;
+        self.len += chunk.len() as u64;
+        self.since_compact += chunk.len() as u64;
+        self.remember(chunk);
+        self.write_full(chunk);
+
+        // Let the file run past the cap by half the tail before rewriting
+        // it, so a flood of output is not rewritten on every chunk
+        let slack = (self.limits.tail() / 2) as u64;
+        if self.limits.max_size > 0 && self.len > self.limits.max_size + slack {
+            self.compact()?;
+        }
+        Ok(())
+    }
+
+    /// Compact the log a final time and close the full copy.
+    pub fn finish(mut self) -> Result<(), DecreeError> {
+        if self.limits.max_size > 0 && self.len > self.limits.max_size && self.since_compact > 0 {
+            self.compact()?;
+        }
+        let truncated = self.elided() > 0;
+        if let Some(mut gzip) = self.full.take() {
+            drop(gzip.stdin.take());
+            let _ = gzip.wait();
+            if !truncated {
+                // Nothing was dropped; routine.log is already the full log
+                let _ = std::fs::remove_file(full_log_path(&self.path));
+            }
+        }
+        Ok(())
+    }
+
+    /// Track the head and tail of everything written.
+    fn remember(&mut self, mut chunk: &[u8]) {
+        self.total += chunk.len() as u64;
+        if self.limits.max_size == 0 {
+            return;
+        }
+        let take = self
+            .limits
+            .head()
+            .saturating_sub(self.head.len())
+            .min(chunk.len());
+        self.head.extend_from_slice(&chunk[..take]);
+        chunk = &chunk[take..];
+
+        let tail_size = self.limits.tail();
+        if chunk.len() >= tail_size {
+            self.tail.clear();
+            self.tail.extend(&chunk[chunk.len() - tail_size..]);
+        } else {
+            let overflow = (self.tail.len() + chunk.len()).saturating_sub(tail_size);
+            self.tail.drain(..overflow);
+            self.tail.extend(chunk);
+        }
+    }
+
+    /// Bytes that are not in the head or tail.
+    fn elided(&self) -> u64 {
+        self.total - self.head.len() as u64 - self.tail.len() as u64
+    }
+
+    /// Rewrite the file as head, marker, and tail.
+    fn compact(&mut self) -> Result<(), DecreeError> {
+        let elided = self.elided();
+        let content = render(&self.head, self.tail.make_contiguous(), elided);
+        atomic::write(&self.path, &content)?;
+        self.file = OpenOptions::new().append(true).open(&self.path)?;
+        self.len = content.len() as u64;
+        self.since_compact = 0;
+        Ok(())
+    }
+
+    fn write_full(&mut self, chunk: &[u8]) {
+        let Some(gzip) = self.full.as_mut() else {
+            return;
+        };
+        let ok = gzip
+            .stdin
+            .as_mut()
+            .is_some_and(|stdin| stdin.write_all(chunk).is_ok());
+        if !ok {
+            eprintln!(
+                "{}: could not write {}; keeping the truncated log only",
+                color::warning("warning"),
+                full_log_path(&self.path).display()
+            );
+            let mut gzip = self.full.take().expect("checked above");
+            drop(gzip.stdin.take());
+            let _ = gzip.wait();
+            let _ = std::fs::remove_file(full_log_path(&self.path));
+        }
+    }
+}
+
+/// Copy `output` to stdout and to `log` until it closes.
+pub fn tee(mut output: impl Read, mut log: CappedLog) -> Result<(), DecreeError> {
+    let mut buf = [0u8; 8192];
+    loop {
+        let n = match output.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+        let mut stdout = std::io::stdout().lock();
+        // A closed terminal must not stop the log from being written
+        let _ = stdout.write_all(&buf[..n]).and_then(|_| stdout.flush());
+        drop(stdout);
+        log.write(&buf[..n])?;
+    }
+    log.finish()
+}
+
+/// Head and tail cut at line boundaries, joined by the elision marker.
+fn render(head: &[u8], tail: &[u8], elided: u64) -> Vec<u8> {
+    let head_end = head
+        .iter()
+        .rposition(|&b| b == b'\n')
+        .map_or(head.len(), |i| i + 1);
+    let tail_start = tail
+        .iter()
+        .position(|&b| b == b'\n')
+        .filter(|&i| i + 1 < tail.len())
+        .map_or(0, |i| i + 1);
+    let elided = elided + (head.len() - head_end + tail_start) as u64;
+
+    let mut content = head[..head_end].to_vec();
+    if content.last().is_some_and(|&b| b != b'\n') {
+        content.push(b'\n');
+    }
+    content.extend_from_slice(format!("[decree] ... {elided} bytes elided ...\n").as_bytes());
+    content.extend_from_slice(&tail[tail_start..]);
+    content
+}
+
+/// Start `gzip -c > path`, or warn and go without a full copy.
+fn start_gzip(path: &Path) -> Option<Child> {
+    let spawned = File::create(path).and_then(|out| {
+        Command::new("gzip")
+            .arg("-c")
+            .stdin(Stdio::piped())
+            .stdout(out)
+            .stderr(Stdio::null())
+            .spawn()
+    });
+    match spawned {
+        Ok(child) => Some(child),
+        Err(e) => {
+            eprintln!(
+                "{}: could not start gzip for {}: {e}",
+                color::warning("warning"),
+                path.display()
+            );
+            let _ = std::fs::remove_file(path);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn limits(max_size: u64, head_size: u64) -> Limits {
+        Limits {
+            max_size,
+            head_size,
+            keep_full: false,
+        }
+    }
+
+    fn write_lines(path: &Path, limits: Limits, count: usize) {
+        let mut log = CappedLog::open(path, limits).unwrap();
+        for i in 0..count {
+            log.write(format!("line {i:04}\n").as_bytes()).unwrap();
+        }
+        log.finish().unwrap();
+    }
+
+    #[test]
+    fn test_disabled_keeps_everything() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("routine.log");
+        write_lines(&path, limits(0, 100), 500);
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 500 * 10);
+    }
+
+    #[test]
+    fn test_under_limit_untouched() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("routine.log");
+        std::fs::write(&path, "[decree] start 2026-01-01T00:00:00\n").unwrap();
+        write_lines(&path, limits(1000, 100), 3);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "[decree] start 2026-01-01T00:00:00\nline 0000\nline 0001\nline 0002\n"
+        );
+    }
+
+    #[test]
+    fn test_keeps_head_and_tail_on_line_boundaries() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("routine.log");
+        std::fs::write(&path, "[decree] start 2026-01-01T00:00:00\n").unwrap();
+        write_lines(&path, limits(200, 60), 1000);
+
+        let log = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = log.lines().collect();
+        assert_eq!(lines[0], "[decree] start 2026-01-01T00:00:00");
+        assert_eq!(lines[1], "line 0000");
+        assert_eq!(lines[2], "line 0001");
+        assert_eq!(lines[3], "[decree] ... 9850 bytes elided ...");
+        assert_eq!(lines[4], "line 0987");
+        assert_eq!(*lines.last().unwrap(), "line 0999");
+        // Every byte is either kept or counted as elided
+        let kept = log.len() - lines[3].len() - 1;
+        assert_eq!(kept + 9850, 35 + 1000 * 10);
+    }
+
+    #[test]
+    fn test_compacts_while_streaming() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("routine.log");
+        let limits = limits(1000, 200);
+        let mut log = CappedLog::open(&path, limits).unwrap();
+        for i in 0..10_000 {
+            log.write(format!("line {i:04}\n").as_bytes()).unwrap();
+            let on_disk = std::fs::metadata(&path).unwrap().len();
+            assert!(on_disk <= 1000 + 400 + 64, "log grew to {on_disk} bytes");
+        }
+        log.finish().unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with("line 0000\n"));
+        assert!(content.contains(" bytes elided ...\n"));
+        assert!(content.ends_with("line 9999\n"));
+    }
+
+    #[test]
+    fn test_single_huge_line() {
+        let head = b"x".repeat(30);
+        let tail = b"y".repeat(40);
+        let content = String::from_utf8(render(&head, &tail, 500)).unwrap();
+        assert_eq!(
+            content,
+            format!(
+                "{}\n[decree] ... 500 bytes elided ...\n{}",
+                "x".repeat(30),
+                "y".repeat(40)
+            )
+        );
+    }
+
+    #[test]
+    fn test_keep_full_log() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("routine.log");
+        let full = full_log_path(&path);
+        assert_eq!(full, dir.path().join("routine.log.gz"));
+
+        let keep = Limits {
+            keep_full: true,
+            ..limits(200, 60)
+        };
+        write_lines(&path, keep, 3);
+        assert!(!full.exists(), "nothing was elided, so no copy is kept");
+
+        write_lines(&path, keep, 1000);
+        let output = Command::new("gzip").arg("-dc").arg(&full).output().unwrap();
+        let text = String::from_utf8(output.stdout).unwrap();
+        assert!(text.starts_with("line 0000\nline 0001\nline 0002\nline 0000\n"));
+        assert!(text.contains("line 0500\n"));
+        assert!(text.ends_with("line 0999\n"));
+    }
+}
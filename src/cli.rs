@@ -37,31 +37,199 @@ pub enum Command {
     Prompt {
         /// Prompt template name
         name: Option<String>,
+        /// Print the rendered prompt to stdout
+        #[arg(long, requires = "name", conflicts_with_all = ["out", "ai"])]
+        print: bool,
+        /// Write the rendered prompt to FILE
+        #[arg(long, value_name = "FILE", requires = "name", conflicts_with = "ai")]
+        out: Option<String>,
+        /// Send the rendered prompt to the AI backend, as `decree ai` does
+        #[arg(long, requires = "name")]
+        ai: bool,
+        /// AI profile to use with --ai (defaults to ai.default)
+        #[arg(long, requires = "ai")]
+        profile: Option<String>,
+        /// Extra template variable (repeatable)
+        #[arg(long = "var", value_name = "KEY=VALUE")]
+        vars: Vec<String>,
     },
 
     /// List routines or show routine detail
     Routine {
         /// Routine name to show detail for
         name: Option<String>,
+        /// Parameter value, skipping the guided flow (repeatable)
+        #[arg(long = "param", value_name = "KEY=VALUE", requires = "name")]
+        params: Vec<String>,
+        /// Message body
+        #[arg(long, requires = "name", conflicts_with = "body_file")]
+        body: Option<String>,
+        /// Read the message body from FILE (`-` for stdin)
+        #[arg(long, value_name = "FILE", requires = "name")]
+        body_file: Option<String>,
+        /// Run without asking for confirmation
+        #[arg(long, short = 'y', requires = "name")]
+        yes: bool,
+        /// Queue the message in the inbox for the daemon instead of running it
+        #[arg(long, requires = "name")]
+        detach: bool,
     },
 
     /// Run all routine pre-checks
     Verify,
 
+    /// Route parked messages or explain how a message is routed
+    Route {
+        /// ID of a message in inbox/needs-routing/ (omit to list them)
+        id: Option<String>,
+        /// Routine to run the message with
+        routine: Option<String>,
+        /// Message file (path, inbox filename, or ID) to explain
+        #[arg(long, value_name = "FILE", conflicts_with_all = ["id", "routine"])]
+        explain: Option<String>,
+    },
+
+    /// Run a prompt through the configured AI backend
+    Ai {
+        /// Prompt text (read from stdin if omitted)
+        prompt: Option<String>,
+        /// AI profile to use (defaults to ai.default)
+        #[arg(long)]
+        profile: Option<String>,
+        /// Only check that the backend is installed
+        #[arg(long, conflicts_with = "prompt")]
+        check: bool,
+    },
+
+    /// Submit a message to the inbox
+    #[command(alias = "enqueue")]
+    Send {
+        /// Message body (read from --file or stdin if omitted)
+        body: Option<String>,
+        /// Routine to run (routed when processed if omitted)
+        #[arg(long)]
+        routine: Option<String>,
+        /// Frontmatter field (repeatable)
+        #[arg(long = "field", value_name = "KEY=VALUE")]
+        fields: Vec<String>,
+        /// Read the body from FILE (`-` for stdin)
+        #[arg(long, value_name = "FILE", conflicts_with = "body")]
+        file: Option<String>,
+        /// Chain name (defaults to the routine, or "message")
+        #[arg(long)]
+        name: Option<String>,
+    },
+
+    /// Approve a message awaiting approval (omit ID to list them)
+    Approve {
+        /// ID of a message in inbox/pending-approval/
+        id: Option<String>,
+    },
+
+    /// Reject a message awaiting approval and dead-letter it
+    Reject {
+        /// ID of a message in inbox/pending-approval/
+        id: String,
+        /// Why the message was rejected (recorded with the dead letter)
+        #[arg(long)]
+        reason: Option<String>,
+    },
+
     /// Daemon: monitor inbox + cron
     Daemon {
         /// Polling interval in seconds
         #[arg(long, default_value = "2")]
         interval: u64,
+        /// Serve the HTTP API on this address (e.g. 127.0.0.1:8787)
+        #[arg(long, value_name = "ADDR")]
+        listen: Option<String>,
+    },
+
+    /// Inspect, validate, or trigger cron jobs
+    Cron {
+        #[command(subcommand)]
+        action: CronAction,
     },
 
     /// Show progress
     Status,
 
+    /// Show AI usage and cost totals
+    Usage {
+        /// Only count usage since a duration ago (12h, 7d, 2w) or a date (YYYY-MM-DD)
+        #[arg(long)]
+        since: Option<String>,
+        /// Group totals by routine, migration, chain, backend, or day
+        #[arg(long, default_value = "routine")]
+        by: String,
+    },
+
+    /// Show the journal of state transitions (.decree/events.jsonl)
+    Events {
+        /// Only events for this message ID or chain (prefix)
+        id: Option<String>,
+        /// Keep printing events as they happen
+        #[arg(short, long)]
+        follow: bool,
+        /// Only show the last N events
+        #[arg(short = 'n', long, value_name = "N")]
+        lines: Option<usize>,
+        /// Print raw JSON lines
+        #[arg(long)]
+        json: bool,
+    },
+
     /// Show execution log
     Log {
         /// Message ID (full, chain, or prefix)
         id: Option<String>,
+        /// Stream output live until the message finishes (default: the running one)
+        #[arg(
+            short,
+            long,
+            conflicts_with_all = ["grep", "attempt", "tail", "since", "until", "routine", "disposition", "migration"]
+        )]
+        follow: bool,
+        /// Show only lines matching REGEX, searching every run unless an ID is given
+        #[arg(long, value_name = "REGEX")]
+        grep: Option<String>,
+        /// Lines of context around --grep matches
+        #[arg(short = 'C', long, value_name = "N", default_value = "2", requires = "grep")]
+        context: usize,
+        /// Only this attempt's log (1 = routine.log, 2 = routine-2.log, ...)
+        #[arg(long, value_name = "N")]
+        attempt: Option<u32>,
+        /// Only the last N lines of each log
+        #[arg(long, value_name = "N")]
+        tail: Option<usize>,
+        /// Only runs started since a duration ago (12h, 7d, 2w) or a date (YYYY-MM-DD)
+        #[arg(long)]
+        since: Option<String>,
+        /// Only runs started before a duration ago or a date
+        #[arg(long)]
+        until: Option<String>,
+        /// Only runs of this routine
+        #[arg(long)]
+        routine: Option<String>,
+        /// Only runs that ended up running, done, skipped, dead, needs_routing, or awaiting_approval
+        #[arg(long)]
+        disposition: Option<String>,
+        /// Only runs of this migration (e.g. 01-setup or 01-setup.md)
+        #[arg(long)]
+        migration: Option<String>,
+    },
+
+    /// Prune old runs according to the `retention` policy in config.yml
+    Gc {
+        /// List what would be pruned without removing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// Keep the newest N runs (overrides retention.keep_runs)
+        #[arg(long, value_name = "N")]
+        keep_runs: Option<usize>,
+        /// Keep runs from the last N days (overrides retention.keep_days)
+        #[arg(long, value_name = "N")]
+        keep_days: Option<u32>,
     },
 
     /// Sync routine registry with filesystem
@@ -75,3 +243,18 @@ pub enum Command {
     /// Verbose help
     Help,
 }
+
+#[derive(Subcommand, Debug)]
+pub enum CronAction {
+    /// List cron jobs with their next fire times
+    List,
+
+    /// Validate cron files, exit non-zero on errors
+    Check,
+
+    /// Enqueue a cron job immediately
+    Run {
+        /// Cron file name (with or without .md)
+        name: String,
+    },
+}
@@ -0,0 +1,102 @@
+use crate::ai::{self, Backend};
+use crate::config::AppConfig;
+use crate::error::{color, DecreeError};
+use crate::usage::{self, UsageRecord};
+use std::io::{IsTerminal, Read};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Run `decree ai [--profile NAME] [--check] [PROMPT]`.
+///
+/// Sends the prompt (or stdin, if no prompt is given) to the configured AI
+/// backend, with its output going straight to ours. `--check` only verifies
+/// that the backend (or one of its fallbacks) is installed, for use in
+/// routine pre-checks.
+///
+/// Without `--profile`, a routine's `DECREE_AI` picks the profile, so the
+/// message's `ai` field and the routine's registry entry apply. When a
+/// backend is missing or hits a quota error, the `ai.fallback` profiles are
+/// tried in order.
+///
+/// Inside a routine (`message_dir` set), each call is appended to the run's
+/// `usage.jsonl`, with token counts and cost estimated from the prompt and
+/// output size and the profile's `pricing`.
+pub fn run(
+    project_root: &Path,
+    prompt: Option<&str>,
+    profile: Option<&str>,
+    check: bool,
+) -> Result<(), DecreeError> {
+    let config = AppConfig::load_from_project(project_root)?;
+    let from_env = std::env::var(ai::DECREE_AI_ENV).ok().filter(|p| !p.is_empty());
+    let backends = ai::candidates(&config, profile.or(from_env.as_deref()))?;
+
+    if check {
+        return match backends.iter().find(|b| b.check().is_ok()) {
+            Some(_) => Ok(()),
+            None => backends[0].check(),
+        };
+    }
+
+    let prompt = match prompt {
+        Some(prompt) => prompt.to_string(),
+        None if std::io::stdin().is_terminal() => {
+            return Err(DecreeError::Other(
+                "no prompt given (pass it as an argument or on stdin)".into(),
+            ));
+        }
+        None => {
+            let mut prompt = String::new();
+            std::io::stdin().read_to_string(&mut prompt)?;
+            prompt
+        }
+    };
+
+    let run_dir = std::env::var_os("message_dir")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_dir());
+
+    ai::with_fallback(
+        &backends,
+        |backend| {
+            let start = Instant::now();
+            let result = backend.run_inherited(&prompt);
+            if let Some(run_dir) = &run_dir {
+                let output_len = result.as_ref().ok().copied().unwrap_or(0);
+                let record = usage_record(backend, prompt.len() as u64, output_len, start.elapsed());
+                if let Err(e) = usage::append(run_dir, &record) {
+                    eprintln!("{}: could not record usage: {e}", color::warning("warning"));
+                }
+            }
+            result.map(|_| ())
+        },
+        |backend, e| {
+            let next = backends
+                .iter()
+                .skip_while(|b| b.name != backend.name)
+                .nth(1)
+                .map(|b| b.name.as_str())
+                .unwrap_or_default();
+            eprintln!("{}: {e}; trying '{next}'", color::warning("warning"));
+        },
+    )
+}
+
+/// An estimated usage record for one `decree ai` call.
+fn usage_record(backend: &Backend, prompt_len: u64, output_len: u64, elapsed: Duration) -> UsageRecord {
+    let input_tokens = usage::estimate_tokens(prompt_len);
+    let output_tokens = usage::estimate_tokens(output_len);
+    let cost = backend.pricing().map(|p| {
+        (input_tokens as f64 * p.input + output_tokens as f64 * p.output) / 1_000_000.0
+    });
+    UsageRecord {
+        recorded_at: Some(chrono::Local::now().to_rfc3339()),
+        backend: backend.name.clone(),
+        model: backend.model().map(str::to_string),
+        input_tokens: Some(input_tokens),
+        output_tokens: Some(output_tokens),
+        cost,
+        duration_secs: Some(elapsed.as_secs_f64()),
+        estimated: true,
+    }
+}
@@ -0,0 +1,865 @@
+//! Local HTTP API served by `decree daemon --listen ADDR`.
+//!
+//! A small HTTP/1.1 server on a background thread lets other services queue
+//! work and check on it without access to `.decree/`:
+//!
+//! - `POST /messages` — queue a message (JSON fields + `body`, or markdown
+//!   with frontmatter)
+//! - `POST /webhooks/<name>` — queue any payload as a message body
+//! - `GET /status`, `GET /runs`, `GET /dead`
+//! - `GET /messages/<id>`, `GET /messages/<id>/log`
+//! - `POST /dead/<id>/requeue`
+//!
+//! When `DECREE_API_TOKEN` is set, every request must carry it as
+//! `Authorization: Bearer <token>` or `X-Decree-Token: <token>`.
+
+use super::{log, send, status};
+use crate::config::{self, AppConfig};
+use crate::error::DecreeError;
+use crate::message;
+use crate::usage;
+use serde_json::{json, Value as Json};
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::Path;
+use std::time::Duration;
+
+/// Environment variable holding the shared secret clients must send.
+pub const TOKEN_ENV: &str = "DECREE_API_TOKEN";
+
+/// Largest request body accepted.
+const MAX_BODY: usize = 1024 * 1024;
+
+/// Largest request line plus headers accepted.
+const MAX_HEAD: usize = 16 * 1024;
+
+/// Runs listed by `GET /runs` unless `?limit=` says otherwise.
+const DEFAULT_RUN_LIMIT: usize = 20;
+
+/// Bind `addr` and serve the API on a background thread.
+///
+/// Returns the bound address, so port 0 can be used. Without a token only
+/// loopback addresses are allowed. Requests are handled one at a time, so
+/// two messages queued together never race for the same chain ID.
+pub fn spawn(
+    project_root: &Path,
+    addr: &str,
+    token: Option<String>,
+) -> Result<SocketAddr, DecreeError> {
+    let listener = TcpListener::bind(addr)
+        .map_err(|e| DecreeError::Other(format!("cannot listen on {addr}: {e}")))?;
+    let local = listener.local_addr()?;
+    if token.is_none() && !local.ip().is_loopback() {
+        return Err(DecreeError::Other(format!(
+            "refusing to listen on {local} without {TOKEN_ENV} set"
+        )));
+    }
+
+    let root = project_root.to_path_buf();
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if let Err(e) = serve_connection(&root, token.as_deref(), stream) {
+                eprintln!("decree daemon: api connection failed: {e}");
+            }
+        }
+    });
+    Ok(local)
+}
+
+/// Read one request from the connection, answer it, and close.
+fn serve_connection(project_root: &Path, token: Option<&str>, stream: TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+    let mut reader = BufReader::new(&stream);
+    let response = match read_request(&mut reader, &mut &stream) {
+        Ok(request) => handle(project_root, token, &request),
+        Err(response) => response,
+    };
+    response.write_to(&mut &stream)
+}
+
+// =================================================================
+// HTTP plumbing
+// =================================================================
+
+#[derive(Debug)]
+struct Request {
+    method: String,
+    path: String,
+    query: BTreeMap<String, String>,
+    /// Header names are lowercased.
+    headers: BTreeMap<String, String>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(String::as_str)
+    }
+}
+
+#[derive(Debug)]
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn json(status: u16, value: Json) -> Self {
+        let mut body = serde_json::to_vec_pretty(&value).unwrap_or_default();
+        body.push(b'\n');
+        Self {
+            status,
+            content_type: "application/json",
+            body,
+        }
+    }
+
+    fn text(body: String) -> Self {
+        Self {
+            status: 200,
+            content_type: "text/plain; charset=utf-8",
+            body: body.into_bytes(),
+        }
+    }
+
+    fn error(status: u16, message: impl Into<String>) -> Self {
+        Self::json(status, json!({ "error": message.into() }))
+    }
+
+    fn write_to(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(
+            out,
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+            self.status,
+            reason_phrase(self.status),
+            self.content_type,
+            self.body.len()
+        )?;
+        if self.status == 401 {
+            write!(out, "WWW-Authenticate: Bearer\r\n")?;
+        }
+        write!(out, "\r\n")?;
+        out.write_all(&self.body)?;
+        out.flush()
+    }
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        411 => "Length Required",
+        413 => "Payload Too Large",
+        431 => "Request Header Fields Too Large",
+        _ => "Internal Server Error",
+    }
+}
+
+/// Parse a request. Problems with the request itself come back as the
+/// response to send. `writer` receives `100 Continue` when asked for.
+fn read_request(reader: &mut impl BufRead, writer: &mut impl Write) -> Result<Request, Response> {
+    let malformed = |_| Response::error(400, "malformed request");
+
+    let mut lines = Vec::new();
+    let mut head_len = 0;
+    loop {
+        let mut line = String::new();
+        let n = reader
+            .by_ref()
+            .take((MAX_HEAD - head_len + 1) as u64)
+            .read_line(&mut line)
+            .map_err(malformed)?;
+        if n == 0 {
+            return Err(Response::error(400, "incomplete request"));
+        }
+        head_len += n;
+        if head_len > MAX_HEAD {
+            return Err(Response::error(431, "request headers too large"));
+        }
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line.is_empty() {
+            break;
+        }
+        lines.push(line.to_string());
+    }
+
+    let mut request_line = lines
+        .first()
+        .map(|l| l.split_whitespace())
+        .into_iter()
+        .flatten();
+    let (Some(method), Some(target)) = (request_line.next(), request_line.next()) else {
+        return Err(Response::error(400, "malformed request line"));
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+    let mut headers = BTreeMap::new();
+    for line in lines.iter().skip(1) {
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+        }
+    }
+
+    if headers.contains_key("transfer-encoding") {
+        return Err(Response::error(411, "send the body with Content-Length"));
+    }
+    let length = match headers.get("content-length") {
+        Some(value) => value
+            .parse::<usize>()
+            .map_err(|_| Response::error(400, "invalid Content-Length"))?,
+        None => 0,
+    };
+    if length > MAX_BODY {
+        return Err(Response::error(
+            413,
+            format!("body larger than {MAX_BODY} bytes"),
+        ));
+    }
+    if headers
+        .get("expect")
+        .is_some_and(|v| v.eq_ignore_ascii_case("100-continue"))
+    {
+        writer
+            .write_all(b"HTTP/1.1 100 Continue\r\n\r\n")
+            .map_err(malformed)?;
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).map_err(malformed)?;
+
+    Ok(Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        query: parse_query(query),
+        headers,
+        body,
+    })
+}
+
+fn parse_query(query: &str) -> BTreeMap<String, String> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key, true), percent_decode(value, true))
+        })
+        .collect()
+}
+
+/// Decode `%XX` escapes (and `+` as a space in query strings).
+fn percent_decode(s: &str, plus_as_space: bool) -> String {
+    let bytes = s.as_bytes();
+    let hex = |i: usize| bytes.get(i).and_then(|&b| (b as char).to_digit(16));
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match (bytes[i], hex(i + 1), hex(i + 2)) {
+            (b'%', Some(high), Some(low)) => {
+                out.push((high * 16 + low) as u8);
+                i += 3;
+                continue;
+            }
+            (b'+', _, _) if plus_as_space => out.push(b' '),
+            (byte, _, _) => out.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+// =================================================================
+// Endpoints
+// =================================================================
+
+/// Route a request to its endpoint.
+fn handle(project_root: &Path, token: Option<&str>, request: &Request) -> Response {
+    if let Some(token) = token {
+        if !authorized(request, token) {
+            return Response::error(401, "missing or invalid token");
+        }
+    }
+
+    let segments: Vec<String> = request
+        .path
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .map(|s| percent_decode(s, false))
+        .collect();
+    let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+
+    let result = match (request.method.as_str(), segments.as_slice()) {
+        ("GET", ["status"]) => get_status(project_root),
+        ("GET", ["runs"]) => get_runs(project_root, request),
+        ("GET", ["dead"]) => get_dead(project_root),
+        ("POST", ["messages"]) => post_message(project_root, request),
+        ("POST", ["webhooks", name]) => post_webhook(project_root, name, request),
+        ("GET", ["messages", id]) => checked_id(id).and_then(|id| get_message(project_root, id)),
+        ("GET", ["messages", id, "log"]) => checked_id(id).and_then(|id| get_log(project_root, id)),
+        ("POST", ["dead", id, "requeue"]) => {
+            checked_id(id).and_then(|id| requeue(project_root, id))
+        }
+        _ => {
+            return Response::error(
+                404,
+                format!("no endpoint for {} {}", request.method, request.path),
+            )
+        }
+    };
+
+    result.unwrap_or_else(|e| {
+        let status = match e {
+            DecreeError::MessageNotFound(_) => 404,
+            DecreeError::Io(_) | DecreeError::Yaml(_) => 500,
+            _ => 400,
+        };
+        Response::error(status, e.to_string())
+    })
+}
+
+/// Whether the request carries `token`, compared in constant time.
+fn authorized(request: &Request, token: &str) -> bool {
+    let given = request
+        .header("authorization")
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .or_else(|| request.header("x-decree-token"));
+    given.is_some_and(|given| {
+        given.len() == token.len()
+            && given
+                .bytes()
+                .zip(token.bytes())
+                .fold(0, |diff, (a, b)| diff | (a ^ b))
+                == 0
+    })
+}
+
+/// Reject IDs that could reach outside `.decree/`.
+fn checked_id(id: &str) -> Result<&str, DecreeError> {
+    if id.is_empty() || id.starts_with('.') || id.contains(['/', '\\']) {
+        return Err(DecreeError::Other(format!("invalid message ID '{id}'")));
+    }
+    Ok(id)
+}
+
+fn get_status(project_root: &Path) -> Result<Response, DecreeError> {
+    let summary = status::summary(project_root)?;
+    Ok(Response::json(
+        200,
+        json!({
+            "migrations": {
+                "processed": summary.migrations_processed,
+                "total": summary.migrations_total,
+                "next": summary.next_migration,
+            },
+            "inbox": {
+                "pending": summary.pending,
+                "dead": summary.dead,
+                "needs_routing": summary.needs_routing,
+                "pending_approval": summary.pending_approval,
+            },
+        }),
+    ))
+}
+
+/// The most recent runs, oldest first, with their state.
+fn get_runs(project_root: &Path, request: &Request) -> Result<Response, DecreeError> {
+    let limit = match request.query.get("limit") {
+        Some(limit) => limit
+            .parse::<usize>()
+            .map_err(|_| DecreeError::Other(format!("invalid limit '{limit}'")))?,
+        None => DEFAULT_RUN_LIMIT,
+    };
+    let runs = message::list_runs(project_root)?;
+    let recent = &runs[runs.len().saturating_sub(limit)..];
+    let runs: Vec<Json> = recent
+        .iter()
+        .map(|id| json!({ "id": id, "state": log::message_state(project_root, id) }))
+        .collect();
+    Ok(Response::json(200, Json::Array(runs)))
+}
+
+fn get_dead(project_root: &Path) -> Result<Response, DecreeError> {
+    let dead: Vec<Json> = message::read_messages_in(&message::dead_dir(project_root))?
+        .into_iter()
+        .map(|m| {
+            json!({
+                "id": m.id.unwrap_or_else(|| m.filename.trim_end_matches(".md").to_string()),
+                "routine": m.routine,
+            })
+        })
+        .collect();
+    Ok(Response::json(200, Json::Array(dead)))
+}
+
+/// Queue a message given as JSON fields plus `body`, or as markdown with
+/// frontmatter. `?name=` names the chain.
+fn post_message(project_root: &Path, request: &Request) -> Result<Response, DecreeError> {
+    let text = body_text(request)?;
+    let is_json = request
+        .header("content-type")
+        .is_some_and(|t| t.starts_with("application/json"));
+
+    let (mut fields, body) = if is_json {
+        let Json::Object(mut object) = serde_json::from_str(&text)
+            .map_err(|e| DecreeError::Other(format!("invalid JSON: {e}")))?
+        else {
+            return Err(DecreeError::Other("expected a JSON object".into()));
+        };
+        let body = match object.remove("body") {
+            Some(Json::String(body)) => body,
+            _ => return Err(DecreeError::Other("\"body\" must be a string".into())),
+        };
+        let fields = object
+            .into_iter()
+            .map(|(key, value)| Ok((key, serde_yaml::to_value(value)?)))
+            .collect::<Result<BTreeMap<_, _>, DecreeError>>()?;
+        (fields, body)
+    } else {
+        message::parse_frontmatter(&text)?
+    };
+
+    let routine = match fields.remove("routine") {
+        Some(serde_yaml::Value::String(routine)) => Some(routine),
+        Some(_) => return Err(DecreeError::Other("\"routine\" must be a string".into())),
+        None => None,
+    };
+    enqueue(
+        project_root,
+        routine.as_deref(),
+        fields,
+        body,
+        request.query.get("name").map(String::as_str),
+    )
+}
+
+/// Queue the raw payload as a message body. Query parameters become
+/// frontmatter fields (`routine` picks the routine), and a `webhook`
+/// field records `name`, which also names the chain.
+fn post_webhook(
+    project_root: &Path,
+    name: &str,
+    request: &Request,
+) -> Result<Response, DecreeError> {
+    let body = body_text(request)?;
+    let mut query = request.query.clone();
+    let routine = query.remove("routine");
+    let mut fields: BTreeMap<String, serde_yaml::Value> = query
+        .into_iter()
+        .map(|(key, value)| (key, serde_yaml::Value::String(value)))
+        .collect();
+    fields.insert(
+        "webhook".into(),
+        serde_yaml::Value::String(name.to_string()),
+    );
+    enqueue(project_root, routine.as_deref(), fields, body, Some(name))
+}
+
+fn enqueue(
+    project_root: &Path,
+    routine: Option<&str>,
+    fields: BTreeMap<String, serde_yaml::Value>,
+    body: String,
+    name: Option<&str>,
+) -> Result<Response, DecreeError> {
+    if let Some(reason) = fields.keys().find_map(|key| send::reserved_field(key)) {
+        return Err(DecreeError::Other(format!("field {reason}")));
+    }
+    if let Some(routine) = routine {
+        let config = AppConfig::load_from_project(project_root)?;
+        send::check_routine(project_root, &config, routine)?;
+    }
+    let id = send::enqueue(project_root, routine, fields, body, name)?;
+    Ok(Response::json(201, json!({ "id": id, "state": "queued" })))
+}
+
+fn body_text(request: &Request) -> Result<String, DecreeError> {
+    String::from_utf8(request.body.clone())
+        .map_err(|_| DecreeError::Other("request body is not UTF-8".into()))
+}
+
+/// State, log files, and usage summary of one message.
+fn get_message(project_root: &Path, id: &str) -> Result<Response, DecreeError> {
+    let state = log::message_state(project_root, id)
+        .ok_or_else(|| DecreeError::MessageNotFound(id.to_string()))?;
+    let run_dir = project_root
+        .join(config::DECREE_DIR)
+        .join(config::RUNS_DIR)
+        .join(id);
+    let (logs, run) = if run_dir.is_dir() {
+        let run = std::fs::read_to_string(run_dir.join(usage::RUN_SUMMARY_FILE))
+            .ok()
+            .and_then(|content| serde_json::from_str::<Json>(&content).ok());
+        (log::run_logs(project_root, id)?, run)
+    } else {
+        (Vec::new(), None)
+    };
+    Ok(Response::json(
+        200,
+        json!({ "id": id, "state": state, "logs": logs, "run": run }),
+    ))
+}
+
+/// Every log file of a run, each under a `=== file ===` header.
+fn get_log(project_root: &Path, id: &str) -> Result<Response, DecreeError> {
+    let run_dir = project_root
+        .join(config::DECREE_DIR)
+        .join(config::RUNS_DIR)
+        .join(id);
+    let mut out = String::new();
+    for log_name in log::run_logs(project_root, id)? {
+        let content = std::fs::read_to_string(run_dir.join(&log_name))?;
+        out.push_str(&format!("=== {log_name} ===\n{content}"));
+        if !content.ends_with('\n') {
+            out.push('\n');
+        }
+    }
+    Ok(Response::text(out))
+}
+
+fn requeue(project_root: &Path, id: &str) -> Result<Response, DecreeError> {
+    let msg = message::requeue_dead(project_root, id)?;
+    let id = msg
+        .id
+        .unwrap_or_else(|| msg.filename.trim_end_matches(".md").to_string());
+    Ok(Response::json(200, json!({ "id": id, "state": "queued" })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use tempfile::TempDir;
+
+    fn setup_project() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        let decree = dir.path().join(".decree");
+        for sub in ["inbox", "runs", "routines", "migrations"] {
+            std::fs::create_dir_all(decree.join(sub)).unwrap();
+        }
+        std::fs::write(
+            decree.join("config.yml"),
+            "commands:\n  ai_router: test\n  ai_interactive: test\n",
+        )
+        .unwrap();
+        std::fs::write(
+            decree.join("routines/develop.sh"),
+            "#!/usr/bin/env bash\n# Develop\nset -euo pipefail\n",
+        )
+        .unwrap();
+        dir
+    }
+
+    fn request(method: &str, target: &str, headers: &[(&str, &str)], body: &str) -> Request {
+        let mut raw = format!("{method} {target} HTTP/1.1\r\nHost: localhost\r\n");
+        for (name, value) in headers {
+            raw.push_str(&format!("{name}: {value}\r\n"));
+        }
+        raw.push_str(&format!("Content-Length: {}\r\n\r\n{body}", body.len()));
+        read_request(&mut Cursor::new(raw.into_bytes()), &mut Vec::new()).unwrap()
+    }
+
+    fn json_body(response: &Response) -> Json {
+        serde_json::from_slice(&response.body).unwrap()
+    }
+
+    #[test]
+    fn test_read_request() {
+        let raw = "POST /webhooks/jira?routine=develop&label=needs%20work HTTP/1.1\r\n\
+                   Content-Type: application/json\r\nExpect: 100-continue\r\n\
+                   Content-Length: 2\r\n\r\n{}";
+        let mut interim = Vec::new();
+        let req = read_request(&mut Cursor::new(raw.as_bytes()), &mut interim).unwrap();
+        assert_eq!(req.method, "POST");
+        assert_eq!(req.path, "/webhooks/jira");
+        assert_eq!(req.query["routine"], "develop");
+        assert_eq!(req.query["label"], "needs work");
+        assert_eq!(req.header("content-type"), Some("application/json"));
+        assert_eq!(req.body, b"{}");
+        assert_eq!(interim, b"HTTP/1.1 100 Continue\r\n\r\n");
+    }
+
+    #[test]
+    fn test_read_request_rejects_bad_input() {
+        let status = |raw: &str| {
+            read_request(&mut Cursor::new(raw.as_bytes()), &mut Vec::new())
+                .unwrap_err()
+                .status
+        };
+        assert_eq!(status("GET /status HTTP/1.1\r\n"), 400);
+        assert_eq!(status("\r\n\r\n"), 400);
+        assert_eq!(
+            status("POST /messages HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n"),
+            411
+        );
+        assert_eq!(
+            status(&format!(
+                "POST /messages HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+                MAX_BODY + 1
+            )),
+            413
+        );
+        let huge = format!(
+            "GET /status HTTP/1.1\r\nX-Pad: {}\r\n\r\n",
+            "a".repeat(MAX_HEAD)
+        );
+        assert_eq!(status(&huge), 431);
+    }
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("a%2Fb+c", true), "a/b c");
+        assert_eq!(percent_decode("a+b%zz%", false), "a+b%zz%");
+        assert_eq!(percent_decode("caf%C3%A9", false), "café");
+    }
+
+    #[test]
+    fn test_post_message_and_get_state() {
+        let dir = setup_project();
+        let root = dir.path();
+
+        let body = r#"{"routine": "develop", "ticket": "ABC-1", "priority": 2, "body": "Fix it."}"#;
+        let post = request(
+            "POST",
+            "/messages?name=abc-1",
+            &[("Content-Type", "application/json")],
+            body,
+        );
+        let response = handle(root, None, &post);
+        assert_eq!(response.status, 201);
+        let id = json_body(&response)["id"].as_str().unwrap().to_string();
+        assert!(id.ends_with("-abc-1-0"), "{id}");
+
+        let content = std::fs::read_to_string(root.join(format!(".decree/inbox/{id}.md"))).unwrap();
+        assert!(content.contains("routine: develop"), "{content}");
+        assert!(content.contains("ticket: ABC-1"), "{content}");
+        assert!(content.contains("priority: 2"), "{content}");
+        assert!(content.ends_with("---\nFix it."), "{content}");
+
+        let response = handle(
+            root,
+            None,
+            &request("GET", &format!("/messages/{id}"), &[], ""),
+        );
+        assert_eq!(response.status, 200);
+        assert_eq!(json_body(&response)["state"], "queued");
+
+        let response = handle(root, None, &request("GET", "/status", &[], ""));
+        assert_eq!(json_body(&response)["inbox"]["pending"], 1);
+    }
+
+    #[test]
+    fn test_post_markdown_message() {
+        let dir = setup_project();
+        let post = request(
+            "POST",
+            "/messages",
+            &[],
+            "---\nroutine: develop\n---\nFrom markdown.\n",
+        );
+        let response = handle(dir.path(), None, &post);
+        assert_eq!(response.status, 201);
+        let id = json_body(&response)["id"].as_str().unwrap().to_string();
+        assert!(id.ends_with("-develop-0"), "{id}");
+    }
+
+    #[test]
+    fn test_post_message_errors() {
+        let dir = setup_project();
+        let root = dir.path();
+        let json = [("Content-Type", "application/json")];
+
+        let response = handle(
+            root,
+            None,
+            &request(
+                "POST",
+                "/messages",
+                &json,
+                r#"{"routine": "nope", "body": "x"}"#,
+            ),
+        );
+        assert_eq!(response.status, 400);
+        assert!(json_body(&response)["error"]
+            .as_str()
+            .unwrap()
+            .contains("unknown routine 'nope'"));
+
+        let response = handle(
+            root,
+            None,
+            &request(
+                "POST",
+                "/messages",
+                &json,
+                r#"{"chain": "mine", "body": "x"}"#,
+            ),
+        );
+        assert_eq!(response.status, 400);
+        assert_eq!(
+            json_body(&response)["error"],
+            "field chain is set by decree"
+        );
+
+        let response = handle(
+            root,
+            None,
+            &request("POST", "/messages", &json, r#"{"routine": "develop"}"#),
+        );
+        assert_eq!(response.status, 400);
+
+        let response = handle(
+            root,
+            None,
+            &request("GET", "/messages/%2E%2E%2Fconfig.yml", &[], ""),
+        );
+        assert_eq!(response.status, 400);
+        let response = handle(
+            root,
+            None,
+            &request("GET", "/messages/D0001-0000-none-0", &[], ""),
+        );
+        assert_eq!(response.status, 404);
+        let response = handle(root, None, &request("DELETE", "/messages", &[], ""));
+        assert_eq!(response.status, 404);
+    }
+
+    #[test]
+    fn test_webhook() {
+        let dir = setup_project();
+        let root = dir.path();
+        let post = request(
+            "POST",
+            "/webhooks/jira?routine=develop&project=WEB",
+            &[],
+            r#"{"issue": 1}"#,
+        );
+        let response = handle(root, None, &post);
+        assert_eq!(response.status, 201);
+        let id = json_body(&response)["id"].as_str().unwrap().to_string();
+        assert!(id.ends_with("-jira-0"), "{id}");
+
+        let msg = message::InboxMessage::from_file(root, &format!("{id}.md")).unwrap();
+        assert_eq!(msg.routine.as_deref(), Some("develop"));
+        assert_eq!(msg.custom_fields["webhook"], "jira");
+        assert_eq!(msg.custom_fields["project"], "WEB");
+        assert_eq!(msg.body.trim(), r#"{"issue": 1}"#);
+    }
+
+    #[test]
+    fn test_runs_logs_and_requeue() {
+        let dir = setup_project();
+        let root = dir.path();
+        let decree = root.join(".decree");
+        let run_dir = decree.join("runs/D0001-1432-fix-0");
+        std::fs::create_dir_all(&run_dir).unwrap();
+        std::fs::write(run_dir.join("routine.log"), "failed\n").unwrap();
+        std::fs::create_dir_all(decree.join("inbox/dead")).unwrap();
+        std::fs::write(
+            decree.join("inbox/dead/D0001-1432-fix-0.md"),
+            "---\nid: D0001-1432-fix-0\nchain: D0001-1432-fix\nseq: 0\nroutine: develop\n---\nFix it.\n",
+        )
+        .unwrap();
+
+        let response = handle(root, None, &request("GET", "/runs", &[], ""));
+        assert_eq!(
+            json_body(&response),
+            json!([{ "id": "D0001-1432-fix-0", "state": "dead" }])
+        );
+        let response = handle(root, None, &request("GET", "/dead", &[], ""));
+        assert_eq!(json_body(&response)[0]["id"], "D0001-1432-fix-0");
+
+        let response = handle(
+            root,
+            None,
+            &request("GET", "/messages/D0001-1432-fix-0/log", &[], ""),
+        );
+        assert_eq!(response.status, 200);
+        assert_eq!(
+            String::from_utf8_lossy(&response.body),
+            "=== routine.log ===\nfailed\n"
+        );
+
+        let response = handle(
+            root,
+            None,
+            &request("GET", "/messages/D0001-1432-fix-0", &[], ""),
+        );
+        assert_eq!(json_body(&response)["logs"], json!(["routine.log"]));
+
+        let response = handle(
+            root,
+            None,
+            &request("POST", "/dead/D0001-1432-fix-0/requeue", &[], ""),
+        );
+        assert_eq!(response.status, 200);
+        assert!(decree.join("inbox/D0001-1432-fix-0.md").exists());
+
+        let response = handle(
+            root,
+            None,
+            &request("POST", "/dead/D0001-1432-fix-0/requeue", &[], ""),
+        );
+        assert_eq!(response.status, 404);
+    }
+
+    #[test]
+    fn test_token_required_when_set() {
+        let dir = setup_project();
+        let root = dir.path();
+
+        let response = handle(root, Some("s3cret"), &request("GET", "/status", &[], ""));
+        assert_eq!(response.status, 401);
+        let wrong = [("Authorization", "Bearer s3cre7")];
+        assert_eq!(
+            handle(root, Some("s3cret"), &request("GET", "/status", &wrong, "")).status,
+            401
+        );
+
+        let bearer = [("Authorization", "Bearer s3cret")];
+        assert_eq!(
+            handle(
+                root,
+                Some("s3cret"),
+                &request("GET", "/status", &bearer, "")
+            )
+            .status,
+            200
+        );
+        let header = [("X-Decree-Token", "s3cret")];
+        assert_eq!(
+            handle(
+                root,
+                Some("s3cret"),
+                &request("GET", "/status", &header, "")
+            )
+            .status,
+            200
+        );
+    }
+
+    #[test]
+    fn test_spawn_serves_requests() {
+        let dir = setup_project();
+        let addr = spawn(dir.path(), "127.0.0.1:0", None).unwrap();
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream
+            .write_all(b"GET /status HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{response}");
+        assert!(response.contains("\"pending\": 0"), "{response}");
+    }
+
+    #[test]
+    fn test_spawn_requires_token_off_loopback() {
+        let dir = setup_project();
+        let err = spawn(dir.path(), "0.0.0.0:0", None).unwrap_err();
+        assert!(err.to_string().contains(TOKEN_ENV), "{err}");
+    }
+}
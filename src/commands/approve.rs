@@ -0,0 +1,63 @@
+use crate::approval;
+use crate::config::{AppConfig, NotifyEvent};
+use crate::error::{color, DecreeError};
+use crate::notify::{self, Notification};
+use std::path::Path;
+
+/// Run `decree approve` with no ID: list messages awaiting approval.
+pub fn list(project_root: &Path) -> Result<(), DecreeError> {
+    let pending = approval::pending_messages(project_root)?;
+    if pending.is_empty() {
+        println!("No messages awaiting approval.");
+        return Ok(());
+    }
+
+    for msg in &pending {
+        let msg_id = msg.id.clone().unwrap_or_else(|| msg.filename.clone());
+        let routine = msg.routine.as_deref().unwrap_or("?");
+        println!("{}  {}", color::bold(&msg_id), routine);
+        if let Some(record) = approval::read_record(project_root, &msg_id) {
+            println!("  {} (since {})", record.reason, record.requested_at);
+        }
+    }
+
+    println!();
+    println!("Approve with `decree approve <id>`, or `decree reject <id> [--reason TEXT]`.");
+    Ok(())
+}
+
+/// Run `decree approve <id>`.
+///
+/// Returns the message to the inbox with `approved` set, so the chain
+/// continues on the next `decree process` (or daemon poll).
+pub fn approve(project_root: &Path, id: &str) -> Result<(), DecreeError> {
+    let msg = approval::find_pending(project_root, id)?;
+    let msg = approval::approve(project_root, msg)?;
+
+    let msg_id = msg.id.unwrap_or(msg.filename);
+    println!(
+        "{} {msg_id}; run `decree process` to continue the chain",
+        color::success("approved")
+    );
+    Ok(())
+}
+
+/// Run `decree reject <id> [--reason TEXT]`.
+///
+/// Dead-letters the message, recording the reason in its frontmatter and
+/// in the run directory's `approval.json`.
+pub fn reject(project_root: &Path, id: &str, reason: Option<&str>) -> Result<(), DecreeError> {
+    let msg = approval::find_pending(project_root, id)?;
+    let msg = approval::reject(project_root, msg, reason)?;
+
+    let config = AppConfig::load_from_project(project_root)?;
+    let notification = Notification {
+        reason: Some(format!("rejected: {}", reason.unwrap_or("no reason given"))),
+        ..Notification::for_message(NotifyEvent::DeadLettered, &msg, None)
+    };
+    notify::send(project_root, &config, &notification);
+
+    let msg_id = msg.id.unwrap_or(msg.filename);
+    println!("{} {msg_id} (moved to inbox/dead/)", color::error("rejected"));
+    Ok(())
+}
@@ -0,0 +1,147 @@
+use crate::config::{self, AppConfig};
+use crate::cron::{self, CronTimezone};
+use crate::error::{color, DecreeError};
+use crate::events::{self, Event, EventKind};
+use crate::message;
+use chrono::Utc;
+use std::path::Path;
+
+/// Number of upcoming fire times shown by `decree cron list`.
+const PREVIEW_COUNT: usize = 3;
+
+/// Run `decree cron list`.
+pub fn list(project_root: &Path) -> Result<(), DecreeError> {
+    let config = AppConfig::load_from_project(project_root)?;
+    let default_tz = cron::default_timezone(&config)?;
+    let scan = cron::scan_cron_dir(project_root)?;
+
+    if scan.files.is_empty() && scan.errors.is_empty() {
+        println!("No cron files found in .decree/cron/");
+        return Ok(());
+    }
+
+    let now = Utc::now();
+    for cf in &scan.files {
+        let tz = cf.effective_timezone(default_tz);
+        println!("{}", color::bold(&cf.name_stem));
+        println!("  schedule: {} ({tz})", cf.schedule);
+        println!(
+            "  routine:  {}",
+            cf.routine.as_deref().unwrap_or("(router)")
+        );
+        let upcoming: Vec<_> = cf
+            .occurrences_after(now, default_tz)
+            .take(PREVIEW_COUNT)
+            .collect();
+        if upcoming.is_empty() {
+            println!("  next:     {}", color::dim("never"));
+        }
+        for (i, t) in upcoming.iter().enumerate() {
+            let label = if i == 0 { "next:" } else { "" };
+            let local = tz.format(*t, "%Y-%m-%d %H:%M %Z");
+            if tz == CronTimezone::Utc {
+                println!("  {label:<9} {local}");
+            } else {
+                println!("  {label:<9} {local} ({} UTC)", t.format("%Y-%m-%d %H:%M"));
+            }
+        }
+        println!();
+    }
+
+    for (filename, reason) in &scan.errors {
+        println!("{} {}: {reason}", color::error("invalid"), filename);
+    }
+    if !scan.errors.is_empty() {
+        println!();
+        println!("Run `decree cron check` for details.");
+    }
+
+    Ok(())
+}
+
+/// Run `decree cron check` — validate every cron file.
+///
+/// Reports parse errors and unknown routines, and fails if any are found.
+pub fn check(project_root: &Path) -> Result<(), DecreeError> {
+    let config = AppConfig::load_from_project(project_root)?;
+    let scan = cron::scan_cron_dir(project_root)?;
+
+    if scan.files.is_empty() && scan.errors.is_empty() {
+        println!("No cron files found in .decree/cron/");
+        return Ok(());
+    }
+
+    let routines = message::list_routines(project_root, &config)?;
+    let total = scan.files.len() + scan.errors.len();
+    let mut invalid = 0;
+
+    for cf in &scan.files {
+        match &cf.routine {
+            Some(routine) if !routines.iter().any(|r| &r.name == routine) => {
+                println!(
+                    "  {:<32} {}: routine '{routine}' not found or disabled",
+                    cf.filename,
+                    color::error("FAIL")
+                );
+                invalid += 1;
+            }
+            _ => println!("  {:<32} {}", cf.filename, color::success("OK")),
+        }
+    }
+    for (filename, reason) in &scan.errors {
+        println!("  {:<32} {}: {reason}", filename, color::error("FAIL"));
+        invalid += 1;
+    }
+
+    println!();
+    println!("{} of {total} cron files valid.", total - invalid);
+
+    if invalid > 0 {
+        return Err(DecreeError::Other(format!(
+            "{invalid} invalid cron file{}",
+            if invalid == 1 { "" } else { "s" }
+        )));
+    }
+    Ok(())
+}
+
+/// Run `decree cron run <name>` — enqueue a cron job immediately.
+///
+/// The message lands in the inbox like a scheduled fire; the schedule
+/// and its persisted fire times are left untouched.
+pub fn run(project_root: &Path, name: &str) -> Result<(), DecreeError> {
+    let scan = cron::scan_cron_dir(project_root)?;
+    let stem = name.strip_suffix(".md").unwrap_or(name);
+
+    let Some(cf) = scan.files.iter().find(|cf| cf.name_stem == stem) else {
+        let filename = format!("{stem}.md");
+        if let Some((_, reason)) = scan.errors.iter().find(|(f, _)| *f == filename) {
+            return Err(DecreeError::Other(format!("cannot run {filename}: {reason}")));
+        }
+        let mut msg = format!("unknown cron job '{name}'");
+        if !scan.files.is_empty() {
+            msg.push_str("\n\nAvailable cron jobs:");
+            for cf in &scan.files {
+                msg.push_str(&format!("\n  {}", cf.name_stem));
+            }
+        }
+        return Err(DecreeError::Other(msg));
+    };
+
+    let inbox_dir = project_root
+        .join(config::DECREE_DIR)
+        .join(config::INBOX_DIR);
+    std::fs::create_dir_all(&inbox_dir)?;
+
+    let msg = cron::cron_to_inbox_message(project_root, cf)?;
+    msg.write_to_inbox(project_root)?;
+    events::record(
+        project_root,
+        Event {
+            detail: Some(format!("cron {}", cf.filename)),
+            ..Event::for_message(EventKind::Enqueued, &msg)
+        },
+    );
+    println!("Enqueued {} from {}", msg.filename, cf.filename);
+    Ok(())
+}
@@ -1,19 +1,30 @@
+use crate::ai;
+use crate::approval;
+use crate::atomic;
+use crate::capture::{self, CappedLog, Limits};
+use crate::commands::{api, gc};
 use crate::commands::routine_sync;
-use crate::config::{self, AppConfig};
-use crate::cron::{self, CronTracker};
+use crate::config::{self, AppConfig, NotifyEvent};
+use crate::cron::{self, CatchUp, Concurrency, CronTracker, Schedule};
 use crate::error::DecreeError;
+use crate::events::{self, Actor, Event, EventKind};
 use crate::hooks::{self, HookContext, HookType};
-use crate::message::{self, InboxMessage};
+use crate::message::{self, InboxMessage, RouterFn};
+use crate::notify::{self, Notification};
+use crate::router;
 use crate::routine;
-use std::collections::BTreeMap;
+use crate::usage;
+use chrono::{DateTime, Local, Utc};
+use std::collections::{BTreeMap, HashSet};
 use std::path::Path;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-/// Run the daemon polling loop.
-pub fn run(project_root: &Path, interval: u64) -> Result<(), DecreeError> {
+/// Run the daemon polling loop, serving the HTTP API on `listen` if given.
+pub fn run(project_root: &Path, interval: u64, listen: Option<&str>) -> Result<(), DecreeError> {
+    events::set_actor(Actor::Daemon);
     let mut config = AppConfig::load_from_project(project_root)?;
 
     // Run discovery at startup
@@ -27,6 +38,12 @@ pub fn run(project_root: &Path, interval: u64) -> Result<(), DecreeError> {
 
     println!("decree daemon: polling every {interval}s");
 
+    if let Some(addr) = listen {
+        let token = std::env::var(api::TOKEN_ENV).ok().filter(|t| !t.is_empty());
+        let bound = api::spawn(project_root, addr, token)?;
+        println!("decree daemon: listening on http://{bound}");
+    }
+
     // Run beforeAll hook
     let all_ctx = HookContext::default();
     if let Err(e) = hooks::run_hook_with_config(project_root, &config.hooks, HookType::BeforeAll, &all_ctx, Some(&config)) {
@@ -34,23 +51,32 @@ pub fn run(project_root: &Path, interval: u64) -> Result<(), DecreeError> {
         return Err(DecreeError::Other(format!("beforeAll hook failed: {e}")));
     }
 
-    let mut cron_tracker = CronTracker::new();
+    let mut cron_tracker =
+        CronTracker::load(project_root)?.with_timezone(cron::default_timezone(&config)?);
+
+    // Last budget cap that paused processing, so it is only reported once
+    let mut budget_hold: Option<String> = None;
+
+    // When retention last ran; the first pass happens at startup
+    let mut last_gc: Option<Instant> = None;
+
+    notify::send(project_root, &config, &Notification::new(NotifyEvent::DaemonStarted));
 
     // Main polling loop
     loop {
         if shutdown.load(Ordering::Relaxed) {
-            println!("decree daemon: shutting down (signal received)");
             // Do NOT run afterAll on signal shutdown
+            shut_down(project_root, &config);
             return Ok(());
         }
 
         // Step 1-2: Check cron and fire due jobs into inbox
-        fire_due_cron_jobs(project_root, &mut cron_tracker);
+        fire_due_cron_jobs(project_root, &config, &mut cron_tracker);
 
         // Step 3-4: Process inbox messages
         loop {
             if shutdown.load(Ordering::Relaxed) {
-                println!("decree daemon: shutting down (signal received)");
+                shut_down(project_root, &config);
                 return Ok(());
             }
 
@@ -62,6 +88,20 @@ pub fn run(project_root: &Path, interval: u64) -> Result<(), DecreeError> {
             // LIFO: process last (newest) message first
             let filename = inbox.last().unwrap().clone();
 
+            // Leave messages queued while a budget cap is reached
+            let over_budget = match InboxMessage::from_file(project_root, &filename) {
+                Ok(next) => usage::budget_exceeded(project_root, &config, &next)?,
+                Err(_) => None,
+            };
+            if let Some(reason) = over_budget {
+                if budget_hold.as_ref() != Some(&reason) {
+                    println!("decree daemon: {reason}; pausing until the budget allows more");
+                }
+                budget_hold = Some(reason);
+                break;
+            }
+            budget_hold = None;
+
             match process_single_message(project_root, &config, &filename, &shutdown) {
                 Ok(()) => {}
                 Err(e) => {
@@ -69,12 +109,23 @@ pub fn run(project_root: &Path, interval: u64) -> Result<(), DecreeError> {
                     eprintln!("decree daemon: error processing {filename}: {e}");
                 }
             }
+
+            // A long message may have spanned scheduled times — check cron again
+            fire_due_cron_jobs(project_root, &config, &mut cron_tracker);
+        }
+
+        // Step 5: Prune old runs when retention.gc_interval_hours is due
+        if let Some(hours) = config.retention.gc_interval_hours.filter(|h| *h > 0) {
+            if last_gc.is_none_or(|t| t.elapsed() >= Duration::from_secs(hours * 3600)) {
+                run_gc(project_root, &config);
+                last_gc = Some(Instant::now());
+            }
         }
 
-        // Step 5: Sleep for the interval (check shutdown periodically)
+        // Step 6: Sleep for the interval (check shutdown periodically)
         for _ in 0..interval {
             if shutdown.load(Ordering::Relaxed) {
-                println!("decree daemon: shutting down (signal received)");
+                shut_down(project_root, &config);
                 return Ok(());
             }
             thread::sleep(Duration::from_secs(1));
@@ -82,6 +133,30 @@ pub fn run(project_root: &Path, interval: u64) -> Result<(), DecreeError> {
     }
 }
 
+/// Apply the retention policy, reporting what was pruned.
+fn run_gc(project_root: &Path, config: &AppConfig) {
+    if !config.retention.is_set() {
+        return;
+    }
+    match gc::collect(project_root, &config.retention, false, Local::now()) {
+        Ok(report) if !report.pruned.is_empty() => {
+            let count = report.pruned.len();
+            println!(
+                "decree daemon: gc pruned {count} run{}",
+                if count == 1 { "" } else { "s" }
+            );
+        }
+        Ok(_) => {}
+        Err(e) => eprintln!("decree daemon: gc failed: {e}"),
+    }
+}
+
+/// Report a signal shutdown to the console and notification sinks.
+fn shut_down(project_root: &Path, config: &AppConfig) {
+    println!("decree daemon: shutting down (signal received)");
+    notify::send(project_root, config, &Notification::new(NotifyEvent::DaemonStopped));
+}
+
 /// Register SIGINT and SIGTERM handlers to set the shutdown flag.
 fn register_signal_handlers(shutdown: Arc<AtomicBool>) -> Result<(), DecreeError> {
     let shutdown2 = Arc::clone(&shutdown);
@@ -91,7 +166,10 @@ fn register_signal_handlers(shutdown: Arc<AtomicBool>) -> Result<(), DecreeError
 }
 
 /// Check cron directory and fire due jobs into inbox.
-fn fire_due_cron_jobs(project_root: &Path, tracker: &mut CronTracker) {
+///
+/// Occurrences missed while the daemon was down or busy are handled by each
+/// cron file's `catch_up` policy. Tracker state is persisted whenever it changes.
+fn fire_due_cron_jobs(project_root: &Path, config: &AppConfig, tracker: &mut CronTracker) {
     let cron_files = match cron::scan_cron_files(project_root) {
         Ok(files) => files,
         Err(e) => {
@@ -100,30 +178,94 @@ fn fire_due_cron_jobs(project_root: &Path, tracker: &mut CronTracker) {
         }
     };
 
+    let now = Utc::now();
+    let mut changed = tracker.retain(&cron_files);
+    let mut fired: HashSet<&str> = HashSet::new();
+
     for cf in &cron_files {
-        if !tracker.is_due(cf) {
-            continue;
+        changed |= tracker.track(cf, now);
+        let check = tracker.check(cf, now);
+
+        if !check.missed.is_empty() {
+            let to_enqueue: &[DateTime<Utc>] = match cf.catch_up {
+                CatchUp::None => &[],
+                CatchUp::Once => &check.missed[check.missed.len() - 1..],
+                CatchUp::All => &check.missed,
+            };
+            let skipped = &check.missed[..check.missed.len() - to_enqueue.len()];
+            if !skipped.is_empty() {
+                println!(
+                    "decree daemon: cron catch-up ({}): {} skipped {} missed run{}: {}",
+                    cf.catch_up,
+                    cf.filename,
+                    skipped.len(),
+                    if skipped.len() == 1 { "" } else { "s" },
+                    format_fire_times(skipped)
+                );
+            }
+            // The watermark only moves past occurrences that were skipped or
+            // enqueued; a failed enqueue is retried on the next poll
+            let mut accounted = skipped.last().copied();
+            let mut failed = false;
+            for &scheduled in to_enqueue {
+                let enqueued = match cron::catch_up_message(project_root, cf, scheduled) {
+                    Ok(msg) => {
+                        if !apply_concurrency(project_root, cf, &msg) {
+                            true
+                        } else if enqueue_cron_message(project_root, config, cf, &msg) {
+                            fired.insert(&cf.filename);
+                            println!(
+                                "decree daemon: cron caught up: {} ({}) -> {}",
+                                cf.filename,
+                                format_fire_times(&[scheduled]),
+                                msg.filename
+                            );
+                            true
+                        } else {
+                            false
+                        }
+                    }
+                    Err(e) => {
+                        eprintln!(
+                            "decree daemon: failed to create catch-up message for {}: {e}",
+                            cf.filename
+                        );
+                        false
+                    }
+                };
+                if !enqueued {
+                    failed = true;
+                    break;
+                }
+                accounted = Some(scheduled);
+            }
+            if let Some(at) = accounted {
+                tracker.record(cf, at);
+                changed = true;
+            }
+            if failed {
+                continue;
+            }
         }
 
+        let Some(due) = check.due else {
+            continue;
+        };
+
         match cron::cron_to_inbox_message(project_root, cf) {
             Ok(msg) => {
-                // Ensure inbox directory exists
-                let inbox_dir = project_root
-                    .join(config::DECREE_DIR)
-                    .join(config::INBOX_DIR);
-                if let Err(e) = std::fs::create_dir_all(&inbox_dir) {
-                    eprintln!("decree daemon: failed to create inbox dir: {e}");
+                if !apply_concurrency(project_root, cf, &msg) {
+                    tracker.record(cf, due);
+                    changed = true;
                     continue;
                 }
-                if let Err(e) = msg.write_to_inbox(project_root) {
-                    eprintln!(
-                        "decree daemon: failed to write cron message for {}: {e}",
-                        cf.filename
-                    );
+                if !enqueue_cron_message(project_root, config, cf, &msg) {
                     continue;
                 }
                 println!("decree daemon: cron fired: {} -> {}", cf.filename, msg.filename);
-                tracker.mark_fired(cf);
+                fired.insert(&cf.filename);
+                tracker.record(cf, due);
+                changed = true;
             }
             Err(e) => {
                 eprintln!(
@@ -133,6 +275,198 @@ fn fire_due_cron_jobs(project_root: &Path, tracker: &mut CronTracker) {
             }
         }
     }
+
+    // One-shot files are archived once their occurrence is accounted for
+    for cf in &cron_files {
+        let Schedule::At(at) = cf.schedule else {
+            continue;
+        };
+        if tracker.last_fire(cf).is_none_or(|last| last < at) {
+            continue;
+        }
+        match cron::archive(project_root, cf) {
+            Ok(dest) => {
+                let dest = dest.strip_prefix(project_root).unwrap_or(&dest);
+                if fired.contains(cf.filename.as_str()) {
+                    println!(
+                        "decree daemon: cron one-shot done: {} archived to {}",
+                        cf.filename,
+                        dest.display()
+                    );
+                } else {
+                    println!(
+                        "decree daemon: cron one-shot expired without firing: {} archived to {}",
+                        cf.filename,
+                        dest.display()
+                    );
+                }
+            }
+            Err(e) => {
+                eprintln!("decree daemon: failed to archive {}: {e}", cf.filename);
+            }
+        }
+    }
+
+    if changed {
+        if let Err(e) = tracker.save(project_root) {
+            eprintln!("decree daemon: failed to save cron state: {e}");
+        }
+    }
+}
+
+/// Apply a cron file's `concurrency` policy before enqueuing `msg`.
+///
+/// Returns `false` when the fire is skipped because an earlier message from
+/// the same cron file is still pending (queued, running, parked for routing,
+/// or awaiting approval). `replace` only drops unstarted first messages.
+/// Skipped and replaced messages are recorded in their run directory as
+/// `skipped.log`.
+fn apply_concurrency(project_root: &Path, cf: &cron::CronFile, msg: &InboxMessage) -> bool {
+    if cf.concurrency == Concurrency::Queue {
+        return true;
+    }
+
+    let pending = match cron::pending_messages(project_root, cf) {
+        Ok(pending) => pending,
+        Err(e) => {
+            eprintln!(
+                "decree daemon: failed to check pending messages for {}: {e}",
+                cf.filename
+            );
+            return true;
+        }
+    };
+    if pending.is_empty() {
+        return true;
+    }
+
+    let msg_id = msg.id.clone().unwrap_or_default();
+    match cf.concurrency {
+        Concurrency::Skip => {
+            let reason = format!("previous run still pending: {}", pending.join(", "));
+            println!(
+                "decree daemon: cron skipped (concurrency: skip): {} ({reason})",
+                cf.filename
+            );
+            if let Err(e) = record_skipped_run(project_root, &msg_id, &msg.serialize(), &reason) {
+                eprintln!("decree daemon: failed to record skipped run {msg_id}: {e}");
+            }
+            false
+        }
+        Concurrency::Replace => {
+            let decree_dir = project_root.join(config::DECREE_DIR);
+            let inbox_dir = decree_dir.join(config::INBOX_DIR);
+            let runs_dir = decree_dir.join(config::RUNS_DIR);
+
+            // Only a fire that hasn't started yet is replaced: follow-ups,
+            // held or parked messages, and anything with a run directory (a
+            // run in progress, possibly under `decree process`) are left alone.
+            let mut removed = Vec::new();
+            for path in &pending {
+                if path.contains('/') {
+                    continue;
+                }
+                let Some(id) = cron::pending_message_id(path) else {
+                    continue;
+                };
+                let pending_id = id.full_id();
+                if id.seq == 0 && !runs_dir.join(id.run_dir_name()).exists() {
+                    let file = inbox_dir.join(path);
+                    let content = std::fs::read_to_string(&file).unwrap_or_default();
+                    if let Err(e) = std::fs::remove_file(&file) {
+                        eprintln!("decree daemon: failed to remove {path}: {e}");
+                        continue;
+                    }
+                    let reason = format!("replaced by {msg_id} (concurrency: replace)");
+                    if let Err(e) = record_skipped_run(project_root, &pending_id, &content, &reason)
+                    {
+                        eprintln!("decree daemon: failed to record skipped run {pending_id}: {e}");
+                    }
+                    removed.push(path.as_str());
+                }
+            }
+            if !removed.is_empty() {
+                println!(
+                    "decree daemon: cron replaced (concurrency: replace): {} removed {}",
+                    cf.filename,
+                    removed.join(", ")
+                );
+            }
+            true
+        }
+        Concurrency::Queue => true,
+    }
+}
+
+/// Record a message that never ran in `.decree/runs/<id>/skipped.log`.
+fn record_skipped_run(
+    project_root: &Path,
+    msg_id: &str,
+    message: &str,
+    reason: &str,
+) -> Result<(), DecreeError> {
+    let run_dir = project_root
+        .join(config::DECREE_DIR)
+        .join(config::RUNS_DIR)
+        .join(msg_id);
+    std::fs::create_dir_all(&run_dir)?;
+
+    let message_path = run_dir.join("message.md");
+    if !message_path.exists() {
+        atomic::write(&message_path, message)?;
+    }
+
+    let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S");
+    append_to_file(
+        &run_dir.join("skipped.log"),
+        &format!("[decree] {timestamp} skipped: {reason}\n"),
+    )
+}
+
+/// Write a cron-generated message to the inbox, logging failures.
+fn enqueue_cron_message(
+    project_root: &Path,
+    config: &AppConfig,
+    cf: &cron::CronFile,
+    msg: &InboxMessage,
+) -> bool {
+    // Ensure inbox directory exists
+    let inbox_dir = project_root
+        .join(config::DECREE_DIR)
+        .join(config::INBOX_DIR);
+    if let Err(e) = std::fs::create_dir_all(&inbox_dir) {
+        eprintln!("decree daemon: failed to create inbox dir: {e}");
+        return false;
+    }
+    if let Err(e) = msg.write_to_inbox(project_root) {
+        eprintln!(
+            "decree daemon: failed to write cron message for {}: {e}",
+            cf.filename
+        );
+        return false;
+    }
+    events::record(
+        project_root,
+        Event {
+            detail: Some(format!("cron {}", cf.filename)),
+            ..Event::for_message(EventKind::Enqueued, msg)
+        },
+    );
+    let notification = Notification {
+        cron: Some(cf.filename.clone()),
+        ..Notification::for_message(NotifyEvent::CronFired, msg, None)
+    };
+    notify::send(project_root, config, &notification);
+    true
+}
+
+/// Format cron occurrences in local time for daemon output.
+fn format_fire_times(times: &[DateTime<Utc>]) -> String {
+    times
+        .iter()
+        .map(|t| t.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
 /// Process a single inbox message through the full pipeline.
@@ -144,10 +478,29 @@ fn process_single_message(
 ) -> Result<(), DecreeError> {
     // Parse and normalize the message
     let mut msg = InboxMessage::from_file(project_root, filename)?;
-    let was_modified = msg.normalize(project_root, config, None)?;
+
+    // Build the AI router callback if configured
+    let ai_router_fn = router::ai_router_fn(config);
+    let ai_router_ref = ai_router_fn
+        .as_ref()
+        .map(|f| f.as_ref() as &RouterFn);
+    let had_routine = msg.routine.is_some();
+    let was_modified = msg.normalize(project_root, config, ai_router_ref)?;
 
     if was_modified {
         msg.write_to_inbox(project_root)?;
+        events::record(project_root, Event::for_message(EventKind::Normalized, &msg));
+    }
+    if !had_routine && msg.routine.is_some() {
+        events::record(project_root, Event::for_message(EventKind::Routed, &msg));
+    }
+
+    // The router was not confident enough; nobody is there to ask, so park it
+    if msg.routine.is_none() {
+        router::park(project_root, &msg)?;
+        let msg_id = msg.id.clone().unwrap_or_else(|| msg.filename.clone());
+        println!("decree daemon: {msg_id} needs routing (decree route {msg_id} <routine>)");
+        return Ok(());
     }
 
     let chain = msg
@@ -169,13 +522,22 @@ fn process_single_message(
         .ok_or_else(|| DecreeError::Other("message has no routine after normalization".into()))?
         .clone();
 
+    // Hold for sign-off before running
+    if approval::needs_approval(&msg) {
+        approval::hold(project_root, &msg, "requires_approval is set")?;
+        println!("decree daemon: {msg_id} is awaiting approval (decree approve {msg_id})");
+        return Ok(());
+    }
+
     // Check depth limit
     if seq >= config.max_depth {
         eprintln!(
             "decree daemon: max depth exceeded for {msg_id} (seq={seq}, limit={})",
             config.max_depth
         );
-        dead_letter(project_root, filename)?;
+        let reason = format!("max depth exceeded (limit: {})", config.max_depth);
+        dead_letter(project_root, filename, &reason)?;
+        notify::dead_lettered(project_root, config, &msg, None, reason);
         return Err(DecreeError::MaxDepthExceeded(config.max_depth));
     }
 
@@ -187,14 +549,26 @@ fn process_single_message(
     std::fs::create_dir_all(&run_dir)?;
 
     // Copy normalized message to run dir
-    std::fs::write(run_dir.join("message.md"), msg.serialize())?;
+    atomic::write(&run_dir.join("message.md"), msg.serialize())?;
 
     // Find the routine script (registry-aware layered lookup)
     let script_path = match routine::resolve_routine(project_root, config, &routine_name) {
         Ok(p) => p,
         Err(e) => {
             eprintln!("decree daemon: routine resolution failed for {msg_id}: {e}");
-            dead_letter(project_root, filename)?;
+            dead_letter(project_root, filename, &e.to_string())?;
+            notify::dead_lettered(project_root, config, &msg, Some(&run_dir), e.to_string());
+            return Err(e);
+        }
+    };
+
+    // Pick the AI backend the routine should use
+    let ai_env = match ai::routine_env(config, &routine_name, &msg.custom_fields) {
+        Ok(env) => env,
+        Err(e) => {
+            eprintln!("decree daemon: AI profile selection failed for {msg_id}: {e}");
+            dead_letter(project_root, filename, &e.to_string())?;
+            notify::dead_lettered(project_root, config, &msg, Some(&run_dir), e.to_string());
             return Err(e);
         }
     };
@@ -204,6 +578,8 @@ fn process_single_message(
         .join(config::INBOX_DIR)
         .join(filename);
 
+    let started = chrono::Local::now();
+
     // Retry loop
     for attempt in 1..=config.max_retries {
         if shutdown.load(Ordering::Relaxed) {
@@ -222,6 +598,14 @@ fn process_single_message(
             routine_exit_code: None,
         };
 
+        events::record(
+            project_root,
+            Event {
+                attempt: Some(attempt),
+                ..Event::for_message(EventKind::AttemptStarted, &msg)
+            },
+        );
+
         // Run beforeEach hook
         if let Err(e) =
             hooks::run_hook_with_config(project_root, &config.hooks, HookType::BeforeEach, &hook_ctx, Some(config))
@@ -249,8 +633,9 @@ fn process_single_message(
             project_root,
             &script_path,
             &msg,
+            &ai_env,
             &run_dir,
-            &log_path,
+            CappedLog::open(&log_path, Limits::from_config(config))?,
         )?;
 
         // Write end timestamp to log
@@ -263,9 +648,42 @@ fn process_single_message(
             end.format("%Y-%m-%dT%H:%M:%S")
         );
         append_to_file(&log_path, &end_line)?;
+        events::record(
+            project_root,
+            Event {
+                attempt: Some(attempt),
+                exit_code: Some(exit_code),
+                duration_secs: Some(duration.num_seconds()),
+                ..Event::for_message(EventKind::AttemptEnded, &msg)
+            },
+        );
+
+        // Total the attempt's AI usage into run.json
+        usage::write_summary(&run_dir, &msg)?;
+
+        if exit_code == approval::AWAITING_APPROVAL_EXIT_CODE {
+            // AWAITING APPROVAL — pause the chain until a human signs off
+            let after_ctx = HookContext {
+                routine_exit_code: Some(exit_code),
+                ..hook_ctx
+            };
+            if let Err(e) =
+                hooks::run_hook_with_config(project_root, &config.hooks, HookType::AfterEach, &after_ctx, Some(config))
+            {
+                eprintln!("decree daemon: afterEach hook failed for {msg_id}: {e}");
+            }
 
-        // Truncate log if needed
-        truncate_log_if_needed(&log_path, config.max_log_size)?;
+            // Follow-ups wait until the routine runs again after approval
+            clear_outbox(project_root)?;
+
+            approval::hold(
+                project_root,
+                &msg,
+                &format!("{routine_name} exited with code {exit_code} (awaiting approval)"),
+            )?;
+            println!("decree daemon: {msg_id} is awaiting approval (decree approve {msg_id})");
+            return Ok(());
+        }
 
         if exit_code == 0 {
             // SUCCESS
@@ -294,6 +712,13 @@ fn process_single_message(
             // If message has migration field, mark as processed
             if let Some(ref migration) = msg.migration {
                 message::mark_processed(project_root, migration)?;
+                let notification = Notification {
+                    attempts: Some(attempt),
+                    exit_code: Some(0),
+                    duration_secs: Some(notify::seconds_since(started)),
+                    ..Notification::for_message(NotifyEvent::MigrationDone, &msg, Some(&run_dir))
+                };
+                notify::send(project_root, config, &notification);
             }
 
             return Ok(());
@@ -320,7 +745,20 @@ fn process_single_message(
             clear_outbox(project_root)?;
 
             // Dead-letter the message
-            dead_letter(project_root, filename)?;
+            dead_letter(
+                project_root,
+                filename,
+                &format!("max retries exhausted (exit code: {exit_code})"),
+            )?;
+
+            let notification = Notification {
+                attempts: Some(attempt),
+                exit_code: Some(exit_code),
+                duration_secs: Some(notify::seconds_since(started)),
+                reason: Some(format!("max retries exhausted (exit code: {exit_code})")),
+                ..Notification::for_message(NotifyEvent::RetriesExhausted, &msg, Some(&run_dir))
+            };
+            notify::send(project_root, config, &notification);
 
             return Err(DecreeError::MaxRetriesExhausted(msg_id));
         }
@@ -334,8 +772,9 @@ fn execute_routine(
     project_root: &Path,
     script_path: &Path,
     msg: &InboxMessage,
+    ai_env: &[(&str, String)],
     run_dir: &Path,
-    log_path: &Path,
+    log: CappedLog,
 ) -> Result<i32, DecreeError> {
     let msg_file_path = project_root
         .join(config::DECREE_DIR)
@@ -346,12 +785,11 @@ fn execute_routine(
     let chain = msg.chain.as_deref().unwrap_or("");
     let seq = msg.seq.map(|s| s.to_string()).unwrap_or_default();
 
-    // Execute: bash <script> 2>&1 | tee -a <log_path>
-    // Using shell to handle the pipe with pipefail so we get the script's exit code
+    // Execute: bash <script> 2>&1, with decree copying the output to the
+    // terminal and the size-capped log
     let cmd_str = format!(
-        "set -o pipefail; bash {} 2>&1 | tee -a {}",
+        "bash {} 2>&1",
         shell_escape(script_path.to_string_lossy().as_ref()),
-        shell_escape(log_path.to_string_lossy().as_ref()),
     );
 
     let mut cmd = std::process::Command::new("bash");
@@ -363,6 +801,9 @@ fn execute_routine(
         .env("message_dir", run_dir.to_string_lossy().as_ref())
         .env("chain", chain)
         .env("seq", &seq);
+    if let Some(path) = routine::path_with_decree() {
+        cmd.env("PATH", path);
+    }
 
     // Pass custom fields as env vars
     for (key, value) in &msg.custom_fields {
@@ -370,8 +811,14 @@ fn execute_routine(
             cmd.env(key, &s);
         }
     }
+    cmd.envs(ai_env.iter().map(|(k, v)| (k, v)));
+    cmd.stdout(std::process::Stdio::piped());
 
-    let status = cmd.status()?;
+    let mut child = cmd.spawn()?;
+    let output = child.stdout.take().expect("stdout is piped");
+    let copied = capture::tee(output, log);
+    let status = child.wait()?;
+    copied?;
 
     Ok(status.code().unwrap_or(1))
 }
@@ -399,9 +846,9 @@ fn collect_outbox(
 
     entries.sort();
 
-    // Warn about non-.md files
+    // Warn about non-.md files; `*.tmp` files are writes still in progress
     for entry in &entries {
-        if !entry.ends_with(".md") {
+        if !entry.ends_with(".md") && !atomic::is_tmp(entry) {
             eprintln!("Warning: non-.md file in outbox ignored: {entry}");
         }
     }
@@ -432,6 +879,14 @@ fn collect_outbox(
             );
             std::fs::create_dir_all(&outbox_dead_dir)?;
             std::fs::rename(&file_path, outbox_dead_dir.join(file))?;
+            events::record(
+                project_root,
+                Event {
+                    chain: Some(chain.to_string()),
+                    detail: Some(format!("outbox/{file}: max depth {} exceeded", config.max_depth)),
+                    ..Event::new(EventKind::DeadLettered)
+                },
+            );
             continue;
         }
 
@@ -466,6 +921,13 @@ fn collect_outbox(
 
         inbox_msg.write_to_inbox(project_root)?;
         std::fs::remove_file(&file_path)?;
+        events::record(
+            project_root,
+            Event {
+                detail: Some(format!("from outbox/{file}")),
+                ..Event::for_message(EventKind::FollowupCollected, &inbox_msg)
+            },
+        );
         next_seq += 1;
     }
 
@@ -492,8 +954,8 @@ fn clear_outbox(project_root: &Path) -> Result<(), DecreeError> {
     Ok(())
 }
 
-/// Move a message to the dead-letter directory.
-fn dead_letter(project_root: &Path, filename: &str) -> Result<(), DecreeError> {
+/// Move a message to the dead-letter directory, recording `reason`.
+fn dead_letter(project_root: &Path, filename: &str, reason: &str) -> Result<(), DecreeError> {
     let inbox_path = project_root
         .join(config::DECREE_DIR)
         .join(config::INBOX_DIR)
@@ -508,7 +970,21 @@ fn dead_letter(project_root: &Path, filename: &str) -> Result<(), DecreeError> {
 
     let dead_path = dead_dir.join(filename);
     if inbox_path.exists() {
+        let event = match InboxMessage::from_file(project_root, filename) {
+            Ok(msg) => Event::for_message(EventKind::DeadLettered, &msg),
+            Err(_) => Event {
+                message_id: Some(filename.trim_end_matches(".md").to_string()),
+                ..Event::new(EventKind::DeadLettered)
+            },
+        };
         std::fs::rename(&inbox_path, &dead_path)?;
+        events::record(
+            project_root,
+            Event {
+                detail: Some(reason.to_string()),
+                ..event
+            },
+        );
     }
 
     Ok(())
@@ -525,41 +1001,6 @@ fn append_to_file(path: &Path, text: &str) -> Result<(), DecreeError> {
     Ok(())
 }
 
-/// Truncate a log file to max_log_size bytes, keeping the tail.
-fn truncate_log_if_needed(path: &Path, max_size: u64) -> Result<(), DecreeError> {
-    if max_size == 0 {
-        return Ok(());
-    }
-
-    let metadata = std::fs::metadata(path)?;
-    if metadata.len() <= max_size {
-        return Ok(());
-    }
-
-    let content = std::fs::read(path)?;
-    let skip = content.len() - max_size as usize;
-    let truncated = &content[skip..];
-
-    let marker = format!("[log truncated — showing last {} of output]\n", format_bytes(max_size));
-    let mut new_content = marker.into_bytes();
-    new_content.extend_from_slice(truncated);
-
-    std::fs::write(path, &new_content)?;
-
-    Ok(())
-}
-
-/// Format a byte count for the truncation marker.
-fn format_bytes(bytes: u64) -> String {
-    if bytes >= 1_048_576 {
-        format!("{}MB", bytes / 1_048_576)
-    } else if bytes >= 1024 {
-        format!("{}KB", bytes / 1024)
-    } else {
-        format!("{bytes}B")
-    }
-}
-
 /// Format a chrono Duration as human-readable.
 fn format_duration(d: chrono::TimeDelta) -> String {
     let total_secs = d.num_seconds();
@@ -619,13 +1060,6 @@ mod tests {
         assert_eq!(format_duration(d), "2m05s");
     }
 
-    #[test]
-    fn test_format_bytes() {
-        assert_eq!(format_bytes(500), "500B");
-        assert_eq!(format_bytes(2048), "2KB");
-        assert_eq!(format_bytes(2_097_152), "2MB");
-    }
-
     #[test]
     fn test_shell_escape() {
         assert_eq!(shell_escape("simple"), "'simple'");
@@ -640,7 +1074,7 @@ mod tests {
         let inbox = dir.path().join(".decree/inbox");
         std::fs::write(inbox.join("test-0.md"), "content").unwrap();
 
-        dead_letter(dir.path(), "test-0.md").unwrap();
+        dead_letter(dir.path(), "test-0.md", "test").unwrap();
 
         assert!(!inbox.join("test-0.md").exists());
         assert!(inbox.join("dead/test-0.md").exists());
@@ -652,7 +1086,7 @@ mod tests {
         setup_decree_dir(&dir);
 
         // Should not error on nonexistent file
-        let result = dead_letter(dir.path(), "nonexistent.md");
+        let result = dead_letter(dir.path(), "nonexistent.md", "test");
         assert!(result.is_ok());
     }
 
@@ -783,43 +1217,6 @@ mod tests {
         assert!(outbox.join("dead/followup.md").exists());
     }
 
-    #[test]
-    fn test_truncate_log_disabled() {
-        let dir = TempDir::new().unwrap();
-        let log = dir.path().join("test.log");
-        std::fs::write(&log, "a".repeat(5000)).unwrap();
-
-        // max_size = 0 disables truncation
-        truncate_log_if_needed(&log, 0).unwrap();
-        assert_eq!(std::fs::metadata(&log).unwrap().len(), 5000);
-    }
-
-    #[test]
-    fn test_truncate_log_under_limit() {
-        let dir = TempDir::new().unwrap();
-        let log = dir.path().join("test.log");
-        std::fs::write(&log, "small log").unwrap();
-
-        truncate_log_if_needed(&log, 1000).unwrap();
-        assert_eq!(std::fs::read_to_string(&log).unwrap(), "small log");
-    }
-
-    #[test]
-    fn test_truncate_log_over_limit() {
-        let dir = TempDir::new().unwrap();
-        let log = dir.path().join("test.log");
-        let content = "x".repeat(200);
-        std::fs::write(&log, &content).unwrap();
-
-        truncate_log_if_needed(&log, 100).unwrap();
-
-        let result = std::fs::read_to_string(&log).unwrap();
-        assert!(result.starts_with("[log truncated"));
-        assert!(result.contains("100B"));
-        // Should end with 100 'x' chars
-        assert!(result.ends_with(&"x".repeat(100)));
-    }
-
     #[test]
     fn test_process_single_message_success() {
         let dir = TempDir::new().unwrap();
@@ -864,6 +1261,113 @@ mod tests {
             .exists());
     }
 
+    #[test]
+    fn test_process_single_message_uses_ai_router() {
+        let dir = TempDir::new().unwrap();
+        setup_decree_dir(&dir);
+        for routine in ["develop", "docs"] {
+            std::fs::write(
+                dir.path().join(format!(".decree/routines/{routine}.sh")),
+                "#!/usr/bin/env bash\necho 'done'\n",
+            )
+            .unwrap();
+        }
+        std::fs::write(dir.path().join(".decree/router.md"), "{routines}\n\n{message}\n").unwrap();
+        std::fs::write(
+            dir.path().join(".decree/config.yml"),
+            "commands:\n  ai_router: printf docs\n  ai_interactive: echo\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join(".decree/inbox/D0001-1432-test-0.md"),
+            "---\nid: D0001-1432-test-0\nchain: D0001-1432-test\nseq: 0\n---\nWrite the docs.\n",
+        )
+        .unwrap();
+
+        let config = AppConfig::load_from_project(dir.path()).unwrap();
+        let shutdown = AtomicBool::new(false);
+        process_single_message(dir.path(), &config, "D0001-1432-test-0.md", &shutdown).unwrap();
+
+        let run_dir = dir.path().join(".decree/runs/D0001-1432-test-0");
+        let run_msg = std::fs::read_to_string(run_dir.join("message.md")).unwrap();
+        assert!(run_msg.contains("routine: docs"));
+        let record = std::fs::read_to_string(run_dir.join("router.json")).unwrap();
+        assert!(record.contains("\"raw_output\": \"docs\""));
+    }
+
+    #[test]
+    fn test_unsure_router_answer_parks_message() {
+        let dir = TempDir::new().unwrap();
+        setup_decree_dir(&dir);
+        std::fs::write(
+            dir.path().join(".decree/routines/develop.sh"),
+            "#!/usr/bin/env bash\ntouch ran\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join(".decree/router.md"), "{routines}\n\n{message}\n").unwrap();
+        std::fs::write(
+            dir.path().join(".decree/config.yml"),
+            "commands:\n  ai_router: printf 'develop 0.4'\n  ai_interactive: echo\nrouter:\n  min_confidence: 0.5\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join(".decree/inbox/D0001-1432-test-0.md"),
+            "---\nid: D0001-1432-test-0\nchain: D0001-1432-test\nseq: 0\n---\nTest body.\n",
+        )
+        .unwrap();
+
+        let config = AppConfig::load_from_project(dir.path()).unwrap();
+        let shutdown = AtomicBool::new(false);
+        process_single_message(dir.path(), &config, "D0001-1432-test-0.md", &shutdown).unwrap();
+
+        // Parked without running, with the router's answer on record
+        assert!(!dir.path().join("ran").exists());
+        assert!(!dir.path().join(".decree/inbox/D0001-1432-test-0.md").exists());
+        assert!(dir
+            .path()
+            .join(".decree/inbox/needs-routing/D0001-1432-test-0.md")
+            .exists());
+        let record = std::fs::read_to_string(
+            dir.path().join(".decree/runs/D0001-1432-test-0/router.json"),
+        )
+        .unwrap();
+        assert!(record.contains("\"needs_routing\": true"));
+    }
+
+    #[test]
+    fn test_process_single_message_awaiting_approval_exit_code() {
+        let dir = TempDir::new().unwrap();
+        setup_decree_dir(&dir);
+
+        // Exits 75 until approved, then succeeds and writes a marker
+        std::fs::write(
+            dir.path().join(".decree/routines/deploy.sh"),
+            "#!/usr/bin/env bash\nif [ -z \"${approved:-}\" ]; then exit 75; fi\ntouch deployed\n",
+        )
+        .unwrap();
+
+        let content = "---\nid: D0001-1432-ship-0\nchain: D0001-1432-ship\nseq: 0\nroutine: deploy\n---\nShip it.\n";
+        std::fs::write(dir.path().join(".decree/inbox/D0001-1432-ship-0.md"), content).unwrap();
+
+        let config = AppConfig::load_from_project(dir.path()).unwrap();
+        let shutdown = AtomicBool::new(false);
+
+        process_single_message(dir.path(), &config, "D0001-1432-ship-0.md", &shutdown).unwrap();
+        assert!(!dir.path().join(".decree/inbox/D0001-1432-ship-0.md").exists());
+        assert!(!dir.path().join(".decree/inbox/dead/D0001-1432-ship-0.md").exists());
+        let record = approval::read_record(dir.path(), "D0001-1432-ship-0").unwrap();
+        assert!(record.reason.contains("exited with code 75"));
+
+        // Only one attempt was made — no retries while awaiting approval
+        assert!(!dir.path().join(".decree/runs/D0001-1432-ship-0/routine-2.log").exists());
+
+        let pending = approval::find_pending(dir.path(), "D0001-1432-ship-0").unwrap();
+        approval::approve(dir.path(), pending).unwrap();
+        process_single_message(dir.path(), &config, "D0001-1432-ship-0.md", &shutdown).unwrap();
+        assert!(dir.path().join("deployed").exists());
+        assert!(approval::pending_messages(dir.path()).unwrap().is_empty());
+    }
+
     #[test]
     fn test_process_single_message_failure_dead_letters() {
         let dir = TempDir::new().unwrap();
@@ -976,7 +1480,7 @@ mod tests {
         .unwrap();
 
         let mut tracker = CronTracker::new();
-        fire_due_cron_jobs(dir.path(), &mut tracker);
+        fire_due_cron_jobs(dir.path(), &AppConfig::default(), &mut tracker);
 
         // Should have created an inbox message
         let inbox_files = message::list_inbox_messages(dir.path()).unwrap();
@@ -993,11 +1497,422 @@ mod tests {
         assert!(!content.contains("cron:"));
 
         // Second fire within same minute should not create duplicate
-        fire_due_cron_jobs(dir.path(), &mut tracker);
+        fire_due_cron_jobs(dir.path(), &AppConfig::default(), &mut tracker);
         let inbox_files2 = message::list_inbox_messages(dir.path()).unwrap();
         assert_eq!(inbox_files2.len(), 1); // Still just one
     }
 
+    /// Seed the cron state so the tracker believes the file last fired `hours` ago.
+    fn seed_cron_state(dir: &TempDir, filename: &str, hours: i64) {
+        let last = Utc::now() - chrono::TimeDelta::hours(hours);
+        std::fs::write(
+            dir.path().join(".decree/cron-state.yml"),
+            format!("{filename}:\n  last_fire: {}\n", last.to_rfc3339()),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_fire_due_cron_jobs_persists_state() {
+        let dir = TempDir::new().unwrap();
+        setup_decree_dir(&dir);
+
+        std::fs::write(
+            dir.path().join(".decree/cron/hourly.md"),
+            "---\ncron: \"0 * * * *\"\n---\nHourly.\n",
+        )
+        .unwrap();
+
+        let mut tracker = CronTracker::new();
+        fire_due_cron_jobs(dir.path(), &AppConfig::default(), &mut tracker);
+
+        let state = std::fs::read_to_string(dir.path().join(".decree/cron-state.yml")).unwrap();
+        assert!(state.contains("hourly.md"));
+        assert!(state.contains("last_fire"));
+    }
+
+    #[test]
+    fn test_fire_due_cron_jobs_catch_up_none_skips() {
+        let dir = TempDir::new().unwrap();
+        setup_decree_dir(&dir);
+
+        std::fs::write(
+            dir.path().join(".decree/cron/hourly.md"),
+            "---\ncron: \"0 0 * * * *\"\n---\nHourly.\n",
+        )
+        .unwrap();
+        seed_cron_state(&dir, "hourly.md", 3);
+
+        let mut tracker = CronTracker::load(dir.path()).unwrap();
+        fire_due_cron_jobs(dir.path(), &AppConfig::default(), &mut tracker);
+
+        let inbox: Vec<String> = message::list_inbox_messages(dir.path())
+            .unwrap()
+            .into_iter()
+            .filter(|f| f.contains("catchup"))
+            .collect();
+        assert!(inbox.is_empty());
+
+        // Missed runs are accounted for — a restart must not replay them
+        let mut reloaded = CronTracker::load(dir.path()).unwrap();
+        fire_due_cron_jobs(dir.path(), &AppConfig::default(), &mut reloaded);
+        let inbox = message::list_inbox_messages(dir.path()).unwrap();
+        assert!(inbox.iter().all(|f| !f.contains("catchup")));
+    }
+
+    #[test]
+    fn test_fire_due_cron_jobs_catch_up_once() {
+        let dir = TempDir::new().unwrap();
+        setup_decree_dir(&dir);
+
+        std::fs::write(
+            dir.path().join(".decree/cron/hourly.md"),
+            "---\ncron: \"0 0 * * * *\"\ncatch_up: once\n---\nHourly.\n",
+        )
+        .unwrap();
+        seed_cron_state(&dir, "hourly.md", 3);
+
+        let mut tracker = CronTracker::load(dir.path()).unwrap();
+        fire_due_cron_jobs(dir.path(), &AppConfig::default(), &mut tracker);
+
+        let catch_ups: Vec<String> = message::list_inbox_messages(dir.path())
+            .unwrap()
+            .into_iter()
+            .filter(|f| f.contains("hourly-catchup-"))
+            .collect();
+        assert_eq!(catch_ups.len(), 1);
+
+        let content =
+            std::fs::read_to_string(dir.path().join(".decree/inbox").join(&catch_ups[0])).unwrap();
+        assert!(content.contains("Hourly."));
+        assert!(!content.contains("catch_up"));
+    }
+
+    #[test]
+    fn test_fire_due_cron_jobs_catch_up_retries_failed_enqueue() {
+        let dir = TempDir::new().unwrap();
+        setup_decree_dir(&dir);
+
+        std::fs::write(
+            dir.path().join(".decree/cron/hourly.md"),
+            "---\ncron: \"0 0 * * * *\"\ncatch_up: once\n---\nHourly.\n",
+        )
+        .unwrap();
+        seed_cron_state(&dir, "hourly.md", 3);
+
+        // An inbox that cannot be written to
+        let inbox = dir.path().join(".decree/inbox");
+        std::fs::remove_dir_all(&inbox).unwrap();
+        std::fs::write(&inbox, "not a directory").unwrap();
+
+        let mut tracker = CronTracker::load(dir.path()).unwrap();
+        let cf = cron::scan_cron_files(dir.path()).unwrap().remove(0);
+        let before = tracker.last_fire(&cf).unwrap();
+        fire_due_cron_jobs(dir.path(), &AppConfig::default(), &mut tracker);
+
+        // Only the skipped occurrences are accounted for; the latest one is not
+        let missed = tracker.check(&cf, Utc::now()).missed;
+        assert_eq!(missed.len(), 1);
+        assert!(tracker.last_fire(&cf).unwrap() > before);
+
+        // Once the inbox is writable again, the occurrence is caught up
+        std::fs::remove_file(&inbox).unwrap();
+        std::fs::create_dir_all(&inbox).unwrap();
+        fire_due_cron_jobs(dir.path(), &AppConfig::default(), &mut tracker);
+        let catch_ups = message::list_inbox_messages(dir.path())
+            .unwrap()
+            .into_iter()
+            .filter(|f| f.contains("hourly-catchup-"))
+            .count();
+        assert_eq!(catch_ups, 1);
+        assert!(tracker.check(&cf, Utc::now()).missed.is_empty());
+    }
+
+    #[test]
+    fn test_fire_due_cron_jobs_catch_up_all() {
+        let dir = TempDir::new().unwrap();
+        setup_decree_dir(&dir);
+
+        std::fs::write(
+            dir.path().join(".decree/cron/hourly.md"),
+            "---\ncron: \"0 0 * * * *\"\ncatch_up: all\n---\nHourly.\n",
+        )
+        .unwrap();
+        seed_cron_state(&dir, "hourly.md", 3);
+
+        let mut tracker = CronTracker::load(dir.path()).unwrap();
+        fire_due_cron_jobs(dir.path(), &AppConfig::default(), &mut tracker);
+
+        let catch_ups = message::list_inbox_messages(dir.path())
+            .unwrap()
+            .into_iter()
+            .filter(|f| f.contains("hourly-catchup-"))
+            .count();
+        // Three hourly boundaries passed in the last three hours (two if we are
+        // exactly on the hour, since that one is due rather than missed)
+        assert!((2..=3).contains(&catch_ups), "got {catch_ups}");
+    }
+
+    #[test]
+    fn test_fire_due_cron_jobs_concurrency_skip() {
+        let dir = TempDir::new().unwrap();
+        setup_decree_dir(&dir);
+
+        std::fs::write(
+            dir.path().join(".decree/cron/minutely.md"),
+            "---\ncron: \"* * * * *\"\nconcurrency: skip\n---\nMinutely.\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join(".decree/inbox/D0001-0000-minutely-0.md"),
+            "Still waiting.\n",
+        )
+        .unwrap();
+
+        let mut tracker = CronTracker::new();
+        fire_due_cron_jobs(dir.path(), &AppConfig::default(), &mut tracker);
+
+        // Nothing new enqueued
+        let inbox_files = message::list_inbox_messages(dir.path()).unwrap();
+        assert_eq!(inbox_files, vec!["D0001-0000-minutely-0.md"]);
+
+        // The skipped fire is recorded in the run history
+        let runs = message::list_runs(dir.path()).unwrap();
+        assert_eq!(runs.len(), 1);
+        let run_dir = dir.path().join(".decree/runs").join(&runs[0]);
+        let log = std::fs::read_to_string(run_dir.join("skipped.log")).unwrap();
+        assert!(log.contains("previous run still pending: D0001-0000-minutely-0.md"));
+        assert!(run_dir.join("message.md").exists());
+
+        // The occurrence is accounted for, so it isn't retried this minute
+        assert!(tracker.last_fire(&cron::scan_cron_files(dir.path()).unwrap()[0]).is_some());
+    }
+
+    #[test]
+    fn test_fire_due_cron_jobs_concurrency_skip_fires_when_idle() {
+        let dir = TempDir::new().unwrap();
+        setup_decree_dir(&dir);
+
+        std::fs::write(
+            dir.path().join(".decree/cron/minutely.md"),
+            "---\ncron: \"* * * * *\"\nconcurrency: skip\n---\nMinutely.\n",
+        )
+        .unwrap();
+        // Messages from other chains don't block the fire
+        std::fs::write(
+            dir.path().join(".decree/inbox/D0001-0000-minutely-report-0.md"),
+            "Other.\n",
+        )
+        .unwrap();
+
+        let mut tracker = CronTracker::new();
+        fire_due_cron_jobs(dir.path(), &AppConfig::default(), &mut tracker);
+
+        let inbox_files = message::list_inbox_messages(dir.path()).unwrap();
+        assert_eq!(inbox_files.len(), 2);
+        assert!(message::list_runs(dir.path()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_fire_due_cron_jobs_concurrency_replace() {
+        let dir = TempDir::new().unwrap();
+        setup_decree_dir(&dir);
+
+        std::fs::write(
+            dir.path().join(".decree/cron/minutely.md"),
+            "---\ncron: \"* * * * *\"\nconcurrency: replace\n---\nMinutely.\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join(".decree/inbox/D0001-0000-minutely-0.md"),
+            "Stale.\n",
+        )
+        .unwrap();
+
+        let mut tracker = CronTracker::new();
+        fire_due_cron_jobs(dir.path(), &AppConfig::default(), &mut tracker);
+
+        let inbox_files = message::list_inbox_messages(dir.path()).unwrap();
+        assert_eq!(inbox_files.len(), 1);
+        assert_ne!(inbox_files[0], "D0001-0000-minutely-0.md");
+
+        let run_dir = dir.path().join(".decree/runs/D0001-0000-minutely-0");
+        let log = std::fs::read_to_string(run_dir.join("skipped.log")).unwrap();
+        assert!(log.contains("replaced by"));
+        assert_eq!(
+            std::fs::read_to_string(run_dir.join("message.md")).unwrap(),
+            "Stale.\n"
+        );
+    }
+
+    #[test]
+    fn test_fire_due_cron_jobs_concurrency_replace_keeps_started_runs() {
+        let dir = TempDir::new().unwrap();
+        setup_decree_dir(&dir);
+
+        std::fs::write(
+            dir.path().join(".decree/cron/minutely.md"),
+            "---\ncron: \"* * * * *\"\nconcurrency: replace\n---\nMinutely.\n",
+        )
+        .unwrap();
+        let inbox = dir.path().join(".decree/inbox");
+        // A first message that `decree process` has already started, its
+        // follow-up, and an unstarted fire from another chain
+        std::fs::write(inbox.join("D0001-0000-minutely-0.md"), "Running.\n").unwrap();
+        std::fs::create_dir_all(dir.path().join(".decree/runs/D0001-0000-minutely-0")).unwrap();
+        std::fs::write(inbox.join("D0001-0000-minutely-1.md"), "Follow-up.\n").unwrap();
+        std::fs::write(inbox.join("D0002-0001-minutely-0.md"), "Stale.\n").unwrap();
+        std::fs::create_dir_all(inbox.join("needs-routing")).unwrap();
+        std::fs::write(
+            inbox.join("needs-routing/D0003-0002-minutely-0.md"),
+            "Parked.\n",
+        )
+        .unwrap();
+
+        let mut tracker = CronTracker::new();
+        fire_due_cron_jobs(dir.path(), &AppConfig::default(), &mut tracker);
+
+        let inbox_files = message::list_inbox_messages(dir.path()).unwrap();
+        assert!(inbox_files.contains(&"D0001-0000-minutely-0.md".to_string()));
+        assert!(inbox_files.contains(&"D0001-0000-minutely-1.md".to_string()));
+        assert!(!inbox_files.contains(&"D0002-0001-minutely-0.md".to_string()));
+        assert_eq!(inbox_files.len(), 3);
+        assert!(inbox.join("needs-routing/D0003-0002-minutely-0.md").exists());
+
+        let runs_dir = dir.path().join(".decree/runs");
+        assert!(runs_dir.join("D0002-0001-minutely-0/skipped.log").exists());
+        assert!(!runs_dir.join("D0001-0000-minutely-0/skipped.log").exists());
+        assert!(!runs_dir.join("D0001-0000-minutely-1").exists());
+    }
+
+    #[test]
+    fn test_fire_due_cron_jobs_concurrency_skip_counts_held_messages() {
+        for held in ["needs-routing", "pending-approval"] {
+            let dir = TempDir::new().unwrap();
+            setup_decree_dir(&dir);
+
+            std::fs::write(
+                dir.path().join(".decree/cron/minutely.md"),
+                "---\ncron: \"* * * * *\"\nconcurrency: skip\n---\nMinutely.\n",
+            )
+            .unwrap();
+            let held_dir = dir.path().join(".decree/inbox").join(held);
+            std::fs::create_dir_all(&held_dir).unwrap();
+            std::fs::write(held_dir.join("D0001-0000-minutely-0.md"), "Held.\n").unwrap();
+
+            let mut tracker = CronTracker::new();
+            fire_due_cron_jobs(dir.path(), &AppConfig::default(), &mut tracker);
+
+            assert!(
+                message::list_inbox_messages(dir.path()).unwrap().is_empty(),
+                "{held}"
+            );
+            let runs = message::list_runs(dir.path()).unwrap();
+            assert_eq!(runs.len(), 1, "{held}");
+            let log = std::fs::read_to_string(
+                dir.path().join(".decree/runs").join(&runs[0]).join("skipped.log"),
+            )
+            .unwrap();
+            assert!(log.contains(&format!("{held}/D0001-0000-minutely-0.md")));
+        }
+    }
+
+    #[test]
+    fn test_fire_due_cron_jobs_one_shot_archived() {
+        let dir = TempDir::new().unwrap();
+        setup_decree_dir(&dir);
+
+        // Due this minute, or just missed if the test straddles a minute
+        // boundary; catch_up: once enqueues it either way.
+        let at = Utc::now().format("%Y-%m-%dT%H:%M:00Z");
+        std::fs::write(
+            dir.path().join(".decree/cron/release.md"),
+            format!("---\nat: {at}\ncatch_up: once\n---\nShip it.\n"),
+        )
+        .unwrap();
+        seed_cron_state(&dir, "release.md", 1);
+
+        let mut tracker = CronTracker::load(dir.path()).unwrap();
+        fire_due_cron_jobs(dir.path(), &AppConfig::default(), &mut tracker);
+
+        assert_eq!(message::list_inbox_messages(dir.path()).unwrap().len(), 1);
+        assert!(!dir.path().join(".decree/cron/release.md").exists());
+        assert!(dir.path().join(".decree/cron/done/release.md").exists());
+
+        // Nothing fires again on the next pass
+        fire_due_cron_jobs(dir.path(), &AppConfig::default(), &mut tracker);
+        assert_eq!(message::list_inbox_messages(dir.path()).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_fire_due_cron_jobs_overdue_one_shot_catches_up() {
+        let dir = TempDir::new().unwrap();
+        setup_decree_dir(&dir);
+
+        // Dropped in after its time had passed, with no prior cron state
+        let at = (Utc::now() - chrono::TimeDelta::hours(2)).format("%Y-%m-%dT%H:%M:00Z");
+        std::fs::write(
+            dir.path().join(".decree/cron/release.md"),
+            format!("---\nat: {at}\ncatch_up: once\n---\nShip it.\n"),
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join(".decree/cron/expired.md"),
+            format!("---\nat: {at}\n---\nToo late.\n"),
+        )
+        .unwrap();
+
+        let mut tracker = CronTracker::new();
+        fire_due_cron_jobs(dir.path(), &AppConfig::default(), &mut tracker);
+
+        // catch_up: once fires it; the default (none) expires it unfired
+        let inbox_files = message::list_inbox_messages(dir.path()).unwrap();
+        assert_eq!(inbox_files.len(), 1);
+        assert!(inbox_files[0].contains("release-catchup-"));
+        assert!(dir.path().join(".decree/cron/done/release.md").exists());
+        assert!(dir.path().join(".decree/cron/done/expired.md").exists());
+    }
+
+    #[test]
+    fn test_fire_due_cron_jobs_one_shot_future_waits() {
+        let dir = TempDir::new().unwrap();
+        setup_decree_dir(&dir);
+
+        let at = (Utc::now() + chrono::TimeDelta::days(1)).to_rfc3339();
+        std::fs::write(
+            dir.path().join(".decree/cron/later.md"),
+            format!("---\nat: \"{at}\"\n---\nLater.\n"),
+        )
+        .unwrap();
+
+        let mut tracker = CronTracker::new();
+        fire_due_cron_jobs(dir.path(), &AppConfig::default(), &mut tracker);
+
+        assert!(message::list_inbox_messages(dir.path()).unwrap().is_empty());
+        assert!(dir.path().join(".decree/cron/later.md").exists());
+    }
+
+    #[test]
+    fn test_fire_due_cron_jobs_every() {
+        let dir = TempDir::new().unwrap();
+        setup_decree_dir(&dir);
+
+        std::fs::write(
+            dir.path().join(".decree/cron/tick.md"),
+            "---\nevery: 1m\n---\nTick.\n",
+        )
+        .unwrap();
+
+        let mut tracker = CronTracker::new();
+        fire_due_cron_jobs(dir.path(), &AppConfig::default(), &mut tracker);
+
+        let inbox_files = message::list_inbox_messages(dir.path()).unwrap();
+        assert_eq!(inbox_files.len(), 1);
+        assert!(inbox_files[0].ends_with("-tick-0.md"));
+        // The schedule file is kept
+        assert!(dir.path().join(".decree/cron/tick.md").exists());
+    }
+
     #[test]
     fn test_value_as_env_string() {
         assert_eq!(
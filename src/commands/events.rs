@@ -0,0 +1,133 @@
+use crate::error::DecreeError;
+use crate::events::{self, Event};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::time::Duration;
+
+/// How often `--follow` checks the journal for new lines.
+const FOLLOW_POLL: Duration = Duration::from_millis(250);
+
+/// Options for `decree events`.
+#[derive(Debug, Default)]
+pub struct Options {
+    /// Only events for this message ID or chain (prefix match).
+    pub id: Option<String>,
+    /// Only the last N matching events.
+    pub lines: Option<usize>,
+    /// Keep printing events as they are appended.
+    pub follow: bool,
+    /// Print raw JSON lines instead of summaries.
+    pub json: bool,
+}
+
+/// Run `decree events`.
+pub fn run(project_root: &Path, options: &Options) -> Result<(), DecreeError> {
+    let path = events::events_path(project_root);
+    let content = match std::fs::read(&path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(e.into()),
+    };
+
+    // Only complete lines; a trailing partial line is picked up by --follow
+    let complete = content.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+    let text = String::from_utf8_lossy(&content[..complete]);
+    let matching: Vec<&str> = text
+        .lines()
+        .filter(|line| !line.trim().is_empty() && matches(line, options.id.as_deref()))
+        .collect();
+    let skip = options
+        .lines
+        .map_or(0, |n| matching.len().saturating_sub(n));
+    for line in &matching[skip..] {
+        print_line(line, options.json);
+    }
+
+    if !options.follow {
+        if matching.is_empty() {
+            println!("No events recorded.");
+        }
+        return Ok(());
+    }
+    follow(&path, complete as u64, options)
+}
+
+/// Print lines appended after `offset` until interrupted.
+fn follow(path: &Path, mut offset: u64, options: &Options) -> Result<(), DecreeError> {
+    let mut pending = String::new();
+    loop {
+        std::thread::sleep(FOLLOW_POLL);
+
+        let len = match std::fs::metadata(path) {
+            Ok(meta) => meta.len(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        };
+        if len < offset {
+            // The journal was truncated or replaced; start over
+            offset = 0;
+            pending.clear();
+        }
+        if len == offset {
+            continue;
+        }
+
+        let mut file = std::fs::File::open(path)?;
+        file.seek(SeekFrom::Start(offset))?;
+        let mut chunk = Vec::new();
+        file.read_to_end(&mut chunk)?;
+        offset += chunk.len() as u64;
+        pending.push_str(&String::from_utf8_lossy(&chunk));
+
+        while let Some(end) = pending.find('\n') {
+            let line: String = pending.drain(..=end).collect();
+            let line = line.trim_end();
+            if !line.is_empty() && matches(line, options.id.as_deref()) {
+                print_line(line, options.json);
+            }
+        }
+    }
+}
+
+/// Whether a journal line is about the message or chain `id`.
+fn matches(line: &str, id: Option<&str>) -> bool {
+    let Some(id) = id else {
+        return true;
+    };
+    match serde_json::from_str::<Event>(line) {
+        Ok(event) => {
+            event.message_id.as_deref().is_some_and(|m| m.starts_with(id))
+                || event.chain.as_deref().is_some_and(|c| c.starts_with(id))
+        }
+        Err(_) => false,
+    }
+}
+
+fn print_line(line: &str, json: bool) {
+    if json {
+        println!("{line}");
+        return;
+    }
+    match serde_json::from_str::<Event>(line) {
+        Ok(event) => println!("{}", event.summary()),
+        // Lines from a newer decree may not parse; show them as-is
+        Err(_) => println!("{line}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINE: &str = r#"{"ts":"2026-01-02T03:04:05+00:00","actor":"process","type":"enqueued","message_id":"D0001-1200-fix-0","chain":"D0001-1200-fix"}"#;
+
+    #[test]
+    fn test_matches_message_or_chain_prefix() {
+        assert!(matches(LINE, None));
+        assert!(matches(LINE, Some("D0001-1200-fix-0")));
+        assert!(matches(LINE, Some("D0001-1200-fix")));
+        assert!(matches(LINE, Some("D0001")));
+        assert!(!matches(LINE, Some("D0002")));
+        assert!(!matches("not json", Some("D0001")));
+    }
+}
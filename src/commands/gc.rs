@@ -0,0 +1,454 @@
+use super::log;
+use crate::config::{self, AppConfig, RetentionConfig};
+use crate::error::{color, DecreeError};
+use crate::events::{self, Event, EventKind};
+use crate::message;
+use crate::usage;
+use chrono::{DateTime, Local};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use walkdir::WalkDir;
+
+/// Directory inside `.decree/` that holds archived runs.
+pub const ARCHIVE_DIR: &str = "archive";
+
+/// Dispositions a run may be pruned in; everything else is still in flight.
+const FINISHED: &[&str] = &["done", "skipped"];
+
+/// A run selected for pruning.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    pub run: String,
+    pub disposition: &'static str,
+    pub started: Option<DateTime<Local>>,
+    pub bytes: u64,
+}
+
+/// What a gc pass removed, or would remove with `dry_run`.
+#[derive(Debug, Default)]
+pub struct Report {
+    pub pruned: Vec<Candidate>,
+    pub kept: usize,
+    /// Tarball the pruned runs went into, when `archive` is on.
+    pub archive: Option<PathBuf>,
+}
+
+impl Report {
+    pub fn bytes(&self) -> u64 {
+        self.pruned.iter().map(|c| c.bytes).sum()
+    }
+}
+
+/// Run `decree gc [--dry-run]`, with optional overrides of the configured policy.
+pub fn run(
+    project_root: &Path,
+    dry_run: bool,
+    keep_runs: Option<usize>,
+    keep_days: Option<u32>,
+) -> Result<(), DecreeError> {
+    let config = AppConfig::load_from_project(project_root)?;
+    let retention = RetentionConfig {
+        keep_runs: keep_runs.or(config.retention.keep_runs),
+        keep_days: keep_days.or(config.retention.keep_days),
+        ..config.retention
+    };
+    if !retention.is_set() {
+        println!(
+            "No retention policy configured; set retention.keep_runs or retention.keep_days in config.yml (or pass --keep-runs/--keep-days)."
+        );
+        return Ok(());
+    }
+
+    let report = collect(project_root, &retention, dry_run, Local::now())?;
+    if report.pruned.is_empty() {
+        println!("Nothing to prune ({} runs kept).", report.kept);
+        return Ok(());
+    }
+
+    let count = report.pruned.len();
+    let runs = if count == 1 { "run" } else { "runs" };
+    let size = format_bytes(report.bytes());
+    if dry_run {
+        println!("Would prune {count} {runs} ({size}):");
+        for c in &report.pruned {
+            let started = c
+                .started
+                .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+                .unwrap_or_else(|| "unknown".into());
+            println!(
+                "  {:<40} {:<8} {started}  {}",
+                c.run,
+                c.disposition,
+                color::dim(&format_bytes(c.bytes))
+            );
+        }
+    } else if let Some(archive) = &report.archive {
+        let shown = archive.strip_prefix(project_root).unwrap_or(archive);
+        println!("Archived {count} {runs} ({size}) to {}", shown.display());
+    } else {
+        println!("Pruned {count} {runs} ({size})");
+    }
+    println!("{} runs kept.", report.kept);
+    Ok(())
+}
+
+/// Apply `retention` to `.decree/runs/`.
+///
+/// Runs still in flight (queued, running, held, or parked) are never
+/// touched, and dead-lettered runs only go when `keep_failed` is off —
+/// together with their message in `inbox/dead/`. The usage records of
+/// pruned runs are first copied into `runs/usage-history.jsonl`, so
+/// `decree usage` totals and budget caps survive pruning.
+pub fn collect(
+    project_root: &Path,
+    retention: &RetentionConfig,
+    dry_run: bool,
+    now: DateTime<Local>,
+) -> Result<Report, DecreeError> {
+    let runs = message::list_runs(project_root)?;
+    let pruned = candidates(project_root, retention, &runs, now);
+    let mut report = Report {
+        kept: runs.len() - pruned.len(),
+        pruned,
+        archive: None,
+    };
+    if dry_run || report.pruned.is_empty() {
+        return Ok(report);
+    }
+
+    let paths: Vec<String> = report
+        .pruned
+        .iter()
+        .flat_map(|c| {
+            let mut paths = vec![format!("{}/{}", config::RUNS_DIR, c.run)];
+            if c.disposition == "dead" {
+                paths.push(format!("{}/{}/{}.md", config::INBOX_DIR, config::DEAD_DIR, c.run));
+            }
+            paths
+        })
+        .collect();
+
+    let names: Vec<String> = report.pruned.iter().map(|c| c.run.clone()).collect();
+    usage::preserve(project_root, &names)?;
+
+    let decree_dir = project_root.join(config::DECREE_DIR);
+    if retention.archive {
+        report.archive = Some(archive(&decree_dir, &paths, now)?);
+    }
+    for path in &paths {
+        let path = decree_dir.join(path);
+        if path.is_dir() {
+            std::fs::remove_dir_all(&path)?;
+        } else if path.exists() {
+            std::fs::remove_file(&path)?;
+        }
+    }
+    for c in &report.pruned {
+        let detail = match &report.archive {
+            Some(archive) => format!(
+                "archived to {}",
+                archive.strip_prefix(project_root).unwrap_or(archive).display()
+            ),
+            None => "deleted".to_string(),
+        };
+        events::record(
+            project_root,
+            Event {
+                message_id: Some(c.run.clone()),
+                detail: Some(detail),
+                ..Event::new(EventKind::Pruned)
+            },
+        );
+    }
+    Ok(report)
+}
+
+/// Runs (oldest first) that fall outside every keep rule.
+///
+/// The newest run is always kept: the next chain's day counter is read
+/// from it, and pruning it would start IDs over at `D0001`.
+fn candidates(
+    project_root: &Path,
+    retention: &RetentionConfig,
+    runs: &[String],
+    now: DateTime<Local>,
+) -> Vec<Candidate> {
+    if !retention.is_set() {
+        return Vec::new();
+    }
+    let keep_newest = retention.keep_runs.unwrap_or(0).max(1);
+    let newest_from = runs.len().saturating_sub(keep_newest);
+    let cutoff = retention
+        .keep_days
+        .map(|days| now - chrono::TimeDelta::days(i64::from(days)));
+    let runs_dir = project_root.join(config::DECREE_DIR).join(config::RUNS_DIR);
+
+    let mut pruned = Vec::new();
+    for (i, run) in runs.iter().enumerate() {
+        if i >= newest_from {
+            continue;
+        }
+        let run_dir = runs_dir.join(run);
+        let started = log::run_started(&run_dir);
+        // A run of unknown age counts as recent
+        if cutoff.is_some_and(|cutoff| started.is_none_or(|t| t >= cutoff)) {
+            continue;
+        }
+        let Some(disposition) = log::message_state(project_root, run) else {
+            continue;
+        };
+        let prunable =
+            FINISHED.contains(&disposition) || (disposition == "dead" && !retention.keep_failed);
+        if prunable {
+            pruned.push(Candidate {
+                run: run.clone(),
+                disposition,
+                started,
+                bytes: dir_size(&run_dir),
+            });
+        }
+    }
+    pruned
+}
+
+/// Pack `paths` (relative to `.decree/`) into a new tarball with `tar`.
+fn archive(decree_dir: &Path, paths: &[String], now: DateTime<Local>) -> Result<PathBuf, DecreeError> {
+    let archive_dir = decree_dir.join(ARCHIVE_DIR);
+    std::fs::create_dir_all(&archive_dir)?;
+    let archive = archive_dir.join(format!("runs-{}.tar.gz", now.format("%Y%m%d-%H%M%S")));
+
+    let mut child = Command::new("tar")
+        .arg("-czf")
+        .arg(&archive)
+        .arg("-C")
+        .arg(decree_dir)
+        .args(["-T", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| DecreeError::Other(format!("failed to run tar: {e}")))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        let existing = paths.iter().filter(|p| decree_dir.join(p).exists());
+        for path in existing {
+            writeln!(stdin, "{path}")?;
+        }
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        let _ = std::fs::remove_file(&archive);
+        return Err(DecreeError::Other(format!(
+            "tar failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(archive)
+}
+
+fn dir_size(dir: &Path) -> u64 {
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter_map(|e| e.metadata().ok())
+        .filter(|m| m.is_file())
+        .map(|m| m.len())
+        .sum()
+}
+
+/// Format bytes as human-readable.
+fn format_bytes(bytes: u64) -> String {
+    if bytes >= 1_048_576 {
+        format!("{}MB", bytes / 1_048_576)
+    } else if bytes >= 1024 {
+        format!("{}KB", bytes / 1024)
+    } else {
+        format!("{bytes}B")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn setup() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        for sub in ["runs", "inbox/dead"] {
+            std::fs::create_dir_all(dir.path().join(".decree").join(sub)).unwrap();
+        }
+        dir
+    }
+
+    fn add_run(dir: &TempDir, id: &str, start: &str) {
+        let run_dir = dir.path().join(".decree/runs").join(id);
+        std::fs::create_dir_all(&run_dir).unwrap();
+        std::fs::write(
+            run_dir.join("routine.log"),
+            format!("[decree] start {start}\nok\n"),
+        )
+        .unwrap();
+    }
+
+    fn now() -> DateTime<Local> {
+        chrono::NaiveDate::from_ymd_opt(2026, 3, 1)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+            .and_local_timezone(Local)
+            .unwrap()
+    }
+
+    fn names(report: &Report) -> Vec<&str> {
+        report.pruned.iter().map(|c| c.run.as_str()).collect()
+    }
+
+    #[test]
+    fn test_keep_runs_keeps_newest() {
+        let dir = setup();
+        for (i, id) in ["D0001-0900-a-0", "D0002-0900-b-0", "D0003-0900-c-0"].iter().enumerate() {
+            add_run(&dir, id, &format!("2026-01-0{}T09:00:00", i + 1));
+        }
+        let retention = RetentionConfig {
+            keep_runs: Some(1),
+            ..RetentionConfig::default()
+        };
+        let report = collect(dir.path(), &retention, false, now()).unwrap();
+        assert_eq!(names(&report), ["D0001-0900-a-0", "D0002-0900-b-0"]);
+        assert_eq!(report.kept, 1);
+        assert_eq!(message::list_runs(dir.path()).unwrap(), ["D0003-0900-c-0"]);
+    }
+
+    #[test]
+    fn test_keep_days_or_keep_runs() {
+        let dir = setup();
+        add_run(&dir, "D0001-0900-old-0", "2026-01-01T09:00:00");
+        add_run(&dir, "D0002-0900-recent-0", "2026-02-27T09:00:00");
+        add_run(&dir, "D0003-0900-newest-0", "2026-01-02T09:00:00");
+        let retention = RetentionConfig {
+            keep_runs: Some(1),
+            keep_days: Some(7),
+            ..RetentionConfig::default()
+        };
+        let report = collect(dir.path(), &retention, true, now()).unwrap();
+        assert_eq!(names(&report), ["D0001-0900-old-0"]);
+        // Dry run leaves everything in place
+        assert_eq!(message::list_runs(dir.path()).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_in_flight_and_dead_runs_are_protected() {
+        let dir = setup();
+        add_run(&dir, "D0001-0900-dead-0", "2026-01-01T09:00:00");
+        add_run(&dir, "D0002-0900-running-0", "2026-01-01T09:00:00");
+        std::fs::write(dir.path().join(".decree/inbox/dead/D0001-0900-dead-0.md"), "x").unwrap();
+        std::fs::write(dir.path().join(".decree/inbox/D0002-0900-running-0.md"), "x").unwrap();
+        let retention = RetentionConfig {
+            keep_days: Some(1),
+            ..RetentionConfig::default()
+        };
+        assert!(collect(dir.path(), &retention, true, now()).unwrap().pruned.is_empty());
+
+        let retention = RetentionConfig {
+            keep_failed: false,
+            ..retention
+        };
+        let report = collect(dir.path(), &retention, false, now()).unwrap();
+        assert_eq!(names(&report), ["D0001-0900-dead-0"]);
+        assert!(!dir.path().join(".decree/inbox/dead/D0001-0900-dead-0.md").exists());
+        assert!(dir.path().join(".decree/runs/D0002-0900-running-0").exists());
+    }
+
+    #[test]
+    fn test_usage_and_budget_survive_pruning() {
+        let dir = setup();
+        add_run(&dir, "D0001-0900-a-0", "2026-01-01T09:00:00");
+        add_run(&dir, "D0002-0900-b-0", "2026-01-02T09:00:00");
+        let runs = dir.path().join(".decree/runs");
+        std::fs::write(
+            runs.join("D0001-0900-a-0/message.md"),
+            "---\nid: D0001-0900-a-0\nchain: D0001-0900-a\nmigration: 01-a.md\n---\n",
+        )
+        .unwrap();
+        let record = usage::UsageRecord {
+            recorded_at: Some(Local::now().to_rfc3339()),
+            backend: "claude".into(),
+            cost: Some(6.0),
+            ..Default::default()
+        };
+        usage::append(&runs.join("D0001-0900-a-0"), &record).unwrap();
+
+        let config = AppConfig {
+            budget: config::BudgetConfig {
+                daily: Some(5.0),
+                per_migration: Some(5.0),
+            },
+            ..AppConfig::default()
+        };
+        let msg = message::InboxMessage {
+            id: Some("D0001-0900-a-1".into()),
+            chain: Some("D0001-0900-a".into()),
+            seq: Some(1),
+            routine: None,
+            migration: None,
+            body: String::new(),
+            custom_fields: Default::default(),
+            filename: "D0001-0900-a-1.md".into(),
+        };
+        assert!(usage::budget_exceeded(dir.path(), &config, &msg).unwrap().is_some());
+
+        let retention = RetentionConfig {
+            keep_runs: Some(1),
+            ..RetentionConfig::default()
+        };
+        let report = collect(dir.path(), &retention, false, now()).unwrap();
+        assert_eq!(names(&report), ["D0001-0900-a-0"]);
+        // Kept apart from the per-run usage.jsonl, whose records differ
+        assert!(runs.join("usage-history.jsonl").is_file());
+        assert!(!runs.join("usage.jsonl").exists());
+
+        let reason = usage::budget_exceeded(dir.path(), &config, &msg).unwrap().unwrap();
+        assert!(reason.contains("daily budget"), "{reason}");
+        let history = usage::all_usage(dir.path()).unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].message_id, "D0001-0900-a-0");
+        assert_eq!(history[0].migration.as_deref(), Some("01-a.md"));
+
+        // Per-migration caps still trip once the daily cap is out of the way
+        let config = AppConfig {
+            budget: config::BudgetConfig {
+                daily: None,
+                per_migration: Some(5.0),
+            },
+            ..config
+        };
+        let reason = usage::budget_exceeded(dir.path(), &config, &msg).unwrap().unwrap();
+        assert!(reason.contains("01-a.md"), "{reason}");
+    }
+
+    #[test]
+    fn test_newest_run_is_kept_for_chain_ids() {
+        let dir = setup();
+        add_run(&dir, "D0006-0900-a-0", "2026-01-01T09:00:00");
+        add_run(&dir, "D0007-2359-b-0", "2026-01-02T23:59:00");
+        // Both runs are older than keep_days; only the newest survives
+        let retention = RetentionConfig {
+            keep_days: Some(1),
+            ..RetentionConfig::default()
+        };
+        let report = collect(dir.path(), &retention, false, now()).unwrap();
+        assert_eq!(names(&report), ["D0006-0900-a-0"]);
+
+        let chain = message::new_chain_id(dir.path(), "next").unwrap();
+        assert!(chain.starts_with("D0007-") || chain.starts_with("D0008-"), "{chain}");
+    }
+
+    #[test]
+    fn test_no_policy_prunes_nothing() {
+        let dir = setup();
+        add_run(&dir, "D0001-0900-a-0", "2020-01-01T09:00:00");
+        let report = collect(dir.path(), &RetentionConfig::default(), false, now()).unwrap();
+        assert!(report.pruned.is_empty());
+        assert_eq!(report.kept, 1);
+    }
+}
@@ -1,3 +1,5 @@
+use crate::ai::{self, BUILTIN_BACKENDS};
+use crate::atomic;
 use crate::config;
 use crate::error::color::is_tty;
 use crate::error::DecreeError;
@@ -5,14 +7,6 @@ use std::io::Write;
 use std::path::Path;
 use std::process::Command;
 
-/// AI backends we search for, in priority order.
-const AI_BACKENDS: &[(&str, &str, &str)] = &[
-    // (command, ai_router template, ai_interactive template)
-    ("opencode", "opencode run {prompt}", "opencode"),
-    ("claude", "claude -p {prompt}", "claude"),
-    ("copilot", "copilot -p {prompt}", "copilot"),
-];
-
 /// Git stash hook routine: git-baseline.sh (beforeEach hook)
 const GIT_BASELINE_SH: &str = include_str!("../templates/git-baseline.sh");
 
@@ -50,24 +44,15 @@ fn is_git_repo() -> bool {
         .unwrap_or(false)
 }
 
-/// Detect available AI backends. Returns list of (command, router_template, interactive_template).
-fn detect_ai_backends() -> Vec<(&'static str, &'static str, &'static str)> {
-    AI_BACKENDS
+/// Detect available AI backends (built-in profiles whose command is on PATH).
+fn detect_ai_backends() -> Vec<&'static str> {
+    BUILTIN_BACKENDS
         .iter()
         .copied()
-        .filter(|(cmd, _, _)| command_exists(cmd))
+        .filter(|cmd| command_exists(cmd))
         .collect()
 }
 
-/// Derive the AI invocation prefix from an ai_router template by stripping {prompt}.
-fn ai_invoke_prefix(ai_router: &str) -> String {
-    ai_router
-        .replace(" {prompt}", "")
-        .replace("{prompt}", "")
-        .trim()
-        .to_string()
-}
-
 /// Detect shared routines in `~/.decree/routines/`.
 fn detect_shared_routines() -> Vec<String> {
     let shared_dir = config::expand_tilde("~/.decree/routines");
@@ -90,19 +75,9 @@ fn detect_shared_routines() -> Vec<String> {
     names
 }
 
-/// Replace AI placeholders in a routine template.
-fn replace_ai_placeholders(template: &str, ai_name: &str, ai_router: &str) -> String {
-    let invoke = ai_invoke_prefix(ai_router);
-    template
-        .replace("{ai_name}", ai_name)
-        .replace("{ai_invoke}", &invoke)
-}
-
 /// Generate config.yml content with the selected AI command.
 fn generate_config(
     ai_name: &str,
-    ai_router: &str,
-    ai_interactive: &str,
     git_hooks: bool,
     routine_names: &[&str],
     shared_routine_names: &[String],
@@ -110,23 +85,69 @@ fn generate_config(
     let mut config = String::new();
 
     config.push_str("commands:\n");
-    config.push_str(&format!("  ai_router: \"{ai_router}\"\n"));
-    config.push_str(&format!("  ai_interactive: \"{ai_interactive}\"\n"));
-
-    // Add commented alternatives
-    for &(name, router, interactive) in AI_BACKENDS {
+    let interactive = |name: &str| ai::builtin_interactive(name).unwrap_or(name).to_string();
+    config.push_str(&format!(
+        "  ai_interactive: \"{}\"   # {{prompt}} or {{prompt_file}} passes the prompt\n",
+        interactive(ai_name)
+    ));
+    for &name in BUILTIN_BACKENDS {
         if name != ai_name {
-            config.push_str(&format!("  # ai_router: \"{router}\"\n"));
-            config.push_str(&format!("  # ai_interactive: \"{interactive}\"\n"));
+            config.push_str(&format!("  # ai_interactive: \"{}\"\n", interactive(name)));
         }
     }
+    config.push('\n');
 
+    // AI backend profiles (used by the router and by `decree ai` in routines)
+    config.push_str("# AI backend for the router and for routines (`decree ai \"<prompt>\"`).\n");
+    config.push_str("# opencode, claude, and copilot are built in; switch by changing `default`.\n");
+    config.push_str("ai:\n");
+    config.push_str(&format!("  default: {ai_name}\n"));
+    let others: Vec<&str> = BUILTIN_BACKENDS
+        .iter()
+        .copied()
+        .filter(|&name| name != ai_name)
+        .collect();
+    for name in &others {
+        config.push_str(&format!("  # default: {name}\n"));
+    }
+    config.push_str(&format!(
+        "  # fallback: [{}]   # tried in order if a backend is missing or over quota\n",
+        others.join(", ")
+    ));
+    config.push_str("  profiles:\n");
+    if let Some(profile) = ai::builtin_profile(ai_name) {
+        let args: Vec<String> = profile.args.iter().map(|a| format!("\"{a}\"")).collect();
+        config.push_str(&format!("    {ai_name}:\n"));
+        config.push_str(&format!("      command: {}\n", profile.command));
+        config.push_str(&format!("      args: [{}]\n", args.join(", ")));
+        config.push_str("      prompt: argv      # argv, stdin, or file\n");
+        config.push_str("      # model: \"<model name>\"  # passed as --model <name>\n");
+        config.push_str("      # timeout: 1800   # seconds\n");
+    }
     config.push('\n');
     config.push_str("max_retries: 3\n");
     config.push_str("max_depth: 10\n");
     config.push_str("max_log_size: 2097152 # Per-log size cap in bytes (2MB), 0 to disable\n");
+    config.push_str("log_head_size: 524288 # Bytes kept from the start of a truncated log; the rest keeps the end\n");
+    config.push_str("# keep_full_log: true # also gzip the untruncated output to routine.log.gz\n");
     config.push_str("default_routine: develop\n");
     config.push_str("routine_source: \"~/.decree/routines\" # optional, shared routines directory\n");
+    config.push_str("# timezone: \"America/Denver\" # cron timezone (UTC if unset, \"local\" for system time)\n");
+    config.push('\n');
+
+    config.push_str("# Routing rules, checked in order before the AI router (first match wins)\n");
+    config.push_str("# routes:\n");
+    config.push_str("#   - name: docs\n");
+    config.push_str("#     routine: develop\n");
+    config.push_str("#     filename: \"*-docs.md\"     # glob on message or migration filename\n");
+    config.push_str("#     frontmatter: { type: docs } # exact frontmatter values\n");
+    config.push_str("#     body: \"(?i)readme\"        # regex searched in the body\n");
+    config.push_str("#     cron: \"nightly-*\"         # glob on the originating cron file\n");
+    config.push('\n');
+
+    config.push_str("# Ask the AI router for ranked candidates and park unsure messages for `decree route`\n");
+    config.push_str("# router:\n");
+    config.push_str("#   min_confidence: 0.6\n");
     config.push('\n');
 
     config.push_str("hooks:\n");
@@ -194,27 +215,27 @@ pub fn run() -> Result<(), DecreeError> {
 
     // 1. Detect AI backend
     let available = detect_ai_backends();
-    let (ai_name, ai_router, ai_interactive) = if available.is_empty() {
+    let ai_name = if available.is_empty() {
         println!("No AI backend detected (opencode, claude, copilot).");
         println!("Visit https://opencode.ai/ to install opencode.");
         println!("Defaulting to opencode.");
-        AI_BACKENDS[0] // opencode defaults
+        BUILTIN_BACKENDS[0] // opencode defaults
     } else if available.len() == 1 {
-        println!("Detected AI backend: {}", available[0].0);
+        println!("Detected AI backend: {}", available[0]);
         available[0]
     } else if is_tty() {
         // Multiple backends found — present selector
-        let options: Vec<String> = available.iter().map(|(cmd, _, _)| cmd.to_string()).collect();
+        let options: Vec<String> = available.iter().map(|cmd| cmd.to_string()).collect();
         let selection = inquire::Select::new("Select AI backend:", options)
             .prompt()
             .map_err(|e| DecreeError::Other(format!("selection cancelled: {e}")))?;
         *available
             .iter()
-            .find(|(cmd, _, _)| *cmd == selection.as_str())
+            .find(|cmd| **cmd == selection.as_str())
             .expect("selection came from available list")
     } else {
         // Non-TTY with multiple: pick first
-        println!("Multiple AI backends detected, using: {}", available[0].0);
+        println!("Multiple AI backends detected, using: {}", available[0]);
         available[0]
     };
 
@@ -268,14 +289,12 @@ pub fn run() -> Result<(), DecreeError> {
 
     let config_content = generate_config(
         ai_name,
-        ai_router,
-        ai_interactive,
         git_hooks,
         &routine_names,
         &shared_routine_names,
     );
-    std::fs::write(
-        format!("{}/{}", config::DECREE_DIR, config::CONFIG_FILE),
+    atomic::write(
+        &Path::new(config::DECREE_DIR).join(config::CONFIG_FILE),
         &config_content,
     )?;
 
@@ -297,16 +316,10 @@ pub fn run() -> Result<(), DecreeError> {
     std::fs::write(format!("{prompts_base}/sow.md"), SOW_PROMPT_MD)?;
     std::fs::write(format!("{prompts_base}/routine.md"), ROUTINE_PROMPT_MD)?;
 
-    // 8. Write routine templates (replace {ai_name}/{ai_invoke} with detected backend)
+    // 8. Write routine templates (they call `decree ai`, so any backend works)
     let routines_base = format!("{}/{}", config::DECREE_DIR, config::ROUTINES_DIR);
-    std::fs::write(
-        format!("{routines_base}/develop.sh"),
-        replace_ai_placeholders(DEVELOP_SH, ai_name, ai_router),
-    )?;
-    std::fs::write(
-        format!("{routines_base}/rust-develop.sh"),
-        replace_ai_placeholders(RUST_DEVELOP_SH, ai_name, ai_router),
-    )?;
+    std::fs::write(format!("{routines_base}/develop.sh"), DEVELOP_SH)?;
+    std::fs::write(format!("{routines_base}/rust-develop.sh"), RUST_DEVELOP_SH)?;
 
     // 9. Write git hook routines if accepted
     if git_hooks {
@@ -352,16 +365,10 @@ mod tests {
 
     #[test]
     fn test_generate_config_without_git_hooks() {
-        let config = generate_config(
-            "claude",
-            "claude -p {prompt}",
-            "claude",
-            false,
-            &["develop", "rust-develop"],
-            &[],
-        );
-        assert!(config.contains("ai_router: \"claude -p {prompt}\""));
-        assert!(config.contains("ai_interactive: \"claude\""));
+        let config = generate_config("claude", false, &["develop", "rust-develop"], &[]);
+        assert!(config.contains("ai:\n  default: claude\n"));
+        assert!(config.contains("    claude:\n      command: claude\n      args: [\"-p\"]\n"));
+        assert!(config.contains("ai_interactive: \"claude {prompt}\""));
         assert!(!config.contains("ai_command"));
         assert!(config.contains("max_retries: 3"));
         assert!(config.contains("beforeEach: \"\""));
@@ -375,14 +382,13 @@ mod tests {
     #[test]
     fn test_generate_config_with_git_hooks() {
         let config = generate_config(
-            "opencode",
-            "opencode run {prompt}",
             "opencode",
             true,
             &["develop", "rust-develop", "git-baseline", "git-stash-changes"],
             &[],
         );
-        assert!(config.contains("ai_router: \"opencode run {prompt}\""));
+        assert!(config.contains("  default: opencode\n"));
+        assert!(config.contains("      args: [\"run\"]\n"));
         assert!(config.contains("beforeEach: \"git-baseline\""));
         assert!(config.contains("afterEach: \"git-stash-changes\""));
         // Should still contain commented versions
@@ -394,26 +400,27 @@ mod tests {
 
     #[test]
     fn test_generate_config_includes_alternatives() {
-        let config = generate_config(
-            "claude",
-            "claude -p {prompt}",
-            "claude",
-            false,
-            &["develop"],
-            &[],
-        );
+        let config = generate_config("claude", false, &["develop"], &[]);
         // Other backends should be commented out
-        assert!(config.contains("# ai_router: \"opencode run {prompt}\""));
-        assert!(config.contains("# ai_router: \"copilot -p {prompt}\""));
+        assert!(config.contains("  # default: opencode\n"));
+        assert!(config.contains("  # default: copilot\n"));
+        assert!(config.contains("  # ai_interactive: \"opencode --prompt {prompt}\"\n"));
+        assert!(config.contains("  # ai_interactive: \"copilot -i {prompt}\"\n"));
         // Selected should not be commented
-        assert!(config.contains("  ai_router: \"claude -p {prompt}\"\n"));
+        assert!(config.contains("  default: claude\n"));
+
+        // The generated config parses, and the profile resolves
+        let parsed: config::AppConfig = serde_yaml::from_str(&config).unwrap();
+        assert!(parsed.commands.ai_router.is_empty());
+        assert_eq!(
+            ai::backend(&parsed, None).unwrap(),
+            ai::backend(&config::AppConfig::default(), Some("claude")).unwrap()
+        );
     }
 
     #[test]
     fn test_generate_config_with_shared_routines() {
         let config = generate_config(
-            "claude",
-            "claude -p {prompt}",
             "claude",
             false,
             &["develop"],
@@ -438,15 +445,14 @@ mod tests {
     #[test]
     fn test_develop_template_has_precheck() {
         assert!(DEVELOP_SH.contains("DECREE_PRE_CHECK"));
-        assert!(DEVELOP_SH.contains("{ai_name}"));
-        assert!(DEVELOP_SH.contains("{ai_invoke}"));
+        assert!(DEVELOP_SH.contains("decree ai --check"));
+        assert!(DEVELOP_SH.contains("decree ai \"Read"));
     }
 
     #[test]
     fn test_rust_develop_template_has_precheck() {
         assert!(RUST_DEVELOP_SH.contains("DECREE_PRE_CHECK"));
-        assert!(RUST_DEVELOP_SH.contains("{ai_name}"));
-        assert!(RUST_DEVELOP_SH.contains("{ai_invoke}"));
+        assert!(RUST_DEVELOP_SH.contains("decree ai --check"));
         assert!(RUST_DEVELOP_SH.contains("cargo"));
     }
 
@@ -498,22 +504,6 @@ mod tests {
         assert!(DECREE_GITIGNORE.contains("runs/"));
     }
 
-    #[test]
-    fn test_ai_placeholder_replacement() {
-        let replaced = replace_ai_placeholders(DEVELOP_SH, "claude", "claude -p {prompt}");
-        assert!(replaced.contains("claude -p \"Read"));
-        assert!(replaced.contains("command -v claude"));
-        assert!(!replaced.contains("{ai_name}"));
-        assert!(!replaced.contains("{ai_invoke}"));
-    }
-
-    #[test]
-    fn test_ai_invoke_prefix() {
-        assert_eq!(ai_invoke_prefix("opencode run {prompt}"), "opencode run");
-        assert_eq!(ai_invoke_prefix("claude -p {prompt}"), "claude -p");
-        assert_eq!(ai_invoke_prefix("copilot -p {prompt}"), "copilot -p");
-    }
-
     #[test]
     fn test_git_baseline_has_precheck() {
         assert!(GIT_BASELINE_SH.contains("DECREE_PRE_CHECK"));
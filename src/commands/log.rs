@@ -1,73 +1,329 @@
 use crate::config;
 use crate::error::color;
 use crate::error::DecreeError;
-use crate::message;
+use crate::message::{self, InboxMessage};
+use crate::usage;
+use chrono::{DateTime, Local, NaiveDateTime};
+use regex::Regex;
+use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::Path;
+use std::time::Duration;
 
-/// Run `decree log [ID]`.
-pub fn run(project_root: &Path, id: Option<&str>) -> Result<(), DecreeError> {
-    let runs = message::list_runs(project_root)?;
+/// How often `--follow` checks for new output.
+const FOLLOW_POLL: Duration = Duration::from_millis(200);
 
-    match id {
-        None => {
-            if runs.is_empty() {
-                println!("No runs found.");
-                return Ok(());
-            }
-            run_no_id(project_root, &runs)
-        }
-        Some(query) => run_with_id(project_root, query),
+/// Dispositions `--disposition` accepts (see [`message_state`]).
+const DISPOSITIONS: &[&str] = &[
+    "running",
+    "done",
+    "skipped",
+    "dead",
+    "needs_routing",
+    "awaiting_approval",
+];
+
+/// Options for `decree log`.
+#[derive(Debug, Default)]
+pub struct Options {
+    /// Stream the running (or named) message until it finishes.
+    pub follow: bool,
+    /// Show only log lines matching this regex, across runs.
+    pub grep: Option<String>,
+    /// Lines of context around each `grep` match.
+    pub context: usize,
+    /// Only this attempt's log (`routine.log` is attempt 1).
+    pub attempt: Option<u32>,
+    /// Only the last N lines of each log.
+    pub tail: Option<usize>,
+    /// Only runs started at or after this time (`12h`, `7d`, `YYYY-MM-DD`).
+    pub since: Option<String>,
+    /// Only runs started before this time.
+    pub until: Option<String>,
+    pub routine: Option<String>,
+    /// Where the message ended up: done, dead, skipped, ...
+    pub disposition: Option<String>,
+    pub migration: Option<String>,
+}
+
+/// Run `decree log [ID]`, or stream a running message with `--follow`.
+pub fn run(project_root: &Path, id: Option<&str>, options: &Options) -> Result<(), DecreeError> {
+    if options.follow {
+        return follow_run(project_root, id);
+    }
+
+    let filter = RunFilter::new(options)?;
+    let mut runs = match id {
+        Some(query) => message::find_matching_runs(project_root, query)?,
+        None => message::list_runs(project_root)?,
+    };
+    if let (Some(query), true) = (id, runs.is_empty()) {
+        return Err(DecreeError::MessageNotFound(query.to_string()));
+    }
+    runs.retain(|run| filter.matches(project_root, run));
+
+    if let Some(pattern) = &options.grep {
+        return grep_runs(project_root, &runs, pattern, options);
     }
+
+    if runs.is_empty() {
+        println!(
+            "{}",
+            if filter.is_active() { "No runs match." } else { "No runs found." }
+        );
+        return Ok(());
+    }
+    if id.is_none() && !filter.is_active() {
+        return run_no_id(project_root, &runs, options);
+    }
+    show_runs(project_root, runs, options)
 }
 
-/// No ID provided.
-fn run_no_id(project_root: &Path, runs: &[String]) -> Result<(), DecreeError> {
+/// No ID or filters provided.
+fn run_no_id(project_root: &Path, runs: &[String], options: &Options) -> Result<(), DecreeError> {
     if color::is_tty() {
         // TTY: arrow-key selector (most recent first)
-        let mut options: Vec<String> = runs.to_vec();
-        options.reverse();
-        let selection = inquire::Select::new("Select run:", options)
+        let mut choices: Vec<String> = runs.to_vec();
+        choices.reverse();
+        let selection = inquire::Select::new("Select run:", choices)
             .prompt()
             .map_err(|e| DecreeError::Other(format!("selection cancelled: {e}")))?;
-        display_run_logs(project_root, &selection)
+        display_run_logs(project_root, &selection, options)
     } else {
         // Non-TTY: print most recent
         if let Some(latest) = runs.last() {
-            display_run_logs(project_root, latest)
+            display_run_logs(project_root, latest, options)
         } else {
             Ok(())
         }
     }
 }
 
-/// ID provided (may be full, chain, or prefix).
-fn run_with_id(project_root: &Path, query: &str) -> Result<(), DecreeError> {
-    let matches = message::find_matching_runs(project_root, query)?;
+/// Runs selected by ID (full, chain, or prefix) or by filters.
+fn show_runs(project_root: &Path, runs: Vec<String>, options: &Options) -> Result<(), DecreeError> {
+    if runs.len() == 1 {
+        return display_run_logs(project_root, &runs[0], options);
+    }
+    if color::is_tty() {
+        // Ambiguous + TTY: arrow-key selector
+        let selection = inquire::Select::new("Multiple matches — select run:", runs)
+            .prompt()
+            .map_err(|e| DecreeError::Other(format!("selection cancelled: {e}")))?;
+        display_run_logs(project_root, &selection, options)
+    } else {
+        // Ambiguous + Non-TTY: list all
+        for run in &runs {
+            display_run_logs(project_root, run, options)?;
+            println!();
+        }
+        Ok(())
+    }
+}
 
-    match matches.len() {
-        0 => Err(DecreeError::MessageNotFound(query.to_string())),
-        1 => display_run_logs(project_root, &matches[0]),
-        _ => {
-            if color::is_tty() {
-                // Ambiguous + TTY: arrow-key selector
-                let selection = inquire::Select::new("Multiple matches — select run:", matches)
-                    .prompt()
-                    .map_err(|e| DecreeError::Other(format!("selection cancelled: {e}")))?;
-                display_run_logs(project_root, &selection)
-            } else {
-                // Ambiguous + Non-TTY: list all
-                for m in &matches {
-                    display_run_logs(project_root, m)?;
-                    println!();
+/// Run-level filters: start time, routine, disposition, and migration.
+struct RunFilter<'a> {
+    since: Option<DateTime<Local>>,
+    until: Option<DateTime<Local>>,
+    routine: Option<&'a str>,
+    disposition: Option<&'a str>,
+    migration: Option<&'a str>,
+}
+
+impl<'a> RunFilter<'a> {
+    fn new(options: &'a Options) -> Result<Self, DecreeError> {
+        let now = Local::now();
+        if let Some(disposition) = options.disposition.as_deref() {
+            if !DISPOSITIONS.contains(&disposition) {
+                return Err(DecreeError::Other(format!(
+                    "invalid --disposition '{disposition}' (expected one of: {})",
+                    DISPOSITIONS.join(", ")
+                )));
+            }
+        }
+        Ok(Self {
+            since: options
+                .since
+                .as_deref()
+                .map(|s| usage::parse_time("--since", s, now))
+                .transpose()?,
+            until: options
+                .until
+                .as_deref()
+                .map(|s| usage::parse_time("--until", s, now))
+                .transpose()?,
+            routine: options.routine.as_deref(),
+            disposition: options.disposition.as_deref(),
+            migration: options.migration.as_deref(),
+        })
+    }
+
+    fn is_active(&self) -> bool {
+        self.since.is_some()
+            || self.until.is_some()
+            || self.routine.is_some()
+            || self.disposition.is_some()
+            || self.migration.is_some()
+    }
+
+    fn matches(&self, project_root: &Path, run_name: &str) -> bool {
+        if !self.is_active() {
+            return true;
+        }
+        let run_dir = project_root
+            .join(config::DECREE_DIR)
+            .join(config::RUNS_DIR)
+            .join(run_name);
+
+        if self.since.is_some() || self.until.is_some() {
+            let Some(started) = run_started(&run_dir) else {
+                return false;
+            };
+            if self.since.is_some_and(|since| started < since)
+                || self.until.is_some_and(|until| started >= until)
+            {
+                return false;
+            }
+        }
+        if let Some(disposition) = self.disposition {
+            if message_state(project_root, run_name) != Some(disposition) {
+                return false;
+            }
+        }
+        if self.routine.is_some() || self.migration.is_some() {
+            let msg = std::fs::read_to_string(run_dir.join("message.md"))
+                .ok()
+                .and_then(|content| InboxMessage::parse("message.md", &content).ok());
+            let Some(msg) = msg else {
+                return false;
+            };
+            if self.routine.is_some_and(|r| msg.routine.as_deref() != Some(r)) {
+                return false;
+            }
+            // `01-fix` matches the migration `01-fix.md`
+            if self.migration.is_some_and(|m| {
+                msg.migration.as_deref().map(|name| name.trim_end_matches(".md"))
+                    != Some(m.trim_end_matches(".md"))
+            }) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// When a run started, from the `[decree] start` line of its first log,
+/// else the run directory's modification time.
+pub(crate) fn run_started(run_dir: &Path) -> Option<DateTime<Local>> {
+    let first_log = std::fs::read_to_string(run_dir.join("routine.log"))
+        .or_else(|_| std::fs::read_to_string(run_dir.join("skipped.log")))
+        .unwrap_or_default();
+    let from_log = first_log.lines().find_map(|line| {
+        let ts = line.strip_prefix("[decree] start ")?;
+        NaiveDateTime::parse_from_str(ts.trim(), "%Y-%m-%dT%H:%M:%S")
+            .ok()?
+            .and_local_timezone(Local)
+            .earliest()
+    });
+    from_log.or_else(|| {
+        let modified = std::fs::metadata(run_dir).ok()?.modified().ok()?;
+        Some(DateTime::<Local>::from(modified))
+    })
+}
+
+/// Print log lines matching `pattern`, with context, grouped by run and log.
+fn grep_runs(
+    project_root: &Path,
+    runs: &[String],
+    pattern: &str,
+    options: &Options,
+) -> Result<(), DecreeError> {
+    let regex = Regex::new(pattern)
+        .map_err(|e| DecreeError::Other(format!("invalid --grep pattern: {e}")))?;
+    let mut matching_runs = 0usize;
+    let mut matches = 0usize;
+
+    for run_name in runs {
+        let run_dir = project_root
+            .join(config::DECREE_DIR)
+            .join(config::RUNS_DIR)
+            .join(run_name);
+        let mut run_matched = false;
+        for log_name in selected_logs(project_root, run_name, options)? {
+            let content = std::fs::read_to_string(run_dir.join(&log_name))?;
+            let (first, lines) = tail_lines(&content, options.tail);
+            let hits: Vec<usize> = (0..lines.len())
+                .filter(|&i| regex.is_match(lines[i]))
+                .collect();
+            if hits.is_empty() {
+                continue;
+            }
+            run_matched = true;
+            matches += hits.len();
+
+            println!("{}", color::bold(&format!("=== {run_name} ({log_name}) ===")));
+            let mut last_shown: Option<usize> = None;
+            for &hit in &hits {
+                let from = hit.saturating_sub(options.context);
+                let to = (hit + options.context).min(lines.len() - 1);
+                let from = match last_shown {
+                    Some(last) if last + 1 >= from => last + 1,
+                    Some(_) => {
+                        println!("{}", color::dim("--"));
+                        from
+                    }
+                    None => from,
+                };
+                for (i, line) in lines.iter().enumerate().take(to + 1).skip(from) {
+                    let number = first + i + 1;
+                    if regex.is_match(line) {
+                        println!("{}:{line}", color::bold(&number.to_string()));
+                    } else {
+                        println!("{}", color::dim(&format!("{number}-{line}")));
+                    }
                 }
-                Ok(())
+                last_shown = Some(last_shown.map_or(to, |last| last.max(to)));
             }
+            println!();
         }
+        if run_matched {
+            matching_runs += 1;
+        }
+    }
+
+    if matches == 0 {
+        println!("No matching log lines.");
+    } else {
+        println!(
+            "{matches} matching line{} in {matching_runs} run{}",
+            if matches == 1 { "" } else { "s" },
+            if matching_runs == 1 { "" } else { "s" }
+        );
     }
+    Ok(())
+}
+
+/// The last `tail` lines of `content` (all if `None`), with the index of
+/// the first one.
+fn tail_lines(content: &str, tail: Option<usize>) -> (usize, Vec<&str>) {
+    let lines: Vec<&str> = content.lines().collect();
+    let first = tail.map_or(0, |n| lines.len().saturating_sub(n));
+    (first, lines[first..].to_vec())
+}
+
+/// Logs of a run, limited to `--attempt` when given.
+fn selected_logs(
+    project_root: &Path,
+    run_name: &str,
+    options: &Options,
+) -> Result<Vec<String>, DecreeError> {
+    let mut logs = run_logs(project_root, run_name)?;
+    if let Some(attempt) = options.attempt {
+        logs.retain(|name| attempt_number(name) == Some(attempt));
+    }
+    Ok(logs)
 }
 
-/// Display all log files from a run directory.
-fn display_run_logs(project_root: &Path, run_name: &str) -> Result<(), DecreeError> {
+/// Log file names in a run directory, in attempt order.
+pub fn run_logs(project_root: &Path, run_name: &str) -> Result<Vec<String>, DecreeError> {
     let run_dir = project_root
         .join(config::DECREE_DIR)
         .join(config::RUNS_DIR)
@@ -77,7 +333,6 @@ fn display_run_logs(project_root: &Path, run_name: &str) -> Result<(), DecreeErr
         return Err(DecreeError::MessageNotFound(run_name.to_string()));
     }
 
-    // Collect log files, sorted
     let mut logs: Vec<String> = std::fs::read_dir(&run_dir)?
         .filter_map(|e| e.ok())
         .filter(|e| {
@@ -87,24 +342,46 @@ fn display_run_logs(project_root: &Path, run_name: &str) -> Result<(), DecreeErr
         })
         .filter_map(|e| e.file_name().into_string().ok())
         .collect();
-    logs.sort();
+    logs.sort_by_key(|name| (attempt_number(name).unwrap_or(u32::MAX), name.clone()));
+    Ok(logs)
+}
+
+/// Attempt a routine log belongs to: `routine.log` is 1, `routine-N.log` is N.
+fn attempt_number(log_name: &str) -> Option<u32> {
+    match log_name.strip_suffix(".log")? {
+        "routine" => Some(1),
+        stem => stem.strip_prefix("routine-")?.parse().ok(),
+    }
+}
+
+/// Display the log files of a run directory, honoring `--attempt` and `--tail`.
+fn display_run_logs(project_root: &Path, run_name: &str, options: &Options) -> Result<(), DecreeError> {
+    let run_dir = project_root
+        .join(config::DECREE_DIR)
+        .join(config::RUNS_DIR)
+        .join(run_name);
+    let all_logs = run_logs(project_root, run_name)?;
+    let logs = selected_logs(project_root, run_name, options)?;
 
     if logs.is_empty() {
-        println!(
-            "{}: no logs found",
-            color::dim(run_name),
-        );
+        let what = match options.attempt {
+            Some(attempt) if !all_logs.is_empty() => format!("no attempt {attempt}"),
+            _ => "no logs found".to_string(),
+        };
+        println!("{}: {what}", color::dim(run_name));
         return Ok(());
     }
 
-    let multiple = logs.len() > 1;
+    let multiple = all_logs.len() > 1;
 
-    for (i, log_name) in logs.iter().enumerate() {
+    for log_name in &logs {
         if multiple {
-            let attempt = i + 1;
+            let attempt = attempt_number(log_name)
+                .map(|n| format!(" — Attempt {n}"))
+                .unwrap_or_default();
             println!(
                 "{}",
-                color::bold(&format!("=== {run_name} — Attempt {attempt} ({log_name}) ==="))
+                color::bold(&format!("=== {run_name}{attempt} ({log_name}) ==="))
             );
         } else {
             println!("{}", color::bold(&format!("=== {run_name} ===")));
@@ -112,12 +389,226 @@ fn display_run_logs(project_root: &Path, run_name: &str) -> Result<(), DecreeErr
 
         let log_path = run_dir.join(log_name);
         let content = std::fs::read_to_string(&log_path)?;
-        print!("{content}");
-
-        if !content.ends_with('\n') {
-            println!();
+        match options.tail {
+            Some(_) => {
+                for line in tail_lines(&content, options.tail).1 {
+                    println!("{line}");
+                }
+            }
+            None => {
+                print!("{content}");
+                if !content.ends_with('\n') {
+                    println!();
+                }
+            }
         }
     }
 
     Ok(())
 }
+
+/// Stream a message's logs as they are written, until it stops running.
+///
+/// Without an ID, attaches to the message that is running now. Output of
+/// the current attempt is shown from the start; when a retry begins the
+/// stream moves on to its `routine-N.log`.
+fn follow_run(project_root: &Path, id: Option<&str>) -> Result<(), DecreeError> {
+    let run_name = match id {
+        Some(query) => follow_target(project_root, query)?,
+        None => running_messages(project_root)?.pop().ok_or_else(|| {
+            DecreeError::Other("no message is running (pass an ID to follow a specific one)".into())
+        })?,
+    };
+    let run_dir = project_root
+        .join(config::DECREE_DIR)
+        .join(config::RUNS_DIR)
+        .join(&run_name);
+
+    if message_state(project_root, &run_name) == Some("queued") {
+        println!("{}", color::dim(&format!("Waiting for {run_name} to start...")));
+        while message_state(project_root, &run_name) == Some("queued") {
+            std::thread::sleep(FOLLOW_POLL);
+        }
+    }
+    if message_state(project_root, &run_name) != Some("running") {
+        return display_run_logs(project_root, &run_name, &Options::default());
+    }
+
+    let mut current: Option<String> = None;
+    let mut offset = 0u64;
+    loop {
+        // Check first, so the last pass below sees everything written
+        let state = message_state(project_root, &run_name);
+        let logs = run_logs(project_root, &run_name)?;
+        let start = match &current {
+            Some(name) => logs.iter().position(|l| l == name).unwrap_or(0),
+            None => logs.len().saturating_sub(1),
+        };
+        for log_name in logs.iter().skip(start) {
+            if current.as_deref() != Some(log_name.as_str()) {
+                if current.is_some() {
+                    println!();
+                }
+                let attempt = attempt_number(log_name)
+                    .map(|n| format!(" — Attempt {n}"))
+                    .unwrap_or_default();
+                println!(
+                    "{}",
+                    color::bold(&format!("=== {run_name}{attempt} ({log_name}) ==="))
+                );
+                current = Some(log_name.clone());
+                offset = 0;
+            }
+            offset = print_appended(&run_dir.join(log_name), offset)?;
+        }
+
+        if state != Some("running") {
+            let state = state.unwrap_or("gone");
+            println!("{}", color::dim(&format!("{run_name} finished: {state}")));
+            return Ok(());
+        }
+        std::thread::sleep(FOLLOW_POLL);
+    }
+}
+
+/// Run to follow for `query`: a running match first, then a queued
+/// message, then the latest matching run.
+fn follow_target(project_root: &Path, query: &str) -> Result<String, DecreeError> {
+    let matches = message::find_matching_runs(project_root, query)?;
+    if let Some(running) = matches
+        .iter()
+        .rev()
+        .find(|m| message_state(project_root, m) == Some("running"))
+    {
+        return Ok(running.clone());
+    }
+    if message_state(project_root, query) == Some("queued") {
+        return Ok(query.to_string());
+    }
+    matches
+        .last()
+        .cloned()
+        .ok_or_else(|| DecreeError::MessageNotFound(query.to_string()))
+}
+
+/// Runs whose message is still in the inbox, oldest first.
+fn running_messages(project_root: &Path) -> Result<Vec<String>, DecreeError> {
+    Ok(message::list_runs(project_root)?
+        .into_iter()
+        .filter(|run| message_state(project_root, run) == Some("running"))
+        .collect())
+}
+
+/// Print what was appended to `path` after `offset`; returns the new offset.
+fn print_appended(path: &Path, offset: u64) -> Result<u64, DecreeError> {
+    let len = match std::fs::metadata(path) {
+        Ok(meta) => meta.len(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(offset),
+        Err(e) => return Err(e.into()),
+    };
+    if len <= offset {
+        // Nothing new, or the log was truncated after the attempt ended
+        return Ok(len);
+    }
+
+    let mut file = std::fs::File::open(path)?;
+    file.seek(SeekFrom::Start(offset))?;
+    let mut chunk = Vec::new();
+    file.take(len - offset).read_to_end(&mut chunk)?;
+    let mut stdout = std::io::stdout().lock();
+    stdout.write_all(&chunk)?;
+    stdout.flush()?;
+    Ok(offset + chunk.len() as u64)
+}
+
+/// Where a message is: `queued`, `running`, `done`, `skipped`, `dead`,
+/// `needs_routing`, or `awaiting_approval`. `None` if decree has never
+/// seen it.
+pub(crate) fn message_state(project_root: &Path, id: &str) -> Option<&'static str> {
+    let decree_dir = project_root.join(config::DECREE_DIR);
+    let inbox_dir = decree_dir.join(config::INBOX_DIR);
+    let run_dir = decree_dir.join(config::RUNS_DIR).join(id);
+    let filename = format!("{id}.md");
+
+    if inbox_dir.join(&filename).exists() {
+        return Some(if run_dir.is_dir() {
+            "running"
+        } else {
+            "queued"
+        });
+    }
+    for (dir, state) in [
+        (config::DEAD_DIR, "dead"),
+        (config::NEEDS_ROUTING_DIR, "needs_routing"),
+        (config::PENDING_APPROVAL_DIR, "awaiting_approval"),
+    ] {
+        if inbox_dir.join(dir).join(&filename).exists() {
+            return Some(state);
+        }
+    }
+    if run_dir.is_dir() {
+        return Some(if run_dir.join("skipped.log").exists() {
+            "skipped"
+        } else {
+            "done"
+        });
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_attempt_number() {
+        assert_eq!(attempt_number("routine.log"), Some(1));
+        assert_eq!(attempt_number("routine-2.log"), Some(2));
+        assert_eq!(attempt_number("routine-12.log"), Some(12));
+        assert_eq!(attempt_number("skipped.log"), None);
+    }
+
+    #[test]
+    fn test_run_logs_in_attempt_order() {
+        let dir = TempDir::new().unwrap();
+        let run_dir = dir.path().join(".decree/runs/D0001-1200-x-0");
+        std::fs::create_dir_all(&run_dir).unwrap();
+        for name in ["routine-10.log", "routine-2.log", "routine.log", "message.md"] {
+            std::fs::write(run_dir.join(name), "").unwrap();
+        }
+        assert_eq!(
+            run_logs(dir.path(), "D0001-1200-x-0").unwrap(),
+            ["routine.log", "routine-2.log", "routine-10.log"]
+        );
+    }
+
+    #[test]
+    fn test_tail_lines() {
+        assert_eq!(tail_lines("a\nb\nc\n", Some(2)), (1, vec!["b", "c"]));
+        assert_eq!(tail_lines("a\nb\n", Some(5)), (0, vec!["a", "b"]));
+        assert_eq!(tail_lines("a\nb\n", None), (0, vec!["a", "b"]));
+    }
+
+    #[test]
+    fn test_run_started_from_log() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(
+            dir.path().join("routine.log"),
+            "[decree] start 2026-03-04T05:06:07\nhello\n",
+        )
+        .unwrap();
+        let started = run_started(dir.path()).unwrap();
+        assert_eq!(started.format("%Y-%m-%d %H:%M:%S").to_string(), "2026-03-04 05:06:07");
+    }
+
+    #[test]
+    fn test_run_filter_rejects_unknown_disposition() {
+        let options = Options {
+            disposition: Some("finished".into()),
+            ..Options::default()
+        };
+        let err = RunFilter::new(&options).err().unwrap().to_string();
+        assert!(err.contains("invalid --disposition 'finished'"), "{err}");
+    }
+}
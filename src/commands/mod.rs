@@ -1,11 +1,20 @@
+pub mod ai;
+pub mod api;
+pub mod approve;
+pub mod cron;
 pub mod daemon;
+pub mod events;
+pub mod gc;
 pub mod init;
 pub mod log;
 pub mod process;
 pub mod prompt;
+pub mod route;
 pub mod routine;
 pub mod routine_sync;
+pub mod send;
 pub mod status;
+pub mod usage;
 
 use crate::error::DecreeError;
 
@@ -13,3 +22,20 @@ pub fn help() -> Result<(), DecreeError> {
     print!("{}", include_str!("../templates/help.txt"));
     Ok(())
 }
+
+/// Parse repeatable `KEY=VALUE` arguments given with `flag`.
+pub(crate) fn parse_key_values(
+    args: &[String],
+    flag: &str,
+) -> Result<Vec<(String, String)>, DecreeError> {
+    args.iter()
+        .map(|arg| match arg.split_once('=') {
+            Some((key, value)) if !key.trim().is_empty() => {
+                Ok((key.trim().to_string(), value.to_string()))
+            }
+            _ => Err(DecreeError::Other(format!(
+                "invalid {flag} '{arg}' (expected KEY=VALUE)"
+            ))),
+        })
+        .collect()
+}
@@ -1,9 +1,17 @@
+use crate::ai;
+use crate::approval;
+use crate::atomic;
+use crate::capture::{self, CappedLog, Limits};
 use crate::commands::routine_sync;
-use crate::config::{self, AppConfig};
+use crate::config::{self, AppConfig, NotifyEvent};
 use crate::error::{color, DecreeError, EXIT_PRECHECK};
+use crate::events::{self, Actor, Event, EventKind};
 use crate::hooks::{self, HookContext, HookType};
-use crate::message::{self, InboxMessage};
+use crate::message::{self, InboxMessage, QueuedMigration, RouterFn};
+use crate::notify::{self, Notification};
+use crate::router;
 use crate::routine;
+use crate::usage;
 use std::collections::BTreeMap;
 use std::os::unix::process::CommandExt;
 use std::path::Path;
@@ -20,6 +28,7 @@ pub fn run(project_root: &Path, dry_run: bool) -> Result<(), DecreeError> {
         return run_dry(project_root);
     }
 
+    events::set_actor(Actor::Process);
     let mut config = AppConfig::load_from_project(project_root)?;
 
     // Run discovery before processing
@@ -82,37 +91,73 @@ pub fn run(project_root: &Path, dry_run: bool) -> Result<(), DecreeError> {
         let migration_content = std::fs::read_to_string(&migration_path)?;
         let migration = message::parse_migration(migration_filename, &migration_content)?;
 
-        // Generate chain ID for this migration
-        let now = chrono::Local::now();
-        let hhmm = now.format("%H%M").to_string();
-        let day = message::next_day_counter(project_root, &hhmm)?;
-        let name = migration_filename.trim_end_matches(".md");
-        let chain = message::build_chain_id(&day, &hhmm, name);
-
-        // Create inbox message with migration content as body
-        let seq = 0u32;
-        let full_id = format!("{chain}-{seq}");
-        let filename = format!("{full_id}.md");
-
-        let msg = InboxMessage {
-            id: Some(full_id),
-            chain: Some(chain.clone()),
-            seq: Some(seq),
-            routine: migration.routine,
-            migration: Some(migration_filename.clone()),
-            body: migration_content,
-            custom_fields: migration.custom_fields,
-            filename,
+        // Resume a message already queued for this migration (e.g. one that
+        // was routed by hand with `decree route`), otherwise start a new chain.
+        // A message still on hold keeps this and later migrations waiting.
+        let chain = match message::queued_migration(project_root, migration_filename)? {
+            Some(QueuedMigration::Inbox(chain)) => chain,
+            Some(QueuedMigration::NeedsRouting) => {
+                print_needs_routing(migration_filename);
+                break;
+            }
+            Some(QueuedMigration::PendingApproval) => {
+                print_migration_awaiting_approval(migration_filename);
+                break;
+            }
+            None => {
+                let now = chrono::Local::now();
+                let hhmm = now.format("%H%M").to_string();
+                let day = message::next_day_counter(project_root, &hhmm)?;
+                let name = migration_filename.trim_end_matches(".md");
+                let chain = message::build_chain_id(&day, &hhmm, name);
+
+                // Create inbox message with migration content as body
+                let seq = 0u32;
+                let full_id = format!("{chain}-{seq}");
+                let filename = format!("{full_id}.md");
+
+                let msg = InboxMessage {
+                    id: Some(full_id),
+                    chain: Some(chain.clone()),
+                    seq: Some(seq),
+                    routine: migration.routine,
+                    migration: Some(migration_filename.clone()),
+                    body: migration_content,
+                    custom_fields: migration.custom_fields,
+                    filename,
+                };
+
+                let inbox_dir = project_root
+                    .join(config::DECREE_DIR)
+                    .join(config::INBOX_DIR);
+                std::fs::create_dir_all(&inbox_dir)?;
+                msg.write_to_inbox(project_root)?;
+                events::record(
+                    project_root,
+                    Event {
+                        detail: Some("migration".into()),
+                        ..Event::for_message(EventKind::Enqueued, &msg)
+                    },
+                );
+                chain
+            }
         };
 
-        let inbox_dir = project_root
-            .join(config::DECREE_DIR)
-            .join(config::INBOX_DIR);
-        std::fs::create_dir_all(&inbox_dir)?;
-        msg.write_to_inbox(project_root)?;
-
         // Drain inbox (process this message and any follow-ups)
-        drain_inbox(project_root, &config, &shutdown, Some(&chain))?;
+        if drain_inbox(project_root, &config, &shutdown, Some(&chain))?.is_some() {
+            break;
+        }
+
+        // Later migrations may depend on this one, so stop if it is on hold
+        let is_migration = |m: &InboxMessage| m.migration.as_deref() == Some(migration_filename.as_str());
+        if router::parked_messages(project_root)?.iter().any(is_migration) {
+            print_needs_routing(migration_filename);
+            break;
+        }
+        if approval::pending_messages(project_root)?.iter().any(is_migration) {
+            print_migration_awaiting_approval(migration_filename);
+            break;
+        }
     }
 
     // Step 7: Run afterAll hook
@@ -146,12 +191,15 @@ pub fn run(project_root: &Path, dry_run: bool) -> Result<(), DecreeError> {
 }
 
 /// Drain the inbox: process all messages LIFO, depth-first within chains.
+///
+/// Stops early, leaving messages in the inbox, when a `budget` cap is
+/// reached; the reason is returned.
 fn drain_inbox(
     project_root: &Path,
     config: &AppConfig,
     shutdown: &Arc<AtomicBool>,
     prefer_chain: Option<&str>,
-) -> Result<(), DecreeError> {
+) -> Result<Option<String>, DecreeError> {
     loop {
         if shutdown.load(Ordering::Relaxed) {
             exit_sigint();
@@ -166,6 +214,16 @@ fn drain_inbox(
         // If prefer_chain is set, prefer messages from that chain.
         let filename = select_next_message(&inbox, prefer_chain);
 
+        if let Ok(next) = InboxMessage::from_file(project_root, &filename) {
+            if let Some(reason) = usage::budget_exceeded(project_root, config, &next)? {
+                println!(
+                    "{}: {reason}; stopping (see `decree usage`, or raise `budget` in config.yml)",
+                    color::warning("budget")
+                );
+                return Ok(Some(reason));
+            }
+        }
+
         match process_single_message(project_root, config, &filename, shutdown) {
             Ok(()) => {}
             Err(e) => {
@@ -173,11 +231,11 @@ fn drain_inbox(
                 // Safety: ensure message is removed from inbox to prevent infinite loop.
                 // process_single_message should dead-letter on all failure paths, but
                 // if it didn't (e.g. early parse/IO error), dead-letter here as fallback.
-                let _ = dead_letter(project_root, &filename);
+                let _ = dead_letter(project_root, &filename, &e.to_string());
             }
         }
     }
-    Ok(())
+    Ok(None)
 }
 
 /// Select next message from inbox: prefer current chain (depth-first), then LIFO.
@@ -230,22 +288,24 @@ pub fn process_single_message(
     let mut msg = InboxMessage::from_file(project_root, filename)?;
 
     // Build the AI router callback if configured
-    let ai_router_cmd = config.commands.ai_router.clone();
-    let ai_router_fn: Option<Box<dyn Fn(&str) -> Result<String, DecreeError>>> =
-        if ai_router_cmd.is_empty() {
-            None
-        } else {
-            Some(Box::new(move |prompt: &str| {
-                invoke_ai_router(&ai_router_cmd, prompt)
-            }))
-        };
+    let ai_router_fn = router::ai_router_fn(config);
     let ai_router_ref = ai_router_fn
         .as_ref()
-        .map(|f| f.as_ref() as &dyn Fn(&str) -> Result<String, DecreeError>);
+        .map(|f| f.as_ref() as &RouterFn);
+    let had_routine = msg.routine.is_some();
     let was_modified = msg.normalize(project_root, config, ai_router_ref)?;
 
     if was_modified {
         msg.write_to_inbox(project_root)?;
+        events::record(project_root, Event::for_message(EventKind::Normalized, &msg));
+    }
+    if !had_routine && msg.routine.is_some() {
+        events::record(project_root, Event::for_message(EventKind::Routed, &msg));
+    }
+
+    // The router was not confident enough: ask, or park the message
+    if msg.routine.is_none() && !route_by_hand(project_root, config, &mut msg)? {
+        return Ok(());
     }
 
     let chain = msg
@@ -267,6 +327,13 @@ pub fn process_single_message(
         .ok_or_else(|| DecreeError::Other("message has no routine after normalization".into()))?
         .clone();
 
+    // Hold for sign-off before running
+    if approval::needs_approval(&msg) {
+        approval::hold(project_root, &msg, "requires_approval is set")?;
+        print_awaiting_approval(&msg_id);
+        return Ok(());
+    }
+
     // Create run directory
     let run_dir = project_root
         .join(config::DECREE_DIR)
@@ -275,7 +342,7 @@ pub fn process_single_message(
     std::fs::create_dir_all(&run_dir)?;
 
     // Copy normalized message to run dir
-    std::fs::write(run_dir.join("message.md"), msg.serialize())?;
+    atomic::write(&run_dir.join("message.md"), msg.serialize())?;
 
     // Find the routine script (registry-aware layered lookup)
     let script_path = match routine::resolve_routine(project_root, config, &routine_name) {
@@ -283,7 +350,20 @@ pub fn process_single_message(
         Err(e) => {
             eprintln!("routine resolution failed for {msg_id}: {e}");
             mark_migration_processed_if_present(project_root, &msg)?;
-            dead_letter(project_root, filename)?;
+            dead_letter(project_root, filename, &e.to_string())?;
+            notify::dead_lettered(project_root, config, &msg, Some(&run_dir), e.to_string());
+            return Err(e);
+        }
+    };
+
+    // Pick the AI backend the routine should use
+    let ai_env = match ai::routine_env(config, &routine_name, &msg.custom_fields) {
+        Ok(env) => env,
+        Err(e) => {
+            eprintln!("AI profile selection failed for {msg_id}: {e}");
+            mark_migration_processed_if_present(project_root, &msg)?;
+            dead_letter(project_root, filename, &e.to_string())?;
+            notify::dead_lettered(project_root, config, &msg, Some(&run_dir), e.to_string());
             return Err(e);
         }
     };
@@ -293,6 +373,8 @@ pub fn process_single_message(
         .join(config::INBOX_DIR)
         .join(filename);
 
+    let started = chrono::Local::now();
+
     // Retry loop
     for attempt in 1..=config.max_retries {
         if shutdown.load(Ordering::Relaxed) {
@@ -337,6 +419,13 @@ pub fn process_single_message(
         let start = chrono::Local::now();
         let start_line = format!("[decree] start {}\n", start.format("%Y-%m-%dT%H:%M:%S"));
         std::fs::write(&log_path, &start_line)?;
+        events::record(
+            project_root,
+            Event {
+                attempt: Some(attempt),
+                ..Event::for_message(EventKind::AttemptStarted, &msg)
+            },
+        );
 
         // Run beforeEach hook
         match hooks::run_hook_with_config(project_root, &config.hooks, HookType::BeforeEach, &hook_ctx, Some(config)) {
@@ -348,7 +437,8 @@ pub fn process_single_message(
                 eprintln!("{}: beforeEach hook failed for {msg_id}: {e}", color::warning("warning"));
                 // beforeEach failure: skip and dead-letter
                 mark_migration_processed_if_present(project_root, &msg)?;
-                dead_letter(project_root, filename)?;
+                dead_letter(project_root, filename, &format!("beforeEach failed: {e}"))?;
+                notify::dead_lettered(project_root, config, &msg, Some(&run_dir), format!("beforeEach failed: {e}"));
                 return Err(DecreeError::Other(format!("beforeEach failed: {e}")));
             }
         }
@@ -361,8 +451,9 @@ pub fn process_single_message(
             project_root,
             &script_path,
             &msg,
+            &ai_env,
             &run_dir,
-            &log_path,
+            CappedLog::open(&log_path, Limits::from_config(config))?,
             shutdown,
         )?;
 
@@ -389,9 +480,46 @@ pub fn process_single_message(
             end.format("%Y-%m-%dT%H:%M:%S")
         );
         append_to_file(&log_path, &end_line)?;
+        events::record(
+            project_root,
+            Event {
+                attempt: Some(attempt),
+                exit_code: Some(exit_code),
+                duration_secs: Some(duration.num_seconds()),
+                ..Event::for_message(EventKind::AttemptEnded, &msg)
+            },
+        );
 
-        // Truncate log if needed
-        truncate_log_if_needed(&log_path, config.max_log_size)?;
+        // Total the attempt's AI usage into run.json
+        usage::write_summary(&run_dir, &msg)?;
+
+        if exit_code == approval::AWAITING_APPROVAL_EXIT_CODE {
+            // AWAITING APPROVAL: pause the chain; the routine runs again once approved
+            let after_ctx = HookContext {
+                routine_exit_code: Some(exit_code),
+                ..hook_ctx
+            };
+            match hooks::run_hook_with_config(project_root, &config.hooks, HookType::AfterEach, &after_ctx, Some(config)) {
+                Ok(hook_output) => {
+                    let _ = write_hook_log(&log_path, HookType::AfterEach, &hook_output.output);
+                }
+                Err(e) => {
+                    let _ = write_hook_log(&log_path, HookType::AfterEach, &e.output);
+                    eprintln!("{}: afterEach hook failed for {msg_id}: {e}", color::warning("warning"));
+                }
+            }
+
+            // Follow-ups wait until the routine runs again after approval
+            clear_outbox(project_root)?;
+
+            approval::hold(
+                project_root,
+                &msg,
+                &format!("{routine_name} exited with code {exit_code} (awaiting approval)"),
+            )?;
+            print_awaiting_approval(&msg_id);
+            return Ok(());
+        }
 
         if exit_code == 0 {
             // SUCCESS
@@ -424,6 +552,13 @@ pub fn process_single_message(
             // If message has migration field, mark as processed
             if let Some(ref migration) = msg.migration {
                 message::mark_processed(project_root, migration)?;
+                let notification = Notification {
+                    attempts: Some(attempt),
+                    exit_code: Some(0),
+                    duration_secs: Some(notify::seconds_since(started)),
+                    ..Notification::for_message(NotifyEvent::MigrationDone, &msg, Some(&run_dir))
+                };
+                notify::send(project_root, config, &notification);
             }
 
             return Ok(());
@@ -457,7 +592,20 @@ pub fn process_single_message(
             mark_migration_processed_if_present(project_root, &msg)?;
 
             // Dead-letter the message
-            dead_letter(project_root, filename)?;
+            dead_letter(
+                project_root,
+                filename,
+                &format!("max retries exhausted (exit code: {exit_code})"),
+            )?;
+
+            let notification = Notification {
+                attempts: Some(attempt),
+                exit_code: Some(exit_code),
+                duration_secs: Some(notify::seconds_since(started)),
+                reason: Some(format!("max retries exhausted (exit code: {exit_code})")),
+                ..Notification::for_message(NotifyEvent::RetriesExhausted, &msg, Some(&run_dir))
+            };
+            notify::send(project_root, config, &notification);
 
             return Err(DecreeError::MaxRetriesExhausted(msg_id));
         }
@@ -471,8 +619,9 @@ fn execute_routine(
     project_root: &Path,
     script_path: &Path,
     msg: &InboxMessage,
+    ai_env: &[(&str, String)],
     run_dir: &Path,
-    log_path: &Path,
+    log: CappedLog,
     shutdown: &Arc<AtomicBool>,
 ) -> Result<i32, DecreeError> {
     let msg_file_path = project_root
@@ -484,11 +633,11 @@ fn execute_routine(
     let chain = msg.chain.as_deref().unwrap_or("");
     let seq = msg.seq.map(|s| s.to_string()).unwrap_or_default();
 
-    // Execute: bash <script> 2>&1 | tee -a <log_path>
+    // Execute: bash <script> 2>&1, with decree copying the output to the
+    // terminal and the size-capped log
     let cmd_str = format!(
-        "set -o pipefail; bash {} 2>&1 | tee -a {}",
+        "bash {} 2>&1",
         shell_escape(script_path.to_string_lossy().as_ref()),
-        shell_escape(log_path.to_string_lossy().as_ref()),
     );
 
     let mut cmd = std::process::Command::new("bash");
@@ -501,6 +650,9 @@ fn execute_routine(
         .env("message_dir", run_dir.to_string_lossy().as_ref())
         .env("chain", chain)
         .env("seq", &seq);
+    if let Some(path) = routine::path_with_decree() {
+        cmd.env("PATH", path);
+    }
 
     // Pass custom fields as env vars
     for (key, value) in &msg.custom_fields {
@@ -508,12 +660,13 @@ fn execute_routine(
             cmd.env(key, &s);
         }
     }
+    cmd.envs(ai_env.iter().map(|(k, v)| (k, v)));
 
     // Put child in its own process group so we can kill the entire tree on SIGINT.
     cmd.process_group(0);
 
     // Ignore SIGTTIN/SIGTTOU in the child so the background process group
-    // doesn't get stopped when writing to the terminal (tee) or if any
+    // doesn't get stopped when probing the terminal, or if any
     // subprocess probes the TTY. The parent stays in the foreground group
     // so it receives Ctrl+C (SIGINT) and can kill the child group.
     unsafe {
@@ -526,11 +679,15 @@ fn execute_routine(
 
     // Routines run unattended — no terminal input needed.
     cmd.stdin(std::process::Stdio::null());
+    cmd.stdout(std::process::Stdio::piped());
 
     let mut child = cmd.spawn()?;
     let child_id = child.id();
     CHILD_PID.store(child_id, Ordering::SeqCst);
 
+    let output = child.stdout.take().expect("stdout is piped");
+    let copier = std::thread::spawn(move || capture::tee(output, log));
+
     // Poll for completion, checking for SIGINT between iterations.
     let exit_code = loop {
         match child.try_wait()? {
@@ -552,6 +709,9 @@ fn execute_routine(
     };
 
     CHILD_PID.store(0, Ordering::SeqCst);
+    copier
+        .join()
+        .map_err(|_| DecreeError::Other("log capture thread panicked".into()))??;
     Ok(exit_code)
 }
 
@@ -578,9 +738,9 @@ fn collect_outbox(
 
     entries.sort();
 
-    // Warn about non-.md files
+    // Warn about non-.md files; `*.tmp` files are writes still in progress
     for entry in &entries {
-        if !entry.ends_with(".md") {
+        if !entry.ends_with(".md") && !atomic::is_tmp(entry) {
             eprintln!(
                 "{}: non-.md file in outbox ignored: {entry}",
                 color::warning("Warning")
@@ -615,6 +775,14 @@ fn collect_outbox(
             );
             std::fs::create_dir_all(&outbox_dead_dir)?;
             std::fs::rename(&file_path, outbox_dead_dir.join(file))?;
+            events::record(
+                project_root,
+                Event {
+                    chain: Some(chain.to_string()),
+                    detail: Some(format!("outbox/{file}: max depth {} exceeded", config.max_depth)),
+                    ..Event::new(EventKind::DeadLettered)
+                },
+            );
             continue;
         }
 
@@ -649,6 +817,13 @@ fn collect_outbox(
 
         inbox_msg.write_to_inbox(project_root)?;
         std::fs::remove_file(&file_path)?;
+        events::record(
+            project_root,
+            Event {
+                detail: Some(format!("from outbox/{file}")),
+                ..Event::for_message(EventKind::FollowupCollected, &inbox_msg)
+            },
+        );
         next_seq += 1;
     }
 
@@ -675,6 +850,88 @@ fn clear_outbox(project_root: &Path) -> Result<(), DecreeError> {
     Ok(())
 }
 
+/// Tell the user a migration is parked and holds up later migrations.
+fn print_needs_routing(migration: &str) {
+    println!("Migration {migration} needs routing; later migrations wait until it is routed.");
+}
+
+/// Tell the user a migration is held for approval and holds up later ones.
+fn print_migration_awaiting_approval(migration: &str) {
+    println!(
+        "Migration {migration} is awaiting approval; later migrations wait until it is approved."
+    );
+}
+
+/// Tell the user how to release a message held for approval.
+fn print_awaiting_approval(msg_id: &str) {
+    println!(
+        "{}: {msg_id} is awaiting approval; run `decree approve {msg_id}` or `decree reject {msg_id}`",
+        color::warning("held")
+    );
+}
+
+/// Route a message the AI router was unsure about.
+///
+/// In a TTY, offers the router's candidates (then every other routine) in a
+/// selector. Otherwise, or if the user leaves it for later, the message is
+/// parked in `inbox/needs-routing/`. Returns `true` if a routine was chosen.
+fn route_by_hand(
+    project_root: &Path,
+    config: &AppConfig,
+    msg: &mut InboxMessage,
+) -> Result<bool, DecreeError> {
+    let msg_id = msg.id.clone().unwrap_or_else(|| msg.filename.clone());
+
+    if color::is_tty() {
+        if let Some(routine) = select_routine(project_root, config, &msg_id)? {
+            msg.routine = Some(routine);
+            msg.write_to_inbox(project_root)?;
+            events::record(
+                project_root,
+                Event {
+                    detail: Some("by hand".into()),
+                    ..Event::for_message(EventKind::Routed, msg)
+                },
+            );
+            return Ok(true);
+        }
+    }
+
+    router::park(project_root, msg)?;
+    println!(
+        "{}: {msg_id} needs routing; run `decree route {msg_id} <routine>` to resolve it",
+        color::warning("parked")
+    );
+    Ok(false)
+}
+
+/// Ask which routine should handle a message; `None` leaves it for later.
+fn select_routine(
+    project_root: &Path,
+    config: &AppConfig,
+    msg_id: &str,
+) -> Result<Option<String>, DecreeError> {
+    const LATER: &str = "(leave for later)";
+
+    let candidates = router::recorded_candidates(project_root, msg_id);
+    let mut options: Vec<String> = candidates
+        .iter()
+        .map(|c| format!("{:<16} {:.0}%", c.routine, c.confidence * 100.0))
+        .collect();
+    for info in message::list_routines(project_root, config)? {
+        if !candidates.iter().any(|c| c.routine == info.name) {
+            options.push(info.name);
+        }
+    }
+    options.push(LATER.to_string());
+
+    let selection = inquire::Select::new(&format!("Route {msg_id} to:"), options).prompt();
+    Ok(match selection {
+        Ok(choice) if choice != LATER => choice.split_whitespace().next().map(str::to_string),
+        _ => None,
+    })
+}
+
 /// If the message originated from a migration, mark it as processed
 /// so the outer migration loop doesn't retry it infinitely.
 fn mark_migration_processed_if_present(
@@ -687,8 +944,8 @@ fn mark_migration_processed_if_present(
     Ok(())
 }
 
-/// Move a message to the dead-letter directory.
-fn dead_letter(project_root: &Path, filename: &str) -> Result<(), DecreeError> {
+/// Move a message to the dead-letter directory, recording `reason`.
+fn dead_letter(project_root: &Path, filename: &str, reason: &str) -> Result<(), DecreeError> {
     let inbox_path = project_root
         .join(config::DECREE_DIR)
         .join(config::INBOX_DIR)
@@ -703,7 +960,21 @@ fn dead_letter(project_root: &Path, filename: &str) -> Result<(), DecreeError> {
 
     let dead_path = dead_dir.join(filename);
     if inbox_path.exists() {
+        let event = match InboxMessage::from_file(project_root, filename) {
+            Ok(msg) => Event::for_message(EventKind::DeadLettered, &msg),
+            Err(_) => Event {
+                message_id: Some(filename.trim_end_matches(".md").to_string()),
+                ..Event::new(EventKind::DeadLettered)
+            },
+        };
         std::fs::rename(&inbox_path, &dead_path)?;
+        events::record(
+            project_root,
+            Event {
+                detail: Some(reason.to_string()),
+                ..event
+            },
+        );
     }
 
     Ok(())
@@ -742,33 +1013,6 @@ fn print_progress(msg: &str) {
     }
 }
 
-/// Invoke the AI router command with the given prompt.
-///
-/// The router command template uses `{prompt}` as a placeholder for the actual prompt.
-/// Falls back to passing the prompt as a trailing argument if no placeholder is found.
-fn invoke_ai_router(cmd_template: &str, prompt: &str) -> Result<String, DecreeError> {
-    let cmd_str = if cmd_template.contains("{prompt}") {
-        cmd_template.replace("{prompt}", &shell_escape(prompt))
-    } else {
-        format!("{} {}", cmd_template, shell_escape(prompt))
-    };
-
-    let output = std::process::Command::new("bash")
-        .arg("-c")
-        .arg(&cmd_str)
-        .output()
-        .map_err(|e| DecreeError::Other(format!("failed to run AI router: {e}")))?;
-
-    if !output.status.success() {
-        return Err(DecreeError::Other(format!(
-            "AI router exited with code {}",
-            output.status.code().unwrap_or(1)
-        )));
-    }
-
-    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
-}
-
 /// Write hook output to a log file.
 ///
 /// Format:
@@ -810,44 +1054,6 @@ fn append_to_file(path: &Path, text: &str) -> Result<(), DecreeError> {
     Ok(())
 }
 
-/// Truncate a log file to max_log_size bytes, keeping the tail.
-fn truncate_log_if_needed(path: &Path, max_size: u64) -> Result<(), DecreeError> {
-    if max_size == 0 {
-        return Ok(());
-    }
-
-    let metadata = std::fs::metadata(path)?;
-    if metadata.len() <= max_size {
-        return Ok(());
-    }
-
-    let content = std::fs::read(path)?;
-    let skip = content.len() - max_size as usize;
-    let truncated = &content[skip..];
-
-    let marker = format!(
-        "[log truncated — showing last {} of output]\n",
-        format_bytes(max_size)
-    );
-    let mut new_content = marker.into_bytes();
-    new_content.extend_from_slice(truncated);
-
-    std::fs::write(path, &new_content)?;
-
-    Ok(())
-}
-
-/// Format a byte count for the truncation marker.
-fn format_bytes(bytes: u64) -> String {
-    if bytes >= 1_048_576 {
-        format!("{}MB", bytes / 1_048_576)
-    } else if bytes >= 1024 {
-        format!("{}KB", bytes / 1024)
-    } else {
-        format!("{bytes}B")
-    }
-}
-
 /// Format a chrono Duration as human-readable.
 fn format_duration(d: chrono::TimeDelta) -> String {
     let total_secs = d.num_seconds();
@@ -988,13 +1194,6 @@ mod tests {
         assert_eq!(format_duration(d), "2m05s");
     }
 
-    #[test]
-    fn test_format_bytes() {
-        assert_eq!(format_bytes(500), "500B");
-        assert_eq!(format_bytes(2048), "2KB");
-        assert_eq!(format_bytes(2_097_152), "2MB");
-    }
-
     #[test]
     fn test_shell_escape() {
         assert_eq!(shell_escape("simple"), "'simple'");
@@ -1009,7 +1208,7 @@ mod tests {
         let inbox = dir.path().join(".decree/inbox");
         std::fs::write(inbox.join("test-0.md"), "content").unwrap();
 
-        dead_letter(dir.path(), "test-0.md").unwrap();
+        dead_letter(dir.path(), "test-0.md", "test").unwrap();
 
         assert!(!inbox.join("test-0.md").exists());
         assert!(inbox.join("dead/test-0.md").exists());
@@ -1019,7 +1218,7 @@ mod tests {
     fn test_dead_letter_nonexistent() {
         let dir = TempDir::new().unwrap();
         setup_decree_dir(&dir);
-        let result = dead_letter(dir.path(), "nonexistent.md");
+        let result = dead_letter(dir.path(), "nonexistent.md", "test");
         assert!(result.is_ok());
     }
 
@@ -1105,41 +1304,6 @@ mod tests {
         assert!(outbox.join("dead/followup.md").exists());
     }
 
-    #[test]
-    fn test_truncate_log_disabled() {
-        let dir = TempDir::new().unwrap();
-        let log = dir.path().join("test.log");
-        std::fs::write(&log, "a".repeat(5000)).unwrap();
-
-        truncate_log_if_needed(&log, 0).unwrap();
-        assert_eq!(std::fs::metadata(&log).unwrap().len(), 5000);
-    }
-
-    #[test]
-    fn test_truncate_log_under_limit() {
-        let dir = TempDir::new().unwrap();
-        let log = dir.path().join("test.log");
-        std::fs::write(&log, "small log").unwrap();
-
-        truncate_log_if_needed(&log, 1000).unwrap();
-        assert_eq!(std::fs::read_to_string(&log).unwrap(), "small log");
-    }
-
-    #[test]
-    fn test_truncate_log_over_limit() {
-        let dir = TempDir::new().unwrap();
-        let log = dir.path().join("test.log");
-        let content = "x".repeat(200);
-        std::fs::write(&log, &content).unwrap();
-
-        truncate_log_if_needed(&log, 100).unwrap();
-
-        let result = std::fs::read_to_string(&log).unwrap();
-        assert!(result.starts_with("[log truncated"));
-        assert!(result.contains("100B"));
-        assert!(result.ends_with(&"x".repeat(100)));
-    }
-
     #[test]
     fn test_process_single_message_success() {
         let dir = TempDir::new().unwrap();
@@ -1492,28 +1656,6 @@ mod tests {
         assert!(log.contains("[decree] hook afterEach end"));
     }
 
-    #[test]
-    fn test_invoke_ai_router_success() {
-        // Use printf to avoid trailing args from the prompt
-        let result = invoke_ai_router("printf rust-develop", "ignored prompt");
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), "rust-develop");
-    }
-
-    #[test]
-    fn test_invoke_ai_router_failure() {
-        let result = invoke_ai_router("exit 1", "test prompt");
-        assert!(result.is_err());
-    }
-
-    #[test]
-    fn test_invoke_ai_router_with_prompt_placeholder() {
-        let result = invoke_ai_router("echo {prompt}", "hello world");
-        assert!(result.is_ok());
-        // The prompt is shell-escaped, so it comes through as the literal string
-        assert!(result.unwrap().contains("hello world"));
-    }
-
     #[test]
     fn test_ai_router_used_in_normalize() {
         let dir = TempDir::new().unwrap();
@@ -1566,6 +1708,90 @@ mod tests {
         )
         .unwrap();
         assert!(run_msg.contains("routine: rust-develop"));
+
+        // The router call is recorded alongside the run
+        let record = std::fs::read_to_string(
+            dir.path().join(".decree/runs/D0001-1432-test-0/router.json"),
+        )
+        .unwrap();
+        assert!(record.contains("\"raw_output\": \"rust-develop\""));
+        assert!(record.contains("\"fallback\": false"));
+    }
+
+    #[test]
+    fn test_unsure_router_answer_parks_message() {
+        let dir = TempDir::new().unwrap();
+        setup_decree_dir(&dir);
+        std::fs::write(
+            dir.path().join(".decree/routines/develop.sh"),
+            "#!/usr/bin/env bash\necho 'done'\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join(".decree/router.md"),
+            "{routines}\n\n{message}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join(".decree/config.yml"),
+            "commands:\n  ai_router: printf 'develop 0.4'\n  ai_interactive: echo\nrouter:\n  min_confidence: 0.5\n",
+        )
+        .unwrap();
+
+        let content = "---\nid: D0001-1432-test-0\nchain: D0001-1432-test\nseq: 0\n---\nTest body.\n";
+        std::fs::write(
+            dir.path().join(".decree/inbox/D0001-1432-test-0.md"),
+            content,
+        )
+        .unwrap();
+
+        let config = AppConfig::load_from_project(dir.path()).unwrap();
+        let shutdown = Arc::new(AtomicBool::new(false));
+        process_single_message(dir.path(), &config, "D0001-1432-test-0.md", &shutdown).unwrap();
+
+        // Parked without running; the router record keeps the candidates
+        assert!(!dir.path().join(".decree/inbox/D0001-1432-test-0.md").exists());
+        let parked = std::fs::read_to_string(
+            dir.path().join(".decree/inbox/needs-routing/D0001-1432-test-0.md"),
+        )
+        .unwrap();
+        assert!(!parked.contains("routine:"));
+        let run_dir = dir.path().join(".decree/runs/D0001-1432-test-0");
+        assert!(!run_dir.join("message.md").exists());
+        let record = std::fs::read_to_string(run_dir.join("router.json")).unwrap();
+        assert!(record.contains("\"needs_routing\": true"));
+    }
+
+    #[test]
+    fn test_requires_approval_holds_message() {
+        let dir = TempDir::new().unwrap();
+        setup_decree_dir(&dir);
+        std::fs::write(
+            dir.path().join(".decree/routines/deploy.sh"),
+            "#!/usr/bin/env bash\ntouch deployed\n",
+        )
+        .unwrap();
+
+        let content = "---\nid: D0001-1432-ship-0\nchain: D0001-1432-ship\nseq: 0\nroutine: deploy\nrequires_approval: true\n---\nShip it.\n";
+        std::fs::write(dir.path().join(".decree/inbox/D0001-1432-ship-0.md"), content).unwrap();
+
+        let config = AppConfig::load_from_project(dir.path()).unwrap();
+        let shutdown = Arc::new(AtomicBool::new(false));
+
+        // Held without running the routine
+        process_single_message(dir.path(), &config, "D0001-1432-ship-0.md", &shutdown).unwrap();
+        assert!(!dir.path().join("deployed").exists());
+        assert!(dir
+            .path()
+            .join(".decree/inbox/pending-approval/D0001-1432-ship-0.md")
+            .exists());
+
+        // Once approved, it runs
+        let pending = approval::find_pending(dir.path(), "D0001-1432-ship-0").unwrap();
+        approval::approve(dir.path(), pending).unwrap();
+        process_single_message(dir.path(), &config, "D0001-1432-ship-0.md", &shutdown).unwrap();
+        assert!(dir.path().join("deployed").exists());
+        assert!(!dir.path().join(".decree/inbox/D0001-1432-ship-0.md").exists());
     }
 
     #[test]
@@ -1,24 +1,47 @@
+use crate::ai;
+use crate::commands;
 use crate::config::{self, AppConfig};
 use crate::error::{color, DecreeError};
 use crate::message;
 use crate::routine;
+use crate::template::{self, Context, Value};
+use std::collections::BTreeMap;
 use std::io::{self, Read as _};
 use std::os::unix::process::CommandExt;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+/// Where a rendered prompt goes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Target {
+    /// Preview, then copy or launch the interactive AI (print when not a TTY).
+    Guided,
+    /// Print to stdout.
+    Stdout,
+    /// Write to a file.
+    File(PathBuf),
+    /// Run through the AI backend, optionally with a specific profile.
+    Ai(Option<String>),
+}
 
-/// Run `decree prompt [name]`.
-pub fn run(project_root: &Path, name: Option<&str>) -> Result<(), DecreeError> {
+/// Run `decree prompt [name] [--print|--out FILE|--ai] [--var KEY=VALUE]...`.
+pub fn run(
+    project_root: &Path,
+    name: Option<&str>,
+    target: Target,
+    vars: &[String],
+) -> Result<(), DecreeError> {
+    let vars = commands::parse_key_values(vars, "--var")?;
     let config = AppConfig::load_from_project(project_root)?;
     let prompts = list_prompts(project_root, &config)?;
 
-    if prompts.is_empty() {
+    if prompts.is_empty() && target == Target::Guided {
         println!("No prompts found in .decree/prompts/");
         return Ok(());
     }
 
     match name {
-        Some(name) => run_named(project_root, &prompts, name),
-        None => run_select(project_root, &prompts),
+        Some(name) => run_named(project_root, &prompts, name, target, &vars),
+        None => run_select(project_root, &prompts, &vars),
     }
 }
 
@@ -104,6 +127,8 @@ fn run_named(
     project_root: &Path,
     prompts: &[PromptInfo],
     name: &str,
+    target: Target,
+    vars: &[(String, String)],
 ) -> Result<(), DecreeError> {
     let info = match prompts.iter().find(|p| p.name == name) {
         Some(p) => p,
@@ -111,21 +136,35 @@ fn run_named(
     };
 
     let config = AppConfig::load_from_project(project_root)?;
-    let prompt_text = build_prompt(project_root, &config, &info.name)?;
+    let prompt_text = build_prompt(project_root, &config, &info.name, vars)?;
 
-    if !color::is_tty() {
-        // Non-TTY: print substituted prompt and exit
-        print!("{prompt_text}");
-        return Ok(());
+    match target {
+        Target::Stdout => {
+            print!("{prompt_text}");
+            Ok(())
+        }
+        Target::File(path) => {
+            std::fs::write(&path, &prompt_text).map_err(|e| {
+                DecreeError::Other(format!("cannot write {}: {e}", path.display()))
+            })
+        }
+        Target::Ai(profile) => {
+            commands::ai::run(project_root, Some(&prompt_text), profile.as_deref(), false)
+        }
+        Target::Guided if !color::is_tty() => {
+            // Non-TTY: print substituted prompt and exit
+            print!("{prompt_text}");
+            Ok(())
+        }
+        Target::Guided => guided_flow(project_root, &prompt_text),
     }
-
-    guided_flow(project_root, &prompt_text)
 }
 
 /// Run with interactive selection.
 fn run_select(
     project_root: &Path,
     prompts: &[PromptInfo],
+    vars: &[(String, String)],
 ) -> Result<(), DecreeError> {
     if !color::is_tty() {
         // Non-TTY: print list and exit
@@ -157,76 +196,67 @@ fn run_select(
     let selected_name = selection.split_whitespace().next().unwrap_or(&selection);
 
     let config = AppConfig::load_from_project(project_root)?;
-    let prompt_text = build_prompt(project_root, &config, selected_name)?;
+    let prompt_text = build_prompt(project_root, &config, selected_name, vars)?;
     guided_flow(project_root, &prompt_text)
 }
 
-/// Build the prompt by reading the template and substituting variables.
+/// Build the prompt by reading the template and rendering it.
 /// Checks project-local first, then shared prompts.
-fn build_prompt(project_root: &Path, config: &AppConfig, name: &str) -> Result<String, DecreeError> {
-    let prompts_dir = project_root
-        .join(config::DECREE_DIR)
-        .join(config::PROMPTS_DIR);
-    let path = prompts_dir.join(format!("{name}.md"));
-
-    // Check project-local first, then shared
-    let template_path = if path.is_file() {
-        path
-    } else if let Some(shared_dir) = config.resolved_shared_prompts_dir() {
-        let shared_path = shared_dir.join(format!("{name}.md"));
-        if shared_path.is_file() {
-            shared_path
-        } else {
-            return Err(DecreeError::Other(format!("prompt template not found: {name}")));
-        }
-    } else {
-        return Err(DecreeError::Other(format!("prompt template not found: {name}")));
-    };
-
-    let template = std::fs::read_to_string(&template_path)?;
-
-    // Substitute known variables
-    let prompt = substitute_variables(project_root, &template)?;
-
-    Ok(prompt)
+fn build_prompt(
+    project_root: &Path,
+    config: &AppConfig,
+    name: &str,
+    vars: &[(String, String)],
+) -> Result<String, DecreeError> {
+    let template_path = template::prompt_path(project_root, config, name)
+        .ok_or_else(|| DecreeError::Other(format!("prompt template not found: {name}")))?;
+    let source = std::fs::read_to_string(&template_path)?;
+    substitute_variables(project_root, config, name, &source, vars)
 }
 
-/// Substitute `{variable}` placeholders with project context.
-fn substitute_variables(project_root: &Path, template: &str) -> Result<String, DecreeError> {
-    let mut result = template.to_string();
-
-    // Only substitute known variables, leave unknown ones as-is
-    if result.contains("{migrations}") {
-        let migrations = build_migrations_text(project_root)?;
-        result = result.replace("{migrations}", &migrations);
-    }
-
-    if result.contains("{routines}") {
-        let routines = build_routines_text(project_root)?;
-        result = result.replace("{routines}", &routines);
-    }
-
-    if result.contains("{processed}") {
-        let processed = build_processed_text(project_root)?;
-        result = result.replace("{processed}", &processed);
-    }
-
-    if result.contains("{config}") {
-        let config = build_config_text(project_root)?;
-        result = result.replace("{config}", &config);
+/// Render a prompt template with project context, `--var` values, and
+/// `prompt_vars`, in that order of precedence.
+fn substitute_variables(
+    project_root: &Path,
+    config: &AppConfig,
+    name: &str,
+    source: &str,
+    vars: &[(String, String)],
+) -> Result<String, DecreeError> {
+    let mut ctx = Context::new();
+    ctx.insert("migrations", build_migrations_value(project_root)?);
+    ctx.insert("routines", build_routines_value(project_root, config)?);
+    ctx.insert("processed", build_processed_text(project_root)?);
+    ctx.insert("config", build_config_text(project_root)?);
+    for (key, value) in vars {
+        if ctx.get(key).is_some() {
+            return Err(DecreeError::Other(format!(
+                "--var {key} would replace a built-in prompt variable"
+            )));
+        }
+        ctx.insert(key.clone(), value.as_str());
     }
+    let ctx = ctx.with_config_vars(config);
 
-    Ok(result)
+    let include = |included: &str| template::load_prompt(project_root, config, included);
+    let env = template::Env {
+        name,
+        project_root,
+        include: &include,
+    };
+    template::render(source, &ctx, &env)
 }
 
-/// Build the `{migrations}` substitution text.
-fn build_migrations_text(project_root: &Path) -> Result<String, DecreeError> {
+/// Build `{migrations}`: each migration's `name`, `title`, `processed`
+/// flag, and frontmatter fields; as text, a bullet list.
+fn build_migrations_value(project_root: &Path) -> Result<Value, DecreeError> {
     let files = message::list_migration_files(project_root)?;
-
     if files.is_empty() {
-        return Ok("None yet".to_string());
+        return Ok(Value::list(Vec::new(), "None yet"));
     }
+    let processed = message::read_processed(project_root)?;
 
+    let mut items = Vec::new();
     let mut lines = Vec::new();
     for filename in &files {
         let path = project_root
@@ -234,9 +264,11 @@ fn build_migrations_text(project_root: &Path) -> Result<String, DecreeError> {
             .join(config::MIGRATIONS_DIR)
             .join(filename);
         let content = std::fs::read_to_string(&path)?;
-        let title = content
+        let migration = message::parse_migration(filename, &content)?;
+        let title = migration
+            .body
             .lines()
-            .find(|l| !l.trim().is_empty() && !l.starts_with("---"))
+            .find(|l| !l.trim().is_empty())
             .unwrap_or("")
             .trim_start_matches("# ")
             .to_string();
@@ -246,18 +278,31 @@ fn build_migrations_text(project_root: &Path) -> Result<String, DecreeError> {
         } else {
             lines.push(format!("- {filename}: {title}"));
         }
+
+        let mut fields: BTreeMap<String, Value> = migration
+            .custom_fields
+            .iter()
+            .map(|(k, v)| (k.clone(), Value::from(v)))
+            .collect();
+        if let Some(routine) = migration.routine {
+            fields.insert("routine".into(), routine.into());
+        }
+        fields.insert("name".into(), filename.as_str().into());
+        fields.insert("title".into(), title.into());
+        fields.insert("processed".into(), processed.contains(filename).into());
+        items.push(Value::Map(fields));
     }
 
-    Ok(lines.join("\n"))
+    Ok(Value::list(items, lines.join("\n")))
 }
 
-/// Build the `{routines}` substitution text.
-fn build_routines_text(project_root: &Path) -> Result<String, DecreeError> {
-    let config = AppConfig::load_from_project(project_root)?;
-    let routines = message::list_routines(project_root, &config)?;
+/// Build `{routines}`: each routine's `name` and `description`; as text,
+/// a bullet list.
+fn build_routines_value(project_root: &Path, config: &AppConfig) -> Result<Value, DecreeError> {
+    let routines = message::list_routines(project_root, config)?;
 
     if routines.is_empty() {
-        return Ok("None yet".to_string());
+        return Ok(Value::list(Vec::new(), "None yet"));
     }
 
     let lines: Vec<String> = routines
@@ -271,7 +316,18 @@ fn build_routines_text(project_root: &Path) -> Result<String, DecreeError> {
         })
         .collect();
 
-    Ok(lines.join("\n"))
+    Ok(Value::list(
+        routines.iter().map(routine_value).collect(),
+        lines.join("\n"),
+    ))
+}
+
+/// A routine as a template value with `name` and `description`.
+fn routine_value(routine: &message::RoutineInfo) -> Value {
+    Value::Map(BTreeMap::from([
+        ("name".to_string(), routine.name.as_str().into()),
+        ("description".to_string(), routine.description.as_str().into()),
+    ]))
 }
 
 /// Build the `{processed}` substitution text.
@@ -402,27 +458,48 @@ fn copy_to_clipboard(text: &str) -> Result<(), DecreeError> {
     ))
 }
 
-/// Launch interactive AI via exec.
-fn launch_ai(project_root: &Path, _prompt_text: &str) -> Result<(), DecreeError> {
+/// Launch the interactive AI, passing the prompt through `{prompt}` or
+/// `{prompt_file}` in `commands.ai_interactive`.
+///
+/// Decree waits for it so it can remove the prompt file afterwards, and
+/// exits with the AI's status.
+fn launch_ai(project_root: &Path, prompt_text: &str) -> Result<(), DecreeError> {
     let config = AppConfig::load_from_project(project_root)?;
-    let ai_interactive = config.commands.ai_interactive.clone();
-
-    let parts: Vec<&str> = ai_interactive.split_whitespace().collect();
-    if parts.is_empty() {
-        return Err(DecreeError::Other("ai_interactive command is empty".into()));
+    let (template, takes_prompt) = ai::interactive_template(&config.commands.ai_interactive);
+    if !takes_prompt {
+        eprintln!(
+            "{}: commands.ai_interactive has no {{prompt}} or {{prompt_file}}; starting without the prompt",
+            color::warning("warning")
+        );
     }
 
-    let cmd = parts[0];
-    let args = &parts[1..];
+    let ai::InteractiveCommand { argv, prompt_file } = ai::interactive_argv(&template, prompt_text)?;
+    let cmd = &argv[0];
 
-    // exec replaces the current process
-    let err = std::process::Command::new(cmd)
-        .args(args)
-        .current_dir(project_root)
-        .exec();
+    // Ctrl+C belongs to the AI; decree ignores it while waiting, and the
+    // child gets the default disposition back
+    let previous = unsafe { libc::signal(libc::SIGINT, libc::SIG_IGN) };
+    let mut command = std::process::Command::new(cmd);
+    command.args(&argv[1..]).current_dir(project_root);
+    unsafe {
+        command.pre_exec(|| {
+            libc::signal(libc::SIGINT, libc::SIG_DFL);
+            Ok(())
+        });
+    }
+    let status = command.status();
+    unsafe {
+        libc::signal(libc::SIGINT, previous);
+    }
 
-    // exec() only returns on error
-    Err(DecreeError::Other(format!("failed to exec {cmd}: {err}")))
+    if let Some(path) = prompt_file {
+        let _ = std::fs::remove_file(path);
+    }
+    let status = status.map_err(|e| DecreeError::Other(format!("failed to start {cmd}: {e}")))?;
+    if !status.success() {
+        std::process::exit(status.code().unwrap_or(1));
+    }
+    Ok(())
 }
 
 /// Handle unknown prompt: fuzzy match or list available.
@@ -472,6 +549,11 @@ mod tests {
         .unwrap();
     }
 
+    fn render(dir: &TempDir, source: &str) -> Result<String, DecreeError> {
+        let config = AppConfig::load_from_project(dir.path()).unwrap();
+        substitute_variables(dir.path(), &config, "test", source, &[])
+    }
+
     #[test]
     fn test_extract_description_basic() {
         assert_eq!(
@@ -529,7 +611,7 @@ mod tests {
         let dir = TempDir::new().unwrap();
         setup_decree_dir(&dir);
 
-        let result = substitute_variables(dir.path(), "No variables here.").unwrap();
+        let result = render(&dir, "No variables here.").unwrap();
         assert_eq!(result, "No variables here.");
     }
 
@@ -538,17 +620,63 @@ mod tests {
         let dir = TempDir::new().unwrap();
         setup_decree_dir(&dir);
 
-        let result = substitute_variables(dir.path(), "Processed: {processed}").unwrap();
+        let result = render(&dir, "Processed: {processed}").unwrap();
         assert_eq!(result, "Processed: None yet");
     }
 
     #[test]
-    fn test_substitute_variables_unknown_left_as_is() {
+    fn test_substitute_variables_unknown_is_an_error() {
+        let dir = TempDir::new().unwrap();
+        setup_decree_dir(&dir);
+
+        let err = render(&dir, "Unknown: {foobar}").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "template error: test:1: unknown variable 'foobar'"
+        );
+    }
+
+    #[test]
+    fn test_substitute_variables_config_vars_and_includes() {
+        let dir = TempDir::new().unwrap();
+        setup_decree_dir(&dir);
+        std::fs::write(
+            dir.path().join(".decree/config.yml"),
+            "commands:\n  ai_interactive: echo\nprompt_vars:\n  stack: Rust\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join(".decree/prompts/_rules.md"),
+            "Use {stack}.",
+        )
+        .unwrap();
+
+        let result = render(&dir, "{#include _rules} ${HOME:-x}").unwrap();
+        assert_eq!(result, "Use Rust. ${HOME:-x}");
+    }
+
+    #[test]
+    fn test_substitute_variables_cli_vars() {
         let dir = TempDir::new().unwrap();
         setup_decree_dir(&dir);
+        std::fs::write(
+            dir.path().join(".decree/config.yml"),
+            "commands:\n  ai_interactive: echo\nprompt_vars:\n  stack: Rust\n  scope: all\n",
+        )
+        .unwrap();
+        let config = AppConfig::load_from_project(dir.path()).unwrap();
+
+        let args = ["scope=auth only".to_string(), "ticket=T-1=x".to_string()];
+        let vars = commands::parse_key_values(&args, "--var").unwrap();
+        let result =
+            substitute_variables(dir.path(), &config, "t", "{stack} {scope} {ticket}", &vars)
+                .unwrap();
+        assert_eq!(result, "Rust auth only T-1=x");
 
-        let result = substitute_variables(dir.path(), "Unknown: {foobar}").unwrap();
-        assert_eq!(result, "Unknown: {foobar}");
+        let vars = commands::parse_key_values(&["processed=none".into()], "--var").unwrap();
+        assert!(substitute_variables(dir.path(), &config, "t", "", &vars).is_err());
+        assert!(commands::parse_key_values(&["novalue".into()], "--var").is_err());
+        assert!(commands::parse_key_values(&["=x".into()], "--var").is_err());
     }
 
     #[test]
@@ -556,8 +684,8 @@ mod tests {
         let dir = TempDir::new().unwrap();
         setup_decree_dir(&dir);
 
-        let text = build_migrations_text(dir.path()).unwrap();
-        assert_eq!(text, "None yet");
+        let result = render(&dir, "{migrations}").unwrap();
+        assert_eq!(result, "None yet");
     }
 
     #[test]
@@ -571,18 +699,38 @@ mod tests {
         )
         .unwrap();
 
-        let text = build_migrations_text(dir.path()).unwrap();
+        let text = render(&dir, "{migrations}").unwrap();
         assert!(text.contains("01-auth.md"));
         assert!(text.contains("Add Authentication"));
     }
 
+    #[test]
+    fn test_migrations_loop_fields() {
+        let dir = TempDir::new().unwrap();
+        setup_decree_dir(&dir);
+
+        std::fs::write(
+            dir.path().join(".decree/migrations/01-auth.md"),
+            "---\nroutine: rust-develop\n---\n# Add Authentication\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join(".decree/migrations/02-docs.md"), "# Docs\n").unwrap();
+        std::fs::write(dir.path().join(".decree/processed.md"), "01-auth.md\n").unwrap();
+
+        let template = "{#each migrations as m}{loop.index} {m.title}\
+                        {#if m.processed} [done]{/if}\
+                        {#if m.routine} ({m.routine}){/if}\n{/each}";
+        let result = render(&dir, template).unwrap();
+        assert_eq!(result, "1 Add Authentication [done] (rust-develop)\n2 Docs\n");
+    }
+
     #[test]
     fn test_build_routines_text_empty() {
         let dir = TempDir::new().unwrap();
         setup_decree_dir(&dir);
 
-        let text = build_routines_text(dir.path()).unwrap();
-        assert_eq!(text, "None yet");
+        let result = render(&dir, "{routines}").unwrap();
+        assert_eq!(result, "None yet");
     }
 
     #[test]
@@ -0,0 +1,201 @@
+use crate::config::{self, AppConfig};
+use crate::error::{color, DecreeError};
+use crate::message::{InboxMessage, RouterFn};
+use crate::router::{self, RouteReason};
+use crate::routine;
+use std::path::{Path, PathBuf};
+
+/// Run `decree route [id]`.
+///
+/// Lists messages parked in `inbox/needs-routing/` with the candidates the
+/// AI router suggested for each.
+pub fn list(project_root: &Path, id: Option<&str>) -> Result<(), DecreeError> {
+    let parked: Vec<InboxMessage> = router::parked_messages(project_root)?
+        .into_iter()
+        .filter(|m| id.is_none_or(|id| is_parked_match(m, id)))
+        .collect();
+
+    if parked.is_empty() {
+        return match id {
+            Some(id) => Err(DecreeError::MessageNotFound(id.to_string())),
+            None => {
+                println!("No messages need routing.");
+                Ok(())
+            }
+        };
+    }
+
+    for msg in &parked {
+        let msg_id = msg.id.clone().unwrap_or_else(|| msg.filename.clone());
+        let summary = msg.body.lines().find(|l| !l.trim().is_empty()).unwrap_or("");
+        println!("{}  {}", color::bold(&msg_id), color::dim(&truncate(summary, 60)));
+
+        let candidates = router::recorded_candidates(project_root, &msg_id);
+        if candidates.is_empty() {
+            println!("  candidates: none");
+        } else {
+            let list: Vec<String> = candidates
+                .iter()
+                .map(|c| format!("{} {:.0}%", c.routine, c.confidence * 100.0))
+                .collect();
+            println!("  candidates: {}", list.join(", "));
+        }
+    }
+
+    println!();
+    println!("Route a message with `decree route <id> <routine>`.");
+    Ok(())
+}
+
+/// Run `decree route <id> <routine>`.
+///
+/// Sets the routine of a parked message and returns it to the inbox, where
+/// the next `decree process` (or daemon poll) runs it.
+pub fn resolve(project_root: &Path, id: &str, routine_name: &str) -> Result<(), DecreeError> {
+    let config = AppConfig::load_from_project(project_root)?;
+    let msg = router::parked_messages(project_root)?
+        .into_iter()
+        .find(|m| is_parked_match(m, id))
+        .ok_or_else(|| DecreeError::MessageNotFound(id.to_string()))?;
+
+    routine::resolve_routine(project_root, &config, routine_name)?;
+    let msg = router::unpark(project_root, msg, routine_name)?;
+
+    let msg_id = msg.id.unwrap_or(msg.filename);
+    println!(
+        "{} {msg_id} → {routine_name}; run `decree process` to run it",
+        color::success("routed")
+    );
+    Ok(())
+}
+
+/// Whether a parked message has the given ID or filename.
+fn is_parked_match(msg: &InboxMessage, id: &str) -> bool {
+    msg.id.as_deref() == Some(id)
+        || msg.filename == id
+        || msg.filename.strip_suffix(".md") == Some(id)
+}
+
+/// Shorten a line for the listing, marking the cut with `...`.
+fn truncate(s: &str, max: usize) -> String {
+    if s.chars().count() <= max {
+        s.to_string()
+    } else {
+        let cut: String = s.chars().take(max.saturating_sub(3)).collect();
+        format!("{cut}...")
+    }
+}
+
+/// Run `decree route --explain <file>`.
+///
+/// Shows each routing rule checked against the message and the decision
+/// that picked its routine. If no rule matches, the AI router is consulted
+/// exactly as `decree process` would.
+pub fn explain(project_root: &Path, file: &str) -> Result<(), DecreeError> {
+    let config = AppConfig::load_from_project(project_root)?;
+    let path = resolve_message_path(project_root, file)
+        .ok_or_else(|| DecreeError::MessageNotFound(file.to_string()))?;
+
+    let filename = path
+        .file_name()
+        .map(|f| f.to_string_lossy().to_string())
+        .unwrap_or_else(|| file.to_string());
+    let content = std::fs::read_to_string(&path)?;
+    let msg = InboxMessage::parse(&filename, &content)?;
+
+    println!("{}", color::bold(&filename));
+
+    if msg.routine.is_none() {
+        println!();
+        if config.routes.is_empty() {
+            println!("  Rules: none configured");
+        } else {
+            println!("  Rules:");
+            for (index, rule) in config.routes.iter().enumerate() {
+                let label = match &rule.name {
+                    Some(name) => format!("#{} {name}", index + 1),
+                    None => format!("#{}", index + 1),
+                };
+                let check = router::check_rule(rule, &msg)?;
+                match &check.failed {
+                    None => {
+                        let detail = if check.matched.is_empty() {
+                            "matches everything".to_string()
+                        } else {
+                            check.matched.join(", ")
+                        };
+                        println!(
+                            "    {label:<20} {} -> {}: {detail}",
+                            color::success("match"),
+                            rule.routine
+                        );
+                        break;
+                    }
+                    Some(failed) => {
+                        println!("    {label:<20} {}: {failed}", color::dim("no match"));
+                    }
+                }
+            }
+        }
+    }
+
+    let ai_router_fn = router::ai_router_fn(&config);
+    let ai_router_ref = ai_router_fn.as_ref().map(|f| f.as_ref() as &RouterFn);
+    let decision = router::route(project_root, &config, &msg, ai_router_ref)?;
+
+    if let Some(call) = &decision.ai_call {
+        println!();
+        if call.cached {
+            println!("  AI router: cached answer ({})", &call.cache_key[..12]);
+        } else if let Some(error) = &call.error {
+            println!("  AI router: {}: {error}", color::error("failed"));
+        } else {
+            let raw = call.raw_output.as_deref().unwrap_or("").trim();
+            println!("  AI router answered: {raw:?}");
+        }
+        for candidate in &call.candidates {
+            println!(
+                "  Candidate: {} ({:.0}%)",
+                candidate.routine,
+                candidate.confidence * 100.0
+            );
+        }
+        if let Some(parsed) = &call.parsed {
+            println!("  Parsed choice: {parsed}");
+        }
+    }
+
+    let note = match decision.reason {
+        RouteReason::Default if ai_router_fn.is_none() => " (AI router not configured)",
+        RouteReason::Default => " (AI router gave no valid routine)",
+        RouteReason::NeedsRouting => " (below router.min_confidence; would be parked)",
+        _ => "",
+    };
+    let routine = match decision.reason {
+        RouteReason::NeedsRouting => "(none)",
+        _ => decision.routine.as_str(),
+    };
+    println!();
+    println!(
+        "  Routine: {} — {}{note}",
+        color::bold(routine),
+        decision.reason
+    );
+
+    Ok(())
+}
+
+/// Find a message by path, or by filename/ID in `.decree/inbox/`.
+fn resolve_message_path(project_root: &Path, file: &str) -> Option<PathBuf> {
+    let direct = PathBuf::from(file);
+    if direct.is_file() {
+        return Some(direct);
+    }
+
+    let inbox_dir = project_root
+        .join(config::DECREE_DIR)
+        .join(config::INBOX_DIR);
+    [inbox_dir.join(file), inbox_dir.join(format!("{file}.md"))]
+        .into_iter()
+        .find(|p| p.is_file())
+}
@@ -1,15 +1,40 @@
 use crate::config::{self, AppConfig};
 use crate::error::{color, DecreeError, EXIT_PRECHECK};
+use crate::events::{self, Event, EventKind};
 use crate::hooks;
 use crate::message::{self, InboxMessage, RoutineInfo};
 use crate::routine::{self, CustomParam, RoutineDetail};
-use chrono::Local;
 use std::collections::BTreeMap;
-use std::io::{self, BufRead};
-use std::path::Path;
+use std::io::{self, BufRead, IsTerminal, Read};
+use std::path::{Path, PathBuf};
+
+/// Flags for running a routine without the guided flow.
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    /// `--param KEY=VALUE` values.
+    pub params: Vec<String>,
+    pub body: Option<String>,
+    /// File to read the body from (`-` for stdin).
+    pub body_file: Option<PathBuf>,
+    /// Skip the confirmation prompt.
+    pub yes: bool,
+    /// Queue the message for the daemon instead of running it here.
+    pub detach: bool,
+}
+
+impl RunOptions {
+    /// Whether any flag asks for a direct, non-interactive run.
+    fn is_direct(&self) -> bool {
+        !self.params.is_empty()
+            || self.body.is_some()
+            || self.body_file.is_some()
+            || self.yes
+            || self.detach
+    }
+}
 
 /// Run the `decree routine [name]` command.
-pub fn run(project_root: &Path, name: Option<&str>) -> Result<(), DecreeError> {
+pub fn run(project_root: &Path, name: Option<&str>, options: &RunOptions) -> Result<(), DecreeError> {
     let mut config = AppConfig::load_from_project(project_root)?;
 
     // Run discovery so we see newly added routines
@@ -25,7 +50,7 @@ pub fn run(project_root: &Path, name: Option<&str>) -> Result<(), DecreeError> {
     }
 
     match name {
-        Some(name) => run_named(project_root, &config, &routines, name),
+        Some(name) => run_named(project_root, &config, &routines, name, options),
         None => run_select(project_root, &config, &routines),
     }
 }
@@ -36,6 +61,7 @@ fn run_named(
     config: &AppConfig,
     routines: &[RoutineInfo],
     name: &str,
+    options: &RunOptions,
 ) -> Result<(), DecreeError> {
     // Find the routine
     let info = match routines.iter().find(|r| r.name == name) {
@@ -43,14 +69,18 @@ fn run_named(
         None => return routine_not_found(name, routines),
     };
 
-    let detail = routine::routine_detail(project_root, &config, info)?;
+    let detail = routine::routine_detail(project_root, config, info)?;
+
+    if options.is_direct() {
+        return run_direct(project_root, config, &detail, options);
+    }
 
     if !color::is_tty() {
         print_detail_view(&detail);
         return Ok(());
     }
 
-    guided_flow(project_root, &config, &detail)
+    guided_flow(project_root, config, &detail)
 }
 
 /// Run with interactive selection (no name given).
@@ -178,18 +208,7 @@ fn guided_flow(
 
     // Step 5: Summary and execute
     println!();
-    println!("Running {}:", color::bold(&detail.info.name));
-    for (name, value) in &param_values {
-        println!("  {name}: {value}");
-    }
-    if !body.is_empty() {
-        let display_body = if body.len() > 60 {
-            format!("\"{}...\"", &body[..57])
-        } else {
-            format!("\"{body}\"")
-        };
-        println!("  body: {display_body}");
-    }
+    print_summary(detail, &param_values, &body);
     println!();
     println!("Press Enter to run, Ctrl-C to cancel.");
 
@@ -203,6 +222,117 @@ fn guided_flow(
     execute_routine(project_root, config, detail, &param_values, &body)
 }
 
+/// Run with values from flags: `--param`, `--body`/`--body-file`/stdin,
+/// `--yes`, and `--detach`. Parameters that aren't given keep their
+/// defaults; a failed pre-check is an error rather than a question.
+fn run_direct(
+    project_root: &Path,
+    config: &AppConfig,
+    detail: &RoutineDetail,
+    options: &RunOptions,
+) -> Result<(), DecreeError> {
+    let param_values = direct_params(detail, &options.params)?;
+    let body = direct_body(options)?;
+
+    if let Some(reason) = routine::run_precheck(project_root, config, &detail.info.name)? {
+        return Err(DecreeError::PreCheckFailed(format!(
+            "{}: {reason}",
+            detail.info.name
+        )));
+    }
+
+    // Only ask when someone is at the terminal to answer
+    if !options.yes && io::stdin().is_terminal() {
+        print_summary(detail, &param_values, &body);
+        println!();
+        let confirmed = inquire::Confirm::new("Run it?")
+            .with_default(true)
+            .prompt()
+            .map_err(|e| DecreeError::Other(format!("prompt cancelled: {e}")))?;
+        if !confirmed {
+            return Ok(());
+        }
+    }
+
+    if options.detach {
+        let msg = write_message(project_root, detail, &param_values, &body)?;
+        println!("Message queued: {}", msg.filename);
+        return Ok(());
+    }
+    execute_routine(project_root, config, detail, &param_values, &body)
+}
+
+/// Every custom parameter's value: from `--param`, else its default.
+fn direct_params(
+    detail: &RoutineDetail,
+    args: &[String],
+) -> Result<Vec<(String, String)>, DecreeError> {
+    let given = super::parse_key_values(args, "--param")?;
+    for (name, _) in &given {
+        if !detail.custom_params.iter().any(|p| &p.name == name) {
+            let known: Vec<&str> = detail.custom_params.iter().map(|p| p.name.as_str()).collect();
+            let hint = if known.is_empty() {
+                "it has no parameters".to_string()
+            } else {
+                format!("parameters: {}", known.join(", "))
+            };
+            return Err(DecreeError::Other(format!(
+                "unknown parameter '{name}' for routine '{}' ({hint})",
+                detail.info.name
+            )));
+        }
+    }
+
+    Ok(detail
+        .custom_params
+        .iter()
+        .map(|p| {
+            let value = given
+                .iter()
+                .rev()
+                .find(|(name, _)| name == &p.name)
+                .map(|(_, value)| value.clone())
+                .unwrap_or_else(|| p.default.clone());
+            (p.name.clone(), value)
+        })
+        .collect())
+}
+
+/// The message body: `--body`, `--body-file` (`-` for stdin), or piped stdin.
+fn direct_body(options: &RunOptions) -> Result<String, DecreeError> {
+    let read_stdin = || -> Result<String, DecreeError> {
+        let mut body = String::new();
+        io::stdin().read_to_string(&mut body)?;
+        Ok(body)
+    };
+    let body = match (&options.body, &options.body_file) {
+        (Some(body), _) => body.clone(),
+        (None, Some(path)) if path.as_os_str() == "-" => read_stdin()?,
+        (None, Some(path)) => std::fs::read_to_string(path).map_err(|e| {
+            DecreeError::Other(format!("cannot read {}: {e}", path.display()))
+        })?,
+        (None, None) if !io::stdin().is_terminal() => read_stdin()?,
+        (None, None) => String::new(),
+    };
+    Ok(body.trim_end_matches('\n').to_string())
+}
+
+/// Print what is about to run.
+fn print_summary(detail: &RoutineDetail, param_values: &[(String, String)], body: &str) {
+    println!("Running {}:", color::bold(&detail.info.name));
+    for (name, value) in param_values {
+        println!("  {name}: {value}");
+    }
+    if !body.is_empty() {
+        let display_body = if body.len() > 60 {
+            format!("\"{}...\"", &body[..57])
+        } else {
+            format!("\"{body}\"")
+        };
+        println!("  body: {display_body}");
+    }
+}
+
 /// Prompt for a single custom parameter value.
 fn prompt_param(param: &CustomParam) -> Result<String, DecreeError> {
     let prompt_text = format!("{} [default: \"{}\"]", param.name, param.default);
@@ -247,10 +377,23 @@ fn execute_routine(
     param_values: &[(String, String)],
     body: &str,
 ) -> Result<(), DecreeError> {
-    let now = Local::now();
-    let hhmm = now.format("%H%M").to_string();
-    let day = message::next_day_counter(project_root, &hhmm)?;
-    let chain = message::build_chain_id(&day, &hhmm, &detail.info.name);
+    let msg = write_message(project_root, detail, param_values, body)?;
+
+    println!("Message created: {}", msg.filename);
+
+    // Process only this single message (no beforeAll/afterAll, no inbox drain)
+    let shutdown = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    super::process::process_single_message(project_root, config, &msg.filename, &shutdown)
+}
+
+/// Write a new chain's first message for the routine to the inbox.
+fn write_message(
+    project_root: &Path,
+    detail: &RoutineDetail,
+    param_values: &[(String, String)],
+    body: &str,
+) -> Result<InboxMessage, DecreeError> {
+    let chain = message::new_chain_id(project_root, &detail.info.name)?;
     let seq = 0u32;
     let full_id = format!("{chain}-{seq}");
     let filename = format!("{full_id}.md");
@@ -271,7 +414,7 @@ fn execute_routine(
         migration: None,
         body: body.to_string(),
         custom_fields,
-        filename: filename.clone(),
+        filename,
     };
 
     // Write to inbox
@@ -280,12 +423,8 @@ fn execute_routine(
         .join(config::INBOX_DIR);
     std::fs::create_dir_all(&inbox_dir)?;
     msg.write_to_inbox(project_root)?;
-
-    println!("Message created: {}", msg.filename);
-
-    // Process only this single message (no beforeAll/afterAll, no inbox drain)
-    let shutdown = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
-    super::process::process_single_message(project_root, config, &filename, &shutdown)
+    events::record(project_root, Event::for_message(EventKind::Enqueued, &msg));
+    Ok(msg)
 }
 
 /// Handle unknown routine: fuzzy match or list available.
@@ -8,7 +8,7 @@ use walkdir::WalkDir;
 pub fn run(project_root: &Path, source: Option<&str>) -> Result<(), DecreeError> {
     let mut config = AppConfig::load_from_project(project_root)?;
 
-    let source_override = source.map(|s| config::expand_tilde(s));
+    let source_override = source.map(config::expand_tilde);
     let changed = discover(project_root, &mut config, source_override.as_deref())?;
 
     if changed {
@@ -311,6 +311,7 @@ mod tests {
             RoutineEntry {
                 enabled: true,
                 deprecated: true,
+                ai: None,
             },
         );
         config.routines = Some(routines);
@@ -359,21 +360,24 @@ mod tests {
         assert_eq!(
             entry_status(&RoutineEntry {
                 enabled: true,
-                deprecated: false
+                deprecated: false,
+                ai: None,
             }),
             "enabled"
         );
         assert_eq!(
             entry_status(&RoutineEntry {
                 enabled: false,
-                deprecated: false
+                deprecated: false,
+                ai: None,
             }),
             "disabled"
         );
         assert_eq!(
             entry_status(&RoutineEntry {
                 enabled: true,
-                deprecated: true
+                deprecated: true,
+                ai: None,
             }),
             "deprecated"
         );
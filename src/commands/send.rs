@@ -0,0 +1,120 @@
+use crate::config::{self, AppConfig};
+use crate::error::DecreeError;
+use crate::events::{self, Event, EventKind};
+use crate::message::{self, InboxMessage};
+use crate::routine;
+use std::collections::BTreeMap;
+use std::io::{IsTerminal, Read};
+use std::path::Path;
+
+/// Frontmatter fields decree sets itself.
+const RESERVED_FIELDS: &[&str] = &["id", "chain", "seq", "routine", "migration"];
+
+/// Run `decree send [--routine NAME] [--field KEY=VALUE]... [BODY | --file F]`.
+///
+/// Writes a new message to the inbox with a fresh chain ID and prints its
+/// ID. The body comes from the argument, `--file` (`-` for stdin), or
+/// piped stdin. Without `--routine`, the message is routed when processed.
+pub fn run(
+    project_root: &Path,
+    body: Option<&str>,
+    file: Option<&Path>,
+    routine_name: Option<&str>,
+    fields: &[String],
+    name: Option<&str>,
+) -> Result<(), DecreeError> {
+    let config = AppConfig::load_from_project(project_root)?;
+
+    if let Some(routine_name) = routine_name {
+        check_routine(project_root, &config, routine_name)?;
+    }
+
+    let mut custom_fields = BTreeMap::new();
+    for (key, value) in super::parse_key_values(fields, "--field")? {
+        if let Some(reason) = reserved_field(&key) {
+            return Err(DecreeError::Other(format!("--field {reason}")));
+        }
+        custom_fields.insert(key, serde_yaml::Value::String(value));
+    }
+
+    let body = read_body(body, file)?;
+
+    let id = enqueue(project_root, routine_name, custom_fields, body, name)?;
+    println!("{id}");
+    Ok(())
+}
+
+/// Write a new message to the inbox and return its ID.
+///
+/// The chain is named after `name`, else the routine, else "message".
+/// Callers check the routine and fields first.
+pub(crate) fn enqueue(
+    project_root: &Path,
+    routine_name: Option<&str>,
+    custom_fields: BTreeMap<String, serde_yaml::Value>,
+    body: String,
+    name: Option<&str>,
+) -> Result<String, DecreeError> {
+    let chain_name = name.or(routine_name).unwrap_or("message");
+    let chain = message::new_chain_id(project_root, chain_name)?;
+    let id = format!("{chain}-0");
+    let msg = InboxMessage {
+        id: Some(id.clone()),
+        chain: Some(chain),
+        seq: Some(0),
+        routine: routine_name.map(str::to_string),
+        migration: None,
+        body,
+        custom_fields,
+        filename: format!("{id}.md"),
+    };
+
+    std::fs::create_dir_all(project_root.join(config::DECREE_DIR).join(config::INBOX_DIR))?;
+    msg.write_to_inbox(project_root)?;
+    events::record(project_root, Event::for_message(EventKind::Enqueued, &msg));
+    Ok(id)
+}
+
+/// Why `key` cannot be set as a custom field, if it is one decree sets itself.
+pub(crate) fn reserved_field(key: &str) -> Option<String> {
+    RESERVED_FIELDS.contains(&key).then(|| {
+        let hint = if key == "routine" { " (use --routine)" } else { "" };
+        format!("{key} is set by decree{hint}")
+    })
+}
+
+/// Fail unless `name` is a registered, enabled routine with a script.
+pub(crate) fn check_routine(
+    project_root: &Path,
+    config: &AppConfig,
+    name: &str,
+) -> Result<(), DecreeError> {
+    if routine::resolve_routine(project_root, config, name).is_ok() {
+        return Ok(());
+    }
+    let routines = message::list_routines(project_root, config)?;
+    let msg = match routine::find_closest_routine(name, &routines, 3) {
+        Some(suggestion) => format!("unknown routine '{name}'\n\nDid you mean '{suggestion}'?"),
+        None => format!("unknown routine '{name}'"),
+    };
+    Err(DecreeError::Other(msg))
+}
+
+/// The message body: the argument, `--file` (`-` for stdin), or piped stdin.
+fn read_body(body: Option<&str>, file: Option<&Path>) -> Result<String, DecreeError> {
+    let read_stdin = || -> Result<String, DecreeError> {
+        let mut body = String::new();
+        std::io::stdin().read_to_string(&mut body)?;
+        Ok(body)
+    };
+    match (body, file) {
+        (Some(body), _) => Ok(body.to_string()),
+        (None, Some(path)) if path.as_os_str() == "-" => read_stdin(),
+        (None, Some(path)) => std::fs::read_to_string(path)
+            .map_err(|e| DecreeError::Other(format!("cannot read {}: {e}", path.display()))),
+        (None, None) if !std::io::stdin().is_terminal() => read_stdin(),
+        (None, None) => Err(DecreeError::Other(
+            "no message body given (pass it as an argument, with --file, or on stdin)".into(),
+        )),
+    }
+}
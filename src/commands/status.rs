@@ -1,33 +1,63 @@
 use crate::config;
 use crate::error::color;
 use crate::error::DecreeError;
-use crate::message;
+use crate::approval;
+use crate::message::{self, InboxMessage};
+use crate::router;
+use serde::Serialize;
 use std::path::Path;
 
+/// Migration and inbox counts, as shown by `decree status`.
+#[derive(Debug, Serialize)]
+pub struct Summary {
+    pub migrations_processed: usize,
+    pub migrations_total: usize,
+    pub next_migration: Option<String>,
+    pub pending: usize,
+    pub dead: usize,
+    pub needs_routing: usize,
+    pub pending_approval: usize,
+}
+
+/// Count migrations and queued messages.
+pub fn summary(project_root: &Path) -> Result<Summary, DecreeError> {
+    let decree_dir = project_root.join(config::DECREE_DIR);
+    let all_migrations = list_migrations(&decree_dir.join(config::MIGRATIONS_DIR))?;
+    let processed = read_processed(&decree_dir.join(config::PROCESSED_FILE))?;
+
+    let inbox_dir = decree_dir.join(config::INBOX_DIR);
+    Ok(Summary {
+        migrations_processed: all_migrations
+            .iter()
+            .filter(|m| processed.contains(m))
+            .count(),
+        migrations_total: all_migrations.len(),
+        next_migration: all_migrations.into_iter().find(|m| !processed.contains(m)),
+        pending: count_files(&inbox_dir)?,
+        dead: count_files(&inbox_dir.join(config::DEAD_DIR))?,
+        needs_routing: count_files(&inbox_dir.join(config::NEEDS_ROUTING_DIR))?,
+        pending_approval: count_files(&inbox_dir.join(config::PENDING_APPROVAL_DIR))?,
+    })
+}
+
 /// Run `decree status`.
 pub fn run(project_root: &Path) -> Result<(), DecreeError> {
     let decree_dir = project_root.join(config::DECREE_DIR);
+    let Summary {
+        migrations_processed: processed_count,
+        migrations_total: total,
+        next_migration,
+        pending,
+        dead,
+        needs_routing,
+        pending_approval,
+    } = summary(project_root)?;
 
     // --- Migrations ---
     println!("{}", color::bold("Migrations:"));
-    let migrations_dir = decree_dir.join(config::MIGRATIONS_DIR);
-    let processed_path = decree_dir.join(config::PROCESSED_FILE);
-
-    let all_migrations = list_migrations(&migrations_dir)?;
-    let processed = read_processed(&processed_path)?;
-
-    let processed_count = all_migrations
-        .iter()
-        .filter(|m| processed.contains(&m.to_string()))
-        .count();
-    let total = all_migrations.len();
-
     println!("  Processed: {} of {}", processed_count, total);
 
-    if let Some(next) = all_migrations
-        .iter()
-        .find(|m| !processed.contains(&m.to_string()))
-    {
+    if let Some(next) = next_migration {
         println!("  Next: {}", next);
     }
 
@@ -35,11 +65,7 @@ pub fn run(project_root: &Path) -> Result<(), DecreeError> {
 
     // --- Inbox ---
     println!("{}", color::bold("Inbox:"));
-    let inbox_dir = decree_dir.join(config::INBOX_DIR);
-    let inbox_dead_dir = inbox_dir.join(config::DEAD_DIR);
-
-    let pending = count_files(&inbox_dir)?;
-    let dead = count_files(&inbox_dead_dir)?;
+    let inbox_dead_dir = decree_dir.join(config::INBOX_DIR).join(config::DEAD_DIR);
 
     println!(
         "  Pending: {} message{}",
@@ -51,6 +77,23 @@ pub fn run(project_root: &Path) -> Result<(), DecreeError> {
         dead,
         if dead == 1 { "" } else { "s" }
     );
+    if needs_routing > 0 {
+        println!(
+            "  Needs routing: {} message{} (see `decree route`)",
+            needs_routing,
+            if needs_routing == 1 { "" } else { "s" }
+        );
+    }
+    if pending_approval > 0 {
+        println!(
+            "  Awaiting approval: {} message{} (see `decree approve`)",
+            pending_approval,
+            if pending_approval == 1 { "" } else { "s" }
+        );
+        for id in held_ids(approval::pending_messages(project_root)?) {
+            println!("    {id}");
+        }
+    }
 
     println!();
 
@@ -63,6 +106,8 @@ pub fn run(project_root: &Path) -> Result<(), DecreeError> {
     } else {
         // Dead-lettered message IDs (files in inbox/dead/)
         let dead_ids = list_dead_ids(&inbox_dead_dir)?;
+        let parked_ids = held_ids(router::parked_messages(project_root)?);
+        let pending_ids = held_ids(approval::pending_messages(project_root)?);
 
         let recent: Vec<&String> = runs.iter().rev().take(5).collect();
         for run_name in recent.iter().rev() {
@@ -70,6 +115,12 @@ pub fn run(project_root: &Path) -> Result<(), DecreeError> {
             let routine = detect_routine(&run_dir);
             let disposition = if dead_ids.iter().any(|d| run_name.starts_with(d)) {
                 color::error("dead")
+            } else if parked_ids.iter().any(|p| p == *run_name) {
+                color::warning("needs routing")
+            } else if pending_ids.iter().any(|p| p == *run_name) {
+                color::warning("awaiting approval")
+            } else if run_dir.join("skipped.log").exists() {
+                color::warning("skipped")
             } else {
                 color::success("done")
             };
@@ -137,6 +188,14 @@ fn count_files(dir: &Path) -> Result<usize, DecreeError> {
     Ok(count)
 }
 
+/// IDs of messages on hold (parked for routing or awaiting approval).
+fn held_ids(messages: Vec<InboxMessage>) -> Vec<String> {
+    messages
+        .into_iter()
+        .map(|m| m.id.unwrap_or(m.filename))
+        .collect()
+}
+
 /// List message IDs from dead letter directory.
 fn list_dead_ids(dead_dir: &Path) -> Result<Vec<String>, DecreeError> {
     if !dead_dir.exists() {
@@ -160,7 +219,7 @@ fn detect_routine(run_dir: &Path) -> String {
     if let Ok(entries) = std::fs::read_dir(run_dir) {
         for entry in entries.flatten() {
             let name = entry.file_name().to_string_lossy().to_string();
-            if name.ends_with(".log") && name != "routine.log" {
+            if name.ends_with(".log") && name != "routine.log" && name != "skipped.log" {
                 return name.trim_end_matches(".log").to_string();
             }
         }
@@ -0,0 +1,152 @@
+use crate::config::AppConfig;
+use crate::error::{color, DecreeError};
+use crate::usage::{self, format_cost, format_tokens, GroupBy, UsageTotals};
+use chrono::Local;
+use std::path::Path;
+
+/// Run `decree usage [--since WHEN] [--by GROUP]`.
+///
+/// Totals the usage records in every run directory, grouped by routine,
+/// migration, chain, backend, or day, and shows spend against any
+/// `budget` caps.
+pub fn run(project_root: &Path, since: Option<&str>, by: &str) -> Result<(), DecreeError> {
+    let config = AppConfig::load_from_project(project_root)?;
+    let by = GroupBy::parse(by)?;
+    let now = Local::now();
+    let since = since.map(|s| usage::parse_since(s, now)).transpose()?;
+
+    let all = usage::all_usage(project_root)?;
+    let selected: Vec<_> = all
+        .iter()
+        .filter(|u| match since {
+            Some(since) => u.recorded_at().is_some_and(|t| t >= since),
+            None => true,
+        })
+        .collect();
+
+    let heading = match since {
+        Some(since) => format!("AI usage since {}", since.format("%Y-%m-%d %H:%M")),
+        None => "AI usage".to_string(),
+    };
+    println!("{}", color::bold(&heading));
+
+    if selected.is_empty() {
+        println!("  No usage recorded.");
+    } else {
+        let groups = usage::group(selected.iter().copied(), by);
+        let mut total = UsageTotals::default();
+        for run in &selected {
+            total.add(&run.record);
+        }
+
+        let width = groups.keys().map(|k| k.len()).max().unwrap_or(0).max(5);
+        println!(
+            "  {:<width$}  {:>5}  {:>7}  {:>7}  {:>9}  {:>8}",
+            group_label(by),
+            "CALLS",
+            "INPUT",
+            "OUTPUT",
+            "COST",
+            "TIME"
+        );
+        for (key, totals) in &groups {
+            println!("  {}", row(key, totals, width));
+        }
+        println!("  {}", color::bold(&row("total", &total, width)));
+
+        if selected.iter().any(|u| u.record.estimated) {
+            println!(
+                "  {}",
+                color::dim("Includes estimates from `decree ai` (size-based tokens, profile pricing).")
+            );
+        }
+    }
+
+    print_budget(&config, &all, now);
+    Ok(())
+}
+
+fn group_label(by: GroupBy) -> &'static str {
+    match by {
+        GroupBy::Routine => "ROUTINE",
+        GroupBy::Migration => "MIGRATION",
+        GroupBy::Chain => "CHAIN",
+        GroupBy::Backend => "BACKEND",
+        GroupBy::Day => "DAY",
+    }
+}
+
+fn row(key: &str, totals: &UsageTotals, width: usize) -> String {
+    format!(
+        "{key:<width$}  {:>5}  {:>7}  {:>7}  {:>9}  {:>8}",
+        totals.calls,
+        format_tokens(totals.input_tokens),
+        format_tokens(totals.output_tokens),
+        format_cost(totals.cost),
+        format_secs(totals.duration_secs),
+    )
+}
+
+/// Show today's spend against the daily cap.
+fn print_budget(config: &AppConfig, all: &[usage::RunUsage], now: chrono::DateTime<Local>) {
+    let budget = &config.budget;
+    if let Some(cap) = budget.daily {
+        let today = now.date_naive();
+        let spent: f64 = all
+            .iter()
+            .filter(|u| u.recorded_at().is_some_and(|t| t.date_naive() == today))
+            .filter_map(|u| u.record.cost)
+            .sum();
+        let line = format!("Budget: {} of {} spent today", format_cost(spent), format_cost(cap));
+        println!();
+        if spent >= cap {
+            println!("{} (processing is paused)", color::warning(&line));
+        } else {
+            println!("{line}");
+        }
+    }
+    if let Some(cap) = budget.per_migration {
+        if budget.daily.is_none() {
+            println!();
+        }
+        println!("Budget: {} per migration (see --by migration)", format_cost(cap));
+    }
+}
+
+fn format_secs(secs: f64) -> String {
+    let total = secs.round() as u64;
+    if total < 60 {
+        format!("{total}s")
+    } else if total < 3600 {
+        format!("{}m{:02}s", total / 60, total % 60)
+    } else {
+        format!("{}h{:02}m", total / 3600, (total % 3600) / 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_secs() {
+        assert_eq!(format_secs(4.4), "4s");
+        assert_eq!(format_secs(190.0), "3m10s");
+        assert_eq!(format_secs(7260.0), "2h01m");
+    }
+
+    #[test]
+    fn test_row_alignment() {
+        let totals = UsageTotals {
+            calls: 2,
+            input_tokens: 12_345,
+            output_tokens: 800,
+            cost: 0.5,
+            duration_secs: 61.0,
+        };
+        assert_eq!(
+            row("develop", &totals, 9),
+            "develop        2    12.3k      800      $0.50     1m01s"
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use crate::atomic;
 use crate::error::DecreeError;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
@@ -13,14 +14,23 @@ pub const OUTBOX_DIR: &str = "outbox";
 pub const RUNS_DIR: &str = "runs";
 pub const MIGRATIONS_DIR: &str = "migrations";
 pub const DEAD_DIR: &str = "dead";
+pub const DONE_DIR: &str = "done";
+pub const NEEDS_ROUTING_DIR: &str = "needs-routing";
+pub const PENDING_APPROVAL_DIR: &str = "pending-approval";
 pub const PROCESSED_FILE: &str = "processed.md";
 pub const ROUTER_FILE: &str = "router.md";
 pub const CONFIG_FILE: &str = "config.yml";
+pub const CRON_STATE_FILE: &str = "cron-state.yml";
+pub const ROUTER_CACHE_FILE: &str = "router-cache.json";
 pub const GITIGNORE_FILE: &str = ".gitignore";
 
 /// Commands configuration — AI tool settings.
+///
+/// `ai_router` is the legacy shell template for the AI router; when an `ai`
+/// section is present, the router uses an AI profile instead.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CommandsConfig {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub ai_router: String,
     pub ai_interactive: String,
 }
@@ -34,6 +44,85 @@ impl Default for CommandsConfig {
     }
 }
 
+/// AI backends configuration (`ai` section).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AiConfig {
+    /// Profile used by `decree ai` when none is named.
+    #[serde(default = "default_ai_profile")]
+    pub default: String,
+    /// Profile used by the AI router (defaults to `default`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub router: Option<String>,
+    /// Profiles to try, in order, when the chosen one is not installed or
+    /// reports a quota or rate-limit error.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallback: Vec<String>,
+    /// Named backend profiles. `opencode`, `claude`, and `copilot` are
+    /// built in and can be used without being listed.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, AiProfile>,
+}
+
+fn default_ai_profile() -> String {
+    "opencode".to_string()
+}
+
+/// How an AI profile receives the prompt.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PromptMode {
+    /// As an argument: replaces `{prompt}` in `args`, or is appended.
+    #[default]
+    Argv,
+    /// Written to the command's stdin.
+    Stdin,
+    /// Written to a temporary file whose path replaces `{prompt_file}` in
+    /// `args`, or is appended.
+    File,
+}
+
+/// One AI backend: how to run it and pass it a prompt.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AiProfile {
+    /// Executable to run (looked up on PATH).
+    pub command: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub prompt: PromptMode,
+    /// Model name, passed as `<model_flag> <model>` before the prompt.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default = "default_model_flag", skip_serializing_if = "is_default_model_flag")]
+    pub model_flag: String,
+    /// Extra environment variables for the command.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    /// Kill the command after this many seconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+    /// Prices used to estimate the cost of `decree ai` calls.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pricing: Option<AiPricing>,
+}
+
+/// Price per million tokens of an AI profile.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct AiPricing {
+    #[serde(default)]
+    pub input: f64,
+    #[serde(default)]
+    pub output: f64,
+}
+
+fn default_model_flag() -> String {
+    "--model".to_string()
+}
+
+fn is_default_model_flag(flag: &String) -> bool {
+    *flag == default_model_flag()
+}
+
 /// Lifecycle hooks configuration.
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct HooksConfig {
@@ -47,6 +136,147 @@ pub struct HooksConfig {
     pub after_each: String,
 }
 
+/// A routing rule (`routes` section), checked before the AI router.
+///
+/// Every condition that is set must match; a rule with no conditions
+/// matches every message.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct RouteRule {
+    /// Optional label shown by `decree route --explain`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Routine to use when the rule matches.
+    pub routine: String,
+    /// Glob matched against the message filename (or its migration filename).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filename: Option<String>,
+    /// Frontmatter fields that must have exactly these values.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub frontmatter: BTreeMap<String, String>,
+    /// Regex searched for in the message body.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    /// Glob matched against the cron file (stem) that produced the message.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cron: Option<String>,
+}
+
+/// AI router settings (`router` section).
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct RouterConfig {
+    /// Ask the AI router for ranked candidates and park messages whose best
+    /// candidate scores below this confidence (0–1).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_confidence: Option<f64>,
+}
+
+impl RouterConfig {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Spending caps (`budget` section), in the same unit as recorded costs.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct BudgetConfig {
+    /// Stop processing once today's recorded spend reaches this.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub daily: Option<f64>,
+    /// Stop processing a migration's chain once its spend reaches this.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub per_migration: Option<f64>,
+}
+
+impl BudgetConfig {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Run history retention (`retention` section), applied by `decree gc`.
+///
+/// A run is kept while it is among the newest `keep_runs` or younger than
+/// `keep_days`; with neither set, nothing is pruned.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RetentionConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep_runs: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep_days: Option<u32>,
+    /// Never prune dead-lettered runs.
+    #[serde(default = "default_true")]
+    pub keep_failed: bool,
+    /// Pack pruned runs into `.decree/archive/*.tar.gz` instead of deleting them.
+    #[serde(default)]
+    pub archive: bool,
+    /// Hours between automatic `gc` passes in the daemon (off when unset).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gc_interval_hours: Option<u64>,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            keep_runs: None,
+            keep_days: None,
+            keep_failed: true,
+            archive: false,
+            gc_interval_hours: None,
+        }
+    }
+}
+
+impl RetentionConfig {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Whether any policy limits how many runs are kept.
+    pub fn is_set(&self) -> bool {
+        self.keep_runs.is_some() || self.keep_days.is_some()
+    }
+}
+
+/// A notification sink (`notifications` section).
+///
+/// Exactly one of `webhook`, `command`, or `file` says where the JSON
+/// payload goes.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct NotificationSink {
+    /// Events to send; every event when empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub on: Vec<NotifyEvent>,
+    /// URL the payload is POSTed to (sent with `curl`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook: Option<String>,
+    /// Extra headers for `webhook`. `${VAR}` is read from the environment.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    /// Shell command run in the project root with the payload on stdin.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    /// File, relative to the project root, the payload is appended to as
+    /// one JSON line.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+}
+
+/// Something a notification sink can be told about.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NotifyEvent {
+    /// A migration's message finished successfully.
+    MigrationDone,
+    /// A message was dead-lettered (including after exhausted retries).
+    DeadLettered,
+    /// A message failed on every attempt.
+    RetriesExhausted,
+    DaemonStarted,
+    DaemonStopped,
+    /// The daemon queued a cron job.
+    CronFired,
+}
+
 /// A routine entry in the registry (routines/shared_routines sections).
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RoutineEntry {
@@ -54,6 +284,9 @@ pub struct RoutineEntry {
     pub enabled: bool,
     #[serde(default, skip_serializing_if = "is_false")]
     pub deprecated: bool,
+    /// AI profile for this routine (overridden by a message's `ai` field).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ai: Option<String>,
 }
 
 fn default_true() -> bool {
@@ -70,6 +303,7 @@ impl RoutineEntry {
         Self {
             enabled,
             deprecated: false,
+            ai: None,
         }
     }
 
@@ -89,12 +323,36 @@ pub struct AppConfig {
     pub max_depth: u32,
     #[serde(default = "default_max_log_size")]
     pub max_log_size: u64,
+    /// Bytes kept from the start of a truncated log; the rest of `max_log_size` keeps the end.
+    #[serde(default = "default_log_head_size")]
+    pub log_head_size: u64,
+    /// Also keep the untruncated output of a truncated log as `<log>.gz`.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub keep_full_log: bool,
     #[serde(default = "default_routine")]
     pub default_routine: String,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub routine_source: Option<String>,
+    /// Default timezone for cron schedules (`UTC` if unset, `local` for system time).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
     #[serde(default)]
     pub hooks: HooksConfig,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub routes: Vec<RouteRule>,
+    #[serde(default, skip_serializing_if = "RouterConfig::is_default")]
+    pub router: RouterConfig,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ai: Option<AiConfig>,
+    #[serde(default, skip_serializing_if = "BudgetConfig::is_default")]
+    pub budget: BudgetConfig,
+    /// Extra variables for prompt templates (built-in variables win).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub prompt_vars: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notifications: Vec<NotificationSink>,
+    #[serde(default, skip_serializing_if = "RetentionConfig::is_default")]
+    pub retention: RetentionConfig,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub routines: Option<BTreeMap<String, RoutineEntry>>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -110,6 +368,9 @@ fn default_max_depth() -> u32 {
 fn default_max_log_size() -> u64 {
     2_097_152
 }
+fn default_log_head_size() -> u64 {
+    524_288
+}
 fn default_routine() -> String {
     "develop".to_string()
 }
@@ -121,9 +382,19 @@ impl Default for AppConfig {
             max_retries: default_max_retries(),
             max_depth: default_max_depth(),
             max_log_size: default_max_log_size(),
+            log_head_size: default_log_head_size(),
+            keep_full_log: false,
             default_routine: default_routine(),
             routine_source: None,
+            timezone: None,
             hooks: HooksConfig::default(),
+            routes: Vec::new(),
+            router: RouterConfig::default(),
+            ai: None,
+            budget: BudgetConfig::default(),
+            prompt_vars: BTreeMap::new(),
+            notifications: Vec::new(),
+            retention: RetentionConfig::default(),
             routines: None,
             shared_routines: None,
         }
@@ -162,11 +433,19 @@ impl AppConfig {
             .and_then(|p| p.parent().map(|parent| parent.join(PROMPTS_DIR)))
     }
 
+    /// Registry entry for a routine (project routines win over shared ones).
+    pub fn routine_entry(&self, name: &str) -> Option<&RoutineEntry> {
+        self.routines
+            .as_ref()
+            .and_then(|r| r.get(name))
+            .or_else(|| self.shared_routines.as_ref().and_then(|r| r.get(name)))
+    }
+
     /// Save config to the project's `.decree/config.yml`.
     pub fn save(&self, project_root: &Path) -> Result<(), DecreeError> {
         let path = project_root.join(DECREE_DIR).join(CONFIG_FILE);
         let yaml = serde_yaml::to_string(self)?;
-        std::fs::write(&path, yaml)?;
+        atomic::write(&path, yaml)?;
         Ok(())
     }
 }
@@ -197,6 +476,8 @@ mod tests {
         assert_eq!(config.max_retries, 3);
         assert_eq!(config.max_depth, 10);
         assert_eq!(config.max_log_size, 2_097_152);
+        assert_eq!(config.log_head_size, 524_288);
+        assert!(!config.keep_full_log);
         assert_eq!(config.default_routine, "develop");
         assert!(config.routine_source.is_none());
         assert!(config.routines.is_none());
@@ -212,6 +493,8 @@ commands:
 max_retries: 5
 max_depth: 20
 max_log_size: 0
+log_head_size: 4096
+keep_full_log: true
 default_routine: rust-develop
 hooks:
   beforeAll: ""
@@ -225,6 +508,8 @@ hooks:
         assert_eq!(config.max_retries, 5);
         assert_eq!(config.max_depth, 20);
         assert_eq!(config.max_log_size, 0);
+        assert_eq!(config.log_head_size, 4096);
+        assert!(config.keep_full_log);
         assert_eq!(config.default_routine, "rust-develop");
         assert_eq!(config.hooks.before_each, "git-baseline");
         assert_eq!(config.hooks.after_each, "git-stash-changes");
@@ -298,6 +583,7 @@ shared_routines:
         let entry = RoutineEntry {
             enabled: true,
             deprecated: true,
+            ai: None,
         };
         assert!(!entry.is_active());
     }
@@ -1,11 +1,237 @@
-use crate::config;
+use crate::atomic;
+use crate::config::{self, AppConfig};
 use crate::error::DecreeError;
-use crate::message::{build_chain_id, next_day_counter, parse_frontmatter, InboxMessage};
-use chrono::{Local, Utc};
-use std::collections::{BTreeMap, HashMap};
-use std::path::Path;
+use crate::router;
+use crate::message::{
+    build_chain_id, list_inbox_messages, next_day_counter, parse_frontmatter, InboxMessage,
+    MessageId,
+};
+use chrono::{DateTime, Local, SecondsFormat, TimeDelta, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
+/// Upper bound on catch-up messages enqueued for a single cron file at once.
+pub const MAX_CATCH_UP: usize = 100;
+
+/// What to do with occurrences that passed while the daemon was down or busy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CatchUp {
+    /// Skip missed occurrences (they are only logged).
+    #[default]
+    None,
+    /// Enqueue a single message for the most recent missed occurrence.
+    Once,
+    /// Enqueue one message per missed occurrence (capped at `MAX_CATCH_UP`).
+    All,
+}
+
+impl CatchUp {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CatchUp::None => "none",
+            CatchUp::Once => "once",
+            CatchUp::All => "all",
+        }
+    }
+}
+
+impl FromStr for CatchUp {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(CatchUp::None),
+            "once" => Ok(CatchUp::Once),
+            "all" => Ok(CatchUp::All),
+            other => Err(format!("expected none, once, or all (got '{other}')")),
+        }
+    }
+}
+
+impl std::fmt::Display for CatchUp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// What to do when a cron job fires while its previous run is still pending.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Concurrency {
+    /// Don't fire; the skipped occurrence is logged and recorded in the runs.
+    Skip,
+    /// Enqueue another message alongside the pending one.
+    #[default]
+    Queue,
+    /// Remove pending first messages that haven't started, then enqueue the
+    /// new one.
+    Replace,
+}
+
+impl Concurrency {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Concurrency::Skip => "skip",
+            Concurrency::Queue => "queue",
+            Concurrency::Replace => "replace",
+        }
+    }
+}
+
+impl FromStr for Concurrency {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "skip" => Ok(Concurrency::Skip),
+            "queue" => Ok(Concurrency::Queue),
+            "replace" => Ok(Concurrency::Replace),
+            other => Err(format!("expected skip, queue, or replace (got '{other}')")),
+        }
+    }
+}
+
+impl std::fmt::Display for Concurrency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Timezone a cron schedule is evaluated in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CronTimezone {
+    /// Coordinated Universal Time (the default).
+    #[default]
+    Utc,
+    /// The system's local timezone.
+    Local,
+    /// An IANA timezone such as `America/Denver`.
+    Named(Tz),
+}
+
+impl CronTimezone {
+    /// Format a UTC instant in this timezone.
+    pub fn format(&self, t: DateTime<Utc>, fmt: &str) -> String {
+        match self {
+            CronTimezone::Utc => t.format(fmt).to_string(),
+            CronTimezone::Local => t.with_timezone(&Local).format(fmt).to_string(),
+            CronTimezone::Named(tz) => t.with_timezone(tz).format(fmt).to_string(),
+        }
+    }
+}
+
+impl FromStr for CronTimezone {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "UTC" | "utc" => Ok(CronTimezone::Utc),
+            "local" => Ok(CronTimezone::Local),
+            other => other
+                .parse::<Tz>()
+                .map(CronTimezone::Named)
+                .map_err(|_| format!("unknown timezone '{other}'")),
+        }
+    }
+}
+
+impl std::fmt::Display for CronTimezone {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CronTimezone::Utc => f.write_str("UTC"),
+            CronTimezone::Local => f.write_str("local"),
+            CronTimezone::Named(tz) => f.write_str(tz.name()),
+        }
+    }
+}
+
+/// Resolve the project-level default cron timezone from `config.yml`.
+pub fn default_timezone(config: &AppConfig) -> Result<CronTimezone, DecreeError> {
+    match config.timezone.as_deref() {
+        None | Some("") => Ok(CronTimezone::default()),
+        Some(s) => s
+            .parse()
+            .map_err(|e| DecreeError::Config(format!("invalid timezone: {e}"))),
+    }
+}
+
+/// When a cron file fires.
+#[derive(Debug, Clone)]
+pub enum Schedule {
+    /// `cron:` — a standard cron expression.
+    Cron {
+        /// The expression as written in the file.
+        expression: String,
+        schedule: Box<cron::Schedule>,
+    },
+    /// `every:` — a fixed interval, aligned to whole intervals since the Unix epoch.
+    Every(TimeDelta),
+    /// `at:` — a single point in time; the file is archived once it fires.
+    At(DateTime<Utc>),
+}
+
+impl std::fmt::Display for Schedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Schedule::Cron { expression, .. } => f.write_str(expression),
+            Schedule::Every(interval) => write!(f, "every {}", format_interval(*interval)),
+            Schedule::At(t) => write!(f, "at {}", t.to_rfc3339_opts(SecondsFormat::Secs, true)),
+        }
+    }
+}
+
+/// Parse an interval like `15m`, `2h`, `1d`, or `1h30m`.
+///
+/// Intervals must be a whole number of minutes, since cron files are
+/// checked once per minute.
+pub fn parse_interval(s: &str) -> Result<TimeDelta, String> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Err("empty interval".into());
+    }
+
+    let mut total = TimeDelta::zero();
+    let mut digits = String::new();
+    for c in s.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+        let n: i64 = digits
+            .parse()
+            .map_err(|_| format!("expected a number before '{c}' in '{s}'"))?;
+        digits.clear();
+        total += match c {
+            'm' => TimeDelta::minutes(n),
+            'h' => TimeDelta::hours(n),
+            'd' => TimeDelta::days(n),
+            other => return Err(format!("unknown unit '{other}' in '{s}' (use m, h, or d)")),
+        };
+    }
+    if !digits.is_empty() {
+        return Err(format!("missing unit after '{digits}' in '{s}' (use m, h, or d)"));
+    }
+    if total < TimeDelta::minutes(1) {
+        return Err(format!("interval '{s}' must be at least 1m"));
+    }
+    Ok(total)
+}
+
+/// Format an interval in the largest units that divide it (`90m` → `1h30m`).
+fn format_interval(interval: TimeDelta) -> String {
+    let mut minutes = interval.num_minutes();
+    let mut out = String::new();
+    for (unit, size) in [("d", 24 * 60), ("h", 60), ("m", 1)] {
+        if minutes >= size {
+            out.push_str(&format!("{}{unit}", minutes / size));
+            minutes %= size;
+        }
+    }
+    out
+}
+
 /// A parsed cron file from `.decree/cron/`.
 #[derive(Debug, Clone)]
 pub struct CronFile {
@@ -13,24 +239,95 @@ pub struct CronFile {
     pub filename: String,
     /// The stem used for chain ID naming (e.g., `hourly-maintenance`).
     pub name_stem: String,
-    /// Parsed cron schedule.
-    pub schedule: cron::Schedule,
+    /// Parsed schedule from the `cron`, `every`, or `at` field.
+    pub schedule: Schedule,
     /// Optional routine override.
     pub routine: Option<String>,
+    /// Missed-run policy from the `catch_up` field.
+    pub catch_up: CatchUp,
+    /// Overlap policy from the `concurrency` field.
+    pub concurrency: Concurrency,
+    /// Timezone override from the `timezone` field (project default if `None`).
+    pub timezone: Option<CronTimezone>,
     /// Custom frontmatter fields (cron field stripped).
     pub custom_fields: BTreeMap<String, serde_yaml::Value>,
     /// Markdown body.
     pub body: String,
 }
 
+impl CronFile {
+    /// The timezone this file's schedule is evaluated in.
+    pub fn effective_timezone(&self, default: CronTimezone) -> CronTimezone {
+        self.timezone.unwrap_or(default)
+    }
+
+    /// Scheduled occurrences strictly after `after`, evaluated in the file's
+    /// timezone (or `default`) so wall-clock schedules follow DST changes.
+    pub fn occurrences_after(
+        &self,
+        after: DateTime<Utc>,
+        default: CronTimezone,
+    ) -> Box<dyn Iterator<Item = DateTime<Utc>> + '_> {
+        let schedule = match &self.schedule {
+            Schedule::Cron { schedule, .. } => schedule,
+            Schedule::Every(interval) => return Box::new(interval_after(*interval, after)),
+            Schedule::At(t) => return Box::new(Some(*t).filter(|t| *t > after).into_iter()),
+        };
+        match self.effective_timezone(default) {
+            CronTimezone::Utc => Box::new(schedule.after(&after)),
+            CronTimezone::Local => Box::new(occurrences_in(schedule, after, Local)),
+            CronTimezone::Named(tz) => Box::new(occurrences_in(schedule, after, tz)),
+        }
+    }
+
+    /// Whether this file fires once (`at:`) and is archived afterwards.
+    pub fn is_one_shot(&self) -> bool {
+        matches!(self.schedule, Schedule::At(_))
+    }
+}
+
+/// Multiples of `interval` since the Unix epoch, strictly after `after`.
+fn interval_after(interval: TimeDelta, after: DateTime<Utc>) -> impl Iterator<Item = DateTime<Utc>> {
+    let step = interval.num_seconds();
+    let next = (after.timestamp().div_euclid(step) + 1) * step;
+    (0..).map_while(move |i: i64| DateTime::from_timestamp(next + i * step, 0))
+}
+
+fn occurrences_in<Z: TimeZone + 'static>(
+    schedule: &cron::Schedule,
+    after: DateTime<Utc>,
+    tz: Z,
+) -> impl Iterator<Item = DateTime<Utc>> {
+    schedule
+        .after_owned(after.with_timezone(&tz))
+        .map(|t| t.with_timezone(&Utc))
+}
+
 /// Scan `.decree/cron/` for valid cron files.
+///
+/// Files that fail to parse are skipped; use [`scan_cron_dir`] to see why.
 pub fn scan_cron_files(project_root: &Path) -> Result<Vec<CronFile>, DecreeError> {
+    Ok(scan_cron_dir(project_root)?.files)
+}
+
+/// Result of scanning `.decree/cron/`.
+#[derive(Debug, Default)]
+pub struct CronScan {
+    /// Successfully parsed cron files, sorted by filename.
+    pub files: Vec<CronFile>,
+    /// Files that failed to parse, as `(filename, reason)`.
+    pub errors: Vec<(String, String)>,
+}
+
+/// Scan `.decree/cron/`, keeping parse errors alongside valid files.
+pub fn scan_cron_dir(project_root: &Path) -> Result<CronScan, DecreeError> {
     let cron_dir = project_root
         .join(config::DECREE_DIR)
         .join(config::CRON_DIR);
 
+    let mut scan = CronScan::default();
     if !cron_dir.exists() {
-        return Ok(Vec::new());
+        return Ok(scan);
     }
 
     let mut entries: Vec<String> = std::fs::read_dir(&cron_dir)?
@@ -41,53 +338,125 @@ pub fn scan_cron_files(project_root: &Path) -> Result<Vec<CronFile>, DecreeError
 
     entries.sort();
 
-    let mut cron_files = Vec::new();
     for filename in entries {
         let path = cron_dir.join(&filename);
         let content = std::fs::read_to_string(&path)?;
         match parse_cron_file(&filename, &content) {
-            Ok(cf) => cron_files.push(cf),
-            Err(_) => {
-                // Skip files with invalid cron expressions or no cron field
-                continue;
-            }
+            Ok(cf) => scan.files.push(cf),
+            Err(e) => scan.errors.push((filename, e.to_string())),
         }
     }
 
-    Ok(cron_files)
+    Ok(scan)
 }
 
 /// Parse a single cron file from its filename and content.
 fn parse_cron_file(filename: &str, content: &str) -> Result<CronFile, DecreeError> {
     let (fields, body) = parse_frontmatter(content)?;
 
-    let cron_expr = fields
-        .get("cron")
-        .and_then(|v| match v {
+    let field = |name: &str| {
+        fields.get(name).and_then(|v| match v {
             serde_yaml::Value::String(s) => Some(s.clone()),
             _ => None,
         })
-        .ok_or_else(|| DecreeError::Other(format!("no cron field in {filename}")))?;
-
-    // The cron crate expects 6 or 7 fields (seconds included).
-    // Standard 5-field cron needs a "0" seconds prefix.
-    let fields_count = cron_expr.split_whitespace().count();
-    let schedule_expr = if fields_count == 5 {
-        format!("0 {cron_expr}")
-    } else {
-        cron_expr.clone()
     };
 
-    let schedule = cron::Schedule::from_str(&schedule_expr)
-        .map_err(|e| DecreeError::Other(format!("invalid cron expression in {filename}: {e}")))?;
+    let schedule = match (field("cron"), field("every"), field("at")) {
+        (Some(cron_expr), None, None) => {
+            // The cron crate expects 6 or 7 fields (seconds included).
+            // Standard 5-field cron needs a "0" seconds prefix.
+            let fields_count = cron_expr.split_whitespace().count();
+            let schedule_expr = if fields_count == 5 {
+                format!("0 {cron_expr}")
+            } else {
+                cron_expr.clone()
+            };
+
+            let schedule = cron::Schedule::from_str(&schedule_expr).map_err(|e| {
+                DecreeError::Other(format!("invalid cron expression in {filename}: {e}"))
+            })?;
+            Schedule::Cron {
+                expression: cron_expr,
+                schedule: Box::new(schedule),
+            }
+        }
+        (None, Some(every), None) => Schedule::Every(
+            parse_interval(&every)
+                .map_err(|e| DecreeError::Other(format!("invalid every in {filename}: {e}")))?,
+        ),
+        (None, None, Some(at)) => Schedule::At(
+            DateTime::parse_from_rfc3339(at.trim())
+                .map_err(|e| {
+                    DecreeError::Other(format!(
+                        "invalid at in {filename}: {e} (expected RFC 3339, e.g. 2026-11-01T08:00:00-07:00)"
+                    ))
+                })?
+                .with_timezone(&Utc),
+        ),
+        (None, None, None) => {
+            return Err(DecreeError::Other(format!(
+                "no cron field in {filename} (expected cron, every, or at)"
+            )))
+        }
+        _ => {
+            return Err(DecreeError::Other(format!(
+                "{filename} sets more than one of cron, every, and at"
+            )))
+        }
+    };
 
     let routine = fields.get("routine").and_then(|v| match v {
         serde_yaml::Value::String(s) => Some(s.clone()),
         _ => None,
     });
 
-    // Collect custom fields, stripping "cron" and known message fields
-    let strip_fields: &[&str] = &["cron", "routine"];
+    let catch_up = match fields.get("catch_up") {
+        None => CatchUp::default(),
+        Some(serde_yaml::Value::String(s)) => s
+            .parse()
+            .map_err(|e| DecreeError::Other(format!("invalid catch_up in {filename}: {e}")))?,
+        Some(_) => {
+            return Err(DecreeError::Other(format!(
+                "invalid catch_up in {filename}: expected none, once, or all"
+            )))
+        }
+    };
+
+    let concurrency = match fields.get("concurrency") {
+        None => Concurrency::default(),
+        Some(serde_yaml::Value::String(s)) => s
+            .parse()
+            .map_err(|e| DecreeError::Other(format!("invalid concurrency in {filename}: {e}")))?,
+        Some(_) => {
+            return Err(DecreeError::Other(format!(
+                "invalid concurrency in {filename}: expected skip, queue, or replace"
+            )))
+        }
+    };
+
+    let timezone = match fields.get("timezone") {
+        None => None,
+        Some(serde_yaml::Value::String(s)) => Some(
+            s.parse()
+                .map_err(|e| DecreeError::Other(format!("invalid timezone in {filename}: {e}")))?,
+        ),
+        Some(_) => {
+            return Err(DecreeError::Other(format!(
+                "invalid timezone in {filename}: expected a name like America/Denver"
+            )))
+        }
+    };
+
+    // Collect custom fields, stripping scheduling fields and known message fields
+    let strip_fields: &[&str] = &[
+        "cron",
+        "every",
+        "at",
+        "routine",
+        "catch_up",
+        "concurrency",
+        "timezone",
+    ];
     let custom_fields: BTreeMap<String, serde_yaml::Value> = fields
         .into_iter()
         .filter(|(k, _)| !strip_fields.contains(&k.as_str()))
@@ -103,16 +472,41 @@ fn parse_cron_file(filename: &str, content: &str) -> Result<CronFile, DecreeErro
         name_stem,
         schedule,
         routine,
+        catch_up,
+        concurrency,
+        timezone,
         custom_fields,
         body,
     })
 }
 
-/// Tracker for preventing duplicate firings within the same minute.
+/// Persisted scheduling state for a single cron file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CronFileState {
+    /// The most recent occurrence accounted for — fired, caught up, or skipped.
+    pub last_fire: DateTime<Utc>,
+}
+
+/// Result of checking a cron file against the current time.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CronCheck {
+    /// Occurrence within the current minute that has not fired yet.
+    pub due: Option<DateTime<Utc>>,
+    /// Occurrences between the last fire and the current minute, oldest first.
+    pub missed: Vec<DateTime<Utc>>,
+}
+
+/// Tracks the last fire time of each cron file.
+///
+/// The state is persisted to `.decree/cron-state.yml` so occurrences that
+/// pass while the daemon is down or busy are detected on the next check.
+/// A cron file seen for the first time starts from the current minute.
 #[derive(Debug, Default)]
 pub struct CronTracker {
-    /// Maps cron filename to the last minute string it fired (e.g., "202603041530").
-    last_fire: HashMap<String, String>,
+    /// Maps cron filename to its scheduling state.
+    last_fire: BTreeMap<String, CronFileState>,
+    /// Project default timezone for files without a `timezone` field.
+    timezone: CronTimezone,
 }
 
 impl CronTracker {
@@ -120,50 +514,257 @@ impl CronTracker {
         Self::default()
     }
 
+    /// Set the project default timezone used to evaluate schedules.
+    pub fn with_timezone(mut self, timezone: CronTimezone) -> Self {
+        self.timezone = timezone;
+        self
+    }
+
+    /// Load persisted state from `.decree/cron-state.yml` (empty if missing).
+    pub fn load(project_root: &Path) -> Result<Self, DecreeError> {
+        let path = state_path(project_root);
+        if !path.exists() {
+            return Ok(Self::new());
+        }
+        let content = std::fs::read_to_string(&path)?;
+        let last_fire = if content.trim().is_empty() {
+            BTreeMap::new()
+        } else {
+            serde_yaml::from_str(&content)?
+        };
+        Ok(Self {
+            last_fire,
+            ..Self::default()
+        })
+    }
+
+    /// Persist state to `.decree/cron-state.yml`.
+    pub fn save(&self, project_root: &Path) -> Result<(), DecreeError> {
+        let yaml = serde_yaml::to_string(&self.last_fire)?;
+        atomic::write(&state_path(project_root), yaml)?;
+        Ok(())
+    }
+
+    /// The last fire time recorded for a cron file, if any.
+    pub fn last_fire(&self, cron_file: &CronFile) -> Option<DateTime<Utc>> {
+        self.last_fire.get(&cron_file.filename).map(|s| s.last_fire)
+    }
+
     /// Check if a cron file is due and hasn't already fired this minute.
     /// Returns true if the job should fire.
     pub fn is_due(&self, cron_file: &CronFile) -> bool {
+        self.check(cron_file, Utc::now()).due.is_some()
+    }
+
+    /// Record that a cron file has fired for the current minute.
+    pub fn mark_fired(&mut self, cron_file: &CronFile) {
         let now = Utc::now();
-        let minute_key = now.format("%Y%m%d%H%M").to_string();
+        let at = self.check(cron_file, now).due.unwrap_or(now);
+        self.record(cron_file, at);
+    }
+
+    /// Classify the occurrences since the last fire relative to `now`.
+    ///
+    /// Only the most recent `MAX_CATCH_UP` missed occurrences are returned.
+    pub fn check(&self, cron_file: &CronFile, now: DateTime<Utc>) -> CronCheck {
+        let minute_start = truncate_to_minute(now);
+        let next_minute = minute_start + TimeDelta::minutes(1);
+        let since = self
+            .last_fire(cron_file)
+            .unwrap_or(minute_start - TimeDelta::seconds(1));
 
-        // Check if already fired this minute
-        if let Some(last) = self.last_fire.get(&cron_file.filename) {
-            if last == &minute_key {
-                return false;
+        let mut check = CronCheck::default();
+        for occurrence in cron_file.occurrences_after(since, self.timezone) {
+            if occurrence >= next_minute {
+                break;
+            }
+            if occurrence >= minute_start {
+                check.due = Some(occurrence);
+                break;
+            }
+            check.missed.push(occurrence);
+            if check.missed.len() > MAX_CATCH_UP {
+                check.missed.remove(0);
             }
         }
+        check
+    }
 
-        // Check if the cron expression matches the current time
-        // Get the next upcoming occurrence and see if it falls within the current minute
-        if let Some(next) = cron_file.schedule.upcoming(Utc).next() {
-            let diff = next.signed_duration_since(now);
-            // If the next occurrence is within 60 seconds, the current minute matches
-            diff.num_seconds() < 60
-        } else {
-            false
+    /// Record an occurrence as accounted for. Never moves the watermark back.
+    pub fn record(&mut self, cron_file: &CronFile, at: DateTime<Utc>) {
+        let entry = self
+            .last_fire
+            .entry(cron_file.filename.clone())
+            .or_insert(CronFileState { last_fire: at });
+        if at > entry.last_fire {
+            entry.last_fire = at;
         }
     }
 
-    /// Record that a cron file has fired.
-    pub fn mark_fired(&mut self, cron_file: &CronFile) {
-        let minute_key = Utc::now().format("%Y%m%d%H%M").to_string();
-        self.last_fire
-            .insert(cron_file.filename.clone(), minute_key);
+    /// Start tracking a cron file from the current minute if it is new.
+    ///
+    /// A one-shot whose time has already passed is tracked from just before
+    /// that time, so its occurrence counts as missed and `catch_up` decides
+    /// whether it still fires.
+    ///
+    /// Returns true if the file was not tracked before.
+    pub fn track(&mut self, cron_file: &CronFile, now: DateTime<Utc>) -> bool {
+        if self.last_fire.contains_key(&cron_file.filename) {
+            return false;
+        }
+        let mut baseline = truncate_to_minute(now) - TimeDelta::seconds(1);
+        if let Schedule::At(at) = cron_file.schedule {
+            baseline = baseline.min(at - TimeDelta::seconds(1));
+        }
+        self.record(cron_file, baseline);
+        true
+    }
+
+    /// Drop state for cron files that no longer exist.
+    ///
+    /// Returns true if anything was removed.
+    pub fn retain(&mut self, cron_files: &[CronFile]) -> bool {
+        let names: HashSet<&str> = cron_files.iter().map(|cf| cf.filename.as_str()).collect();
+        let before = self.last_fire.len();
+        self.last_fire.retain(|name, _| names.contains(name.as_str()));
+        self.last_fire.len() != before
     }
 }
 
+/// Path to the persisted cron state file.
+fn state_path(project_root: &Path) -> std::path::PathBuf {
+    project_root
+        .join(config::DECREE_DIR)
+        .join(config::CRON_STATE_FILE)
+}
+
+/// Drop seconds and sub-second precision from a timestamp.
+fn truncate_to_minute(t: DateTime<Utc>) -> DateTime<Utc> {
+    t.with_second(0)
+        .and_then(|t| t.with_nanosecond(0))
+        .unwrap_or(t)
+}
+
+/// Move a fired one-shot cron file to `.decree/cron/done/`.
+///
+/// An existing file of the same name in `done/` is kept; the archived copy
+/// gets a timestamp suffix instead. Returns the archive path.
+pub fn archive(project_root: &Path, cron_file: &CronFile) -> Result<PathBuf, DecreeError> {
+    let cron_dir = project_root
+        .join(config::DECREE_DIR)
+        .join(config::CRON_DIR);
+    let done_dir = cron_dir.join(config::DONE_DIR);
+    std::fs::create_dir_all(&done_dir)?;
+
+    let mut dest = done_dir.join(&cron_file.filename);
+    if dest.exists() {
+        let stamp = Local::now().format("%Y%m%d%H%M%S");
+        dest = done_dir.join(format!("{}-{stamp}.md", cron_file.name_stem));
+    }
+    std::fs::rename(cron_dir.join(&cron_file.filename), &dest)?;
+    Ok(dest)
+}
+
+/// Inbox messages that belong to a cron file's chains, as paths relative to
+/// the inbox, sorted.
+///
+/// Covers scheduled fires, catch-up messages, and their follow-ups. A message
+/// stays in the inbox until it finishes processing, so this includes runs
+/// that are still in progress, as well as messages parked in `needs-routing/`
+/// or held in `pending-approval/` (listed as `<dir>/<filename>`).
+pub fn pending_messages(
+    project_root: &Path,
+    cron_file: &CronFile,
+) -> Result<Vec<String>, DecreeError> {
+    let inbox_dir = project_root
+        .join(config::DECREE_DIR)
+        .join(config::INBOX_DIR);
+    let mut pending = list_inbox_messages(project_root)?;
+    for held in [config::NEEDS_ROUTING_DIR, config::PENDING_APPROVAL_DIR] {
+        let dir = inbox_dir.join(held);
+        if !dir.is_dir() {
+            continue;
+        }
+        for entry in std::fs::read_dir(&dir)?.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            if path.is_file() && path.extension().is_some_and(|ext| ext == "md") {
+                if let Ok(name) = entry.file_name().into_string() {
+                    pending.push(format!("{held}/{name}"));
+                }
+            }
+        }
+    }
+
+    let mut pending: Vec<String> = pending
+        .into_iter()
+        .filter(|path| {
+            pending_message_id(path)
+                .is_some_and(|id| is_cron_chain(&id.chain, &cron_file.name_stem))
+        })
+        .collect();
+    pending.sort();
+    Ok(pending)
+}
+
+/// Parse the message ID from an inbox-relative path returned by
+/// [`pending_messages`].
+pub fn pending_message_id(path: &str) -> Option<MessageId> {
+    let filename = path.rsplit('/').next().unwrap_or(path);
+    MessageId::parse(filename.strip_suffix(".md").unwrap_or(filename)).ok()
+}
+
+/// Whether a chain ID (`D<NNNN>-HHmm-<name>`) was created for the cron file `stem`.
+fn is_cron_chain(chain: &str, stem: &str) -> bool {
+    let Some(name) = chain.splitn(3, '-').nth(2) else {
+        return false;
+    };
+    name == stem
+        || name
+            .strip_prefix(stem)
+            .is_some_and(|rest| rest.starts_with("-catchup-"))
+}
+
 /// Create an inbox message from a fired cron job.
 pub fn cron_to_inbox_message(
     project_root: &Path,
     cron_file: &CronFile,
+) -> Result<InboxMessage, DecreeError> {
+    build_cron_message(project_root, cron_file, &cron_file.name_stem)
+}
+
+/// Create an inbox message for a missed occurrence being caught up.
+///
+/// The chain name carries the scheduled time (`<stem>-catchup-YYYYMMDDHHmm`)
+/// so several catch-up messages enqueued in the same minute don't collide.
+pub fn catch_up_message(
+    project_root: &Path,
+    cron_file: &CronFile,
+    scheduled: DateTime<Utc>,
+) -> Result<InboxMessage, DecreeError> {
+    let stamp = scheduled.with_timezone(&Local).format("%Y%m%d%H%M");
+    let name = format!("{}-catchup-{stamp}", cron_file.name_stem);
+    build_cron_message(project_root, cron_file, &name)
+}
+
+fn build_cron_message(
+    project_root: &Path,
+    cron_file: &CronFile,
+    name: &str,
 ) -> Result<InboxMessage, DecreeError> {
     let now = Local::now();
     let hhmm = now.format("%H%M").to_string();
     let day = next_day_counter(project_root, &hhmm)?;
-    let chain = build_chain_id(&day, &hhmm, &cron_file.name_stem);
+    let chain = build_chain_id(&day, &hhmm, name);
     let filename = format!("{chain}-0.md");
     let id = format!("{chain}-0");
 
+    // Tag the message with its cron file so routing rules can match on it
+    let mut custom_fields = cron_file.custom_fields.clone();
+    custom_fields.insert(
+        router::CRON_SOURCE_FIELD.to_string(),
+        serde_yaml::Value::String(cron_file.name_stem.clone()),
+    );
+
     Ok(InboxMessage {
         id: Some(id),
         chain: Some(chain),
@@ -171,7 +772,7 @@ pub fn cron_to_inbox_message(
         routine: cron_file.routine.clone(),
         migration: None,
         body: cron_file.body.clone(),
-        custom_fields: cron_file.custom_fields.clone(),
+        custom_fields,
         filename,
     })
 }
@@ -316,6 +917,26 @@ mod tests {
         assert_eq!(files[0].filename, "valid.md");
     }
 
+    #[test]
+    fn test_scan_cron_dir_reports_errors() {
+        let dir = TempDir::new().unwrap();
+        setup_decree_dir(&dir);
+        let cron_dir = dir.path().join(".decree/cron");
+
+        std::fs::write(cron_dir.join("valid.md"), "---\ncron: \"0 * * * *\"\n---\n").unwrap();
+        std::fs::write(cron_dir.join("invalid.md"), "---\ncron: \"bad\"\n---\n").unwrap();
+        std::fs::write(cron_dir.join("no-cron.md"), "---\nroutine: develop\n---\n").unwrap();
+
+        let scan = scan_cron_dir(dir.path()).unwrap();
+        assert_eq!(scan.files.len(), 1);
+        assert_eq!(scan.files[0].schedule.to_string(), "0 * * * *");
+        assert_eq!(scan.errors.len(), 2);
+        assert_eq!(scan.errors[0].0, "invalid.md");
+        assert!(scan.errors[0].1.contains("invalid cron expression"));
+        assert_eq!(scan.errors[1].0, "no-cron.md");
+        assert!(scan.errors[1].1.contains("no cron field"));
+    }
+
     #[test]
     fn test_cron_tracker_prevents_duplicate() {
         let content = "---\ncron: \"* * * * *\"\n---\nBody.\n";
@@ -333,6 +954,419 @@ mod tests {
         assert!(!tracker.is_due(&cf));
     }
 
+    #[test]
+    fn test_parse_cron_file_catch_up() {
+        let cf = parse_cron_file("t.md", "---\ncron: \"0 * * * *\"\n---\n").unwrap();
+        assert_eq!(cf.catch_up, CatchUp::None);
+
+        let cf =
+            parse_cron_file("t.md", "---\ncron: \"0 * * * *\"\ncatch_up: once\n---\n").unwrap();
+        assert_eq!(cf.catch_up, CatchUp::Once);
+        assert!(!cf.custom_fields.contains_key("catch_up"));
+
+        let cf =
+            parse_cron_file("t.md", "---\ncron: \"0 * * * *\"\ncatch_up: all\n---\n").unwrap();
+        assert_eq!(cf.catch_up, CatchUp::All);
+    }
+
+    #[test]
+    fn test_parse_cron_file_concurrency() {
+        let cf = parse_cron_file("t.md", "---\ncron: \"0 * * * *\"\n---\n").unwrap();
+        assert_eq!(cf.concurrency, Concurrency::Queue);
+
+        let cf = parse_cron_file("t.md", "---\ncron: \"0 * * * *\"\nconcurrency: skip\n---\n")
+            .unwrap();
+        assert_eq!(cf.concurrency, Concurrency::Skip);
+        assert!(!cf.custom_fields.contains_key("concurrency"));
+
+        let cf =
+            parse_cron_file("t.md", "---\ncron: \"0 * * * *\"\nconcurrency: replace\n---\n")
+                .unwrap();
+        assert_eq!(cf.concurrency, Concurrency::Replace);
+
+        let err = parse_cron_file("t.md", "---\ncron: \"0 * * * *\"\nconcurrency: wait\n---\n")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("invalid concurrency in t.md"));
+    }
+
+    #[test]
+    fn test_parse_interval() {
+        assert_eq!(parse_interval("15m"), Ok(TimeDelta::minutes(15)));
+        assert_eq!(parse_interval("2h"), Ok(TimeDelta::hours(2)));
+        assert_eq!(parse_interval("1d"), Ok(TimeDelta::days(1)));
+        assert_eq!(parse_interval("1h30m"), Ok(TimeDelta::minutes(90)));
+        assert!(parse_interval("").is_err());
+        assert!(parse_interval("15").is_err());
+        assert!(parse_interval("30s").is_err());
+        assert!(parse_interval("0m").is_err());
+        assert!(parse_interval("m").is_err());
+    }
+
+    #[test]
+    fn test_format_interval() {
+        assert_eq!(format_interval(TimeDelta::minutes(15)), "15m");
+        assert_eq!(format_interval(TimeDelta::minutes(90)), "1h30m");
+        assert_eq!(format_interval(TimeDelta::days(1)), "1d");
+    }
+
+    #[test]
+    fn test_parse_cron_file_every() {
+        let cf = parse_cron_file("t.md", "---\nevery: 15m\nroutine: develop\n---\n").unwrap();
+        assert!(matches!(cf.schedule, Schedule::Every(d) if d == TimeDelta::minutes(15)));
+        assert_eq!(cf.schedule.to_string(), "every 15m");
+        assert!(!cf.is_one_shot());
+        assert!(!cf.custom_fields.contains_key("every"));
+
+        let times: Vec<_> = cf
+            .occurrences_after(at("2026-03-04T12:07:30Z"), CronTimezone::Utc)
+            .take(3)
+            .collect();
+        assert_eq!(
+            times,
+            vec![
+                at("2026-03-04T12:15:00Z"),
+                at("2026-03-04T12:30:00Z"),
+                at("2026-03-04T12:45:00Z"),
+            ]
+        );
+
+        // Strictly after: an exact boundary moves on to the next one
+        let next = cf
+            .occurrences_after(at("2026-03-04T12:15:00Z"), CronTimezone::Utc)
+            .next();
+        assert_eq!(next, Some(at("2026-03-04T12:30:00Z")));
+    }
+
+    #[test]
+    fn test_parse_cron_file_at() {
+        let cf =
+            parse_cron_file("t.md", "---\nat: 2026-11-01T08:00:00-06:00\n---\nOnce.\n").unwrap();
+        assert!(cf.is_one_shot());
+        assert_eq!(cf.schedule.to_string(), "at 2026-11-01T14:00:00Z");
+        assert!(!cf.custom_fields.contains_key("at"));
+
+        let times: Vec<_> = cf
+            .occurrences_after(at("2026-10-01T00:00:00Z"), CronTimezone::Utc)
+            .collect();
+        assert_eq!(times, vec![at("2026-11-01T14:00:00Z")]);
+        assert_eq!(
+            cf.occurrences_after(at("2026-11-01T14:00:00Z"), CronTimezone::Utc)
+                .next(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_cron_file_schedule_errors() {
+        let err = parse_cron_file("t.md", "---\nevery: soon\n---\n").unwrap_err();
+        assert!(err.to_string().contains("invalid every in t.md"));
+
+        let err = parse_cron_file("t.md", "---\nat: tomorrow\n---\n").unwrap_err();
+        assert!(err.to_string().contains("invalid at in t.md"));
+
+        let err =
+            parse_cron_file("t.md", "---\ncron: \"0 * * * *\"\nevery: 15m\n---\n").unwrap_err();
+        assert!(err.to_string().contains("more than one of cron, every, and at"));
+    }
+
+    #[test]
+    fn test_archive_one_shot() {
+        let dir = TempDir::new().unwrap();
+        setup_decree_dir(&dir);
+        let cron_dir = dir.path().join(".decree/cron");
+        let content = "---\nat: 2026-11-01T08:00:00Z\n---\n";
+
+        std::fs::write(cron_dir.join("once.md"), content).unwrap();
+        let cf = parse_cron_file("once.md", content).unwrap();
+        let dest = archive(dir.path(), &cf).unwrap();
+        assert_eq!(dest, cron_dir.join("done/once.md"));
+        assert!(!cron_dir.join("once.md").exists());
+
+        // A second archive with the same name doesn't clobber the first
+        std::fs::write(cron_dir.join("once.md"), content).unwrap();
+        let dest = archive(dir.path(), &cf).unwrap();
+        assert_ne!(dest, cron_dir.join("done/once.md"));
+        assert!(cron_dir.join("done/once.md").exists());
+        assert!(dest.exists());
+
+        // Archived files are not scanned
+        assert!(scan_cron_files(dir.path()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_is_cron_chain() {
+        assert!(is_cron_chain("D0001-1400-hourly", "hourly"));
+        assert!(is_cron_chain("D0012-0900-hourly-catchup-202603041200", "hourly"));
+        assert!(!is_cron_chain("D0001-1400-hourly-report", "hourly"));
+        assert!(!is_cron_chain("D0001-1400-code-review", "review"));
+        assert!(!is_cron_chain("hourly", "hourly"));
+    }
+
+    #[test]
+    fn test_pending_messages() {
+        let dir = TempDir::new().unwrap();
+        setup_decree_dir(&dir);
+        let inbox = dir.path().join(".decree/inbox");
+        for name in [
+            "D0001-1400-hourly-0.md",
+            "D0001-1400-hourly-1.md",
+            "D0002-1500-hourly-catchup-202603041400-0.md",
+            "D0003-1500-hourly-report-0.md",
+            "D0004-1500-other-0.md",
+        ] {
+            std::fs::write(inbox.join(name), "body\n").unwrap();
+        }
+
+        let cf = parse_cron_file("hourly.md", "---\ncron: \"0 * * * *\"\n---\n").unwrap();
+        assert_eq!(
+            pending_messages(dir.path(), &cf).unwrap(),
+            vec![
+                "D0001-1400-hourly-0.md",
+                "D0001-1400-hourly-1.md",
+                "D0002-1500-hourly-catchup-202603041400-0.md",
+            ]
+        );
+
+        // Parked and held messages are still pending
+        for held in ["needs-routing", "pending-approval"] {
+            std::fs::create_dir_all(inbox.join(held)).unwrap();
+        }
+        std::fs::write(inbox.join("needs-routing/D0005-1600-hourly-0.md"), "body\n").unwrap();
+        std::fs::write(inbox.join("pending-approval/D0006-1700-hourly-0.md"), "body\n").unwrap();
+        std::fs::write(inbox.join("pending-approval/D0007-1700-other-0.md"), "body\n").unwrap();
+        let pending = pending_messages(dir.path(), &cf).unwrap();
+        assert_eq!(pending.len(), 5);
+        assert!(pending.contains(&"needs-routing/D0005-1600-hourly-0.md".to_string()));
+        assert!(pending.contains(&"pending-approval/D0006-1700-hourly-0.md".to_string()));
+        assert_eq!(
+            pending_message_id("pending-approval/D0006-1700-hourly-0.md").unwrap(),
+            MessageId::new("D0006-1700-hourly", 0)
+        );
+    }
+
+    #[test]
+    fn test_parse_cron_file_invalid_catch_up() {
+        let result = parse_cron_file("t.md", "---\ncron: \"0 * * * *\"\ncatch_up: maybe\n---\n");
+        let err = result.unwrap_err().to_string();
+        assert!(err.contains("invalid catch_up in t.md"));
+    }
+
+    fn at(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_parse_cron_file_timezone() {
+        let cf = parse_cron_file("t.md", "---\ncron: \"0 9 * * *\"\n---\n").unwrap();
+        assert_eq!(cf.timezone, None);
+
+        let cf = parse_cron_file(
+            "t.md",
+            "---\ncron: \"0 9 * * *\"\ntimezone: America/Denver\n---\n",
+        )
+        .unwrap();
+        assert_eq!(
+            cf.timezone,
+            Some(CronTimezone::Named(chrono_tz::America::Denver))
+        );
+        assert!(!cf.custom_fields.contains_key("timezone"));
+
+        let cf =
+            parse_cron_file("t.md", "---\ncron: \"0 9 * * *\"\ntimezone: local\n---\n").unwrap();
+        assert_eq!(cf.timezone, Some(CronTimezone::Local));
+    }
+
+    #[test]
+    fn test_parse_cron_file_invalid_timezone() {
+        let result =
+            parse_cron_file("t.md", "---\ncron: \"0 9 * * *\"\ntimezone: Mars/Base\n---\n");
+        let err = result.unwrap_err().to_string();
+        assert!(err.contains("invalid timezone in t.md"));
+        assert!(err.contains("Mars/Base"));
+    }
+
+    #[test]
+    fn test_default_timezone_from_config() {
+        let mut config = AppConfig::default();
+        assert_eq!(default_timezone(&config).unwrap(), CronTimezone::Utc);
+
+        config.timezone = Some("Europe/Berlin".into());
+        assert_eq!(
+            default_timezone(&config).unwrap(),
+            CronTimezone::Named(chrono_tz::Europe::Berlin)
+        );
+
+        config.timezone = Some("Nowhere/Special".into());
+        assert!(default_timezone(&config).is_err());
+    }
+
+    #[test]
+    fn test_occurrences_follow_dst() {
+        let cf = parse_cron_file(
+            "t.md",
+            "---\ncron: \"0 9 * * *\"\ntimezone: America/Denver\n---\n",
+        )
+        .unwrap();
+
+        // US daylight saving starts 2026-03-08: 9:00 MST is 16:00Z, 9:00 MDT is 15:00Z
+        let times: Vec<_> = cf
+            .occurrences_after(at("2026-03-07T00:00:00Z"), CronTimezone::Utc)
+            .take(3)
+            .collect();
+        assert_eq!(
+            times,
+            vec![
+                at("2026-03-07T16:00:00Z"),
+                at("2026-03-08T15:00:00Z"),
+                at("2026-03-09T15:00:00Z"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_file_timezone_overrides_default() {
+        let cf = parse_cron_file("t.md", "---\ncron: \"0 9 * * *\"\n---\n").unwrap();
+        let berlin = CronTimezone::Named(chrono_tz::Europe::Berlin);
+        assert_eq!(cf.effective_timezone(berlin), berlin);
+
+        let next = cf.occurrences_after(at("2026-01-10T00:00:00Z"), berlin).next();
+        assert_eq!(next, Some(at("2026-01-10T08:00:00Z")));
+
+        let cf = parse_cron_file("t.md", "---\ncron: \"0 9 * * *\"\ntimezone: UTC\n---\n")
+            .unwrap();
+        assert_eq!(cf.effective_timezone(berlin), CronTimezone::Utc);
+    }
+
+    #[test]
+    fn test_cron_tracker_check_uses_timezone() {
+        let cf = parse_cron_file("t.md", "---\ncron: \"0 9 * * *\"\n---\n").unwrap();
+        let tracker =
+            CronTracker::new().with_timezone(CronTimezone::Named(chrono_tz::America::Denver));
+
+        let check = tracker.check(&cf, at("2026-07-01T15:00:10Z"));
+        assert_eq!(check.due, Some(at("2026-07-01T15:00:00Z")));
+
+        let check = tracker.check(&cf, at("2026-07-01T09:00:10Z"));
+        assert_eq!(check.due, None);
+    }
+
+    #[test]
+    fn test_cron_tracker_check_new_file_has_no_missed() {
+        let cf = parse_cron_file("t.md", "---\ncron: \"0 * * * *\"\n---\n").unwrap();
+        let tracker = CronTracker::new();
+
+        let check = tracker.check(&cf, at("2026-03-04T15:00:20Z"));
+        assert_eq!(check.due, Some(at("2026-03-04T15:00:00Z")));
+        assert!(check.missed.is_empty());
+
+        let check = tracker.check(&cf, at("2026-03-04T15:30:00Z"));
+        assert_eq!(check.due, None);
+        assert!(check.missed.is_empty());
+    }
+
+    #[test]
+    fn test_cron_tracker_track_overdue_one_shot() {
+        let cf = parse_cron_file("t.md", "---\nat: 2026-03-04T12:00:00Z\n---\n").unwrap();
+        let mut tracker = CronTracker::new();
+        assert!(tracker.track(&cf, at("2026-03-04T15:00:30Z")));
+
+        // The passed occurrence is missed rather than silently baselined away
+        let check = tracker.check(&cf, at("2026-03-04T15:00:30Z"));
+        assert_eq!(check.missed, vec![at("2026-03-04T12:00:00Z")]);
+        assert_eq!(check.due, None);
+
+        // A future one-shot is tracked from the current minute as usual
+        let cf = parse_cron_file("u.md", "---\nat: 2026-03-05T12:00:00Z\n---\n").unwrap();
+        assert!(tracker.track(&cf, at("2026-03-04T15:00:30Z")));
+        assert_eq!(tracker.last_fire(&cf), Some(at("2026-03-04T14:59:59Z")));
+    }
+
+    #[test]
+    fn test_cron_tracker_check_missed() {
+        let cf = parse_cron_file("t.md", "---\ncron: \"0 * * * *\"\n---\n").unwrap();
+        let mut tracker = CronTracker::new();
+        tracker.record(&cf, at("2026-03-04T12:00:00Z"));
+
+        let check = tracker.check(&cf, at("2026-03-04T15:00:30Z"));
+        assert_eq!(
+            check.missed,
+            vec![at("2026-03-04T13:00:00Z"), at("2026-03-04T14:00:00Z")]
+        );
+        assert_eq!(check.due, Some(at("2026-03-04T15:00:00Z")));
+    }
+
+    #[test]
+    fn test_cron_tracker_check_caps_missed() {
+        let cf = parse_cron_file("t.md", "---\ncron: \"* * * * *\"\n---\n").unwrap();
+        let mut tracker = CronTracker::new();
+        tracker.record(&cf, at("2026-03-04T00:00:00Z"));
+
+        let check = tracker.check(&cf, at("2026-03-04T12:00:30Z"));
+        assert_eq!(check.missed.len(), MAX_CATCH_UP);
+        // The most recent occurrences are kept
+        assert_eq!(check.missed.last(), Some(&at("2026-03-04T11:59:00Z")));
+    }
+
+    #[test]
+    fn test_cron_tracker_record_never_moves_back() {
+        let cf = parse_cron_file("t.md", "---\ncron: \"0 * * * *\"\n---\n").unwrap();
+        let mut tracker = CronTracker::new();
+        tracker.record(&cf, at("2026-03-04T12:00:00Z"));
+        tracker.record(&cf, at("2026-03-04T11:00:00Z"));
+        assert_eq!(tracker.last_fire(&cf), Some(at("2026-03-04T12:00:00Z")));
+    }
+
+    #[test]
+    fn test_cron_tracker_save_and_load() {
+        let dir = TempDir::new().unwrap();
+        setup_decree_dir(&dir);
+
+        let cf = parse_cron_file("t.md", "---\ncron: \"0 * * * *\"\n---\n").unwrap();
+        let mut tracker = CronTracker::new();
+        tracker.record(&cf, at("2026-03-04T12:00:00Z"));
+        tracker.save(dir.path()).unwrap();
+
+        let loaded = CronTracker::load(dir.path()).unwrap();
+        assert_eq!(loaded.last_fire(&cf), Some(at("2026-03-04T12:00:00Z")));
+    }
+
+    #[test]
+    fn test_cron_tracker_load_missing_file() {
+        let dir = TempDir::new().unwrap();
+        setup_decree_dir(&dir);
+        let cf = parse_cron_file("t.md", "---\ncron: \"0 * * * *\"\n---\n").unwrap();
+        let tracker = CronTracker::load(dir.path()).unwrap();
+        assert_eq!(tracker.last_fire(&cf), None);
+    }
+
+    #[test]
+    fn test_cron_tracker_retain() {
+        let a = parse_cron_file("a.md", "---\ncron: \"0 * * * *\"\n---\n").unwrap();
+        let b = parse_cron_file("b.md", "---\ncron: \"0 * * * *\"\n---\n").unwrap();
+        let mut tracker = CronTracker::new();
+        tracker.record(&a, at("2026-03-04T12:00:00Z"));
+        tracker.record(&b, at("2026-03-04T12:00:00Z"));
+
+        assert!(tracker.retain(std::slice::from_ref(&a)));
+        assert!(tracker.last_fire(&a).is_some());
+        assert!(tracker.last_fire(&b).is_none());
+        assert!(!tracker.retain(std::slice::from_ref(&a)));
+    }
+
+    #[test]
+    fn test_catch_up_message_chain_name() {
+        let dir = TempDir::new().unwrap();
+        setup_decree_dir(&dir);
+
+        let cf = parse_cron_file("hourly.md", "---\ncron: \"0 * * * *\"\n---\nBody.\n").unwrap();
+        let msg = catch_up_message(dir.path(), &cf, at("2026-03-04T12:00:00Z")).unwrap();
+        let chain = msg.chain.unwrap();
+        assert!(chain.contains("hourly-catchup-"), "{chain}");
+        assert!(msg.filename.ends_with("-0.md"));
+        assert_eq!(msg.body, "Body.\n");
+    }
+
     #[test]
     fn test_cron_to_inbox_message() {
         let dir = TempDir::new().unwrap();
@@ -360,6 +1394,12 @@ mod tests {
             Some(&serde_yaml::Value::String("high".into()))
         );
 
+        // Check the cron source is recorded for routing rules
+        assert_eq!(
+            msg.custom_fields.get("cron_source"),
+            Some(&serde_yaml::Value::String("hourly-maintenance".into()))
+        );
+
         // Check body preserved
         assert_eq!(msg.body, "Hourly maintenance.\n");
 
@@ -383,22 +1423,22 @@ mod tests {
     fn test_various_cron_expressions() {
         // Every minute
         let cf = parse_cron_file("t.md", "---\ncron: \"* * * * *\"\n---\n").unwrap();
-        assert!(cf.schedule.upcoming(Utc).next().is_some());
+        assert!(cf.occurrences_after(Utc::now(), CronTimezone::Utc).next().is_some());
 
         // Every hour
         let cf = parse_cron_file("t.md", "---\ncron: \"0 * * * *\"\n---\n").unwrap();
-        assert!(cf.schedule.upcoming(Utc).next().is_some());
+        assert!(cf.occurrences_after(Utc::now(), CronTimezone::Utc).next().is_some());
 
         // Daily at 9am
         let cf = parse_cron_file("t.md", "---\ncron: \"0 9 * * *\"\n---\n").unwrap();
-        assert!(cf.schedule.upcoming(Utc).next().is_some());
+        assert!(cf.occurrences_after(Utc::now(), CronTimezone::Utc).next().is_some());
 
         // Weekdays at 9am
         let cf = parse_cron_file("t.md", "---\ncron: \"0 9 * * 1-5\"\n---\n").unwrap();
-        assert!(cf.schedule.upcoming(Utc).next().is_some());
+        assert!(cf.occurrences_after(Utc::now(), CronTimezone::Utc).next().is_some());
 
         // Every 15 minutes
         let cf = parse_cron_file("t.md", "---\ncron: \"*/15 * * * *\"\n---\n").unwrap();
-        assert!(cf.schedule.upcoming(Utc).next().is_some());
+        assert!(cf.occurrences_after(Utc::now(), CronTimezone::Utc).next().is_some());
     }
 }
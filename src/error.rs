@@ -33,6 +33,17 @@ pub enum DecreeError {
     #[error("config error: {0}")]
     Config(String),
 
+    /// An AI backend's command is not installed.
+    #[error("{0}")]
+    AiUnavailable(String),
+
+    /// An AI backend reported a quota or rate-limit error.
+    #[error("{0}")]
+    AiQuota(String),
+
+    #[error("template error: {0}")]
+    Template(String),
+
     #[error("io error: {0}")]
     Io(#[from] std::io::Error),
 
@@ -87,11 +98,10 @@ pub mod color {
     /// Must be called once at startup.
     pub fn init(no_color_flag: bool) {
         INIT.call_once(|| {
-            if no_color_flag {
-                colored::control::set_override(false);
-            } else if std::env::var("NO_COLOR").is_ok() {
-                colored::control::set_override(false);
-            } else if !std::io::stdout().is_terminal() {
+            if no_color_flag
+                || std::env::var("NO_COLOR").is_ok()
+                || !std::io::stdout().is_terminal()
+            {
                 colored::control::set_override(false);
             }
             // else: color enabled by default
@@ -0,0 +1,305 @@
+//! Append-only journal of state transitions (`.decree/events.jsonl`).
+//!
+//! Every move a message makes — queued, normalized, routed, held, run,
+//! dead-lettered — is appended as one JSON line with a timestamp and the
+//! actor that made it. `decree events` reads the journal back. Recording
+//! never fails the caller: a write problem prints a warning and moves on.
+
+use crate::atomic;
+use crate::config;
+use crate::error::color;
+use crate::message::InboxMessage;
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// File name of the journal inside `.decree/`.
+pub const EVENTS_FILE: &str = "events.jsonl";
+
+/// Set (to the container name) by the Docker image's entrypoint.
+pub const CONTAINER_ENV: &str = "DECREE_CONTAINER";
+
+static ACTOR: OnceLock<Actor> = OnceLock::new();
+
+/// Who made a transition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Actor {
+    /// `decree process` (or bare `decree`).
+    Process,
+    /// `decree daemon`, including its HTTP API.
+    Daemon,
+    /// Any other command, such as `decree send` or `decree approve`.
+    Cli,
+}
+
+impl Actor {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Actor::Process => "process",
+            Actor::Daemon => "daemon",
+            Actor::Cli => "cli",
+        }
+    }
+}
+
+/// Attribute events recorded by this process to `actor`.
+///
+/// Only the first call counts; commands call it once on entry.
+pub fn set_actor(actor: Actor) {
+    let _ = ACTOR.set(actor);
+}
+
+/// The actor name written to the journal; `container` inside the Docker image.
+pub fn actor() -> &'static str {
+    if std::env::var_os(CONTAINER_ENV).is_some_and(|v| !v.is_empty()) {
+        return "container";
+    }
+    ACTOR.get().copied().unwrap_or(Actor::Cli).as_str()
+}
+
+/// The kind of transition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    /// A new message landed in the inbox.
+    Enqueued,
+    /// Missing fields were filled in and the message rewritten.
+    Normalized,
+    /// A routine was picked (by a rule, the AI router, or by hand).
+    Routed,
+    /// Parked in `inbox/needs-routing/` for a human to route.
+    Parked,
+    /// Held in `inbox/pending-approval/`.
+    Held,
+    /// Released from approval back into the inbox.
+    Approved,
+    AttemptStarted,
+    AttemptEnded,
+    HookRan,
+    /// An outbox file became a follow-up message in the chain.
+    FollowupCollected,
+    DeadLettered,
+    /// Moved from the dead-letter directory back into the inbox.
+    Requeued,
+    MigrationProcessed,
+    /// A finished run was deleted or archived by `decree gc`.
+    Pruned,
+}
+
+impl EventKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EventKind::Enqueued => "enqueued",
+            EventKind::Normalized => "normalized",
+            EventKind::Routed => "routed",
+            EventKind::Parked => "parked",
+            EventKind::Held => "held",
+            EventKind::Approved => "approved",
+            EventKind::AttemptStarted => "attempt_started",
+            EventKind::AttemptEnded => "attempt_ended",
+            EventKind::HookRan => "hook_ran",
+            EventKind::FollowupCollected => "followup_collected",
+            EventKind::DeadLettered => "dead_lettered",
+            EventKind::Requeued => "requeued",
+            EventKind::MigrationProcessed => "migration_processed",
+            EventKind::Pruned => "pruned",
+        }
+    }
+}
+
+/// One journal line. Fields that do not apply are left out.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Event {
+    pub ts: String,
+    pub actor: String,
+    #[serde(rename = "type")]
+    pub kind: EventKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub routine: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub migration: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attempt: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_secs: Option<i64>,
+    /// Hook type for `hook_ran`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hook: Option<String>,
+    /// Free-form context: a dead-letter reason, the source of a message, etc.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl Event {
+    /// An event of `kind` happening now, made by this process's actor.
+    pub fn new(kind: EventKind) -> Self {
+        Self {
+            ts: Local::now().to_rfc3339(),
+            actor: actor().to_string(),
+            kind,
+            message_id: None,
+            chain: None,
+            routine: None,
+            migration: None,
+            attempt: None,
+            exit_code: None,
+            duration_secs: None,
+            hook: None,
+            detail: None,
+        }
+    }
+
+    /// An event about `msg`.
+    pub fn for_message(kind: EventKind, msg: &InboxMessage) -> Self {
+        Self {
+            message_id: msg.id.clone(),
+            chain: msg.chain.clone(),
+            routine: msg.routine.clone(),
+            migration: msg.migration.clone(),
+            ..Self::new(kind)
+        }
+    }
+
+    /// A one-line, human-readable rendering for `decree events`.
+    pub fn summary(&self) -> String {
+        let time = chrono::DateTime::parse_from_rfc3339(&self.ts)
+            .map(|ts| ts.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_else(|_| self.ts.clone());
+        let mut line = format!(
+            "{time}  {:<9} {:<19} {}",
+            self.actor,
+            self.kind.as_str(),
+            self.message_id.as_deref().unwrap_or("-")
+        );
+        let mut extra = |key: &str, value: Option<String>| {
+            if let Some(value) = value {
+                line.push_str(&format!(" {key}={value}"));
+            }
+        };
+        extra("routine", self.routine.clone());
+        extra("migration", self.migration.clone());
+        extra("hook", self.hook.clone());
+        extra("attempt", self.attempt.map(|a| a.to_string()));
+        extra("exit", self.exit_code.map(|c| c.to_string()));
+        extra("duration", self.duration_secs.map(|d| format!("{d}s")));
+        if let Some(detail) = &self.detail {
+            line.push_str(&format!(" — {detail}"));
+        }
+        line
+    }
+}
+
+/// Path of the journal.
+pub fn events_path(project_root: &Path) -> PathBuf {
+    project_root.join(config::DECREE_DIR).join(EVENTS_FILE)
+}
+
+/// Append `event` to the journal.
+///
+/// Each event is a single `write` to a file opened in append mode, so lines
+/// from concurrent writers do not interleave.
+pub fn record(project_root: &Path, event: Event) {
+    let result = serde_json::to_string(&event)
+        .map_err(std::io::Error::other)
+        .and_then(|json| atomic::append_line(&events_path(project_root), &json));
+    if let Err(e) = result {
+        eprintln!(
+            "{}: could not record {} event: {e}",
+            color::warning("warning"),
+            event.kind.as_str()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn project() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join(config::DECREE_DIR)).unwrap();
+        dir
+    }
+
+    fn message() -> InboxMessage {
+        InboxMessage {
+            id: Some("D0001-1200-fix-0".into()),
+            chain: Some("D0001-1200-fix".into()),
+            seq: Some(0),
+            routine: Some("develop".into()),
+            migration: Some("01-fix.md".into()),
+            body: "Fix it".into(),
+            custom_fields: Default::default(),
+            filename: "D0001-1200-fix-0.md".into(),
+        }
+    }
+
+    #[test]
+    fn test_record_appends_json_lines() {
+        let dir = project();
+        record(dir.path(), Event::for_message(EventKind::Enqueued, &message()));
+        record(
+            dir.path(),
+            Event {
+                attempt: Some(1),
+                exit_code: Some(0),
+                ..Event::for_message(EventKind::AttemptEnded, &message())
+            },
+        );
+
+        let content = std::fs::read_to_string(events_path(dir.path())).unwrap();
+        let events: Vec<Event> = content
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].kind, EventKind::Enqueued);
+        assert_eq!(events[0].message_id.as_deref(), Some("D0001-1200-fix-0"));
+        assert_eq!(events[1].kind, EventKind::AttemptEnded);
+        assert_eq!(events[1].exit_code, Some(0));
+    }
+
+    #[test]
+    fn test_json_leaves_out_unset_fields() {
+        let json = serde_json::to_value(Event::new(EventKind::HookRan)).unwrap();
+        let keys: Vec<&str> = json.as_object().unwrap().keys().map(|k| k.as_str()).collect();
+        assert_eq!(keys, ["actor", "ts", "type"]);
+        assert_eq!(json["type"], "hook_ran");
+    }
+
+    #[test]
+    fn test_kind_names_match_serde() {
+        for kind in [
+            EventKind::Enqueued,
+            EventKind::AttemptStarted,
+            EventKind::FollowupCollected,
+            EventKind::MigrationProcessed,
+        ] {
+            assert_eq!(serde_json::to_value(kind).unwrap(), kind.as_str());
+        }
+    }
+
+    #[test]
+    fn test_summary() {
+        let event = Event {
+            ts: "2026-01-02T03:04:05+00:00".into(),
+            actor: "daemon".into(),
+            attempt: Some(2),
+            exit_code: Some(1),
+            ..Event::for_message(EventKind::AttemptEnded, &message())
+        };
+        assert_eq!(
+            event.summary(),
+            "2026-01-02 03:04:05  daemon    attempt_ended       D0001-1200-fix-0 \
+             routine=develop migration=01-fix.md attempt=2 exit=1"
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use crate::config::{self, AppConfig, HooksConfig};
+use crate::events::{self, Event, EventKind};
 use crate::routine;
 use std::fmt;
 use std::path::Path;
@@ -89,7 +90,7 @@ impl fmt::Display for HookError {
 
 /// Resolve the routine name for a given hook type from config.
 /// Returns `None` if the hook value is empty or absent.
-pub fn hook_routine_name<'a>(hooks: &'a HooksConfig, hook_type: HookType) -> Option<&'a str> {
+pub fn hook_routine_name(hooks: &HooksConfig, hook_type: HookType) -> Option<&str> {
     let name = match hook_type {
         HookType::BeforeAll => &hooks.before_all,
         HookType::AfterAll => &hooks.after_all,
@@ -172,6 +173,9 @@ pub fn run_hook_with_config(
         .env("message_dir", &ctx.message_dir)
         .env("chain", &ctx.chain)
         .env("seq", &ctx.seq);
+    if let Some(path) = routine::path_with_decree() {
+        cmd.env("PATH", path);
+    }
 
     if let Some(attempt) = ctx.attempt {
         cmd.env("DECREE_ATTEMPT", attempt.to_string());
@@ -197,10 +201,24 @@ pub fn run_hook_with_config(
     let combined = format!("{}{}", stdout, stderr);
     let combined = combined.trim().to_string();
 
+    let exit_code = cmd_output.status.code().unwrap_or(1);
+    let non_empty = |s: &str| (!s.is_empty()).then(|| s.to_string());
+    events::record(
+        project_root,
+        Event {
+            message_id: non_empty(&ctx.message_id),
+            chain: non_empty(&ctx.chain),
+            routine: Some(routine_name.to_string()),
+            attempt: ctx.attempt,
+            exit_code: Some(exit_code),
+            hook: Some(hook_type.as_str().to_string()),
+            ..Event::new(EventKind::HookRan)
+        },
+    );
+
     if cmd_output.status.success() {
         Ok(HookOutput { output: combined })
     } else {
-        let exit_code = cmd_output.status.code().unwrap_or(1);
         Err(HookError {
             hook_type,
             routine_name: routine_name.to_string(),
@@ -1,8 +1,17 @@
+pub mod ai;
+pub mod approval;
+pub mod atomic;
+pub mod capture;
 pub mod cli;
 pub mod commands;
 pub mod config;
 pub mod cron;
 pub mod error;
+pub mod events;
 pub mod hooks;
 pub mod message;
+pub mod notify;
 pub mod routine;
+pub mod router;
+pub mod template;
+pub mod usage;
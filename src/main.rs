@@ -1,5 +1,5 @@
 use clap::Parser;
-use decree::cli::{Cli, Command};
+use decree::cli::{Cli, Command, CronAction};
 use decree::commands;
 use decree::error::{self, color, DecreeError, EXIT_SUCCESS};
 use std::process;
@@ -38,12 +38,134 @@ fn dispatch(command: Option<Command>) -> Result<(), DecreeError> {
             let root = error::require_project_root()?;
             match cmd {
                 Command::Process { dry_run } => commands::process::run(&root, dry_run),
-                Command::Prompt { name } => commands::prompt::run(&root, name.as_deref()),
-                Command::Routine { name } => commands::routine::run(&root, name.as_deref()),
+                Command::Prompt {
+                    name,
+                    print,
+                    out,
+                    ai,
+                    profile,
+                    vars,
+                } => {
+                    let target = if print {
+                        commands::prompt::Target::Stdout
+                    } else if let Some(out) = out {
+                        commands::prompt::Target::File(out.into())
+                    } else if ai {
+                        commands::prompt::Target::Ai(profile)
+                    } else {
+                        commands::prompt::Target::Guided
+                    };
+                    commands::prompt::run(&root, name.as_deref(), target, &vars)
+                }
+                Command::Routine {
+                    name,
+                    params,
+                    body,
+                    body_file,
+                    yes,
+                    detach,
+                } => {
+                    let options = commands::routine::RunOptions {
+                        params,
+                        body,
+                        body_file: body_file.map(Into::into),
+                        yes,
+                        detach,
+                    };
+                    commands::routine::run(&root, name.as_deref(), &options)
+                }
                 Command::Verify => commands::routine::verify(&root),
-                Command::Daemon { interval } => commands::daemon::run(&root, interval),
+                Command::Route {
+                    id,
+                    routine,
+                    explain,
+                } => match (explain, id, routine) {
+                    (Some(file), _, _) => commands::route::explain(&root, &file),
+                    (None, Some(id), Some(routine)) => commands::route::resolve(&root, &id, &routine),
+                    (None, id, _) => commands::route::list(&root, id.as_deref()),
+                },
+                Command::Ai {
+                    prompt,
+                    profile,
+                    check,
+                } => commands::ai::run(&root, prompt.as_deref(), profile.as_deref(), check),
+                Command::Send {
+                    body,
+                    routine,
+                    fields,
+                    file,
+                    name,
+                } => commands::send::run(
+                    &root,
+                    body.as_deref(),
+                    file.as_deref().map(std::path::Path::new),
+                    routine.as_deref(),
+                    &fields,
+                    name.as_deref(),
+                ),
+                Command::Approve { id } => match id {
+                    Some(id) => commands::approve::approve(&root, &id),
+                    None => commands::approve::list(&root),
+                },
+                Command::Reject { id, reason } => {
+                    commands::approve::reject(&root, &id, reason.as_deref())
+                }
+                Command::Daemon { interval, listen } => {
+                    commands::daemon::run(&root, interval, listen.as_deref())
+                }
+                Command::Cron { action } => match action {
+                    CronAction::List => commands::cron::list(&root),
+                    CronAction::Check => commands::cron::check(&root),
+                    CronAction::Run { name } => commands::cron::run(&root, &name),
+                },
                 Command::Status => commands::status::run(&root),
-                Command::Log { id } => commands::log::run(&root, id.as_deref()),
+                Command::Usage { since, by } => commands::usage::run(&root, since.as_deref(), &by),
+                Command::Events {
+                    id,
+                    follow,
+                    lines,
+                    json,
+                } => {
+                    let options = commands::events::Options {
+                        id,
+                        lines,
+                        follow,
+                        json,
+                    };
+                    commands::events::run(&root, &options)
+                }
+                Command::Log {
+                    id,
+                    follow,
+                    grep,
+                    context,
+                    attempt,
+                    tail,
+                    since,
+                    until,
+                    routine,
+                    disposition,
+                    migration,
+                } => {
+                    let options = commands::log::Options {
+                        follow,
+                        grep,
+                        context,
+                        attempt,
+                        tail,
+                        since,
+                        until,
+                        routine,
+                        disposition,
+                        migration,
+                    };
+                    commands::log::run(&root, id.as_deref(), &options)
+                }
+                Command::Gc {
+                    dry_run,
+                    keep_runs,
+                    keep_days,
+                } => commands::gc::run(&root, dry_run, keep_runs, keep_days),
                 Command::RoutineSync { source } => {
                     commands::routine_sync::run(&root, source.as_deref())
                 }
@@ -1,13 +1,22 @@
+use crate::approval;
+use crate::atomic;
 use crate::config::{self, AppConfig};
 use crate::error::DecreeError;
+use crate::events::{self, Event, EventKind};
+use crate::router;
+use crate::template::{self, Context, Value};
 use chrono::Local;
 use std::collections::{BTreeMap, HashSet};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 
 // Known frontmatter field names (everything else is "custom").
 const KNOWN_FIELDS: &[&str] = &["id", "chain", "seq", "routine", "migration"];
 
+/// Callback used for AI-based routine selection: receives the populated
+/// router prompt and returns the raw router answer.
+pub type RouterFn = dyn Fn(&str) -> Result<String, DecreeError>;
+
 /// A parsed message ID with the form `<chain>-<seq>`.
 ///
 /// Chain format: `D<NNNN>-HHmm-<name>`
@@ -137,6 +146,37 @@ pub fn build_chain_id(day_counter: &str, hhmm: &str, name: &str) -> String {
     format!("{}-{}-{}", day_counter, hhmm, name)
 }
 
+/// A chain ID for a new chain named `name`, unused by any inbox message
+/// or run. A taken name gets a `-2`, `-3`, ... suffix, and characters that
+/// don't belong in a filename (like the `/` of nested routines) become `-`.
+pub fn new_chain_id(project_root: &Path, name: &str) -> Result<String, DecreeError> {
+    let now = Local::now();
+    let hhmm = now.format("%H%M").to_string();
+    let day = next_day_counter(project_root, &hhmm)?;
+
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
+        .collect();
+    let decree_dir = project_root.join(config::DECREE_DIR);
+    let taken = |chain: &str| {
+        let first = format!("{chain}-0.md");
+        decree_dir.join(config::RUNS_DIR).join(format!("{chain}-0")).exists()
+            || WalkDir::new(decree_dir.join(config::INBOX_DIR))
+                .into_iter()
+                .filter_map(|e| e.ok())
+                .any(|e| e.file_name().to_str() == Some(first.as_str()))
+    };
+
+    let mut chain = build_chain_id(&day, &hhmm, &name);
+    let mut n = 2;
+    while taken(&chain) {
+        chain = build_chain_id(&day, &hhmm, &format!("{name}-{n}"));
+        n += 1;
+    }
+    Ok(chain)
+}
+
 /// List all run directories, sorted by name (chronological).
 pub fn list_runs(project_root: &Path) -> Result<Vec<String>, DecreeError> {
     let runs_dir = project_root
@@ -188,11 +228,11 @@ pub fn parse_frontmatter(
     // Find closing "---" delimiter
     let (yaml_str, body) = if let Some(pos) = after_open.find("\n---\n") {
         (&after_open[..pos], &after_open[pos + 5..]) // skip "\n---\n"
-    } else if after_open.ends_with("\n---") {
-        (&after_open[..after_open.len() - 4], "")
-    } else if after_open.starts_with("---\n") {
+    } else if let Some(yaml) = after_open.strip_suffix("\n---") {
+        (yaml, "")
+    } else if let Some(rest) = after_open.strip_prefix("---\n") {
         // Empty frontmatter: ---\n---\n...
-        ("", &after_open[4..])
+        ("", rest)
     } else if after_open == "---" {
         ("", "")
     } else {
@@ -250,7 +290,7 @@ pub fn read_processed(project_root: &Path) -> Result<HashSet<String>, DecreeErro
         .join(config::PROCESSED_FILE);
 
     if !path.exists() {
-        std::fs::write(&path, "")?;
+        atomic::write(&path, "")?;
         return Ok(HashSet::new());
     }
 
@@ -272,18 +312,21 @@ pub fn unprocessed_migrations(project_root: &Path) -> Result<Vec<String>, Decree
 }
 
 /// Append a filename to `.decree/processed.md`.
+///
+/// Each entry is a single appended write, so a crash never tears an
+/// existing entry.
 pub fn mark_processed(project_root: &Path, filename: &str) -> Result<(), DecreeError> {
-    use std::io::Write;
     let path = project_root
         .join(config::DECREE_DIR)
         .join(config::PROCESSED_FILE);
-
-    let mut file = std::fs::OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(&path)?;
-
-    writeln!(file, "{}", filename)?;
+    atomic::append_line(&path, filename)?;
+    events::record(
+        project_root,
+        Event {
+            migration: Some(filename.to_string()),
+            ..Event::new(EventKind::MigrationProcessed)
+        },
+    );
     Ok(())
 }
 
@@ -380,11 +423,13 @@ impl InboxMessage {
     ///
     /// `ai_router` is an optional callback for AI-based routine selection.
     /// It receives the populated router prompt and should return the routine name.
+    /// If the router is not confident enough (`router.min_confidence`), `routine`
+    /// stays unset and the caller decides how the message gets routed.
     pub fn normalize(
         &mut self,
         project_root: &Path,
         config: &AppConfig,
-        ai_router: Option<&dyn Fn(&str) -> Result<String, DecreeError>>,
+        ai_router: Option<&RouterFn>,
     ) -> Result<bool, DecreeError> {
         if self.is_complete() {
             return Ok(false);
@@ -432,7 +477,14 @@ impl InboxMessage {
 
         // 5. Routine selection
         if self.routine.is_none() {
-            self.routine = Some(select_routine(project_root, config, &self.body, ai_router)?);
+            let decision = router::route(project_root, config, self, ai_router)?;
+            if let Some(id) = &self.id {
+                router::record_decision(project_root, id, &decision)?;
+            }
+            // Left unset when the message needs manual routing
+            if decision.reason != router::RouteReason::NeedsRouting {
+                self.routine = Some(decision.routine);
+            }
         }
 
         Ok(true)
@@ -477,17 +529,121 @@ impl InboxMessage {
     }
 
     /// Write the message to `.decree/inbox/`.
+    ///
+    /// The write is atomic, so a polling daemon never reads a half-written
+    /// message.
     pub fn write_to_inbox(&self, project_root: &Path) -> Result<(), DecreeError> {
         let path = project_root
             .join(config::DECREE_DIR)
             .join(config::INBOX_DIR)
             .join(&self.filename);
-
-        std::fs::write(&path, self.serialize())?;
+        atomic::write(&path, self.serialize())?;
         Ok(())
     }
 }
 
+/// Parse every `*.md` message in a holding directory (e.g. `inbox/needs-routing/`),
+/// sorted by filename. A missing directory holds no messages.
+pub fn read_messages_in(dir: &Path) -> Result<Vec<InboxMessage>, DecreeError> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut filenames: Vec<String> = std::fs::read_dir(dir)?
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_file())
+        .filter_map(|e| e.file_name().into_string().ok())
+        .filter(|name| name.ends_with(".md"))
+        .collect();
+    filenames.sort();
+
+    filenames
+        .into_iter()
+        .map(|filename| {
+            let content = std::fs::read_to_string(dir.join(&filename))?;
+            InboxMessage::parse(&filename, &content)
+        })
+        .collect()
+}
+
+/// `.decree/inbox/dead/`, where messages that failed all retries wait.
+pub fn dead_dir(project_root: &Path) -> PathBuf {
+    project_root
+        .join(config::DECREE_DIR)
+        .join(config::INBOX_DIR)
+        .join(config::DEAD_DIR)
+}
+
+/// Move a dead-lettered message (by ID or filename) back to the inbox.
+///
+/// It runs again on the next `decree process` or daemon poll.
+pub fn requeue_dead(project_root: &Path, id: &str) -> Result<InboxMessage, DecreeError> {
+    let msg = read_messages_in(&dead_dir(project_root))?
+        .into_iter()
+        .find(|m| {
+            m.id.as_deref() == Some(id)
+                || m.filename == id
+                || m.filename.strip_suffix(".md") == Some(id)
+        })
+        .ok_or_else(|| DecreeError::MessageNotFound(id.to_string()))?;
+
+    let inbox_path = project_root
+        .join(config::DECREE_DIR)
+        .join(config::INBOX_DIR)
+        .join(&msg.filename);
+    if inbox_path.exists() {
+        return Err(DecreeError::Other(format!(
+            "{} is already in the inbox",
+            msg.filename
+        )));
+    }
+    std::fs::rename(dead_dir(project_root).join(&msg.filename), inbox_path)?;
+    events::record(project_root, Event::for_message(EventKind::Requeued, &msg));
+    Ok(msg)
+}
+
+/// Where a message already created for a migration is waiting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueuedMigration {
+    /// In the inbox, ready to run on this chain.
+    Inbox(String),
+    /// Parked in `needs-routing/` until routed by hand.
+    NeedsRouting,
+    /// Held in `pending-approval/` until approved or rejected.
+    PendingApproval,
+}
+
+/// Find a message already created for `migration`, if one is waiting.
+///
+/// A migration message parked for manual routing returns to the inbox once
+/// routed, and `decree process` resumes it instead of starting a new chain.
+/// While it is still parked or held for approval, the migration counts as
+/// queued so it isn't enqueued (and routed or held) a second time.
+pub fn queued_migration(
+    project_root: &Path,
+    migration: &str,
+) -> Result<Option<QueuedMigration>, DecreeError> {
+    for filename in list_inbox_messages(project_root)? {
+        let msg = InboxMessage::from_file(project_root, &filename)?;
+        if msg.migration.as_deref() == Some(migration) {
+            let chain = msg
+                .chain
+                .or_else(|| chain_seq_from_filename(&filename).map(|(chain, _)| chain));
+            if let Some(chain) = chain {
+                return Ok(Some(QueuedMigration::Inbox(chain)));
+            }
+        }
+    }
+    let is_migration = |m: &InboxMessage| m.migration.as_deref() == Some(migration);
+    if router::parked_messages(project_root)?.iter().any(is_migration) {
+        return Ok(Some(QueuedMigration::NeedsRouting));
+    }
+    if approval::pending_messages(project_root)?.iter().any(is_migration) {
+        return Ok(Some(QueuedMigration::PendingApproval));
+    }
+    Ok(None)
+}
+
 /// List all `*.md` files in `.decree/inbox/`, sorted alphabetically.
 pub fn list_inbox_messages(project_root: &Path) -> Result<Vec<String>, DecreeError> {
     let dir = project_root
@@ -668,9 +824,12 @@ pub fn extract_routine_description(content: &str) -> String {
 
 /// Build the router prompt for AI-based routine selection.
 ///
-/// Reads `.decree/router.md` and populates `{routines}` and `{message}`.
+/// Renders `.decree/router.md` as a template with `{routines}` (each with
+/// `name` and `description`), `{message}`, and any `prompt_vars`;
+/// `{#include}` reads from the prompts directory.
 pub fn build_router_prompt(
     project_root: &Path,
+    config: &AppConfig,
     routines: &[RoutineInfo],
     message_body: &str,
 ) -> Result<String, DecreeError> {
@@ -678,7 +837,7 @@ pub fn build_router_prompt(
         .join(config::DECREE_DIR)
         .join(config::ROUTER_FILE);
 
-    let template = std::fs::read_to_string(&router_path)?;
+    let source = std::fs::read_to_string(&router_path)?;
 
     let routines_text: String = routines
         .iter()
@@ -691,45 +850,28 @@ pub fn build_router_prompt(
         })
         .collect::<Vec<_>>()
         .join("\n");
+    let items = routines
+        .iter()
+        .map(|r| {
+            Value::Map(BTreeMap::from([
+                ("name".to_string(), r.name.as_str().into()),
+                ("description".to_string(), r.description.as_str().into()),
+            ]))
+        })
+        .collect();
 
-    let prompt = template
-        .replace("{routines}", &routines_text)
-        .replace("{message}", message_body);
-
-    Ok(prompt)
-}
-
-/// Select a routine for a message.
-///
-/// Fallback chain: AI router → config `default_routine` → `"develop"`.
-fn select_routine(
-    project_root: &Path,
-    config: &AppConfig,
-    message_body: &str,
-    ai_router: Option<&dyn Fn(&str) -> Result<String, DecreeError>>,
-) -> Result<String, DecreeError> {
-    // Try AI router if provided
-    if let Some(router_fn) = ai_router {
-        let routines = list_routines(project_root, config)?;
-        if !routines.is_empty() {
-            if let Ok(prompt) = build_router_prompt(project_root, &routines, message_body) {
-                if let Ok(selected) = router_fn(&prompt) {
-                    let trimmed = selected.trim().to_string();
-                    if routines.iter().any(|r| r.name == trimmed) {
-                        return Ok(trimmed);
-                    }
-                }
-            }
-        }
-    }
-
-    // Fallback: config default
-    if !config.default_routine.is_empty() {
-        return Ok(config.default_routine.clone());
-    }
+    let mut ctx = Context::new();
+    ctx.insert("routines", Value::list(items, routines_text));
+    ctx.insert("message", message_body);
+    let ctx = ctx.with_config_vars(config);
 
-    // Ultimate fallback
-    Ok("develop".to_string())
+    let include = |name: &str| template::load_prompt(project_root, config, name);
+    let env = template::Env {
+        name: "router",
+        project_root,
+        include: &include,
+    };
+    template::render(&source, &ctx, &env)
 }
 
 // =================================================================
@@ -1518,6 +1660,44 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_queued_migration() {
+        let dir = TempDir::new().unwrap();
+        setup_decree_dir(&dir);
+        let inbox = dir.path().join(".decree/inbox");
+        std::fs::write(
+            inbox.join("D0001-1432-01-add-auth-0.md"),
+            "---\nroutine: develop\nmigration: 01-add-auth.md\n---\nBody.\n",
+        )
+        .unwrap();
+        std::fs::create_dir_all(inbox.join("needs-routing")).unwrap();
+        std::fs::write(
+            inbox.join("needs-routing/D0002-1500-02-vague-0.md"),
+            "---\nid: D0002-1500-02-vague-0\nchain: D0002-1500-02-vague\nseq: 0\nmigration: 02-vague.md\n---\nBody.\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            queued_migration(dir.path(), "01-add-auth.md").unwrap(),
+            Some(QueuedMigration::Inbox("D0001-1432-01-add-auth".into()))
+        );
+        assert_eq!(
+            queued_migration(dir.path(), "02-vague.md").unwrap(),
+            Some(QueuedMigration::NeedsRouting)
+        );
+        std::fs::create_dir_all(inbox.join("pending-approval")).unwrap();
+        std::fs::write(
+            inbox.join("pending-approval/D0003-1600-03-deploy-0.md"),
+            "---\nid: D0003-1600-03-deploy-0\nchain: D0003-1600-03-deploy\nseq: 0\nmigration: 03-deploy.md\nrequires_approval: true\n---\nBody.\n",
+        )
+        .unwrap();
+        assert_eq!(
+            queued_migration(dir.path(), "03-deploy.md").unwrap(),
+            Some(QueuedMigration::PendingApproval)
+        );
+        assert_eq!(queued_migration(dir.path(), "04-other.md").unwrap(), None);
+    }
+
     // --- Routine listing tests ---
 
     #[test]
@@ -1609,6 +1789,46 @@ mod tests {
         assert!(routines.is_empty());
     }
 
+    #[test]
+    fn test_new_chain_id_is_unique_and_safe() {
+        let dir = TempDir::new().unwrap();
+        setup_decree_dir(&dir);
+
+        let chain = new_chain_id(dir.path(), "deploy/staging").unwrap();
+        assert!(chain.ends_with("-deploy-staging"), "{chain}");
+
+        std::fs::write(dir.path().join(format!(".decree/inbox/{chain}-0.md")), "").unwrap();
+        let second = new_chain_id(dir.path(), "deploy/staging").unwrap();
+        assert!(second.ends_with("-deploy-staging-2"), "{second}");
+
+        std::fs::create_dir_all(dir.path().join(format!(".decree/runs/{second}-0"))).unwrap();
+        let third = new_chain_id(dir.path(), "deploy/staging").unwrap();
+        assert!(third.ends_with("-deploy-staging-3"), "{third}");
+    }
+
+    #[test]
+    fn test_requeue_dead() {
+        let dir = TempDir::new().unwrap();
+        setup_decree_dir(&dir);
+        let dead = dead_dir(dir.path());
+        std::fs::create_dir_all(&dead).unwrap();
+        std::fs::write(
+            dead.join("D0001-1432-fix-0.md"),
+            "---\nid: D0001-1432-fix-0\nroutine: develop\n---\nFix it.\n",
+        )
+        .unwrap();
+
+        assert!(matches!(
+            requeue_dead(dir.path(), "D0001-1432-other-0"),
+            Err(DecreeError::MessageNotFound(_))
+        ));
+
+        let msg = requeue_dead(dir.path(), "D0001-1432-fix-0").unwrap();
+        assert_eq!(msg.routine.as_deref(), Some("develop"));
+        assert!(dir.path().join(".decree/inbox/D0001-1432-fix-0.md").exists());
+        assert!(!dead.join("D0001-1432-fix-0.md").exists());
+    }
+
     // --- Router prompt tests ---
 
     #[test]
@@ -1633,7 +1853,7 @@ mod tests {
         ];
 
         let prompt =
-            build_router_prompt(dir.path(), &routines, "Add auth.").unwrap();
+            build_router_prompt(dir.path(), &AppConfig::default(), &routines, "Add auth.").unwrap();
 
         assert!(prompt.contains("- **develop**: General purpose."));
         assert!(prompt.contains("- **rust-develop**: Rust specific."));
@@ -1656,11 +1876,43 @@ mod tests {
         }];
 
         let prompt =
-            build_router_prompt(dir.path(), &routines, "Body.").unwrap();
+            build_router_prompt(dir.path(), &AppConfig::default(), &routines, "Body.").unwrap();
         assert!(prompt.contains("- **develop**"));
         assert!(!prompt.contains("- **develop**:"));
     }
 
+    #[test]
+    fn test_build_router_prompt_template_features() {
+        let dir = TempDir::new().unwrap();
+        setup_decree_dir(&dir);
+        std::fs::write(
+            dir.path().join(".decree/router.md"),
+            "{#each routines as r}{r.name}{#if !loop.last}|{/if}{/each} ({team})\n{message}\n",
+        )
+        .unwrap();
+
+        let routines = vec![
+            RoutineInfo {
+                name: "develop".into(),
+                description: String::new(),
+            },
+            RoutineInfo {
+                name: "docs".into(),
+                description: String::new(),
+            },
+        ];
+        let mut config = AppConfig::default();
+        config.prompt_vars.insert("team".into(), "infra".into());
+
+        // Message bodies are inserted verbatim, never rendered
+        let prompt = build_router_prompt(dir.path(), &config, &routines, "Fix {team}.").unwrap();
+        assert_eq!(prompt, "develop|docs (infra)\nFix {team}.\n");
+
+        std::fs::write(dir.path().join(".decree/router.md"), "{nope}").unwrap();
+        let err = build_router_prompt(dir.path(), &config, &routines, "x").unwrap_err();
+        assert!(err.to_string().contains("router:1: unknown variable 'nope'"));
+    }
+
     // --- value helper tests ---
 
     #[test]
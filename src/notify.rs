@@ -0,0 +1,425 @@
+//! Outbound notifications (`notifications` section of config.yml).
+//!
+//! When a migration finishes, a message is dead-lettered, the daemon starts
+//! or stops, or a cron job fires, every sink subscribed to that event gets
+//! a JSON payload: POSTed to a webhook, piped to a command, or appended to
+//! a JSONL file. Delivery problems are reported as warnings and never stop
+//! processing.
+
+use crate::atomic;
+use crate::config::{AppConfig, NotificationSink, NotifyEvent};
+use crate::error::color;
+use crate::message::InboxMessage;
+use chrono::{DateTime, Local};
+use regex::Regex;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Lines of the latest log included in the payload.
+pub const LOG_TAIL_LINES: usize = 20;
+
+/// Seconds a webhook gets to answer.
+const WEBHOOK_TIMEOUT_SECS: u32 = 10;
+
+impl NotifyEvent {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NotifyEvent::MigrationDone => "migration_done",
+            NotifyEvent::DeadLettered => "dead_lettered",
+            NotifyEvent::RetriesExhausted => "retries_exhausted",
+            NotifyEvent::DaemonStarted => "daemon_started",
+            NotifyEvent::DaemonStopped => "daemon_stopped",
+            NotifyEvent::CronFired => "cron_fired",
+        }
+    }
+
+    /// Whether a sink subscribed to `self` hears about `event`.
+    ///
+    /// Exhausted retries end in a dead letter, so `dead_lettered` covers them.
+    pub fn covers(self, event: NotifyEvent) -> bool {
+        self == event
+            || (self == NotifyEvent::DeadLettered && event == NotifyEvent::RetriesExhausted)
+    }
+
+    /// What happened to the message, if the event is about one.
+    fn disposition(self) -> Option<&'static str> {
+        match self {
+            NotifyEvent::MigrationDone => Some("done"),
+            NotifyEvent::DeadLettered | NotifyEvent::RetriesExhausted => Some("dead"),
+            NotifyEvent::CronFired => Some("queued"),
+            NotifyEvent::DaemonStarted | NotifyEvent::DaemonStopped => None,
+        }
+    }
+}
+
+/// One event to deliver. Fields that do not apply stay `None`.
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub event: NotifyEvent,
+    pub message_id: Option<String>,
+    pub chain: Option<String>,
+    pub routine: Option<String>,
+    pub migration: Option<String>,
+    /// Seconds from the first attempt to the outcome.
+    pub duration_secs: Option<i64>,
+    pub attempts: Option<u32>,
+    pub exit_code: Option<i32>,
+    /// Why the message was dead-lettered.
+    pub reason: Option<String>,
+    /// Cron file that fired.
+    pub cron: Option<String>,
+    pub log_tail: Option<String>,
+}
+
+impl Notification {
+    pub fn new(event: NotifyEvent) -> Self {
+        Self {
+            event,
+            message_id: None,
+            chain: None,
+            routine: None,
+            migration: None,
+            duration_secs: None,
+            attempts: None,
+            exit_code: None,
+            reason: None,
+            cron: None,
+            log_tail: None,
+        }
+    }
+
+    /// An event about `msg`, with the tail of the newest log in `run_dir`.
+    pub fn for_message(event: NotifyEvent, msg: &InboxMessage, run_dir: Option<&Path>) -> Self {
+        Self {
+            message_id: msg.id.clone(),
+            chain: msg.chain.clone(),
+            routine: msg.routine.clone(),
+            migration: msg.migration.clone(),
+            log_tail: run_dir.and_then(latest_log_tail),
+            ..Self::new(event)
+        }
+    }
+
+    /// The JSON payload sent to sinks.
+    pub fn payload(&self, project_root: &Path) -> Value {
+        let project = project_root
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned());
+        let mut payload = json!({
+            "event": self.event.as_str(),
+            "timestamp": Local::now().to_rfc3339(),
+            "project": project,
+            "message_id": self.message_id,
+            "chain": self.chain,
+            "routine": self.routine,
+            "migration": self.migration,
+            "disposition": self.event.disposition(),
+            "duration_secs": self.duration_secs,
+            "attempts": self.attempts,
+            "exit_code": self.exit_code,
+            "reason": self.reason,
+            "cron": self.cron,
+            "log_tail": self.log_tail,
+        });
+        if let Value::Object(fields) = &mut payload {
+            fields.retain(|_, value| !value.is_null());
+        }
+        payload
+    }
+}
+
+/// Deliver `notification` to every sink subscribed to its event.
+pub fn send(project_root: &Path, config: &AppConfig, notification: &Notification) {
+    let sinks: Vec<&NotificationSink> = config
+        .notifications
+        .iter()
+        .filter(|sink| sink.on.is_empty() || sink.on.iter().any(|on| on.covers(notification.event)))
+        .collect();
+    if sinks.is_empty() {
+        return;
+    }
+
+    let payload = notification.payload(project_root).to_string();
+    for sink in sinks {
+        if let Err(e) = deliver(project_root, sink, notification.event, &payload) {
+            eprintln!(
+                "{}: {} notification not delivered: {e}",
+                color::warning("warning"),
+                notification.event.as_str()
+            );
+        }
+    }
+}
+
+/// Tell notification sinks that `msg` was dead-lettered for `reason`.
+pub fn dead_lettered(
+    project_root: &Path,
+    config: &AppConfig,
+    msg: &InboxMessage,
+    run_dir: Option<&Path>,
+    reason: String,
+) {
+    let notification = Notification {
+        reason: Some(reason),
+        ..Notification::for_message(NotifyEvent::DeadLettered, msg, run_dir)
+    };
+    send(project_root, config, &notification);
+}
+
+/// Whole seconds elapsed since `start`, for `duration_secs`.
+pub fn seconds_since(start: DateTime<Local>) -> i64 {
+    Local::now().signed_duration_since(start).num_seconds()
+}
+
+fn deliver(
+    project_root: &Path,
+    sink: &NotificationSink,
+    event: NotifyEvent,
+    payload: &str,
+) -> Result<(), String> {
+    match (&sink.webhook, &sink.command, &sink.file) {
+        (Some(url), None, None) => post_webhook(url, &sink.headers, payload),
+        (None, Some(command), None) => run_command(project_root, command, event, payload),
+        (None, None, Some(file)) => append_line(&project_root.join(file), payload),
+        _ => Err("a notification sink needs exactly one of webhook, command, or file".into()),
+    }
+}
+
+/// POST the payload with `curl`, which also covers HTTPS and proxies.
+fn post_webhook(
+    url: &str,
+    headers: &BTreeMap<String, String>,
+    payload: &str,
+) -> Result<(), String> {
+    let mut cmd = Command::new("curl");
+    cmd.args(["-sS", "--fail", "-X", "POST", "-m"])
+        .arg(WEBHOOK_TIMEOUT_SECS.to_string())
+        .args(["-H", "Content-Type: application/json"]);
+    for (name, value) in headers {
+        cmd.arg("-H").arg(format!("{name}: {}", expand_env(value)));
+    }
+    cmd.args(["--data-binary", "@-", url]);
+    pipe_to(cmd, payload).map_err(|e| format!("webhook {url}: {e}"))
+}
+
+fn run_command(
+    project_root: &Path,
+    command: &str,
+    event: NotifyEvent,
+    payload: &str,
+) -> Result<(), String> {
+    let mut cmd = Command::new("bash");
+    cmd.arg("-c")
+        .arg(command)
+        .current_dir(project_root)
+        .env("DECREE_EVENT", event.as_str());
+    pipe_to(cmd, &format!("{payload}\n")).map_err(|e| format!("command '{command}': {e}"))
+}
+
+/// Run `cmd` with `input` on stdin; fail with its stderr if it exits non-zero.
+fn pipe_to(mut cmd: Command, input: &str) -> Result<(), String> {
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A command that ignores its input may close stdin early
+        let _ = stdin.write_all(input.as_bytes());
+    }
+    let output = child.wait_with_output().map_err(|e| e.to_string())?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let code = output
+        .status
+        .code()
+        .map_or("signal".to_string(), |c| c.to_string());
+    Err(format!("exit {code}: {}", stderr.trim()))
+}
+
+fn append_line(path: &Path, payload: &str) -> Result<(), String> {
+    let append = || -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        atomic::append_line(path, payload)
+    };
+    append().map_err(|e| format!("{}: {e}", path.display()))
+}
+
+/// Replace `${VAR}` with the variable's value (empty if unset).
+fn expand_env(value: &str) -> String {
+    let re = Regex::new(r"\$\{([A-Za-z_][A-Za-z0-9_]*)\}").expect("valid regex");
+    re.replace_all(value, |caps: &regex::Captures| {
+        std::env::var(&caps[1]).unwrap_or_default()
+    })
+    .into_owned()
+}
+
+/// Last lines of the most recently written `*.log` in a run directory.
+fn latest_log_tail(run_dir: &Path) -> Option<String> {
+    let latest = std::fs::read_dir(run_dir)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "log"))
+        .max_by_key(|e| e.metadata().and_then(|m| m.modified()).ok())?;
+    let content = std::fs::read_to_string(latest.path()).ok()?;
+    let lines: Vec<&str> = content.lines().collect();
+    let tail = lines[lines.len().saturating_sub(LOG_TAIL_LINES)..].join("\n");
+    Some(tail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn sink(on: Vec<NotifyEvent>, file: &str) -> NotificationSink {
+        NotificationSink {
+            on,
+            file: Some(file.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn message() -> InboxMessage {
+        InboxMessage {
+            id: Some("D0001-1432-auth-0".into()),
+            chain: Some("D0001-1432-auth".into()),
+            seq: Some(0),
+            routine: Some("develop".into()),
+            migration: Some("01-auth.md".into()),
+            body: String::new(),
+            custom_fields: Default::default(),
+            filename: "D0001-1432-auth-0.md".into(),
+        }
+    }
+
+    #[test]
+    fn test_covers() {
+        assert!(NotifyEvent::DeadLettered.covers(NotifyEvent::DeadLettered));
+        assert!(NotifyEvent::DeadLettered.covers(NotifyEvent::RetriesExhausted));
+        assert!(!NotifyEvent::RetriesExhausted.covers(NotifyEvent::DeadLettered));
+        assert!(!NotifyEvent::MigrationDone.covers(NotifyEvent::CronFired));
+    }
+
+    #[test]
+    fn test_payload_for_message() {
+        let dir = TempDir::new().unwrap();
+        let run_dir = dir.path().join("run");
+        std::fs::create_dir_all(&run_dir).unwrap();
+        let log: Vec<String> = (1..=30).map(|i| format!("line {i}")).collect();
+        std::fs::write(run_dir.join("routine.log"), log.join("\n")).unwrap();
+
+        let notification = Notification {
+            attempts: Some(3),
+            exit_code: Some(1),
+            duration_secs: Some(42),
+            ..Notification::for_message(NotifyEvent::RetriesExhausted, &message(), Some(&run_dir))
+        };
+        let payload = notification.payload(dir.path());
+
+        assert_eq!(payload["event"], "retries_exhausted");
+        assert_eq!(payload["message_id"], "D0001-1432-auth-0");
+        assert_eq!(payload["routine"], "develop");
+        assert_eq!(payload["migration"], "01-auth.md");
+        assert_eq!(payload["disposition"], "dead");
+        assert_eq!(payload["duration_secs"], 42);
+        assert_eq!(payload["attempts"], 3);
+        let tail = payload["log_tail"].as_str().unwrap();
+        assert!(
+            tail.starts_with("line 11\n") && tail.ends_with("line 30"),
+            "{tail}"
+        );
+        assert!(payload.get("cron").is_none(), "unset fields are left out");
+    }
+
+    #[test]
+    fn test_send_filters_by_event() {
+        let dir = TempDir::new().unwrap();
+        let config = AppConfig {
+            notifications: vec![
+                sink(vec![NotifyEvent::DeadLettered], "dead.jsonl"),
+                sink(vec![NotifyEvent::MigrationDone], "done.jsonl"),
+                sink(Vec::new(), "logs/all.jsonl"),
+            ],
+            ..AppConfig::default()
+        };
+
+        let exhausted = Notification::for_message(NotifyEvent::RetriesExhausted, &message(), None);
+        send(dir.path(), &config, &exhausted);
+        send(
+            dir.path(),
+            &config,
+            &Notification::new(NotifyEvent::DaemonStarted),
+        );
+
+        let dead = std::fs::read_to_string(dir.path().join("dead.jsonl")).unwrap();
+        assert_eq!(dead.lines().count(), 1);
+        let line: Value = serde_json::from_str(dead.lines().next().unwrap()).unwrap();
+        assert_eq!(line["event"], "retries_exhausted");
+
+        assert!(!dir.path().join("done.jsonl").exists());
+        let all = std::fs::read_to_string(dir.path().join("logs/all.jsonl")).unwrap();
+        assert_eq!(all.lines().count(), 2);
+    }
+
+    #[test]
+    fn test_command_sink_gets_payload_on_stdin() {
+        let dir = TempDir::new().unwrap();
+        let config = AppConfig {
+            notifications: vec![NotificationSink {
+                command: Some("cat > payload.json; echo \"$DECREE_EVENT\" > event.txt".into()),
+                ..Default::default()
+            }],
+            ..AppConfig::default()
+        };
+
+        let notification = Notification {
+            cron: Some("nightly.md".into()),
+            ..Notification::new(NotifyEvent::CronFired)
+        };
+        send(dir.path(), &config, &notification);
+
+        let payload = std::fs::read_to_string(dir.path().join("payload.json")).unwrap();
+        let payload: Value = serde_json::from_str(&payload).unwrap();
+        assert_eq!(payload["cron"], "nightly.md");
+        assert_eq!(payload["disposition"], "queued");
+        let event = std::fs::read_to_string(dir.path().join("event.txt")).unwrap();
+        assert_eq!(event.trim(), "cron_fired");
+    }
+
+    #[test]
+    fn test_deliver_errors() {
+        let dir = TempDir::new().unwrap();
+        let empty = NotificationSink::default();
+        assert!(
+            deliver(dir.path(), &empty, NotifyEvent::DaemonStarted, "{}")
+                .unwrap_err()
+                .contains("exactly one")
+        );
+
+        let failing = NotificationSink {
+            command: Some("echo nope >&2; exit 4".into()),
+            ..Default::default()
+        };
+        let err = deliver(dir.path(), &failing, NotifyEvent::DaemonStarted, "{}").unwrap_err();
+        assert!(err.contains("exit 4: nope"), "{err}");
+    }
+
+    #[test]
+    fn test_expand_env() {
+        std::env::set_var("DECREE_TEST_NOTIFY_TOKEN", "abc");
+        assert_eq!(
+            expand_env("Bearer ${DECREE_TEST_NOTIFY_TOKEN}"),
+            "Bearer abc"
+        );
+        assert_eq!(expand_env("${DECREE_TEST_NOTIFY_UNSET}x"), "x");
+    }
+}
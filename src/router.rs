@@ -0,0 +1,1194 @@
+use crate::ai;
+use crate::atomic;
+use crate::config::{self, AppConfig, RouteRule};
+use crate::error::DecreeError;
+use crate::events::{self, Event, EventKind};
+use crate::message::{
+    build_router_prompt, list_routines, read_messages_in, InboxMessage, RouterFn, RoutineInfo,
+};
+use chrono::{DateTime, Local, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+/// File in a run directory recording the AI router consultation.
+pub const ROUTER_RECORD_FILE: &str = "router.json";
+
+/// Custom field that cron messages carry to name the cron file they came from.
+pub const CRON_SOURCE_FIELD: &str = "cron_source";
+
+/// Why a routine was chosen for a message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RouteReason {
+    /// The message named its routine in frontmatter.
+    Frontmatter,
+    /// A rule from the `routes` config section matched (0-based index).
+    Rule { index: usize, name: Option<String> },
+    /// The AI router picked the routine.
+    AiRouter,
+    /// No rule matched and the AI router was not configured or gave no valid answer.
+    Default,
+    /// `router.min_confidence` is set and the AI router was not confident
+    /// enough; the message waits in `inbox/needs-routing/` for a human.
+    NeedsRouting,
+}
+
+impl std::fmt::Display for RouteReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RouteReason::Frontmatter => f.write_str("routine set in frontmatter"),
+            RouteReason::Rule { index, name: Some(name) } => {
+                write!(f, "rule #{} '{name}'", index + 1)
+            }
+            RouteReason::Rule { index, name: None } => write!(f, "rule #{}", index + 1),
+            RouteReason::AiRouter => f.write_str("AI router"),
+            RouteReason::Default => f.write_str("default_routine fallback"),
+            RouteReason::NeedsRouting => f.write_str("needs manual routing"),
+        }
+    }
+}
+
+/// The routine chosen for a message and why.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteDecision {
+    /// The chosen routine. For [`RouteReason::NeedsRouting`] this is the
+    /// fallback that would have been used; it is not applied.
+    pub routine: String,
+    pub reason: RouteReason,
+    /// Details of the AI router consultation, if it was consulted.
+    pub ai_call: Option<RouterCall>,
+}
+
+/// One consultation of the AI router, recorded in the run directory.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RouterCall {
+    /// Hash of the message body and routine set, used as the cache key.
+    pub cache_key: String,
+    /// Whether the answer came from the cache instead of a fresh call.
+    pub cached: bool,
+    /// The populated router prompt (absent for cache hits).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+    /// Raw stdout of the router command.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw_output: Option<String>,
+    /// Why the call failed, if it did.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Routine parsed from the answer, if it named a known routine.
+    pub parsed: Option<String>,
+    /// Confidence of the parsed routine (ranked answers only).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f64>,
+    /// Ranked candidates, best first (ranked answers only).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub candidates: Vec<Candidate>,
+}
+
+/// A routine suggested by a ranked router answer.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Candidate {
+    pub routine: String,
+    /// Confidence from 0 to 1; 0 when the answer gave none.
+    pub confidence: f64,
+}
+
+/// Appended to the router prompt when `router.min_confidence` is set.
+const RANKED_ANSWER_INSTRUCTIONS: &str = "\n\n## Ranked Answer\n\n\
+Instead of a single name, respond with up to 3 candidate routines, best first, \
+one per line, each followed by your confidence from 0 to 1 \
+(e.g., `develop 0.8`). No explanation.\n";
+
+/// What `router.json` in a run directory records.
+#[derive(Serialize)]
+struct RouterRecord<'a> {
+    timestamp: String,
+    #[serde(flatten)]
+    call: &'a RouterCall,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    routine: Option<&'a str>,
+    fallback: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    needs_routing: bool,
+}
+
+/// A cached router answer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    routine: String,
+    at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    confidence: Option<f64>,
+}
+
+/// Maximum number of router answers kept in `.decree/router-cache.json`.
+const MAX_CACHE_ENTRIES: usize = 1000;
+
+/// Result of checking one rule against a message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleCheck {
+    /// Conditions that matched, in evaluation order.
+    pub matched: Vec<String>,
+    /// The first condition that did not match, if any.
+    pub failed: Option<String>,
+}
+
+impl RuleCheck {
+    pub fn is_match(&self) -> bool {
+        self.failed.is_none()
+    }
+}
+
+/// Choose a routine for a message.
+///
+/// Order: frontmatter `routine` → first matching `routes` rule → AI router →
+/// config `default_routine` → `"develop"`. With `router.min_confidence` set,
+/// an AI router answer below the threshold (or no usable answer) yields
+/// [`RouteReason::NeedsRouting`] instead of the default.
+pub fn route(
+    project_root: &Path,
+    config: &AppConfig,
+    msg: &InboxMessage,
+    ai_router: Option<&RouterFn>,
+) -> Result<RouteDecision, DecreeError> {
+    if let Some(routine) = &msg.routine {
+        return Ok(RouteDecision {
+            routine: routine.clone(),
+            reason: RouteReason::Frontmatter,
+            ai_call: None,
+        });
+    }
+
+    for (index, rule) in config.routes.iter().enumerate() {
+        if check_rule(rule, msg)?.is_match() {
+            return Ok(RouteDecision {
+                routine: rule.routine.clone(),
+                reason: RouteReason::Rule {
+                    index,
+                    name: rule.name.clone(),
+                },
+                ai_call: None,
+            });
+        }
+    }
+
+    let routine = if config.default_routine.is_empty() {
+        "develop".to_string()
+    } else {
+        config.default_routine.clone()
+    };
+
+    let min_confidence = config.router.min_confidence;
+    if let Some(min) = min_confidence {
+        if !(0.0..=1.0).contains(&min) {
+            return Err(DecreeError::Config(format!(
+                "router.min_confidence must be between 0 and 1, got {min}"
+            )));
+        }
+    }
+
+    if let Some(router_fn) = ai_router {
+        let routines = list_routines(project_root, config)?;
+        if !routines.is_empty() {
+            let call = consult_ai_router(project_root, config, &routines, msg, router_fn, min_confidence);
+            let confident = is_confident(call.confidence, min_confidence);
+            return Ok(match call.parsed.clone() {
+                Some(selected) if confident => RouteDecision {
+                    routine: selected,
+                    reason: RouteReason::AiRouter,
+                    ai_call: Some(call),
+                },
+                _ if min_confidence.is_some() => RouteDecision {
+                    routine,
+                    reason: RouteReason::NeedsRouting,
+                    ai_call: Some(call),
+                },
+                _ => RouteDecision {
+                    routine,
+                    reason: RouteReason::Default,
+                    ai_call: Some(call),
+                },
+            });
+        }
+    }
+
+    Ok(RouteDecision {
+        routine,
+        reason: RouteReason::Default,
+        ai_call: None,
+    })
+}
+
+/// Whether a confidence clears the threshold (always true without one).
+fn is_confident(confidence: Option<f64>, min_confidence: Option<f64>) -> bool {
+    min_confidence.is_none_or(|min| confidence.is_some_and(|c| c >= min))
+}
+
+/// Ask the AI router for a routine, using the answer cache when possible.
+///
+/// With a confidence threshold the prompt asks for a ranked answer, and
+/// only answers that clear the threshold are cached. Failures are captured
+/// in the returned [`RouterCall`] rather than propagated, so routing can
+/// always fall back.
+fn consult_ai_router(
+    project_root: &Path,
+    config: &AppConfig,
+    routines: &[RoutineInfo],
+    msg: &InboxMessage,
+    router_fn: &RouterFn,
+    min_confidence: Option<f64>,
+) -> RouterCall {
+    let mut prompt = match build_router_prompt(project_root, config, routines, &msg.body) {
+        Ok(prompt) => prompt,
+        Err(e) => {
+            return RouterCall {
+                cache_key: String::new(),
+                cached: false,
+                prompt: None,
+                raw_output: None,
+                error: Some(format!("failed to build router prompt: {e}")),
+                parsed: None,
+                confidence: None,
+                candidates: Vec::new(),
+            };
+        }
+    };
+    if min_confidence.is_some() {
+        prompt.push_str(RANKED_ANSWER_INSTRUCTIONS);
+    }
+
+    let cache_key = cache_key(&prompt, routines, &router_identity(config));
+    let mut cache = load_cache(project_root);
+
+    if let Some(entry) = cache.get(&cache_key) {
+        if routines.iter().any(|r| r.name == entry.routine)
+            && is_confident(entry.confidence, min_confidence)
+        {
+            return RouterCall {
+                cache_key,
+                cached: true,
+                prompt: None,
+                raw_output: None,
+                error: None,
+                parsed: Some(entry.routine.clone()),
+                confidence: entry.confidence,
+                candidates: Vec::new(),
+            };
+        }
+    }
+
+    let mut call = RouterCall {
+        cache_key,
+        cached: false,
+        prompt: None,
+        raw_output: None,
+        error: None,
+        parsed: None,
+        confidence: None,
+        candidates: Vec::new(),
+    };
+
+    match router_fn(&prompt) {
+        Ok(output) if min_confidence.is_some() => {
+            call.candidates = parse_ranked_answer(&output, routines);
+            match call.candidates.first() {
+                Some(best) => {
+                    call.parsed = Some(best.routine.clone());
+                    call.confidence = Some(best.confidence);
+                }
+                None => call.parsed = parse_router_answer(&output, routines),
+            }
+            call.raw_output = Some(output);
+        }
+        Ok(output) => {
+            call.parsed = parse_router_answer(&output, routines);
+            call.raw_output = Some(output);
+        }
+        Err(e) => call.error = Some(e.to_string()),
+    }
+    call.prompt = Some(prompt);
+
+    if let Some(routine) = call
+        .parsed
+        .as_ref()
+        .filter(|_| is_confident(call.confidence, min_confidence))
+    {
+        cache.insert(
+            call.cache_key.clone(),
+            CacheEntry {
+                routine: routine.clone(),
+                at: Utc::now(),
+                confidence: call.confidence,
+            },
+        );
+        // The cache is an optimization; failing to persist it is not an error.
+        let _ = save_cache(project_root, cache);
+    }
+
+    call
+}
+
+/// Extract a routine name from a router answer.
+///
+/// Accepts a bare name, one wrapped in backticks, quotes, or bold markers,
+/// and `Routine: <name>` lines. Anything else — including prose that merely
+/// mentions a routine, such as "not develop" — yields `None`.
+pub fn parse_router_answer(output: &str, routines: &[RoutineInfo]) -> Option<String> {
+    let is_routine = |s: &str| routines.iter().any(|r| r.name == s);
+    let clean = |s: &str| {
+        let s = s.trim().trim_matches(|c: char| "`'\"*_.:".contains(c)).trim();
+        let lower = s.to_ascii_lowercase();
+        let s = if lower.starts_with("routine:") {
+            &s["routine:".len()..]
+        } else {
+            s
+        };
+        s.trim().trim_matches(|c: char| "`'\"*_.".contains(c)).trim().to_string()
+    };
+
+    let whole = clean(output);
+    if is_routine(&whole) {
+        return Some(whole);
+    }
+    output
+        .lines()
+        .map(clean)
+        .find(|line| is_routine(line))
+}
+
+/// One ranked candidate line: an optional list marker, the routine name
+/// (optionally in backticks, quotes, or bold), an optional `:`, `-`, or `=`,
+/// and an optional confidence (optionally in parentheses). Nothing else may
+/// follow but trailing punctuation.
+static RANKED_LINE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"^\s*(?:[-*•]|\d+[.)])?\s*[`'"*_]*(?P<name>[A-Za-z0-9][A-Za-z0-9_./-]*?)[`'"*_]*\s*(?:[:=]|-+|—|–)?\s*(?:\(?(?P<confidence>\d+(?:\.\d+)?%?)\)?)?\s*[.,;]?\s*$"#,
+    )
+    .expect("valid regex")
+});
+
+/// Extract ranked candidates from a router answer, best first.
+///
+/// Each line of the form `<routine>: <confidence>` or `<routine> <confidence>`
+/// naming a known routine is a candidate; other lines, including prose that
+/// merely mentions a routine (such as "not develop — docs 0.9"), are ignored.
+/// Numbers above 1 or ending in `%` are read as percentages. List markers and
+/// light formatting are allowed, repeated routines keep their first entry,
+/// and a missing or out-of-range confidence counts as 0.
+pub fn parse_ranked_answer(output: &str, routines: &[RoutineInfo]) -> Vec<Candidate> {
+    let mut candidates: Vec<Candidate> = Vec::new();
+    for line in output.lines() {
+        let Some(caps) = RANKED_LINE_RE.captures(line) else {
+            continue;
+        };
+        let routine = &caps["name"];
+        if !routines.iter().any(|r| r.name == routine)
+            || candidates.iter().any(|c| c.routine == routine)
+        {
+            continue;
+        }
+        let confidence = caps
+            .name("confidence")
+            .and_then(|m| parse_confidence(m.as_str()))
+            .unwrap_or(0.0);
+        candidates.push(Candidate {
+            routine: routine.to_string(),
+            confidence,
+        });
+    }
+    candidates.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    candidates
+}
+
+/// Parse `0.8`, `80`, or `80%` as a confidence from 0 to 1.
+fn parse_confidence(word: &str) -> Option<f64> {
+    let (number, percent) = match word.strip_suffix('%') {
+        Some(number) => (number, true),
+        None => (word, false),
+    };
+    let value: f64 = number.parse().ok()?;
+    let value = if percent || value > 1.0 { value / 100.0 } else { value };
+    (0.0..=1.0).contains(&value).then_some(value)
+}
+
+/// Write `router.json` to the message's run directory if the AI router was consulted.
+pub fn record_decision(
+    project_root: &Path,
+    msg_id: &str,
+    decision: &RouteDecision,
+) -> Result<(), DecreeError> {
+    let Some(call) = &decision.ai_call else {
+        return Ok(());
+    };
+
+    let run_dir = project_root
+        .join(config::DECREE_DIR)
+        .join(config::RUNS_DIR)
+        .join(msg_id);
+    std::fs::create_dir_all(&run_dir)?;
+
+    let record = RouterRecord {
+        timestamp: Local::now().to_rfc3339(),
+        call,
+        routine: (decision.reason != RouteReason::NeedsRouting).then_some(&decision.routine),
+        fallback: decision.reason == RouteReason::Default,
+        needs_routing: decision.reason == RouteReason::NeedsRouting,
+    };
+    let json = serde_json::to_string_pretty(&record)
+        .map_err(|e| DecreeError::Other(format!("failed to serialize router record: {e}")))?;
+    atomic::write(&run_dir.join(ROUTER_RECORD_FILE), json + "\n")?;
+    Ok(())
+}
+
+/// Ranked candidates recorded in a message's `router.json`, if any.
+pub fn recorded_candidates(project_root: &Path, msg_id: &str) -> Vec<Candidate> {
+    let path = project_root
+        .join(config::DECREE_DIR)
+        .join(config::RUNS_DIR)
+        .join(msg_id)
+        .join(ROUTER_RECORD_FILE);
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .and_then(|record| record.get("candidates").cloned())
+        .and_then(|candidates| serde_json::from_value(candidates).ok())
+        .unwrap_or_default()
+}
+
+/// `.decree/inbox/needs-routing/`, where messages wait for manual routing.
+pub fn needs_routing_dir(project_root: &Path) -> PathBuf {
+    project_root
+        .join(config::DECREE_DIR)
+        .join(config::INBOX_DIR)
+        .join(config::NEEDS_ROUTING_DIR)
+}
+
+/// Move a normalized message from the inbox to `inbox/needs-routing/`.
+pub fn park(project_root: &Path, msg: &InboxMessage) -> Result<(), DecreeError> {
+    let dir = needs_routing_dir(project_root);
+    std::fs::create_dir_all(&dir)?;
+    atomic::write(&dir.join(&msg.filename), msg.serialize())?;
+
+    let inbox_path = project_root
+        .join(config::DECREE_DIR)
+        .join(config::INBOX_DIR)
+        .join(&msg.filename);
+    if inbox_path.exists() {
+        std::fs::remove_file(inbox_path)?;
+    }
+    events::record(project_root, Event::for_message(EventKind::Parked, msg));
+    Ok(())
+}
+
+/// Messages waiting in `inbox/needs-routing/`, sorted by filename.
+pub fn parked_messages(project_root: &Path) -> Result<Vec<InboxMessage>, DecreeError> {
+    read_messages_in(&needs_routing_dir(project_root))
+}
+
+/// Set the routine of a parked message and move it back to the inbox.
+pub fn unpark(
+    project_root: &Path,
+    mut msg: InboxMessage,
+    routine: &str,
+) -> Result<InboxMessage, DecreeError> {
+    msg.routine = Some(routine.to_string());
+    std::fs::create_dir_all(
+        project_root
+            .join(config::DECREE_DIR)
+            .join(config::INBOX_DIR),
+    )?;
+    msg.write_to_inbox(project_root)?;
+    std::fs::remove_file(needs_routing_dir(project_root).join(&msg.filename))?;
+    events::record(
+        project_root,
+        Event {
+            detail: Some("by hand".into()),
+            ..Event::for_message(EventKind::Routed, &msg)
+        },
+    );
+    Ok(msg)
+}
+
+/// SHA-256 of the full router prompt (router.md, routines, and body), the
+/// available routine names, and the router command, so editing router.md
+/// or switching profile or model starts a fresh cache.
+fn cache_key(prompt: &str, routines: &[RoutineInfo], router: &str) -> String {
+    let mut names: Vec<&str> = routines.iter().map(|r| r.name.as_str()).collect();
+    names.sort_unstable();
+
+    let mut hasher = Sha256::new();
+    hasher.update(prompt.as_bytes());
+    hasher.update([0]);
+    hasher.update(names.join("\n").as_bytes());
+    hasher.update([0]);
+    hasher.update(router.as_bytes());
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// The router backends' names and command lines, models included.
+fn router_identity(config: &AppConfig) -> String {
+    match ai::router_backends(config) {
+        Some(Ok(backends)) => backends
+            .iter()
+            .map(|b| format!("{} {}", b.name, b.command_line()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Some(Err(e)) => e.to_string(),
+        None => String::new(),
+    }
+}
+
+fn cache_path(project_root: &Path) -> PathBuf {
+    project_root
+        .join(config::DECREE_DIR)
+        .join(config::ROUTER_CACHE_FILE)
+}
+
+/// Load the router cache; a missing or unreadable cache is treated as empty.
+fn load_cache(project_root: &Path) -> BTreeMap<String, CacheEntry> {
+    std::fs::read_to_string(cache_path(project_root))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Save the router cache, dropping the oldest entries beyond `MAX_CACHE_ENTRIES`.
+fn save_cache(
+    project_root: &Path,
+    mut cache: BTreeMap<String, CacheEntry>,
+) -> Result<(), DecreeError> {
+    if cache.len() > MAX_CACHE_ENTRIES {
+        let mut by_age: Vec<(DateTime<Utc>, String)> =
+            cache.iter().map(|(k, e)| (e.at, k.clone())).collect();
+        by_age.sort();
+        let excess = cache.len() - MAX_CACHE_ENTRIES;
+        for (_, key) in by_age.into_iter().take(excess) {
+            cache.remove(&key);
+        }
+    }
+    let json = serde_json::to_string_pretty(&cache)
+        .map_err(|e| DecreeError::Other(format!("failed to serialize router cache: {e}")))?;
+    atomic::write(&cache_path(project_root), json)?;
+    Ok(())
+}
+
+/// Check a single rule against a message.
+///
+/// Conditions are evaluated in order — filename, cron, frontmatter, body —
+/// stopping at the first one that fails. An invalid glob or regex is a
+/// config error.
+pub fn check_rule(rule: &RouteRule, msg: &InboxMessage) -> Result<RuleCheck, DecreeError> {
+    let mut check = RuleCheck {
+        matched: Vec::new(),
+        failed: None,
+    };
+
+    if let Some(pattern) = &rule.filename {
+        let re = glob_regex(pattern)?;
+        let label = format!("filename \"{pattern}\"");
+        let hit = re.is_match(&msg.filename)
+            || msg.migration.as_deref().is_some_and(|m| re.is_match(m));
+        if !hit {
+            check.failed = Some(label);
+            return Ok(check);
+        }
+        check.matched.push(label);
+    }
+
+    if let Some(pattern) = &rule.cron {
+        let re = glob_regex(pattern)?;
+        let label = format!("cron \"{pattern}\"");
+        let source = msg.custom_fields.get(CRON_SOURCE_FIELD).and_then(value_as_string);
+        if !source.is_some_and(|s| re.is_match(&s)) {
+            check.failed = Some(label);
+            return Ok(check);
+        }
+        check.matched.push(label);
+    }
+
+    for (key, expected) in &rule.frontmatter {
+        let label = format!("{key}: {expected}");
+        let actual = match key.as_str() {
+            "migration" => msg.migration.clone(),
+            _ => msg.custom_fields.get(key).and_then(value_as_string),
+        };
+        if actual.as_deref() != Some(expected.as_str()) {
+            check.failed = Some(label);
+            return Ok(check);
+        }
+        check.matched.push(label);
+    }
+
+    if let Some(pattern) = &rule.body {
+        let re = Regex::new(pattern).map_err(|e| {
+            DecreeError::Config(format!("invalid body regex in route to '{}': {e}", rule.routine))
+        })?;
+        let label = format!("body /{pattern}/");
+        if !re.is_match(&msg.body) {
+            check.failed = Some(label);
+            return Ok(check);
+        }
+        check.matched.push(label);
+    }
+
+    Ok(check)
+}
+
+/// Compile a filename glob (`*`, `**`, `?`) into an anchored regex.
+fn glob_regex(glob: &str) -> Result<Regex, DecreeError> {
+    let mut pattern = String::from("^");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                pattern.push_str(".*");
+            }
+            '*' => pattern.push_str("[^/]*"),
+            '?' => pattern.push_str("[^/]"),
+            c => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    pattern.push('$');
+    Regex::new(&pattern).map_err(|e| DecreeError::Config(format!("invalid glob '{glob}': {e}")))
+}
+
+/// Build the AI router callback from config, if one is configured.
+///
+/// Uses the `ai.router` profile (or `ai.default`), then the `ai.fallback`
+/// profiles, or else the legacy `commands.ai_router` template. An unknown profile is reported on
+/// each call, so routing falls back instead of failing.
+pub fn ai_router_fn(config: &AppConfig) -> Option<Box<RouterFn>> {
+    let backends = ai::router_backends(config)?.map_err(|e| e.to_string());
+    Some(Box::new(move |prompt: &str| match &backends {
+        Ok(backends) => ai::with_fallback(backends, |b| b.run_captured(prompt), |_, _| {}),
+        Err(e) => Err(DecreeError::Config(e.clone())),
+    }))
+}
+
+fn value_as_string(v: &serde_yaml::Value) -> Option<String> {
+    match v {
+        serde_yaml::Value::String(s) => Some(s.clone()),
+        serde_yaml::Value::Number(n) => Some(n.to_string()),
+        serde_yaml::Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use tempfile::TempDir;
+
+    fn msg(filename: &str, content: &str) -> InboxMessage {
+        InboxMessage::parse(filename, content).unwrap()
+    }
+
+    fn rule(routine: &str) -> RouteRule {
+        RouteRule {
+            routine: routine.into(),
+            ..RouteRule::default()
+        }
+    }
+
+    fn setup_decree_dir(dir: &TempDir) {
+        let decree = dir.path().join(".decree");
+        std::fs::create_dir_all(decree.join("routines")).unwrap();
+        std::fs::write(decree.join("routines/develop.sh"), "#!/usr/bin/env bash\n").unwrap();
+        std::fs::write(decree.join("routines/docs.sh"), "#!/usr/bin/env bash\n").unwrap();
+        std::fs::write(decree.join("router.md"), "{routines}\n{message}\n").unwrap();
+    }
+
+    fn routines(names: &[&str]) -> Vec<RoutineInfo> {
+        names
+            .iter()
+            .map(|n| RoutineInfo {
+                name: n.to_string(),
+                description: String::new(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_router_answer() {
+        let rs = routines(&["develop", "rust-develop", "docs"]);
+        let parse = |s: &str| parse_router_answer(s, &rs);
+
+        assert_eq!(parse("develop").as_deref(), Some("develop"));
+        assert_eq!(parse("  rust-develop\n").as_deref(), Some("rust-develop"));
+        assert_eq!(parse("`develop`").as_deref(), Some("develop"));
+        assert_eq!(parse("**docs**").as_deref(), Some("docs"));
+        assert_eq!(parse("\"develop\".").as_deref(), Some("develop"));
+        assert_eq!(parse("Routine: develop").as_deref(), Some("develop"));
+        assert_eq!(parse("routine: `rust-develop`").as_deref(), Some("rust-develop"));
+        assert_eq!(
+            parse("I looked at the message.\nRoutine: docs\n").as_deref(),
+            Some("docs")
+        );
+
+        // Prose, ambiguous, or unknown answers are rejected
+        assert_eq!(parse("The best fit is rust-develop because it builds."), None);
+        assert_eq!(parse("not develop, this needs a human"), None);
+        assert_eq!(parse("either develop or docs"), None);
+        assert_eq!(parse("deploy"), None);
+        assert_eq!(parse(""), None);
+    }
+
+    #[test]
+    fn test_cache_key_stable_and_sensitive() {
+        let a = cache_key("prompt", &routines(&["develop", "docs"]), "claude");
+        assert_eq!(a.len(), 64);
+        assert_eq!(a, cache_key("prompt", &routines(&["docs", "develop"]), "claude"));
+        assert_ne!(a, cache_key("other prompt", &routines(&["develop", "docs"]), "claude"));
+        assert_ne!(a, cache_key("prompt", &routines(&["develop"]), "claude"));
+        assert_ne!(a, cache_key("prompt", &routines(&["develop", "docs"]), "claude --model x"));
+    }
+
+    #[test]
+    fn test_route_caches_ai_answers() {
+        let dir = TempDir::new().unwrap();
+        setup_decree_dir(&dir);
+        let config = AppConfig::default();
+
+        let calls = std::rc::Rc::new(std::cell::Cell::new(0));
+        let counter = calls.clone();
+        let ai = move |_: &str| -> Result<String, DecreeError> {
+            counter.set(counter.get() + 1);
+            Ok("Routine: `docs`".into())
+        };
+
+        let m = msg("a.md", "Write the docs.\n");
+        let first = route(dir.path(), &config, &m, Some(&ai)).unwrap();
+        assert_eq!(first.routine, "docs");
+        let call = first.ai_call.unwrap();
+        assert!(!call.cached);
+        assert_eq!(call.raw_output.as_deref(), Some("Routine: `docs`"));
+        assert!(call.prompt.unwrap().contains("Write the docs."));
+
+        let second = route(dir.path(), &config, &m, Some(&ai)).unwrap();
+        assert_eq!(second.routine, "docs");
+        assert!(second.ai_call.unwrap().cached);
+        assert_eq!(calls.get(), 1);
+
+        // A different body misses the cache
+        route(dir.path(), &config, &msg("b.md", "Other.\n"), Some(&ai)).unwrap();
+        assert_eq!(calls.get(), 2);
+
+        // So does the same body once router.md changes
+        std::fs::write(
+            dir.path().join(".decree/router.md"),
+            "Pick carefully.\n{routines}\n{message}\n",
+        )
+        .unwrap();
+        let third = route(dir.path(), &config, &m, Some(&ai)).unwrap();
+        assert!(!third.ai_call.unwrap().cached);
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[test]
+    fn test_route_does_not_cache_fallbacks() {
+        let dir = TempDir::new().unwrap();
+        setup_decree_dir(&dir);
+        let config = AppConfig::default();
+
+        let calls = std::rc::Rc::new(std::cell::Cell::new(0));
+        let counter = calls.clone();
+        let ai = move |_: &str| -> Result<String, DecreeError> {
+            counter.set(counter.get() + 1);
+            Err(DecreeError::Other("AI router exited with code 1".into()))
+        };
+
+        let m = msg("a.md", "Body.\n");
+        let decision = route(dir.path(), &config, &m, Some(&ai)).unwrap();
+        assert_eq!(decision.reason, RouteReason::Default);
+        let call = decision.ai_call.unwrap();
+        assert_eq!(call.error.as_deref(), Some("AI router exited with code 1"));
+        assert_eq!(call.parsed, None);
+
+        route(dir.path(), &config, &m, Some(&ai)).unwrap();
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[test]
+    fn test_parse_ranked_answer() {
+        let rs = routines(&["develop", "rust-develop", "docs"]);
+        let parse = |s: &str| parse_ranked_answer(s, &rs);
+        let pairs = |cs: Vec<Candidate>| -> Vec<(String, f64)> {
+            cs.into_iter().map(|c| (c.routine, c.confidence)).collect()
+        };
+
+        assert_eq!(
+            pairs(parse("docs 0.7\ndevelop 0.2\n")),
+            vec![("docs".into(), 0.7), ("develop".into(), 0.2)]
+        );
+        // List markers, punctuation, and percentages
+        assert_eq!(
+            pairs(parse("1. `develop`: 40%\n2. **rust-develop** (85)\n")),
+            vec![("rust-develop".into(), 0.85), ("develop".into(), 0.4)]
+        );
+        // Missing confidence counts as 0; repeats keep the first entry
+        assert_eq!(
+            pairs(parse("docs\ndevelop 0.3\ndocs 0.9")),
+            vec![("develop".into(), 0.3), ("docs".into(), 0.0)]
+        );
+        // Out-of-range numbers are ignored
+        assert_eq!(pairs(parse("docs 250")), vec![("docs".into(), 0.0)]);
+        assert!(parse("deploy 0.9\nno idea").is_empty());
+        // Prose that mentions routines is not a candidate line
+        assert_eq!(
+            pairs(parse("not develop — docs 0.9\nI'd pick docs, maybe 0.4\ndocs: 0.6")),
+            vec![("docs".into(), 0.6)]
+        );
+        assert!(parse("Use develop here, confidence 0.8").is_empty());
+    }
+
+    #[test]
+    fn test_route_min_confidence() {
+        let dir = TempDir::new().unwrap();
+        setup_decree_dir(&dir);
+        let config = AppConfig {
+            router: crate::config::RouterConfig {
+                min_confidence: Some(0.6),
+            },
+            ..AppConfig::default()
+        };
+
+        let prompts = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let seen = prompts.clone();
+        let ai = move |prompt: &str| -> Result<String, DecreeError> {
+            seen.borrow_mut().push(prompt.to_string());
+            Ok(if prompt.contains("Write the docs.") {
+                "docs 0.9\ndevelop 0.1".into()
+            } else {
+                "develop 0.4\ndocs 0.35".into()
+            })
+        };
+
+        let confident = route(dir.path(), &config, &msg("a.md", "Write the docs.\n"), Some(&ai))
+            .unwrap();
+        assert_eq!(confident.routine, "docs");
+        assert_eq!(confident.reason, RouteReason::AiRouter);
+        assert_eq!(confident.ai_call.unwrap().confidence, Some(0.9));
+        assert!(prompts.borrow()[0].contains("## Ranked Answer"));
+
+        let unsure = route(dir.path(), &config, &msg("b.md", "Hmm.\n"), Some(&ai)).unwrap();
+        assert_eq!(unsure.reason, RouteReason::NeedsRouting);
+        let call = unsure.ai_call.unwrap();
+        assert_eq!(call.parsed.as_deref(), Some("develop"));
+        assert_eq!(call.candidates.len(), 2);
+
+        // Only the confident answer was cached
+        route(dir.path(), &config, &msg("a.md", "Write the docs.\n"), Some(&ai)).unwrap();
+        route(dir.path(), &config, &msg("b.md", "Hmm.\n"), Some(&ai)).unwrap();
+        assert_eq!(prompts.borrow().len(), 3);
+    }
+
+    #[test]
+    fn test_route_min_confidence_parks_failures_and_rejects_bad_values() {
+        let dir = TempDir::new().unwrap();
+        setup_decree_dir(&dir);
+        let mut config = AppConfig {
+            router: crate::config::RouterConfig {
+                min_confidence: Some(0.5),
+            },
+            ..AppConfig::default()
+        };
+        let ai = |_: &str| -> Result<String, DecreeError> {
+            Err(DecreeError::Other("AI router exited with code 1".into()))
+        };
+
+        let decision = route(dir.path(), &config, &msg("a.md", "Body.\n"), Some(&ai)).unwrap();
+        assert_eq!(decision.reason, RouteReason::NeedsRouting);
+
+        config.router.min_confidence = Some(1.5);
+        let err = route(dir.path(), &config, &msg("a.md", "Body.\n"), Some(&ai)).unwrap_err();
+        assert!(matches!(err, DecreeError::Config(_)));
+    }
+
+    #[test]
+    fn test_park_and_unpark() {
+        let dir = TempDir::new().unwrap();
+        setup_decree_dir(&dir);
+        let inbox = dir.path().join(".decree/inbox");
+        std::fs::create_dir_all(&inbox).unwrap();
+
+        let mut m = msg("D0001-1200-a-0.md", "Body.\n");
+        m.id = Some("D0001-1200-a-0".into());
+        m.write_to_inbox(dir.path()).unwrap();
+
+        park(dir.path(), &m).unwrap();
+        assert!(!inbox.join("D0001-1200-a-0.md").exists());
+        assert!(inbox.join("needs-routing/D0001-1200-a-0.md").exists());
+
+        let parked = parked_messages(dir.path()).unwrap();
+        assert_eq!(parked.len(), 1);
+
+        let routed = unpark(dir.path(), parked.into_iter().next().unwrap(), "docs").unwrap();
+        assert_eq!(routed.routine.as_deref(), Some("docs"));
+        assert!(parked_messages(dir.path()).unwrap().is_empty());
+        let content = std::fs::read_to_string(inbox.join("D0001-1200-a-0.md")).unwrap();
+        assert!(content.contains("routine: docs"));
+    }
+
+    #[test]
+    fn test_recorded_candidates() {
+        let dir = TempDir::new().unwrap();
+        setup_decree_dir(&dir);
+        let config = AppConfig {
+            router: crate::config::RouterConfig {
+                min_confidence: Some(0.8),
+            },
+            ..AppConfig::default()
+        };
+        let ai = |_: &str| -> Result<String, DecreeError> { Ok("docs 0.5\ndevelop 0.3".into()) };
+        let decision = route(dir.path(), &config, &msg("a.md", "Body.\n"), Some(&ai)).unwrap();
+        record_decision(dir.path(), "D0001-1200-a-0", &decision).unwrap();
+
+        let record: serde_json::Value = serde_json::from_str(
+            &std::fs::read_to_string(dir.path().join(".decree/runs/D0001-1200-a-0/router.json"))
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(record["needs_routing"], true);
+        assert_eq!(record["routine"], serde_json::Value::Null);
+
+        let candidates = recorded_candidates(dir.path(), "D0001-1200-a-0");
+        assert_eq!(candidates[0].routine, "docs");
+        assert_eq!(candidates[1].confidence, 0.3);
+        assert!(recorded_candidates(dir.path(), "D0001-1200-b-0").is_empty());
+    }
+
+    #[test]
+    fn test_record_decision_writes_router_json() {
+        let dir = TempDir::new().unwrap();
+        setup_decree_dir(&dir);
+        let config = AppConfig::default();
+
+        let ai = |_: &str| -> Result<String, DecreeError> { Ok("no idea".into()) };
+        let m = msg("a.md", "Body.\n");
+        let decision = route(dir.path(), &config, &m, Some(&ai)).unwrap();
+        record_decision(dir.path(), "D0001-1200-a-0", &decision).unwrap();
+
+        let path = dir.path().join(".decree/runs/D0001-1200-a-0/router.json");
+        let record: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(record["raw_output"], "no idea");
+        assert_eq!(record["parsed"], serde_json::Value::Null);
+        assert_eq!(record["routine"], "develop");
+        assert_eq!(record["fallback"], true);
+        assert_eq!(record["cached"], false);
+        assert!(record["prompt"].as_str().unwrap().contains("Body."));
+    }
+
+    #[test]
+    fn test_record_decision_skips_without_ai_call() {
+        let dir = TempDir::new().unwrap();
+        setup_decree_dir(&dir);
+        let decision = RouteDecision {
+            routine: "develop".into(),
+            reason: RouteReason::Frontmatter,
+            ai_call: None,
+        };
+        record_decision(dir.path(), "D0001-1200-a-0", &decision).unwrap();
+        assert!(!dir.path().join(".decree/runs").exists());
+    }
+
+    #[test]
+    fn test_save_cache_prunes_oldest() {
+        let dir = TempDir::new().unwrap();
+        setup_decree_dir(&dir);
+        let base = Utc::now();
+        let cache: BTreeMap<String, CacheEntry> = (0..MAX_CACHE_ENTRIES + 5)
+            .map(|i| {
+                (
+                    format!("key{i:05}"),
+                    CacheEntry {
+                        routine: "develop".into(),
+                        at: base + chrono::TimeDelta::seconds(i as i64),
+                        confidence: None,
+                    },
+                )
+            })
+            .collect();
+        save_cache(dir.path(), cache).unwrap();
+
+        let loaded = load_cache(dir.path());
+        assert_eq!(loaded.len(), MAX_CACHE_ENTRIES);
+        assert!(!loaded.contains_key("key00000"));
+        assert!(loaded.contains_key(&format!("key{:05}", MAX_CACHE_ENTRIES + 4)));
+    }
+
+    #[test]
+    fn test_glob_regex() {
+        let re = glob_regex("*-docs.md").unwrap();
+        assert!(re.is_match("update-docs.md"));
+        assert!(!re.is_match("update-docs.md.bak"));
+        assert!(!re.is_match("a/b-docs.md"));
+        assert!(glob_regex("**/x.md").unwrap().is_match("a/b/x.md"));
+        assert!(glob_regex("file?.md").unwrap().is_match("file1.md"));
+        assert!(glob_regex("a.md").unwrap().is_match("a.md"));
+        assert!(!glob_regex("a.md").unwrap().is_match("axmd"));
+    }
+
+    #[test]
+    fn test_check_rule_filename() {
+        let mut r = rule("docs");
+        r.filename = Some("*docs*".into());
+
+        let check = check_rule(&r, &msg("update-docs.md", "Body.\n")).unwrap();
+        assert!(check.is_match());
+        assert_eq!(check.matched, vec!["filename \"*docs*\""]);
+
+        let check = check_rule(&r, &msg("fix-bug.md", "Body.\n")).unwrap();
+        assert!(!check.is_match());
+        assert_eq!(check.failed.as_deref(), Some("filename \"*docs*\""));
+
+        // Migration filename also counts
+        let m = msg("D0001-1200-x-0.md", "---\nmigration: 02-docs.md\n---\n");
+        assert!(check_rule(&r, &m).unwrap().is_match());
+    }
+
+    #[test]
+    fn test_check_rule_frontmatter_and_body() {
+        let mut r = rule("bugfix");
+        r.frontmatter = BTreeMap::from([("type".to_string(), "bug".to_string())]);
+        r.body = Some("(?i)crash".into());
+
+        let m = msg("a.md", "---\ntype: bug\n---\nApp CRASHES on start.\n");
+        let check = check_rule(&r, &m).unwrap();
+        assert!(check.is_match());
+        assert_eq!(check.matched, vec!["type: bug", "body /(?i)crash/"]);
+
+        let m = msg("a.md", "---\ntype: feature\n---\nCrash.\n");
+        assert_eq!(check_rule(&r, &m).unwrap().failed.as_deref(), Some("type: bug"));
+
+        let m = msg("a.md", "---\ntype: bug\n---\nSlow.\n");
+        assert_eq!(
+            check_rule(&r, &m).unwrap().failed.as_deref(),
+            Some("body /(?i)crash/")
+        );
+    }
+
+    #[test]
+    fn test_check_rule_frontmatter_non_string_values() {
+        let mut r = rule("urgent");
+        r.frontmatter = BTreeMap::from([
+            ("priority".to_string(), "1".to_string()),
+            ("urgent".to_string(), "true".to_string()),
+        ]);
+        let m = msg("a.md", "---\npriority: 1\nurgent: true\n---\n");
+        assert!(check_rule(&r, &m).unwrap().is_match());
+    }
+
+    #[test]
+    fn test_check_rule_cron_source() {
+        let mut r = rule("report");
+        r.cron = Some("nightly-*".into());
+
+        let m = msg("a.md", "---\ncron_source: nightly-report\n---\n");
+        assert!(check_rule(&r, &m).unwrap().is_match());
+
+        let m = msg("a.md", "---\ncron_source: hourly\n---\n");
+        assert!(!check_rule(&r, &m).unwrap().is_match());
+
+        // Messages that didn't come from cron never match
+        assert!(!check_rule(&r, &msg("nightly-x.md", "Body.\n")).unwrap().is_match());
+    }
+
+    #[test]
+    fn test_check_rule_empty_matches_everything() {
+        let check = check_rule(&rule("develop"), &msg("a.md", "Body.\n")).unwrap();
+        assert!(check.is_match());
+        assert!(check.matched.is_empty());
+    }
+
+    #[test]
+    fn test_check_rule_invalid_regex() {
+        let mut r = rule("develop");
+        r.body = Some("(unclosed".into());
+        let err = check_rule(&r, &msg("a.md", "Body.\n")).unwrap_err();
+        assert!(matches!(err, DecreeError::Config(_)));
+    }
+
+    #[test]
+    fn test_route_frontmatter_wins() {
+        let dir = TempDir::new().unwrap();
+        setup_decree_dir(&dir);
+        let config = AppConfig {
+            routes: vec![rule("docs")],
+            ..AppConfig::default()
+        };
+
+        let m = msg("a.md", "---\nroutine: develop\n---\n");
+        let decision = route(dir.path(), &config, &m, None).unwrap();
+        assert_eq!(decision.routine, "develop");
+        assert_eq!(decision.reason, RouteReason::Frontmatter);
+    }
+
+    #[test]
+    fn test_route_first_matching_rule_skips_ai_router() {
+        let dir = TempDir::new().unwrap();
+        setup_decree_dir(&dir);
+        let mut docs = rule("docs");
+        docs.name = Some("documentation".into());
+        docs.body = Some("README".into());
+        let mut never = rule("develop");
+        never.filename = Some("nope-*".into());
+        let config = AppConfig {
+            routes: vec![never, docs, rule("develop")],
+            ..AppConfig::default()
+        };
+
+        let called = std::rc::Rc::new(std::cell::Cell::new(false));
+        let flag = called.clone();
+        let ai = move |_: &str| -> Result<String, DecreeError> {
+            flag.set(true);
+            Ok("develop".into())
+        };
+
+        let m = msg("a.md", "Update the README.\n");
+        let decision = route(dir.path(), &config, &m, Some(&ai)).unwrap();
+        assert_eq!(decision.routine, "docs");
+        assert_eq!(
+            decision.reason,
+            RouteReason::Rule {
+                index: 1,
+                name: Some("documentation".into())
+            }
+        );
+        assert_eq!(decision.reason.to_string(), "rule #2 'documentation'");
+        assert!(!called.get());
+    }
+
+    #[test]
+    fn test_route_falls_through_to_ai_router_and_default() {
+        let dir = TempDir::new().unwrap();
+        setup_decree_dir(&dir);
+        let mut docs = rule("docs");
+        docs.body = Some("README".into());
+        let config = AppConfig {
+            routes: vec![docs],
+            ..AppConfig::default()
+        };
+
+        let ai = |_: &str| -> Result<String, DecreeError> { Ok("docs\n".into()) };
+        let m = msg("a.md", "Fix the bug.\n");
+        let decision = route(dir.path(), &config, &m, Some(&ai)).unwrap();
+        assert_eq!(decision.routine, "docs");
+        assert_eq!(decision.reason, RouteReason::AiRouter);
+
+        let bad = |_: &str| -> Result<String, DecreeError> { Ok("nonsense".into()) };
+        let m = msg("b.md", "Something else.\n");
+        let decision = route(dir.path(), &config, &m, Some(&bad)).unwrap();
+        assert_eq!(decision.routine, "develop");
+        assert_eq!(decision.reason, RouteReason::Default);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::ai;
 use crate::config::{self, AppConfig};
 use crate::error::DecreeError;
 use crate::message::RoutineInfo;
@@ -168,9 +169,9 @@ fn parse_param_assignment(line: &str) -> Option<CustomParam> {
     let rest = rest.trim();
 
     // Must be quoted: "..." or '...'
-    let inner = if rest.starts_with('"') && rest.ends_with('"') && rest.len() >= 2 {
-        &rest[1..rest.len() - 1]
-    } else if rest.starts_with('\'') && rest.ends_with('\'') && rest.len() >= 2 {
+    let double_quoted = rest.starts_with('"') && rest.ends_with('"');
+    let single_quoted = rest.starts_with('\'') && rest.ends_with('\'');
+    let inner = if (double_quoted || single_quoted) && rest.len() >= 2 {
         &rest[1..rest.len() - 1]
     } else {
         return None;
@@ -314,6 +315,15 @@ pub fn resolve_routine(
     }
 }
 
+/// PATH with the running decree binary's directory first, so routines and
+/// hooks can call `decree ai` even when decree is not installed on PATH.
+pub fn path_with_decree() -> Option<std::ffi::OsString> {
+    let exe = std::env::current_exe().ok()?;
+    let dir = exe.parent()?.to_path_buf();
+    let rest = std::env::var_os("PATH").unwrap_or_default();
+    std::env::join_paths(std::iter::once(dir).chain(std::env::split_paths(&rest))).ok()
+}
+
 /// Run the pre-check for a routine by executing it with DECREE_PRE_CHECK=true.
 ///
 /// Returns Ok(None) on success, Ok(Some(reason)) on failure.
@@ -324,12 +334,18 @@ pub fn run_precheck(
 ) -> Result<Option<String>, DecreeError> {
     let script_path = find_routine_script_layered(project_root, config, routine_name)?;
 
-    let output = Command::new("bash")
-        .arg(&script_path)
+    let mut cmd = Command::new("bash");
+    cmd.arg(&script_path)
         .env("DECREE_PRE_CHECK", "true")
-        .current_dir(project_root)
-        .output()
-        .map_err(|e| DecreeError::Io(e))?;
+        .current_dir(project_root);
+    if let Some(path) = path_with_decree() {
+        cmd.env("PATH", path);
+    }
+    // Check the routine's own AI profile, if its registry entry names one
+    if let Some(profile) = config.routine_entry(routine_name).and_then(|e| e.ai.as_deref()) {
+        cmd.env(ai::DECREE_AI_ENV, profile);
+    }
+    let output = cmd.output().map_err(DecreeError::Io)?;
 
     if output.status.success() {
         Ok(None)
@@ -350,11 +366,11 @@ pub fn levenshtein(a: &str, b: &str) -> usize {
     let b_len = b.len();
     let mut matrix = vec![vec![0usize; b_len + 1]; a_len + 1];
 
-    for i in 0..=a_len {
-        matrix[i][0] = i;
+    for (i, row) in matrix.iter_mut().enumerate() {
+        row[0] = i;
     }
-    for j in 0..=b_len {
-        matrix[0][j] = j;
+    for (j, cell) in matrix[0].iter_mut().enumerate() {
+        *cell = j;
     }
 
     for (i, ac) in a.chars().enumerate() {
@@ -375,10 +391,8 @@ pub fn find_closest_routine(name: &str, routines: &[RoutineInfo], max_distance:
 
     for r in routines {
         let dist = levenshtein(name, &r.name);
-        if dist <= max_distance {
-            if best.as_ref().is_none_or(|(d, _)| dist < *d) {
-                best = Some((dist, r.name.clone()));
-            }
+        if dist <= max_distance && best.as_ref().is_none_or(|(d, _)| dist < *d) {
+            best = Some((dist, r.name.clone()));
         }
     }
 
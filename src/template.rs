@@ -0,0 +1,789 @@
+//! Template rendering for prompts and `router.md`.
+//!
+//! Syntax:
+//!
+//! - `{name}`, `{item.field}` — a variable (unknown names are an error)
+//! - `{#if cond}…{#else}…{/if}` — `cond` is `name`, `!name`,
+//!   `name == "text"`, or `name != "text"`
+//! - `{#each list as item}…{/each}` — with `{loop.index}` (from 1),
+//!   `{loop.first}`, and `{loop.last}` inside
+//! - `{#include name}` — another prompt template, rendered in place
+//! - `{file:path}` — a file's contents, relative to the project root
+//! - `{cmd:command}` — a shell command's output, run in the project root
+//! - `{{` — a literal `{`
+//!
+//! Anything else in braces (`${VAR}`, `{ echo; }`, JSON) is left alone.
+
+use crate::config::{self, AppConfig};
+use crate::error::DecreeError;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// How deeply `{#include}` may nest before it is treated as a cycle.
+const MAX_INCLUDE_DEPTH: usize = 10;
+
+/// A value a template can refer to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Text(String),
+    Bool(bool),
+    /// Items to loop over, and how the list reads when output directly.
+    List { items: Vec<Value>, text: String },
+    Map(BTreeMap<String, Value>),
+}
+
+impl Value {
+    /// A list whose text form is its items' text forms, one per line.
+    pub fn list(items: Vec<Value>, text: impl Into<String>) -> Self {
+        Self::List {
+            items,
+            text: text.into(),
+        }
+    }
+
+    fn is_truthy(&self) -> bool {
+        match self {
+            Self::Text(s) => !s.is_empty() && s != "false" && s != "0",
+            Self::Bool(b) => *b,
+            Self::List { items, .. } => !items.is_empty(),
+            Self::Map(_) => true,
+        }
+    }
+
+    /// How the value reads in output; `None` for maps, which have no text form.
+    pub fn as_text(&self) -> Option<String> {
+        match self {
+            Self::Text(s) => Some(s.clone()),
+            Self::Bool(b) => Some(b.to_string()),
+            Self::List { text, .. } => Some(text.clone()),
+            Self::Map(_) => None,
+        }
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Self::Text(s.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Self::Text(s)
+    }
+}
+
+impl From<&serde_yaml::Value> for Value {
+    fn from(v: &serde_yaml::Value) -> Self {
+        match v {
+            serde_yaml::Value::String(s) => Self::Text(s.clone()),
+            serde_yaml::Value::Bool(b) => Self::Bool(*b),
+            serde_yaml::Value::Number(n) => Self::Text(n.to_string()),
+            serde_yaml::Value::Null => Self::Text(String::new()),
+            other => Self::Text(
+                serde_yaml::to_string(other)
+                    .unwrap_or_default()
+                    .trim_end()
+                    .to_string(),
+            ),
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Self::Bool(b)
+    }
+}
+
+/// Named values available to a template.
+#[derive(Debug, Clone, Default)]
+pub struct Context {
+    vars: BTreeMap<String, Value>,
+}
+
+impl Context {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set a variable, replacing any earlier value.
+    pub fn insert(&mut self, name: impl Into<String>, value: impl Into<Value>) {
+        self.vars.insert(name.into(), value.into());
+    }
+
+    /// Set a variable unless it is already set.
+    pub fn insert_default(&mut self, name: impl Into<String>, value: impl Into<Value>) {
+        self.vars.entry(name.into()).or_insert_with(|| value.into());
+    }
+
+    /// Add `prompt_vars` from config; built-in variables keep precedence.
+    pub fn with_config_vars(mut self, config: &AppConfig) -> Self {
+        for (name, value) in &config.prompt_vars {
+            self.insert_default(name.clone(), value.as_str());
+        }
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.vars.get(name)
+    }
+}
+
+/// Where a template is rendered: its name (for errors), the project root
+/// (for `{file:}` and `{cmd:}`), and how `{#include}` finds templates.
+pub struct Env<'a> {
+    pub name: &'a str,
+    pub project_root: &'a Path,
+    pub include: &'a dyn Fn(&str) -> Result<String, DecreeError>,
+}
+
+/// Render a template.
+pub fn render(source: &str, ctx: &Context, env: &Env<'_>) -> Result<String, DecreeError> {
+    let mut scope = Scope {
+        ctx,
+        locals: Vec::new(),
+    };
+    render_nested(source, &mut scope, env, &mut vec![env.name.to_string()])
+}
+
+/// Render `source` in `scope`, so an included template sees the loop
+/// variables of the `{#each}` it is included from.
+fn render_nested(
+    source: &str,
+    scope: &mut Scope<'_>,
+    env: &Env<'_>,
+    stack: &mut Vec<String>,
+) -> Result<String, DecreeError> {
+    let nodes = parse(source, env.name)?;
+    let mut out = String::new();
+    render_nodes(&nodes, scope, env, stack, &mut out)?;
+    Ok(out)
+}
+
+/// The template `name` from `.decree/prompts/` (or the shared prompts
+/// directory), for use as an `{#include}` loader.
+pub fn load_prompt(project_root: &Path, config: &AppConfig, name: &str) -> Result<String, DecreeError> {
+    let path = prompt_path(project_root, config, name)
+        .ok_or_else(|| DecreeError::Template(format!("prompt template not found: {name}")))?;
+    Ok(std::fs::read_to_string(path)?)
+}
+
+/// Locate a prompt template, checking project-local first, then shared.
+pub fn prompt_path(project_root: &Path, config: &AppConfig, name: &str) -> Option<PathBuf> {
+    let filename = format!("{name}.md");
+    let local = project_root
+        .join(config::DECREE_DIR)
+        .join(config::PROMPTS_DIR)
+        .join(&filename);
+    if local.is_file() {
+        return Some(local);
+    }
+    config
+        .resolved_shared_prompts_dir()
+        .map(|dir| dir.join(&filename))
+        .filter(|p| p.is_file())
+}
+
+// =================================================================
+// Parsing
+// =================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Text(String),
+    Var { path: String, line: usize },
+    File { path: String, line: usize },
+    Cmd { command: String, line: usize },
+    Include { name: String, line: usize },
+    If {
+        cond: Cond,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+        line: usize,
+    },
+    Each {
+        list: String,
+        item: String,
+        body: Vec<Node>,
+        line: usize,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Cond {
+    Truthy(String),
+    Falsy(String),
+    Eq(String, String),
+    Ne(String, String),
+}
+
+/// A tag found while scanning, before blocks are assembled.
+#[derive(Debug)]
+enum Token {
+    Node(Node),
+    If(Cond, usize),
+    Else(usize),
+    EndIf(usize),
+    Each(String, String, usize),
+    EndEach(usize),
+}
+
+fn parse(source: &str, name: &str) -> Result<Vec<Node>, DecreeError> {
+    let tokens = tokenize(source, name)?;
+    let mut iter = tokens.into_iter();
+    let (nodes, end) = parse_block(&mut iter, name)?;
+    match end {
+        None => Ok(nodes),
+        Some(token) => Err(template_error(
+            name,
+            token_line(&token),
+            "unexpected closing tag with no matching opening tag",
+        )),
+    }
+}
+
+/// Parse nodes until a block-ending token (returned) or the end.
+fn parse_block(
+    iter: &mut impl Iterator<Item = Token>,
+    name: &str,
+) -> Result<(Vec<Node>, Option<Token>), DecreeError> {
+    let mut nodes = Vec::new();
+    while let Some(token) = iter.next() {
+        match token {
+            Token::Node(node) => nodes.push(node),
+            Token::If(cond, line) => {
+                let (then, end) = parse_block(iter, name)?;
+                let otherwise = match end {
+                    Some(Token::EndIf(_)) => Vec::new(),
+                    Some(Token::Else(_)) => match parse_block(iter, name)? {
+                        (otherwise, Some(Token::EndIf(_))) => otherwise,
+                        _ => return Err(template_error(name, line, "{#if} without {/if}")),
+                    },
+                    _ => return Err(template_error(name, line, "{#if} without {/if}")),
+                };
+                nodes.push(Node::If {
+                    cond,
+                    then,
+                    otherwise,
+                    line,
+                });
+            }
+            Token::Each(list, item, line) => match parse_block(iter, name)? {
+                (body, Some(Token::EndEach(_))) => nodes.push(Node::Each {
+                    list,
+                    item,
+                    body,
+                    line,
+                }),
+                _ => return Err(template_error(name, line, "{#each} without {/each}")),
+            },
+            end => return Ok((nodes, Some(end))),
+        }
+    }
+    Ok((nodes, None))
+}
+
+fn token_line(token: &Token) -> usize {
+    match token {
+        Token::If(_, line)
+        | Token::Else(line)
+        | Token::EndIf(line)
+        | Token::Each(_, _, line)
+        | Token::EndEach(line) => *line,
+        Token::Node(_) => 0,
+    }
+}
+
+fn tokenize(source: &str, name: &str) -> Result<Vec<Token>, DecreeError> {
+    let mut tokens = Vec::new();
+    let mut text = String::new();
+    let mut line = 1;
+    let mut rest = source;
+
+    while let Some(pos) = rest.find('{') {
+        let before = &rest[..pos];
+        line += before.matches('\n').count();
+        text.push_str(before);
+        let after = &rest[pos + 1..];
+
+        // `{{` is a literal brace; `${...}` belongs to the shell
+        if let Some(escaped) = after.strip_prefix('{') {
+            text.push('{');
+            rest = escaped;
+            continue;
+        }
+        if text.ends_with('$') {
+            text.push('{');
+            rest = after;
+            continue;
+        }
+
+        match scan_tag(after, name, line)? {
+            Some((token, len)) => {
+                if !text.is_empty() {
+                    tokens.push(Token::Node(Node::Text(std::mem::take(&mut text))));
+                }
+                line += after[..len].matches('\n').count();
+                tokens.push(token);
+                rest = &after[len + 1..];
+            }
+            None => {
+                text.push('{');
+                rest = after;
+            }
+        }
+    }
+    text.push_str(rest);
+    if !text.is_empty() {
+        tokens.push(Token::Node(Node::Text(text)));
+    }
+    Ok(tokens)
+}
+
+/// Recognize a tag at the start of `s` (just past its `{`), returning the
+/// token and the length of its contents (up to the closing `}`).
+fn scan_tag(s: &str, name: &str, line: usize) -> Result<Option<(Token, usize)>, DecreeError> {
+    // Expansions may contain braces, so match them up
+    for (prefix, is_cmd) in [("cmd:", true), ("file:", false)] {
+        if let Some(body) = s.strip_prefix(prefix) {
+            let len = matching_brace(body).ok_or_else(|| {
+                template_error(name, line, &format!("unclosed {{{prefix}…}}"))
+            })?;
+            let arg = body[..len].trim().to_string();
+            let node = if is_cmd {
+                Node::Cmd { command: arg, line }
+            } else {
+                Node::File { path: arg, line }
+            };
+            return Ok(Some((Token::Node(node), prefix.len() + len)));
+        }
+    }
+
+    let Some(end) = s.find(['}', '\n']).filter(|&i| s.as_bytes()[i] == b'}') else {
+        return Ok(None);
+    };
+    let inner = &s[..end];
+
+    let token = if is_path(inner) {
+        Token::Node(Node::Var {
+            path: inner.to_string(),
+            line,
+        })
+    } else if let Some(cond) = inner.strip_prefix("#if ") {
+        Token::If(parse_cond(cond.trim(), name, line)?, line)
+    } else if inner == "#else" {
+        Token::Else(line)
+    } else if inner == "/if" {
+        Token::EndIf(line)
+    } else if let Some(spec) = inner.strip_prefix("#each ") {
+        let parts: Vec<&str> = spec.split_whitespace().collect();
+        match parts.as_slice() {
+            [list, "as", item] if is_path(list) && is_name(item) => {
+                Token::Each(list.to_string(), item.to_string(), line)
+            }
+            _ => {
+                return Err(template_error(
+                    name,
+                    line,
+                    &format!("expected {{#each list as item}}, got {{{inner}}}"),
+                ))
+            }
+        }
+    } else if inner == "/each" {
+        Token::EndEach(line)
+    } else if let Some(include) = inner.strip_prefix("#include ") {
+        Token::Node(Node::Include {
+            name: include.trim().to_string(),
+            line,
+        })
+    } else {
+        return Ok(None);
+    };
+    Ok(Some((token, end)))
+}
+
+/// Length of `s` up to the `}` that closes an already-open brace.
+fn matching_brace(s: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    for (i, c) in s.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' if depth == 0 => return Some(i),
+            '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+fn parse_cond(cond: &str, name: &str, line: usize) -> Result<Cond, DecreeError> {
+    let invalid = || template_error(name, line, &format!("invalid condition '{cond}'"));
+    for (op, is_eq) in [("==", true), ("!=", false)] {
+        if let Some((lhs, rhs)) = cond.split_once(op) {
+            let lhs = lhs.trim();
+            let rhs = rhs.trim();
+            let literal = rhs
+                .strip_prefix('"')
+                .and_then(|r| r.strip_suffix('"'))
+                .ok_or_else(invalid)?;
+            if !is_path(lhs) {
+                return Err(invalid());
+            }
+            return Ok(if is_eq {
+                Cond::Eq(lhs.to_string(), literal.to_string())
+            } else {
+                Cond::Ne(lhs.to_string(), literal.to_string())
+            });
+        }
+    }
+    match cond.strip_prefix('!') {
+        Some(path) if is_path(path.trim()) => Ok(Cond::Falsy(path.trim().to_string())),
+        None if is_path(cond) => Ok(Cond::Truthy(cond.to_string())),
+        _ => Err(invalid()),
+    }
+}
+
+/// `name` or `name.field.field`, with non-empty segments.
+fn is_path(s: &str) -> bool {
+    !s.is_empty() && s.split('.').all(is_name)
+}
+
+fn is_name(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+// =================================================================
+// Rendering
+// =================================================================
+
+/// Variables in scope: loop variables (innermost last) over the context.
+struct Scope<'a> {
+    ctx: &'a Context,
+    locals: Vec<(String, Value)>,
+}
+
+impl Scope<'_> {
+    /// Look up a path. `Ok(None)` means a known variable lacks the field.
+    fn lookup(&self, path: &str) -> Result<Option<Value>, String> {
+        let mut segments = path.split('.');
+        let first = segments.next().unwrap_or_default();
+        let root = self
+            .locals
+            .iter()
+            .rev()
+            .find(|(name, _)| name == first)
+            .map(|(_, v)| v)
+            .or_else(|| self.ctx.get(first))
+            .ok_or_else(|| format!("unknown variable '{first}'"))?;
+
+        let mut value = root;
+        for segment in segments {
+            match value {
+                Value::Map(map) => match map.get(segment) {
+                    Some(v) => value = v,
+                    None => return Ok(None),
+                },
+                _ => return Err(format!("'{path}': '{segment}' is not a field")),
+            }
+        }
+        Ok(Some(value.clone()))
+    }
+}
+
+fn render_nodes(
+    nodes: &[Node],
+    scope: &mut Scope<'_>,
+    env: &Env<'_>,
+    stack: &mut Vec<String>,
+    out: &mut String,
+) -> Result<(), DecreeError> {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Var { path, line } => {
+                let value = scope
+                    .lookup(path)
+                    .map_err(|e| template_error(env.name, *line, &e))?
+                    .ok_or_else(|| {
+                        template_error(
+                            env.name,
+                            *line,
+                            &format!("'{path}' is not set (guard it with {{#if {path}}})"),
+                        )
+                    })?;
+                let text = value.as_text().ok_or_else(|| {
+                    template_error(env.name, *line, &format!("'{path}' has fields; pick one"))
+                })?;
+                out.push_str(&text);
+            }
+            Node::File { path, line } => {
+                let full = env.project_root.join(path);
+                let content = std::fs::read_to_string(&full).map_err(|e| {
+                    template_error(env.name, *line, &format!("cannot read {path}: {e}"))
+                })?;
+                out.push_str(&content);
+            }
+            Node::Cmd { command, line } => {
+                out.push_str(&run_command(command, env.project_root).map_err(|e| {
+                    template_error(env.name, *line, &e)
+                })?);
+            }
+            Node::Include { name, line } => {
+                if stack.iter().any(|n| n == name) || stack.len() > MAX_INCLUDE_DEPTH {
+                    return Err(template_error(
+                        env.name,
+                        *line,
+                        &format!("include cycle: {} -> {name}", stack.join(" -> ")),
+                    ));
+                }
+                let source = (env.include)(name)
+                    .map_err(|e| template_error(env.name, *line, &e.to_string()))?;
+                let child = Env {
+                    name,
+                    project_root: env.project_root,
+                    include: env.include,
+                };
+                stack.push(name.clone());
+                let rendered = render_nested(&source, scope, &child, stack);
+                stack.pop();
+                out.push_str(&rendered?);
+            }
+            Node::If {
+                cond,
+                then,
+                otherwise,
+                line,
+            } => {
+                let holds = eval_cond(cond, scope).map_err(|e| template_error(env.name, *line, &e))?;
+                let branch = if holds { then } else { otherwise };
+                render_nodes(branch, scope, env, stack, out)?;
+            }
+            Node::Each {
+                list,
+                item,
+                body,
+                line,
+            } => {
+                let items = match scope
+                    .lookup(list)
+                    .map_err(|e| template_error(env.name, *line, &e))?
+                {
+                    Some(Value::List { items, .. }) => items,
+                    None => Vec::new(),
+                    Some(_) => {
+                        return Err(template_error(
+                            env.name,
+                            *line,
+                            &format!("'{list}' is not a list"),
+                        ))
+                    }
+                };
+                let count = items.len();
+                for (i, value) in items.into_iter().enumerate() {
+                    let info = BTreeMap::from([
+                        ("index".to_string(), Value::Text((i + 1).to_string())),
+                        ("first".to_string(), Value::Bool(i == 0)),
+                        ("last".to_string(), Value::Bool(i + 1 == count)),
+                    ]);
+                    scope.locals.push(("loop".to_string(), Value::Map(info)));
+                    scope.locals.push((item.clone(), value));
+                    let result = render_nodes(body, scope, env, stack, out);
+                    scope.locals.truncate(scope.locals.len() - 2);
+                    result?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn eval_cond(cond: &Cond, scope: &Scope<'_>) -> Result<bool, String> {
+    let text = |path: &str| -> Result<String, String> {
+        Ok(scope
+            .lookup(path)?
+            .and_then(|v| v.as_text())
+            .unwrap_or_default())
+    };
+    let truthy = |path: &str| -> Result<bool, String> {
+        Ok(scope.lookup(path)?.is_some_and(|v| v.is_truthy()))
+    };
+    match cond {
+        Cond::Truthy(path) => truthy(path),
+        Cond::Falsy(path) => truthy(path).map(|t| !t),
+        Cond::Eq(path, literal) => Ok(text(path)? == *literal),
+        Cond::Ne(path, literal) => Ok(text(path)? != *literal),
+    }
+}
+
+/// Run a `{cmd:}` expansion and return its output without the trailing newline.
+fn run_command(command: &str, project_root: &Path) -> Result<String, String> {
+    let output = Command::new("bash")
+        .arg("-c")
+        .arg(command)
+        .current_dir(project_root)
+        .output()
+        .map_err(|e| format!("cannot run `{command}`: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!(
+            "`{command}` exited with code {}: {}",
+            output.status.code().unwrap_or(1),
+            stderr.trim()
+        ));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(stdout.trim_end_matches('\n').to_string())
+}
+
+fn template_error(name: &str, line: usize, msg: &str) -> DecreeError {
+    if line == 0 {
+        DecreeError::Template(format!("{name}: {msg}"))
+    } else {
+        DecreeError::Template(format!("{name}:{line}: {msg}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn no_includes(name: &str) -> Result<String, DecreeError> {
+        Err(DecreeError::Template(format!("prompt template not found: {name}")))
+    }
+
+    fn render_in(dir: &Path, source: &str, ctx: &Context) -> Result<String, DecreeError> {
+        let env = Env {
+            name: "test",
+            project_root: dir,
+            include: &no_includes,
+        };
+        render(source, ctx, &env)
+    }
+
+    fn render_str(source: &str, ctx: &Context) -> Result<String, DecreeError> {
+        render_in(Path::new("."), source, ctx)
+    }
+
+    fn sample() -> Context {
+        let item = |name: &str, done: bool| {
+            Value::Map(BTreeMap::from([
+                ("name".to_string(), Value::from(name)),
+                ("done".to_string(), Value::Bool(done)),
+            ]))
+        };
+        let mut ctx = Context::new();
+        ctx.insert("project", "decree");
+        ctx.insert(
+            "migrations",
+            Value::list(vec![item("01-a.md", true), item("02-b.md", false)], "- 01-a.md\n- 02-b.md"),
+        );
+        ctx.insert("empty", Value::list(Vec::new(), "None yet"));
+        ctx
+    }
+
+    #[test]
+    fn test_variables_and_literal_braces() {
+        let ctx = sample();
+        assert_eq!(render_str("Hi {project}!", &ctx).unwrap(), "Hi decree!");
+        assert_eq!(render_str("{migrations}", &ctx).unwrap(), "- 01-a.md\n- 02-b.md");
+        // Shell syntax, JSON, and escapes pass through
+        let shell = "x=\"${project:-}\" || { echo no; }\n{\"a\": 1} {{project}";
+        assert_eq!(
+            render_str(shell, &ctx).unwrap(),
+            "x=\"${project:-}\" || { echo no; }\n{\"a\": 1} {project}"
+        );
+    }
+
+    #[test]
+    fn test_unknown_variable_is_an_error() {
+        let err = render_str("line one\nuses {nope} here", &sample()).unwrap_err();
+        assert_eq!(err.to_string(), "template error: test:2: unknown variable 'nope'");
+        let err = render_str("{project.name}", &sample()).unwrap_err();
+        assert!(err.to_string().contains("is not a field"));
+    }
+
+    #[test]
+    fn test_conditionals() {
+        let ctx = sample();
+        let t = "{#if project}yes{#else}no{/if} {#if empty}full{#else}none{/if} {#if !empty}!{/if}";
+        assert_eq!(render_str(t, &ctx).unwrap(), "yes none !");
+        let t = "{#if project == \"decree\"}match{/if}{#if project != \"decree\"}miss{/if}";
+        assert_eq!(render_str(t, &ctx).unwrap(), "match");
+        assert!(render_str("{#if project}unclosed", &ctx).is_err());
+        assert!(render_str("{/if}", &ctx).is_err());
+        assert!(render_str("{#if nope}x{/if}", &ctx).is_err());
+    }
+
+    #[test]
+    fn test_loops() {
+        let ctx = sample();
+        let t = "{#each migrations as m}{loop.index}. {m.name}{#if m.done} (done){/if}{#if !loop.last}, {/if}{/each}";
+        assert_eq!(render_str(t, &ctx).unwrap(), "1. 01-a.md (done), 2. 02-b.md");
+        assert_eq!(render_str("{#each empty as m}{m.name}{/each}", &ctx).unwrap(), "");
+
+        // Missing fields error when output but are false in conditions
+        let t = "{#each migrations as m}{#if m.routine}{m.routine}{/if}{/each}";
+        assert_eq!(render_str(t, &ctx).unwrap(), "");
+        let err = render_str("{#each migrations as m}{m.routine}{/each}", &ctx).unwrap_err();
+        assert!(err.to_string().contains("'m.routine' is not set"));
+        assert!(render_str("{#each project as p}{/each}", &ctx).is_err());
+    }
+
+    #[test]
+    fn test_includes() {
+        let include = |name: &str| match name {
+            "header" => Ok("# {project}\n".to_string()),
+            "loop" => Ok("{#include loop}".to_string()),
+            "item" => Ok("{loop.index}. {m.name}{#if !loop.last}, {/if}".to_string()),
+            other => no_includes(other),
+        };
+        let env = Env {
+            name: "main",
+            project_root: Path::new("."),
+            include: &include,
+        };
+        let ctx = sample();
+        assert_eq!(render("{#include header}body", &ctx, &env).unwrap(), "# decree\nbody");
+        // A partial included in a loop sees the loop variables
+        assert_eq!(
+            render("{#each migrations as m}{#include item}{/each}", &ctx, &env).unwrap(),
+            "1. 01-a.md, 2. 02-b.md"
+        );
+        assert!(render("{#include item}", &ctx, &env).is_err());
+        let err = render("{#include loop}", &ctx, &env).unwrap_err();
+        assert!(err.to_string().contains("include cycle"));
+        let err = render("{#include missing}", &ctx, &env).unwrap_err();
+        assert!(err.to_string().contains("prompt template not found: missing"));
+    }
+
+    #[test]
+    fn test_file_and_cmd_expansions() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "from file").unwrap();
+        let ctx = Context::new();
+        assert_eq!(render_in(dir.path(), "{file:notes.txt}", &ctx).unwrap(), "from file");
+        assert_eq!(
+            render_in(dir.path(), "{cmd:cat notes.txt | { read x; echo \"$x!\"; }}", &ctx).unwrap(),
+            "from file!"
+        );
+        assert!(render_in(dir.path(), "{file:missing.txt}", &ctx).is_err());
+        let err = render_in(dir.path(), "{cmd:exit 3}", &ctx).unwrap_err();
+        assert!(err.to_string().contains("exited with code 3"));
+    }
+
+    #[test]
+    fn test_config_vars() {
+        let mut config = AppConfig::default();
+        config.prompt_vars.insert("team".into(), "infra".into());
+        config.prompt_vars.insert("project".into(), "ignored".into());
+        let ctx = sample().with_config_vars(&config);
+        assert_eq!(render_str("{team}/{project}", &ctx).unwrap(), "infra/decree");
+    }
+}
@@ -20,17 +20,17 @@ seq="${seq:-}"
 
 # Pre-check: verify AI tool is available
 if [ "${DECREE_PRE_CHECK:-}" = "true" ]; then
-    command -v {ai_name} >/dev/null 2>&1 || { echo "{ai_name} not found" >&2; exit 1; }
+    decree ai --check || { echo "AI backend not available (see ai in .decree/config.yml)" >&2; exit 1; }
     exit 0
 fi
 
 # Implementation
-{ai_invoke} "Read ${message_file} and implement all requirements.
+decree ai "Read ${message_file} and implement all requirements.
 Previous attempt logs (if any) are in ${message_dir} for context.
 Follow best practices: clean code, proper error handling, and tests
 where appropriate."
 
 # Verification
-{ai_invoke} "Read ${message_file}. Verify that all requirements and
+decree ai "Read ${message_file}. Verify that all requirements and
 acceptance criteria are met. Run any tests. Report what passes and what
 fails. Exit 0 if everything passes, exit 1 if anything fails."
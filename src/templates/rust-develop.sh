@@ -19,13 +19,13 @@ seq="${seq:-}"
 
 # Pre-check: verify AI tool and cargo are available
 if [ "${DECREE_PRE_CHECK:-}" = "true" ]; then
-    command -v {ai_name} >/dev/null 2>&1 || { echo "{ai_name} not found" >&2; exit 1; }
+    decree ai --check || { echo "AI backend not available (see ai in .decree/config.yml)" >&2; exit 1; }
     command -v cargo >/dev/null 2>&1 || { echo "cargo not found" >&2; exit 1; }
     exit 0
 fi
 
 # Step 1: Implementation
-{ai_invoke} "You are a senior Rust engineer. Read ${message_file} and
+decree ai "You are a senior Rust engineer. Read ${message_file} and
 implement all requirements with proper error handling and tests.
 Previous attempt logs (if any) are in ${message_dir} for context."
 
@@ -36,6 +36,6 @@ echo "=== Running tests ==="
 cargo test 2>&1 | tee "${message_dir}/test-output.log" || true
 
 # Step 3: QA
-{ai_invoke} "Read ${message_file}, build output at ${message_dir}/build.log,
+decree ai "Read ${message_file}, build output at ${message_dir}/build.log,
 test output at ${message_dir}/test-output.log. Fix any failures. Run cargo
 build --release and cargo test again. Exit 0 only if everything passes."
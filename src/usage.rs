@@ -0,0 +1,572 @@
+use crate::atomic;
+use crate::config::{self, AppConfig};
+use crate::error::DecreeError;
+use crate::message::InboxMessage;
+use chrono::{DateTime, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// File in a run directory that usage records are appended to, one JSON
+/// object per line.
+pub const USAGE_FILE: &str = "usage.jsonl";
+
+/// File in a run directory holding the run's metadata and usage totals.
+pub const RUN_SUMMARY_FILE: &str = "run.json";
+
+/// File directly in `runs/` holding the usage of runs pruned by `decree gc`,
+/// so totals and budgets do not forget spend when run directories go.
+pub const HISTORY_FILE: &str = "usage-history.jsonl";
+
+/// One AI invocation, as appended to `usage.jsonl`.
+///
+/// Every field but `backend` is optional so routines can record whatever
+/// their tool reports.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageRecord {
+    /// When the call finished (RFC 3339). Defaults to the file's mtime.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recorded_at: Option<String>,
+    pub backend: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_tokens: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_tokens: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_secs: Option<f64>,
+    /// Token counts and cost were estimated from the prompt and output size.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub estimated: bool,
+}
+
+fn is_false(v: &bool) -> bool {
+    !v
+}
+
+/// Summed usage over some set of records.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageTotals {
+    pub calls: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost: f64,
+    pub duration_secs: f64,
+}
+
+impl UsageTotals {
+    /// Add one record to the totals.
+    pub fn add(&mut self, record: &UsageRecord) {
+        self.calls += 1;
+        self.input_tokens += record.input_tokens.unwrap_or(0);
+        self.output_tokens += record.output_tokens.unwrap_or(0);
+        self.cost += record.cost.unwrap_or(0.0);
+        self.duration_secs += record.duration_secs.unwrap_or(0.0);
+    }
+}
+
+/// What `run.json` in a run directory records.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunSummary {
+    pub message_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub migration: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub routine: Option<String>,
+    pub updated_at: String,
+    pub usage: UsageTotals,
+}
+
+/// Append a usage record to a run directory's `usage.jsonl`.
+pub fn append(run_dir: &Path, record: &UsageRecord) -> Result<(), DecreeError> {
+    let line = serde_json::to_string(record)
+        .map_err(|e| DecreeError::Other(format!("failed to serialize usage record: {e}")))?;
+    atomic::append_line(&run_dir.join(USAGE_FILE), &line)?;
+    Ok(())
+}
+
+/// Read a run directory's usage records, skipping lines that don't parse.
+///
+/// Records without `recorded_at` get the file's modification time.
+pub fn read_records(run_dir: &Path) -> Vec<UsageRecord> {
+    let path = run_dir.join(USAGE_FILE);
+    let Ok(content) = std::fs::read_to_string(&path) else {
+        return Vec::new();
+    };
+    let mtime = std::fs::metadata(&path)
+        .and_then(|m| m.modified())
+        .ok()
+        .map(|t| DateTime::<Local>::from(t).to_rfc3339());
+    content
+        .lines()
+        .filter_map(|line| serde_json::from_str::<UsageRecord>(line).ok())
+        .map(|mut record| {
+            if record.recorded_at.is_none() {
+                record.recorded_at = mtime.clone();
+            }
+            record
+        })
+        .collect()
+}
+
+/// Total a run's usage records into its `run.json`.
+///
+/// Called after each routine attempt, so the summary covers every attempt.
+pub fn write_summary(run_dir: &Path, msg: &InboxMessage) -> Result<RunSummary, DecreeError> {
+    let mut usage = UsageTotals::default();
+    for record in read_records(run_dir) {
+        usage.add(&record);
+    }
+    let summary = RunSummary {
+        message_id: msg.id.clone().unwrap_or_default(),
+        chain: msg.chain.clone(),
+        migration: msg.migration.clone(),
+        routine: msg.routine.clone(),
+        updated_at: Local::now().to_rfc3339(),
+        usage,
+    };
+    let json = serde_json::to_string_pretty(&summary)
+        .map_err(|e| DecreeError::Other(format!("failed to serialize run summary: {e}")))?;
+    atomic::write(&run_dir.join(RUN_SUMMARY_FILE), json + "\n")?;
+    Ok(summary)
+}
+
+/// A usage record together with the run it belongs to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunUsage {
+    pub message_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain: Option<String>,
+    /// The migration that started the chain, for follow-ups too.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub migration: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub routine: Option<String>,
+    #[serde(flatten)]
+    pub record: UsageRecord,
+}
+
+impl RunUsage {
+    /// When the record was made, in local time.
+    pub fn recorded_at(&self) -> Option<DateTime<Local>> {
+        self.record
+            .recorded_at
+            .as_deref()
+            .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+            .map(|t| t.with_timezone(&Local))
+    }
+}
+
+/// Path of the usage history kept for pruned runs.
+pub fn history_path(project_root: &Path) -> PathBuf {
+    project_root
+        .join(config::DECREE_DIR)
+        .join(config::RUNS_DIR)
+        .join(HISTORY_FILE)
+}
+
+/// Every usage record under `.decree/runs/`, with its run's details.
+///
+/// Run details come from `run.json`, or `message.md` for a run that has
+/// not been summarized yet. Follow-up messages inherit the migration of
+/// their chain. Records of pruned runs come from `runs/usage-history.jsonl`.
+pub fn all_usage(project_root: &Path) -> Result<Vec<RunUsage>, DecreeError> {
+    collect_usage(project_root, true)
+}
+
+fn collect_usage(project_root: &Path, with_history: bool) -> Result<Vec<RunUsage>, DecreeError> {
+    let runs_dir = project_root.join(config::DECREE_DIR).join(config::RUNS_DIR);
+    let Ok(entries) = std::fs::read_dir(&runs_dir) else {
+        return Ok(Vec::new());
+    };
+    let mut run_dirs: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_dir())
+        .collect();
+    run_dirs.sort();
+
+    let history = if with_history {
+        std::fs::read_to_string(runs_dir.join(HISTORY_FILE)).unwrap_or_default()
+    } else {
+        String::new()
+    };
+    let mut usage: Vec<RunUsage> = history
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    let mut chain_migrations: HashMap<String, String> = usage
+        .iter()
+        .filter_map(|u| Some((u.chain.clone()?, u.migration.clone()?)))
+        .collect();
+    for dir in run_dirs {
+        let (chain, migration, routine) = run_details(&dir);
+        if let (Some(chain), Some(migration)) = (&chain, &migration) {
+            chain_migrations.insert(chain.clone(), migration.clone());
+        }
+        let message_id = dir
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        for record in read_records(&dir) {
+            usage.push(RunUsage {
+                message_id: message_id.clone(),
+                chain: chain.clone(),
+                migration: migration.clone(),
+                routine: routine.clone(),
+                record,
+            });
+        }
+    }
+
+    for run in &mut usage {
+        if run.migration.is_none() {
+            run.migration = run
+                .chain
+                .as_ref()
+                .and_then(|c| chain_migrations.get(c))
+                .cloned();
+        }
+    }
+    Ok(usage)
+}
+
+/// Copy the usage of `runs` into `runs/usage-history.jsonl` before they are pruned.
+///
+/// Records are written with their run's details resolved (follow-ups get
+/// their chain's migration), since the run directories will be gone.
+pub fn preserve(project_root: &Path, runs: &[String]) -> Result<(), DecreeError> {
+    let mut lines = String::new();
+    for run in collect_usage(project_root, false)? {
+        if runs.contains(&run.message_id) {
+            let line = serde_json::to_string(&run)
+                .map_err(|e| DecreeError::Other(format!("failed to serialize usage record: {e}")))?;
+            lines.push_str(&line);
+            lines.push('\n');
+        }
+    }
+    if lines.is_empty() {
+        return Ok(());
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(history_path(project_root))?;
+    file.write_all(lines.as_bytes())?;
+    Ok(())
+}
+
+/// Chain, migration, and routine of a run directory.
+fn run_details(dir: &Path) -> (Option<String>, Option<String>, Option<String>) {
+    if let Some(summary) = std::fs::read_to_string(dir.join(RUN_SUMMARY_FILE))
+        .ok()
+        .and_then(|c| serde_json::from_str::<RunSummary>(&c).ok())
+    {
+        return (summary.chain, summary.migration, summary.routine);
+    }
+    std::fs::read_to_string(dir.join("message.md"))
+        .ok()
+        .and_then(|c| InboxMessage::parse("message.md", &c).ok())
+        .map(|m| (m.chain, m.migration, m.routine))
+        .unwrap_or_default()
+}
+
+/// How `decree usage` groups its totals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupBy {
+    Routine,
+    Migration,
+    Chain,
+    Backend,
+    Day,
+}
+
+impl GroupBy {
+    /// Parse a `--by` value.
+    pub fn parse(s: &str) -> Result<Self, DecreeError> {
+        match s {
+            "routine" => Ok(Self::Routine),
+            "migration" => Ok(Self::Migration),
+            "chain" => Ok(Self::Chain),
+            "backend" => Ok(Self::Backend),
+            "day" => Ok(Self::Day),
+            other => Err(DecreeError::Other(format!(
+                "unknown grouping '{other}' (expected routine, migration, chain, backend, or day)"
+            ))),
+        }
+    }
+
+    /// The group a record falls in.
+    pub fn key(self, run: &RunUsage) -> String {
+        let key = match self {
+            Self::Routine => run.routine.clone(),
+            Self::Migration => run.migration.clone(),
+            Self::Chain => run.chain.clone(),
+            Self::Backend => Some(run.record.backend.clone()),
+            Self::Day => run.recorded_at().map(|t| t.format("%Y-%m-%d").to_string()),
+        };
+        key.unwrap_or_else(|| "(none)".to_string())
+    }
+}
+
+/// Total usage per group, sorted by group name.
+pub fn group<'a>(
+    runs: impl IntoIterator<Item = &'a RunUsage>,
+    by: GroupBy,
+) -> BTreeMap<String, UsageTotals> {
+    let mut groups: BTreeMap<String, UsageTotals> = BTreeMap::new();
+    for run in runs {
+        groups.entry(by.key(run)).or_default().add(&run.record);
+    }
+    groups
+}
+
+/// Parse a `--since` value: a duration back from now (`30m`, `12h`, `7d`,
+/// `2w`) or a date (`2026-01-31`, from local midnight).
+pub fn parse_since(s: &str, now: DateTime<Local>) -> Result<DateTime<Local>, DecreeError> {
+    parse_time("--since", s, now)
+}
+
+/// Parse a point in time given with `flag`, in the same forms as `--since`.
+pub fn parse_time(flag: &str, s: &str, now: DateTime<Local>) -> Result<DateTime<Local>, DecreeError> {
+    if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return date
+            .and_hms_opt(0, 0, 0)
+            .and_then(|t| t.and_local_timezone(Local).earliest())
+            .ok_or_else(|| DecreeError::Other(format!("invalid date '{s}'")));
+    }
+
+    let invalid = || {
+        DecreeError::Other(format!(
+            "invalid {flag} '{s}' (expected e.g. 12h, 7d, 2w, or YYYY-MM-DD)"
+        ))
+    };
+    let split = s.find(|c: char| !c.is_ascii_digit()).ok_or_else(invalid)?;
+    let amount: i64 = s[..split].parse().map_err(|_| invalid())?;
+    let delta = match &s[split..] {
+        "m" => chrono::TimeDelta::minutes(amount),
+        "h" => chrono::TimeDelta::hours(amount),
+        "d" => chrono::TimeDelta::days(amount),
+        "w" => chrono::TimeDelta::weeks(amount),
+        _ => return Err(invalid()),
+    };
+    Ok(now - delta)
+}
+
+/// Check the `budget` caps before running a message.
+///
+/// Returns why processing must stop, if today's spend or the spend on the
+/// message's migration has reached its cap.
+pub fn budget_exceeded(
+    project_root: &Path,
+    config: &AppConfig,
+    msg: &InboxMessage,
+) -> Result<Option<String>, DecreeError> {
+    let budget = &config.budget;
+    if budget.daily.is_none() && budget.per_migration.is_none() {
+        return Ok(None);
+    }
+    let usage = all_usage(project_root)?;
+
+    if let Some(cap) = budget.daily {
+        let today = Local::now().date_naive();
+        let spent: f64 = usage
+            .iter()
+            .filter(|u| u.recorded_at().is_some_and(|t| t.date_naive() == today))
+            .filter_map(|u| u.record.cost)
+            .sum();
+        if spent >= cap {
+            return Ok(Some(format!(
+                "daily budget reached ({} of {})",
+                format_cost(spent),
+                format_cost(cap)
+            )));
+        }
+    }
+
+    if let Some(cap) = budget.per_migration {
+        let migration = msg.migration.clone().or_else(|| {
+            let chain = msg.chain.as_ref()?;
+            usage
+                .iter()
+                .find(|u| u.chain.as_ref() == Some(chain))
+                .and_then(|u| u.migration.clone())
+        });
+        if let Some(migration) = migration {
+            let spent: f64 = usage
+                .iter()
+                .filter(|u| u.migration.as_ref() == Some(&migration))
+                .filter_map(|u| u.record.cost)
+                .sum();
+            if spent >= cap {
+                return Ok(Some(format!(
+                    "budget for {migration} reached ({} of {})",
+                    format_cost(spent),
+                    format_cost(cap)
+                )));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// Rough token count for text whose real count is unknown (~4 bytes/token).
+pub fn estimate_tokens(bytes: u64) -> u64 {
+    bytes.div_ceil(4)
+}
+
+/// Format a cost for display.
+pub fn format_cost(cost: f64) -> String {
+    format!("${cost:.2}")
+}
+
+/// Format a token count compactly (`950`, `12.3k`, `4.1M`).
+pub fn format_tokens(tokens: u64) -> String {
+    match tokens {
+        0..=999 => tokens.to_string(),
+        1_000..=999_999 => format!("{:.1}k", tokens as f64 / 1_000.0),
+        _ => format!("{:.1}M", tokens as f64 / 1_000_000.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn run_dir(dir: &TempDir, id: &str, frontmatter: &str) -> PathBuf {
+        let run = dir.path().join(".decree/runs").join(id);
+        std::fs::create_dir_all(&run).unwrap();
+        std::fs::write(run.join("message.md"), format!("---\nid: {id}\n{frontmatter}\n---\n")).unwrap();
+        run
+    }
+
+    fn record(backend: &str, cost: f64, at: &str) -> UsageRecord {
+        UsageRecord {
+            recorded_at: Some(at.to_string()),
+            backend: backend.to_string(),
+            input_tokens: Some(1000),
+            output_tokens: Some(200),
+            cost: Some(cost),
+            duration_secs: Some(2.0),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_append_read_and_summarize() {
+        let dir = TempDir::new().unwrap();
+        let run = run_dir(&dir, "D0001-1200-a-0", "chain: D0001-1200-a\nroutine: develop");
+        let now = Local::now().to_rfc3339();
+        append(&run, &record("claude", 0.5, &now)).unwrap();
+        append(&run, &record("opencode", 0.25, &now)).unwrap();
+        // Hand-written lines need only a backend; junk lines are skipped
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(run.join(USAGE_FILE))
+            .unwrap()
+            .write_all(b"{\"backend\":\"copilot\",\"cost\":1}\nnot json\n")
+            .unwrap();
+
+        let records = read_records(&run);
+        assert_eq!(records.len(), 3);
+        assert!(records[2].recorded_at.is_some());
+
+        let msg = InboxMessage::parse(
+            "m.md",
+            "---\nid: D0001-1200-a-0\nchain: D0001-1200-a\nroutine: develop\nmigration: 01-a.md\n---\n",
+        )
+        .unwrap();
+        let summary = write_summary(&run, &msg).unwrap();
+        assert_eq!(summary.usage.calls, 3);
+        assert_eq!(summary.usage.input_tokens, 2000);
+        assert!((summary.usage.cost - 1.75).abs() < 1e-9);
+        let saved: RunSummary =
+            serde_json::from_str(&std::fs::read_to_string(run.join(RUN_SUMMARY_FILE)).unwrap())
+                .unwrap();
+        assert_eq!(saved, summary);
+    }
+
+    #[test]
+    fn test_grouping_and_chain_migration() {
+        let dir = TempDir::new().unwrap();
+        let now = Local::now().to_rfc3339();
+        let first = run_dir(
+            &dir,
+            "D0001-1200-a-0",
+            "chain: D0001-1200-a\nroutine: develop\nmigration: 01-a.md",
+        );
+        let follow_up = run_dir(&dir, "D0001-1200-a-1", "chain: D0001-1200-a\nroutine: review");
+        append(&first, &record("claude", 1.0, &now)).unwrap();
+        append(&follow_up, &record("claude", 2.0, &now)).unwrap();
+
+        let usage = all_usage(dir.path()).unwrap();
+        assert_eq!(usage.len(), 2);
+        assert!(usage.iter().all(|u| u.migration.as_deref() == Some("01-a.md")));
+
+        let by_migration = group(&usage, GroupBy::Migration);
+        assert!((by_migration["01-a.md"].cost - 3.0).abs() < 1e-9);
+        let by_routine = group(&usage, GroupBy::Routine);
+        assert_eq!(by_routine.keys().collect::<Vec<_>>(), ["develop", "review"]);
+        assert_eq!(group(&usage, GroupBy::Backend)["claude"].calls, 2);
+        assert!(GroupBy::parse("nope").is_err());
+    }
+
+    #[test]
+    fn test_budget_exceeded() {
+        let dir = TempDir::new().unwrap();
+        let now = Local::now().to_rfc3339();
+        let run = run_dir(&dir, "D0001-1200-a-0", "chain: D0001-1200-a\nmigration: 01-a.md");
+        append(&run, &record("claude", 3.0, &now)).unwrap();
+        append(&run, &record("claude", 4.0, "2020-01-01T00:00:00+00:00")).unwrap();
+
+        let follow_up =
+            InboxMessage::parse("f.md", "---\nchain: D0001-1200-a\nseq: 1\n---\n").unwrap();
+        let other = InboxMessage::parse("o.md", "---\nmigration: 02-b.md\n---\n").unwrap();
+
+        let mut config = AppConfig::default();
+        assert_eq!(budget_exceeded(dir.path(), &config, &follow_up).unwrap(), None);
+
+        // Only today's spend counts toward the daily cap
+        config.budget.daily = Some(5.0);
+        assert_eq!(budget_exceeded(dir.path(), &config, &other).unwrap(), None);
+        config.budget.daily = Some(3.0);
+        let reason = budget_exceeded(dir.path(), &config, &other).unwrap().unwrap();
+        assert!(reason.contains("daily budget reached ($3.00 of $3.00)"));
+
+        // The per-migration cap counts all time, and follow-ups by chain
+        config.budget.daily = None;
+        config.budget.per_migration = Some(6.0);
+        let reason = budget_exceeded(dir.path(), &config, &follow_up).unwrap().unwrap();
+        assert!(reason.contains("budget for 01-a.md reached ($7.00 of $6.00)"));
+        assert_eq!(budget_exceeded(dir.path(), &config, &other).unwrap(), None);
+    }
+
+    #[test]
+    fn test_parse_since() {
+        let now = Local::now();
+        assert_eq!(parse_since("7d", now).unwrap(), now - chrono::TimeDelta::days(7));
+        assert_eq!(parse_since("12h", now).unwrap(), now - chrono::TimeDelta::hours(12));
+        assert_eq!(parse_since("2w", now).unwrap(), now - chrono::TimeDelta::weeks(2));
+        let date = parse_since("2026-01-31", now).unwrap();
+        assert_eq!(date.format("%Y-%m-%d %H:%M").to_string(), "2026-01-31 00:00");
+        assert!(parse_since("7 days", now).is_err());
+        assert!(parse_since("d", now).is_err());
+    }
+
+    #[test]
+    fn test_formatting() {
+        assert_eq!(format_tokens(950), "950");
+        assert_eq!(format_tokens(12_345), "12.3k");
+        assert_eq!(format_tokens(4_100_000), "4.1M");
+        assert_eq!(format_cost(0.4249), "$0.42");
+        assert_eq!(estimate_tokens(9), 3);
+    }
+}
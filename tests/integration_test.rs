@@ -6,7 +6,7 @@ use tempfile::TempDir;
 
 /// Helper: run decree in a temp directory.
 fn decree_cmd(dir: &TempDir) -> Command {
-    let mut cmd = Command::from(cargo_bin_cmd!("decree"));
+    let mut cmd = cargo_bin_cmd!("decree");
     cmd.current_dir(dir.path());
     // Force non-TTY behavior + no color for predictable output
     cmd.env("NO_COLOR", "1");
@@ -62,7 +62,8 @@ fn test_init_config_has_required_fields() {
 
     let config = fs::read_to_string(dir.path().join(".decree/config.yml")).unwrap();
 
-    assert!(config.contains("ai_router:"));
+    assert!(config.contains("ai:\n  default: "));
+    assert!(config.contains("  profiles:\n"));
     assert!(config.contains("ai_interactive:"));
     assert!(config.contains("max_retries: 3"));
     assert!(config.contains("max_depth: 10"));
@@ -83,13 +84,12 @@ fn test_init_config_has_commented_alternatives() {
 
     let config = fs::read_to_string(dir.path().join(".decree/config.yml")).unwrap();
 
-    // At least two of the three backends should appear (one uncommented, others commented)
+    // All three backends should appear (one uncommented, others commented)
     let ai_lines: Vec<&str> = config
         .lines()
-        .filter(|l| l.contains("ai_router"))
+        .filter(|l| l.trim_start().starts_with("# default:"))
         .collect();
-    // Should have one active + at least one commented alternative
-    assert!(ai_lines.len() >= 2, "Expected multiple ai_router entries, got: {ai_lines:?}");
+    assert_eq!(ai_lines.len(), 2, "Expected two commented ai defaults, got: {ai_lines:?}");
 }
 
 #[test]
@@ -145,7 +145,7 @@ fn test_init_gitignore_content() {
 }
 
 #[test]
-fn test_init_routines_use_ai_cmd_placeholder() {
+fn test_init_routines_call_decree_ai() {
     let dir = TempDir::new().unwrap();
 
     decree_cmd(&dir).arg("init").assert().success();
@@ -154,15 +154,156 @@ fn test_init_routines_use_ai_cmd_placeholder() {
     let rust_develop =
         fs::read_to_string(dir.path().join(".decree/routines/rust-develop.sh")).unwrap();
 
-    // {AI_CMD} should have been replaced with a real command name
-    assert!(
-        !develop.contains("{AI_CMD}"),
-        "develop.sh should not contain raw {{AI_CMD}} placeholder"
-    );
-    assert!(
-        !rust_develop.contains("{AI_CMD}"),
-        "rust-develop.sh should not contain raw {{AI_CMD}} placeholder"
+    // Routines go through `decree ai` instead of a hard-coded backend
+    for routine in [&develop, &rust_develop] {
+        assert!(routine.contains("decree ai --check"));
+        assert!(routine.contains("decree ai \""));
+        assert!(!routine.contains("{ai_"), "routine should not contain raw placeholders");
+    }
+}
+
+#[test]
+fn test_ai_runs_configured_profile() {
+    let dir = TempDir::new().unwrap();
+    decree_cmd(&dir).arg("init").assert().success();
+
+    let config_path = dir.path().join(".decree/config.yml");
+    let config = fs::read_to_string(&config_path).unwrap().replace(
+        "  profiles:\n",
+        "  profiles:\n    echo:\n      command: echo\n      args: [\"said:\"]\n    stdin:\n      command: cat\n      prompt: stdin\n    missing:\n      command: decree-no-such-ai\n",
     );
+    fs::write(&config_path, config).unwrap();
+
+    decree_cmd(&dir)
+        .args(["ai", "--profile", "echo", "hello"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("said: hello"));
+
+    decree_cmd(&dir)
+        .args(["ai", "--profile", "stdin"])
+        .write_stdin("from stdin")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("from stdin"));
+
+    decree_cmd(&dir)
+        .args(["ai", "--profile", "echo", "--check"])
+        .assert()
+        .success();
+
+    decree_cmd(&dir)
+        .args(["ai", "--profile", "missing", "--check"])
+        .assert()
+        .failure();
+
+    decree_cmd(&dir)
+        .args(["ai", "--profile", "nope", "hi"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("unknown AI profile 'nope'"));
+}
+
+#[test]
+fn test_process_picks_ai_profile_per_message_and_routine() {
+    let dir = TempDir::new().unwrap();
+    decree_cmd(&dir).arg("init").assert().success();
+
+    let config_path = dir.path().join(".decree/config.yml");
+    let config = fs::read_to_string(&config_path)
+        .unwrap()
+        .replace("ai:\n", "ai:\n  fallback: [works]\n")
+        .replace(
+            "  profiles:\n",
+            "  profiles:\n    missing:\n      command: decree-no-such-ai\n    works:\n      command: printf\n      args: [\"[works] %s\\\\n\"]\n    strong:\n      command: printf\n      args: [\"[strong] %s\\\\n\"]\n",
+        )
+        .replace("routines:\n", "routines:\n  reviewer:\n    enabled: true\n    ai: strong\n");
+    let config = config.replacen("  default: ", "  default: missing\n  # was: ", 1);
+    fs::write(&config_path, config).unwrap();
+
+    let script = "#!/usr/bin/env bash\n# Reviewer\n\nif [ \"${DECREE_PRE_CHECK:-}\" = \"true\" ]; then\n    exit 0\nfi\n\ndecree ai \"$tag via $DECREE_AI\"\n";
+    fs::write(dir.path().join(".decree/routines/reviewer.sh"), script).unwrap();
+    fs::write(dir.path().join(".decree/routines/plain.sh"), script).unwrap();
+
+    let inbox = dir.path().join(".decree/inbox");
+    fs::write(inbox.join("a.md"), "---\nroutine: reviewer\ntag: a\n---\n").unwrap();
+    fs::write(inbox.join("b.md"), "---\nroutine: reviewer\ntag: b\nai: works\n---\n").unwrap();
+    fs::write(inbox.join("c.md"), "---\nroutine: plain\ntag: c\n---\n").unwrap();
+
+    decree_cmd(&dir)
+        .arg("process")
+        .assert()
+        .success()
+        // Registry entry picks the profile
+        .stdout(predicate::str::contains("[strong] a via strong"))
+        // Message frontmatter wins over the registry entry
+        .stdout(predicate::str::contains("[works] b via works"))
+        // The default profile is not installed, so the fallback is used
+        .stdout(predicate::str::contains("[works] c via works"));
+}
+
+#[test]
+fn test_usage_is_recorded_reported_and_capped() {
+    let dir = TempDir::new().unwrap();
+    decree_cmd(&dir).arg("init").assert().success();
+
+    let config_path = dir.path().join(".decree/config.yml");
+    let config = fs::read_to_string(&config_path)
+        .unwrap()
+        .replace("ai:\n  default: ", "ai:\n  default: priced\n  # was: ")
+        .replace(
+            "  profiles:\n",
+            "  profiles:\n    priced:\n      command: echo\n      pricing: { input: 1000000, output: 0 }\n",
+        );
+    fs::write(&config_path, &config).unwrap();
+
+    // One call through `decree ai`, one record written by hand
+    fs::write(
+        dir.path().join(".decree/routines/spender.sh"),
+        "#!/usr/bin/env bash\n# Spender\n\nif [ \"${DECREE_PRE_CHECK:-}\" = \"true\" ]; then\n    exit 0\nfi\n\ndecree ai \"abcdefgh\"\necho '{\"backend\":\"manual\",\"input_tokens\":10,\"output_tokens\":5,\"cost\":0.5}' >> \"$message_dir/usage.jsonl\"\n",
+    )
+    .unwrap();
+    fs::write(dir.path().join(".decree/inbox/a.md"), "---\nroutine: spender\n---\n").unwrap();
+    decree_cmd(&dir).arg("process").assert().success();
+
+    let runs: Vec<_> = fs::read_dir(dir.path().join(".decree/runs"))
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .collect();
+    assert_eq!(runs.len(), 1);
+    let summary = fs::read_to_string(runs[0].join("run.json")).unwrap();
+    assert!(summary.contains("\"calls\": 2"), "{summary}");
+    assert!(summary.contains("\"routine\": \"spender\""));
+
+    // 8 bytes of prompt ~ 2 tokens at 1 per token, plus the manual 0.5
+    decree_cmd(&dir)
+        .args(["usage", "--by", "backend", "--since", "1d"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("priced"))
+        .stdout(predicate::str::contains("manual"))
+        .stdout(predicate::str::contains("$2.50"));
+
+    decree_cmd(&dir)
+        .args(["usage", "--by", "nope"])
+        .assert()
+        .failure();
+
+    // Over the daily cap: the next message waits in the inbox
+    fs::write(&config_path, config + "budget:\n  daily: 2\n").unwrap();
+    fs::write(dir.path().join(".decree/inbox/b.md"), "---\nroutine: spender\n---\n").unwrap();
+    decree_cmd(&dir)
+        .arg("process")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("daily budget reached ($2.50 of $2.00)"));
+    assert!(dir.path().join(".decree/inbox/b.md").exists());
+
+    decree_cmd(&dir)
+        .arg("usage")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("processing is paused"));
 }
 
 #[test]
@@ -430,11 +571,231 @@ fn test_log_multiple_attempts() {
         .stdout(predicate::str::contains("Attempt 2"));
 }
 
+#[test]
+fn test_log_filters_and_grep() {
+    let dir = TempDir::new().unwrap();
+    decree_cmd(&dir).arg("init").assert().success();
+
+    let runs = dir.path().join(".decree/runs");
+    let add_run = |id: &str, routine: &str, migration: &str, start: &str, logs: &[(&str, &str)]| {
+        let run_dir = runs.join(id);
+        fs::create_dir_all(&run_dir).unwrap();
+        fs::write(
+            run_dir.join("message.md"),
+            format!("---\nid: {id}\nroutine: {routine}\nmigration: {migration}\n---\nbody\n"),
+        )
+        .unwrap();
+        for (name, body) in logs {
+            fs::write(run_dir.join(name), format!("[decree] start {start}\n{body}")).unwrap();
+        }
+    };
+    add_run(
+        "D0001-0900-build-0",
+        "develop",
+        "01-build.md",
+        "2026-01-05T09:00:00",
+        &[
+            ("routine.log", "compiling\nerror[E0308]: mismatched types\nfailed\n"),
+            ("routine-2.log", "compiling\nok\nline a\nline b\n"),
+        ],
+    );
+    add_run(
+        "D0002-1000-docs-0",
+        "docs",
+        "02-docs.md",
+        "2026-02-10T10:00:00",
+        &[("routine.log", "writing docs\nerror: broken link\n")],
+    );
+    fs::write(
+        dir.path().join(".decree/inbox/dead/D0002-1000-docs-0.md"),
+        "---\nid: D0002-1000-docs-0\n---\nbody\n",
+    )
+    .unwrap();
+
+    decree_cmd(&dir)
+        .args(["log", "--grep", "error", "-C", "1"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("=== D0001-0900-build-0 (routine.log) ==="))
+        .stdout(predicate::str::contains("2-compiling"))
+        .stdout(predicate::str::contains("3:error[E0308]: mismatched types"))
+        .stdout(predicate::str::contains("4-failed"))
+        .stdout(predicate::str::contains("=== D0002-1000-docs-0 (routine.log) ==="))
+        .stdout(predicate::str::contains("routine-2.log").not())
+        .stdout(predicate::str::contains("2 matching lines in 2 runs"));
+
+    decree_cmd(&dir)
+        .args(["log", "--grep", "error", "--routine", "develop", "-C", "0"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("D0002").not())
+        .stdout(predicate::str::contains("1 matching line in 1 run"));
+
+    decree_cmd(&dir)
+        .args(["log", "D0001", "--attempt", "2", "--tail", "2"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Attempt 2 (routine-2.log)"))
+        .stdout(predicate::str::contains("line a\nline b\n"))
+        .stdout(predicate::str::contains("compiling").not())
+        .stdout(predicate::str::contains("mismatched").not());
+
+    decree_cmd(&dir)
+        .args(["log", "--disposition", "dead"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("broken link"))
+        .stdout(predicate::str::contains("D0001").not());
+
+    decree_cmd(&dir)
+        .args(["log", "--since", "2026-02-01", "--until", "2026-03-01", "--migration", "02-docs"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("=== D0002-1000-docs-0 ==="));
+
+    decree_cmd(&dir)
+        .args(["log", "--since", "2026-03-01"])
+        .assert()
+        .success()
+        .stdout("No runs match.\n");
+
+    decree_cmd(&dir)
+        .args(["log", "--disposition", "finished"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid --disposition"));
+}
+
+#[test]
+fn test_log_follow_streams_until_done() {
+    let dir = TempDir::new().unwrap();
+    decree_cmd(&dir).arg("init").assert().success();
+
+    let script = dir.path().join(".decree/routines/slow.sh");
+    fs::write(
+        &script,
+        "#!/usr/bin/env bash\n# Slow\nset -euo pipefail\n\nif [ \"${DECREE_PRE_CHECK:-}\" = \"true\" ]; then\n    exit 0\nfi\n\nif [ ! -f tried ]; then touch tried; echo \"first try\"; sleep 1; exit 1; fi\necho \"second try\"\nsleep 1\necho \"all done\"\n",
+    )
+    .unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    decree_cmd(&dir).arg("routine-sync").assert().success();
+    fs::write(
+        dir.path().join(".decree/migrations/01-slow.md"),
+        "---\nroutine: slow\n---\nTake your time.\n",
+    )
+    .unwrap();
+
+    decree_cmd(&dir)
+        .args(["log", "-f"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("no message is running"));
+
+    let mut process = std::process::Command::new(env!("CARGO_BIN_EXE_decree"))
+        .arg("process")
+        .current_dir(dir.path())
+        .env("NO_COLOR", "1")
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+
+    // Attach while the first attempt is still running
+    let runs = dir.path().join(".decree/runs");
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+    while !fs::read_dir(&runs)
+        .unwrap()
+        .any(|e| e.unwrap().path().join("routine.log").exists())
+    {
+        assert!(std::time::Instant::now() < deadline, "routine never started");
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+
+    let output = decree_cmd(&dir)
+        .args(["log", "--follow"])
+        .timeout(std::time::Duration::from_secs(20))
+        .output()
+        .unwrap();
+    assert!(process.wait().unwrap().success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{stdout}");
+    let first = stdout.find("first try").expect(&stdout);
+    let retry = stdout.find("Attempt 2 (routine-2.log)").expect(&stdout);
+    let second = stdout.find("second try").expect(&stdout);
+    assert!(first < retry && retry < second, "{stdout}");
+    assert!(stdout.contains("all done"), "{stdout}");
+    assert!(stdout.trim_end().ends_with("finished: done"), "{stdout}");
+}
+
+#[test]
+fn test_long_routine_log_keeps_head_and_tail() {
+    let dir = TempDir::new().unwrap();
+    decree_cmd(&dir).arg("init").assert().success();
+
+    let config_path = dir.path().join(".decree/config.yml");
+    let config = fs::read_to_string(&config_path)
+        .unwrap()
+        .replace("max_log_size: 2097152", "max_log_size: 4096")
+        .replace("log_head_size: 524288", "log_head_size: 1024")
+        .replace("# keep_full_log: true", "keep_full_log: true");
+    fs::write(&config_path, config).unwrap();
+
+    let script = dir.path().join(".decree/routines/chatty.sh");
+    fs::write(
+        &script,
+        "#!/usr/bin/env bash\n# Chatty\nset -euo pipefail\n\nif [ \"${DECREE_PRE_CHECK:-}\" = \"true\" ]; then\n    exit 0\nfi\n\necho \"plan: fix the parser\"\nseq 1 20000\necho \"final error: boom\" >&2\n",
+    )
+    .unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    decree_cmd(&dir).arg("routine-sync").assert().success();
+    fs::write(
+        dir.path().join(".decree/migrations/01-chatty.md"),
+        "---\nroutine: chatty\n---\nSay a lot.\n",
+    )
+    .unwrap();
+
+    decree_cmd(&dir)
+        .arg("process")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("19999\n20000\n"));
+
+    let run_dir = fs::read_dir(dir.path().join(".decree/runs"))
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .find(|p| p.is_dir())
+        .unwrap();
+    let log = fs::read_to_string(run_dir.join("routine.log")).unwrap();
+    assert!(log.len() < 4096 + 200, "log is {} bytes", log.len());
+    assert!(log.starts_with("[decree] start "));
+    assert!(log.contains("plan: fix the parser\n1\n2\n"));
+    assert!(log.contains(" bytes elided ...\n"));
+    assert!(log.contains("20000\nfinal error: boom\n[decree] duration "));
+
+    let full = std::process::Command::new("gzip")
+        .arg("-dc")
+        .arg(run_dir.join("routine.log.gz"))
+        .output()
+        .unwrap();
+    let full = String::from_utf8(full.stdout).unwrap();
+    assert!(full.contains("\n10000\n"));
+    assert!(full.ends_with("20000\nfinal error: boom\n"));
+}
+
 // --- decree --version ---
 
 #[test]
 fn test_version_flag() {
-    Command::from(cargo_bin_cmd!("decree"))
+    cargo_bin_cmd!("decree")
         .arg("--version")
         .assert()
         .success()
@@ -482,6 +843,71 @@ fn test_routine_named_non_tty_shows_detail() {
         .stdout(predicate::str::contains(".decree/routines/develop.sh"));
 }
 
+#[test]
+fn test_routine_runs_non_interactively_with_flags() {
+    let dir = TempDir::new().unwrap();
+    decree_cmd(&dir).arg("init").assert().success();
+
+    let decree = dir.path().join(".decree");
+    let script = decree.join("routines/resize.sh");
+    fs::write(
+        &script,
+        "#!/usr/bin/env bash\n# Resize\nset -euo pipefail\nmessage_file=\"${message_file:-}\"\n\nif [ \"${DECREE_PRE_CHECK:-}\" = \"true\" ]; then\n    [ ! -e \"${PWD}/blocked\" ] || { echo \"blocked\" >&2; exit 1; }\n    exit 0\nfi\n\nwidth=\"${width:-100}\"\nheight=\"${height:-50}\"\n\necho \"size=${width}x${height}\" >> \"$PWD/out.txt\"\nsed -n '/^---$/,/^---$/!p' \"$message_file\" >> \"$PWD/out.txt\"\n",
+    )
+    .unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    decree_cmd(&dir)
+        .args(["routine", "resize", "--param", "width=800", "--body", "from a flag", "--yes"])
+        .assert()
+        .success();
+    decree_cmd(&dir)
+        .args(["routine", "resize", "--yes"])
+        .write_stdin("from stdin\n")
+        .assert()
+        .success();
+    let out = fs::read_to_string(dir.path().join("out.txt")).unwrap();
+    assert!(out.contains("size=800x50\nfrom a flag"), "{out}");
+    assert!(out.contains("size=100x50\nfrom stdin"), "{out}");
+
+    // Detached: queued for the daemon, not run
+    decree_cmd(&dir)
+        .args(["routine", "resize", "--param", "height=9", "--body", "later", "--detach"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Message queued:"));
+    let queued: Vec<String> = fs::read_dir(decree.join("inbox"))
+        .unwrap()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_file())
+        .map(|e| fs::read_to_string(e.path()).unwrap())
+        .collect();
+    assert_eq!(queued.len(), 1);
+    assert!(queued[0].contains("routine: resize"));
+    assert!(queued[0].contains("height: '9'"), "{}", queued[0]);
+    assert!(queued[0].contains("later"));
+
+    decree_cmd(&dir)
+        .args(["routine", "resize", "--param", "depth=3", "--yes"])
+        .write_stdin("")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "unknown parameter 'depth' for routine 'resize' (parameters: width, height)",
+        ));
+
+    fs::write(dir.path().join("blocked"), "").unwrap();
+    decree_cmd(&dir)
+        .args(["routine", "resize", "--body", "x", "--yes"])
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("blocked"));
+}
+
 #[test]
 fn test_routine_unknown_with_close_match() {
     let dir = TempDir::new().unwrap();
@@ -581,6 +1007,99 @@ fn test_routine_custom_params_shown_in_detail() {
         .stdout(predicate::str::contains("[default: \"large\"]"));
 }
 
+// --- decree prompt (non-TTY) ---
+
+#[test]
+fn test_prompt_renders_templates() {
+    let dir = TempDir::new().unwrap();
+    decree_cmd(&dir).arg("init").assert().success();
+
+    // The bundled prompts render as-is
+    for name in ["migration", "routine", "sow"] {
+        decree_cmd(&dir).args(["prompt", name]).assert().success();
+    }
+
+    let decree = dir.path().join(".decree");
+    let config = fs::read_to_string(decree.join("config.yml")).unwrap();
+    fs::write(
+        decree.join("config.yml"),
+        format!("{config}\nprompt_vars:\n  team: platform\n"),
+    )
+    .unwrap();
+    fs::write(decree.join("migrations/01-auth.md"), "---\nroutine: develop\n---\n# Auth\n").unwrap();
+    fs::write(decree.join("migrations/02-docs.md"), "# Docs\n").unwrap();
+    fs::write(decree.join("processed.md"), "01-auth.md\n").unwrap();
+    fs::write(dir.path().join("NOTES"), "from a file").unwrap();
+    fs::write(decree.join("prompts/_footer.md"), "-- {team}").unwrap();
+    fs::write(
+        decree.join("prompts/plan.md"),
+        "{#each migrations as m}{#if !m.processed}todo: {m.title}\n{/if}{/each}\
+         {file:NOTES} {cmd:echo from a command}\n{#include _footer}\n",
+    )
+    .unwrap();
+
+    decree_cmd(&dir)
+        .args(["prompt", "plan"])
+        .assert()
+        .success()
+        .stdout("todo: Docs\nfrom a file from a command\n-- platform\n");
+
+    fs::write(decree.join("prompts/plan.md"), "# Plan\n\n{mystery}\n").unwrap();
+    decree_cmd(&dir)
+        .args(["prompt", "plan"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("plan:3: unknown variable 'mystery'"));
+}
+
+#[test]
+fn test_prompt_renders_non_interactively() {
+    let dir = TempDir::new().unwrap();
+    decree_cmd(&dir).arg("init").assert().success();
+
+    let decree = dir.path().join(".decree");
+    let config = fs::read_to_string(decree.join("config.yml")).unwrap().replace(
+        "  profiles:\n",
+        "  profiles:\n    echo:\n      command: echo\n      args: [\"said:\"]\n",
+    );
+    fs::write(decree.join("config.yml"), config).unwrap();
+    fs::write(decree.join("prompts/review.md"), "Review {area} for {team}.").unwrap();
+
+    decree_cmd(&dir)
+        .args(["prompt", "review", "--print", "--var", "area=auth", "--var", "team=core"])
+        .assert()
+        .success()
+        .stdout("Review auth for core.");
+
+    decree_cmd(&dir)
+        .args(["prompt", "review", "--out", "review.txt", "--var", "area=db", "--var", "team=x"])
+        .assert()
+        .success()
+        .stdout("");
+    assert_eq!(
+        fs::read_to_string(dir.path().join("review.txt")).unwrap(),
+        "Review db for x."
+    );
+
+    decree_cmd(&dir)
+        .args(["prompt", "review", "--ai", "--profile", "echo"])
+        .args(["--var", "area=ui", "--var", "team=web"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("said: Review ui for web."));
+
+    decree_cmd(&dir)
+        .args(["prompt", "review", "--print", "--var", "area"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("expected KEY=VALUE"));
+
+    decree_cmd(&dir)
+        .args(["prompt", "--print"])
+        .assert()
+        .failure();
+}
+
 // --- decree verify ---
 
 #[test]
@@ -778,9 +1297,850 @@ fn test_verify_no_hooks_configured_no_hook_section() {
 
 // --- exit codes ---
 
+// --- decree send ---
+
+#[test]
+fn test_send_queues_messages_for_processing() {
+    let dir = TempDir::new().unwrap();
+    decree_cmd(&dir).arg("init").assert().success();
+
+    let decree = dir.path().join(".decree");
+    let script = decree.join("routines/note.sh");
+    fs::write(
+        &script,
+        "#!/usr/bin/env bash\n# Note\nset -euo pipefail\n\nif [ \"${DECREE_PRE_CHECK:-}\" = \"true\" ]; then\n    exit 0\nfi\n\necho \"$message_id $tag\" >> \"$PWD/notes.txt\"\n",
+    )
+    .unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    // Register the new routine
+    decree_cmd(&dir).arg("routine-sync").assert().success();
+
+    let out = decree_cmd(&dir)
+        .args(["send", "--routine", "note", "--field", "tag=first", "Write it down."])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let first = String::from_utf8(out).unwrap().trim().to_string();
+    assert!(first.ends_with("-note-0"), "{first}");
+
+    let message = fs::read_to_string(decree.join(format!("inbox/{first}.md"))).unwrap();
+    assert!(message.contains("routine: note"));
+    assert!(message.contains("tag: first"));
+    assert!(message.ends_with("---\nWrite it down."), "{message}");
+
+    // Same routine in the same minute gets its own chain; the alias works too
+    let out = decree_cmd(&dir)
+        .args(["enqueue", "--routine", "note", "--field", "tag=second"])
+        .write_stdin("From stdin.\n")
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let second = String::from_utf8(out).unwrap().trim().to_string();
+    assert_ne!(first, second);
+    assert!(second.ends_with("-note-2-0"), "{second}");
+
+    decree_cmd(&dir).arg("process").assert().success();
+    let notes = fs::read_to_string(dir.path().join("notes.txt")).unwrap();
+    assert!(notes.contains(&format!("{first} first")), "{notes}");
+    assert!(notes.contains(&format!("{second} second")), "{notes}");
+
+    decree_cmd(&dir)
+        .args(["send", "--routine", "nott", "x"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Did you mean 'note'?"));
+    decree_cmd(&dir)
+        .args(["send", "--field", "chain=mine", "x"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--field chain is set by decree"));
+}
+
+#[test]
+fn test_in_progress_tmp_files_are_ignored() {
+    let dir = TempDir::new().unwrap();
+    decree_cmd(&dir).arg("init").assert().success();
+
+    let decree = dir.path().join(".decree");
+    let script = decree.join("routines/draft.sh");
+    fs::write(
+        &script,
+        "#!/usr/bin/env bash\n# Draft\nset -euo pipefail\n\nif [ \"${DECREE_PRE_CHECK:-}\" = \"true\" ]; then\n    exit 0\nfi\n\necho \"Not done yet.\" > .decree/outbox/later.md.tmp\n",
+    )
+    .unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    decree_cmd(&dir).arg("routine-sync").assert().success();
+
+    // A message still being written is not picked up
+    let partial = decree.join("inbox/D0001-1200-half-0.md.tmp");
+    fs::write(&partial, "---\nrouti").unwrap();
+    decree_cmd(&dir)
+        .args(["send", "--routine", "draft", "Start a draft."])
+        .assert()
+        .success();
+
+    decree_cmd(&dir)
+        .arg("process")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("non-.md file").not());
+
+    assert!(partial.exists());
+    assert!(decree.join("outbox/later.md.tmp").exists());
+    let inbox: Vec<_> = fs::read_dir(decree.join("inbox"))
+        .unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .filter(|name| name.ends_with(".md"))
+        .collect();
+    assert!(inbox.is_empty(), "{inbox:?}");
+}
+
+// --- decree daemon --listen ---
+
+/// Send one HTTP request and return the status code and body.
+fn http(addr: &str, method: &str, path: &str, token: Option<&str>, body: &str) -> (u16, String) {
+    use std::io::{Read, Write};
+    let mut stream = std::net::TcpStream::connect(addr).unwrap();
+    let auth = token
+        .map(|t| format!("Authorization: Bearer {t}\r\n"))
+        .unwrap_or_default();
+    write!(
+        stream,
+        "{method} {path} HTTP/1.1\r\nHost: {addr}\r\n{auth}Content-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+        body.len()
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    let status = head.split_whitespace().nth(1).unwrap().parse().unwrap();
+    (status, body.to_string())
+}
+
+#[test]
+fn test_daemon_http_api() {
+    use std::io::BufRead;
+
+    let dir = TempDir::new().unwrap();
+    decree_cmd(&dir).arg("init").assert().success();
+
+    let script = dir.path().join(".decree/routines/note.sh");
+    fs::write(
+        &script,
+        "#!/usr/bin/env bash\n# Note\nset -euo pipefail\n\nif [ \"${DECREE_PRE_CHECK:-}\" = \"true\" ]; then\n    exit 0\nfi\n\necho \"noted $ticket\"\n",
+    )
+    .unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    decree_cmd(&dir).arg("routine-sync").assert().success();
+
+    let mut daemon = std::process::Command::new(env!("CARGO_BIN_EXE_decree"))
+        .args(["daemon", "--interval", "1", "--listen", "127.0.0.1:0"])
+        .current_dir(dir.path())
+        .env("NO_COLOR", "1")
+        .env("DECREE_API_TOKEN", "s3cret")
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+    let mut stdout = std::io::BufReader::new(daemon.stdout.take().unwrap()).lines();
+    let addr = stdout
+        .by_ref()
+        .map(|line| line.unwrap())
+        .find_map(|line| line.split_once("listening on http://").map(|(_, a)| a.to_string()))
+        .unwrap();
+    // Keep draining so the daemon never writes to a closed pipe
+    std::thread::spawn(move || stdout.for_each(drop));
+
+    let token = Some("s3cret");
+    assert_eq!(http(&addr, "GET", "/status", None, "").0, 401);
+
+    let (status, body) = http(
+        &addr,
+        "POST",
+        "/messages",
+        token,
+        r#"{"routine": "note", "ticket": "ABC-1", "body": "Note the ticket."}"#,
+    );
+    assert_eq!(status, 201, "{body}");
+    let id: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let id = id["id"].as_str().unwrap().to_string();
+
+    let path = format!("/messages/{id}");
+    let mut state = String::new();
+    for _ in 0..100 {
+        let (_, body) = http(&addr, "GET", &path, token, "");
+        let message: serde_json::Value = serde_json::from_str(&body).unwrap();
+        state = message["state"].as_str().unwrap().to_string();
+        if state == "done" {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+    assert_eq!(state, "done");
+
+    let (status, log) = http(&addr, "GET", &format!("/messages/{id}/log"), token, "");
+    assert_eq!(status, 200);
+    assert!(log.contains("noted ABC-1"), "{log}");
+
+    let (status, _) = http(&addr, "POST", "/dead/nope-0/requeue", token, "");
+    assert_eq!(status, 404);
+
+    daemon.kill().unwrap();
+    daemon.wait().unwrap();
+}
+
+// --- notifications ---
+
+#[test]
+fn test_notifications_on_done_and_dead_letter() {
+    let dir = TempDir::new().unwrap();
+    decree_cmd(&dir).arg("init").assert().success();
+
+    let script = dir.path().join(".decree/routines/check.sh");
+    fs::write(
+        &script,
+        "#!/usr/bin/env bash\n# Check\nset -euo pipefail\n\nif [ \"${DECREE_PRE_CHECK:-}\" = \"true\" ]; then\n    exit 0\nfi\n\necho \"checking $message_id\"\nif grep -q broken \"$message_file\"; then echo \"it is broken\"; exit 1; fi\n",
+    )
+    .unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    decree_cmd(&dir).arg("routine-sync").assert().success();
+
+    let config_path = dir.path().join(".decree/config.yml");
+    let mut config = fs::read_to_string(&config_path)
+        .unwrap()
+        .replace("max_retries: 3", "max_retries: 2");
+    config.push_str(
+        "\nnotifications:\n  - file: notify/all.jsonl\n  - on: [dead_lettered]\n    command: \"cat >> notify/dead.jsonl\"\n",
+    );
+    fs::write(&config_path, config).unwrap();
+
+    let migrations = dir.path().join(".decree/migrations");
+    fs::write(migrations.join("01-good.md"), "---\nroutine: check\n---\nAll fine.\n").unwrap();
+    fs::write(migrations.join("02-bad.md"), "---\nroutine: check\n---\nThis is broken.\n").unwrap();
+
+    decree_cmd(&dir).arg("process").assert().success();
+
+    let read_events = |name: &str| -> Vec<serde_json::Value> {
+        fs::read_to_string(dir.path().join("notify").join(name))
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    };
+
+    let all = read_events("all.jsonl");
+    assert_eq!(all.len(), 2, "{all:?}");
+    assert_eq!(all[0]["event"], "migration_done");
+    assert_eq!(all[0]["migration"], "01-good.md");
+    assert_eq!(all[0]["disposition"], "done");
+    assert_eq!(all[0]["attempts"], 1);
+
+    let dead = read_events("dead.jsonl");
+    assert_eq!(dead.len(), 1, "{dead:?}");
+    assert_eq!(dead[0]["event"], "retries_exhausted");
+    assert_eq!(dead[0]["migration"], "02-bad.md");
+    assert_eq!(dead[0]["routine"], "check");
+    assert_eq!(dead[0]["attempts"], 2);
+    assert_eq!(dead[0]["exit_code"], 1);
+    assert!(dead[0]["log_tail"].as_str().unwrap().contains("it is broken"));
+    assert_eq!(dead[0], all[1]);
+}
+
+// --- decree gc ---
+
+#[test]
+fn test_gc_dry_run_and_archive() {
+    let dir = TempDir::new().unwrap();
+    decree_cmd(&dir).arg("init").assert().success();
+
+    decree_cmd(&dir)
+        .arg("gc")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No retention policy configured"));
+
+    let runs = dir.path().join(".decree/runs");
+    for (id, start) in [
+        ("D0001-0900-old-0", "2020-01-01T09:00:00"),
+        ("D0002-0900-dead-0", "2020-01-02T09:00:00"),
+        ("D0003-0900-new-0", "2020-01-03T09:00:00"),
+    ] {
+        fs::create_dir_all(runs.join(id)).unwrap();
+        fs::write(runs.join(id).join("routine.log"), format!("[decree] start {start}\n")).unwrap();
+    }
+    fs::write(dir.path().join(".decree/inbox/dead/D0002-0900-dead-0.md"), "dead\n").unwrap();
+
+    let config_path = dir.path().join(".decree/config.yml");
+    let mut config = fs::read_to_string(&config_path).unwrap();
+    config.push_str("\nretention:\n  keep_runs: 1\n  archive: true\n");
+    fs::write(&config_path, config).unwrap();
+
+    decree_cmd(&dir)
+        .args(["gc", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Would prune 1 run"))
+        .stdout(predicate::str::contains("D0001-0900-old-0"))
+        .stdout(predicate::str::contains("D0002").not());
+    assert!(runs.join("D0001-0900-old-0").exists());
+
+    decree_cmd(&dir)
+        .arg("gc")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Archived 1 run"))
+        .stdout(predicate::str::contains("2 runs kept."));
+    assert!(!runs.join("D0001-0900-old-0").exists());
+    assert!(runs.join("D0002-0900-dead-0").exists());
+
+    let archives: Vec<_> = fs::read_dir(dir.path().join(".decree/archive"))
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .collect();
+    assert_eq!(archives.len(), 1);
+    let listing = std::process::Command::new("tar")
+        .arg("-tzf")
+        .arg(&archives[0])
+        .output()
+        .unwrap();
+    assert!(String::from_utf8_lossy(&listing.stdout).contains("runs/D0001-0900-old-0/routine.log"));
+
+    let journal = fs::read_to_string(dir.path().join(".decree/events.jsonl")).unwrap();
+    assert!(journal.contains("\"type\":\"pruned\""), "{journal}");
+
+    decree_cmd(&dir)
+        .args(["gc", "--keep-runs", "5"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Nothing to prune (2 runs kept)."));
+}
+
+// --- decree events ---
+
+#[test]
+fn test_events_journal_records_transitions() {
+    let dir = TempDir::new().unwrap();
+    decree_cmd(&dir).arg("init").assert().success();
+
+    let script = dir.path().join(".decree/routines/check.sh");
+    fs::write(
+        &script,
+        "#!/usr/bin/env bash\n# Check\nset -euo pipefail\n\nif [ \"${DECREE_PRE_CHECK:-}\" = \"true\" ]; then\n    exit 0\nfi\n\nif [ \"$seq\" = \"0\" ]; then printf -- '---\\nroutine: check\\n---\\nfollow up\\n' > .decree/outbox/next.md; fi\nif grep -q broken \"$message_file\"; then exit 1; fi\n",
+    )
+    .unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    decree_cmd(&dir).arg("routine-sync").assert().success();
+
+    let config_path = dir.path().join(".decree/config.yml");
+    let config = fs::read_to_string(&config_path)
+        .unwrap()
+        .replace("max_retries: 3", "max_retries: 1");
+    fs::write(&config_path, config).unwrap();
+
+    let migrations = dir.path().join(".decree/migrations");
+    fs::write(migrations.join("01-good.md"), "---\nroutine: check\n---\nAll fine.\n").unwrap();
+    decree_cmd(&dir)
+        .env_remove("DECREE_CONTAINER")
+        .arg("process")
+        .assert()
+        .success();
+
+    let events: Vec<serde_json::Value> = fs::read_to_string(dir.path().join(".decree/events.jsonl"))
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let kinds: Vec<&str> = events.iter().map(|e| e["type"].as_str().unwrap()).collect();
+    assert_eq!(
+        kinds,
+        [
+            "enqueued",
+            "attempt_started",
+            "attempt_ended",
+            "followup_collected",
+            "migration_processed",
+            "attempt_started",
+            "attempt_ended",
+        ],
+        "{events:?}"
+    );
+    assert!(events.iter().all(|e| e["actor"] == "process"));
+    assert_eq!(events[0]["migration"], "01-good.md");
+    assert_eq!(events[2]["exit_code"], 0);
+    let followup = events[3]["message_id"].as_str().unwrap();
+    assert!(followup.ends_with("-1"), "{followup}");
+
+    decree_cmd(&dir)
+        .args(["events", "-n", "2"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("attempt_started"))
+        .stdout(predicate::str::contains("enqueued").not())
+        .stdout(predicate::str::contains(format!("{followup} routine=check attempt=1 exit=0")));
+
+    decree_cmd(&dir)
+        .args(["events", "--json", followup])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\"type\":\"followup_collected\""))
+        .stdout(predicate::str::contains("\"type\":\"migration_processed\"").not());
+}
+
+// --- decree route ---
+
+#[test]
+fn test_route_explain_rule_match() {
+    let dir = TempDir::new().unwrap();
+    decree_cmd(&dir).arg("init").assert().success();
+
+    let config_path = dir.path().join(".decree/config.yml");
+    let mut config = fs::read_to_string(&config_path).unwrap();
+    config.push_str(
+        "routes:\n  - name: nope\n    routine: develop\n    filename: \"*-docs.md\"\n  - name: bugs\n    routine: rust-develop\n    frontmatter:\n      type: bug\n    body: \"(?i)crash\"\n",
+    );
+    fs::write(&config_path, config).unwrap();
+
+    fs::write(
+        dir.path().join(".decree/inbox/report.md"),
+        "---\ntype: bug\n---\nIt crashes.\n",
+    )
+    .unwrap();
+
+    decree_cmd(&dir)
+        .args(["route", "--explain", "report.md"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("#1 nope"))
+        .stdout(predicate::str::contains("no match: filename \"*-docs.md\""))
+        .stdout(predicate::str::contains("#2 bugs"))
+        .stdout(predicate::str::contains("match -> rust-develop: type: bug, body /(?i)crash/"))
+        .stdout(predicate::str::contains("Routine: rust-develop — rule #2 'bugs'"));
+}
+
+#[test]
+fn test_route_explain_frontmatter_routine() {
+    let dir = TempDir::new().unwrap();
+    decree_cmd(&dir).arg("init").assert().success();
+
+    let path = dir.path().join("task.md");
+    fs::write(&path, "---\nroutine: develop\n---\nDo it.\n").unwrap();
+
+    decree_cmd(&dir)
+        .args(["route", "--explain", path.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Routine: develop — routine set in frontmatter"));
+}
+
+#[test]
+fn test_route_explain_missing_file() {
+    let dir = TempDir::new().unwrap();
+    decree_cmd(&dir).arg("init").assert().success();
+
+    decree_cmd(&dir)
+        .args(["route", "--explain", "nope.md"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_process_parks_unsure_router_answers() {
+    let dir = TempDir::new().unwrap();
+    decree_cmd(&dir).arg("init").assert().success();
+
+    let config_path = dir.path().join(".decree/config.yml");
+    let mut config = fs::read_to_string(&config_path)
+        .unwrap()
+        .replace("ai:\n", "ai:\n  router: unsure\n")
+        .replace(
+            "  profiles:\n",
+            "  profiles:\n    unsure:\n      command: printf\n      args: [\"develop 0.3\"]\n",
+        );
+    config.push_str("\nrouter:\n  min_confidence: 0.6\n");
+    fs::write(&config_path, config).unwrap();
+
+    fs::write(dir.path().join(".decree/inbox/task.md"), "Something vague.\n").unwrap();
+
+    decree_cmd(&dir)
+        .arg("process")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("needs routing"));
+
+    let parked_dir = dir.path().join(".decree/inbox/needs-routing");
+    let parked: Vec<String> = fs::read_dir(&parked_dir)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .collect();
+    assert_eq!(parked.len(), 1);
+    assert!(!dir.path().join(".decree/inbox/task.md").exists());
+    let parked_content = fs::read_to_string(parked_dir.join(&parked[0])).unwrap();
+    let id = parked_content
+        .lines()
+        .find_map(|l| l.strip_prefix("id: "))
+        .unwrap()
+        .to_string();
+
+    decree_cmd(&dir)
+        .arg("status")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Needs routing: 1 message"));
+
+    decree_cmd(&dir)
+        .arg("route")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(&id))
+        .stdout(predicate::str::contains("candidates: develop 30%"));
+
+    decree_cmd(&dir)
+        .args(["route", &id, "no-such-routine"])
+        .assert()
+        .failure();
+
+    decree_cmd(&dir)
+        .args(["route", &id, "develop"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("develop"));
+
+    assert!(!parked_dir.join(&parked[0]).exists());
+    let content = fs::read_to_string(dir.path().join(".decree/inbox").join(&parked[0])).unwrap();
+    assert!(content.contains("routine: develop"));
+
+    decree_cmd(&dir)
+        .arg("route")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No messages need routing."));
+}
+
+#[test]
+fn test_process_does_not_requeue_parked_migration() {
+    let dir = TempDir::new().unwrap();
+    decree_cmd(&dir).arg("init").assert().success();
+
+    let config_path = dir.path().join(".decree/config.yml");
+    let mut config = fs::read_to_string(&config_path)
+        .unwrap()
+        .replace("ai:\n", "ai:\n  router: unsure\n")
+        .replace(
+            "  profiles:\n",
+            "  profiles:\n    unsure:\n      command: printf\n      args: [\"develop 0.3\"]\n",
+        );
+    config.push_str("\nrouter:\n  min_confidence: 0.6\n");
+    fs::write(&config_path, config).unwrap();
+    fs::write(
+        dir.path().join(".decree/migrations/01-vague.md"),
+        "Something vague.\n",
+    )
+    .unwrap();
+
+    decree_cmd(&dir)
+        .arg("process")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("parked:"));
+    // The parked message counts as queued: no second copy is created and
+    // routed (a later minute would give it a new chain ID)
+    decree_cmd(&dir)
+        .arg("process")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Migration 01-vague.md needs routing"))
+        .stdout(predicate::str::contains("parked:").not());
+
+    let parked = fs::read_dir(dir.path().join(".decree/inbox/needs-routing"))
+        .unwrap()
+        .count();
+    assert_eq!(parked, 1);
+    let queued = fs::read_dir(dir.path().join(".decree/inbox"))
+        .unwrap()
+        .filter(|e| e.as_ref().unwrap().path().extension().is_some_and(|x| x == "md"))
+        .count();
+    assert_eq!(queued, 0);
+}
+
+// --- decree approve / reject ---
+
+fn hold_for_approval(dir: &TempDir, id: &str) {
+    let pending = dir.path().join(".decree/inbox/pending-approval");
+    fs::create_dir_all(&pending).unwrap();
+    fs::write(
+        pending.join(format!("{id}.md")),
+        format!("---\nid: {id}\nchain: D0001-1200-ship\nseq: 0\nroutine: develop\nrequires_approval: true\n---\nShip it.\n"),
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_process_does_not_requeue_held_migration() {
+    let dir = TempDir::new().unwrap();
+    decree_cmd(&dir).arg("init").assert().success();
+    fs::write(
+        dir.path().join(".decree/migrations/01-deploy.md"),
+        "---\nroutine: develop\nrequires_approval: true\n---\nDeploy.\n",
+    )
+    .unwrap();
+
+    decree_cmd(&dir)
+        .arg("process")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("is awaiting approval"));
+    // A held message counts as queued: no second copy is held (a later
+    // minute would give it a new chain ID, and approving one copy would
+    // leave the other to run the deploy again)
+    decree_cmd(&dir)
+        .arg("process")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Migration 01-deploy.md is awaiting approval",
+        ))
+        .stdout(predicate::str::contains("held:").not());
+
+    let held = fs::read_dir(dir.path().join(".decree/inbox/pending-approval"))
+        .unwrap()
+        .count();
+    assert_eq!(held, 1);
+}
+
+#[test]
+fn test_approve_lists_and_releases_message() {
+    let dir = TempDir::new().unwrap();
+    decree_cmd(&dir).arg("init").assert().success();
+
+    decree_cmd(&dir)
+        .arg("approve")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No messages awaiting approval."));
+
+    hold_for_approval(&dir, "D0001-1200-ship-0");
+
+    decree_cmd(&dir)
+        .arg("status")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Awaiting approval: 1 message"))
+        .stdout(predicate::str::contains("D0001-1200-ship-0"));
+
+    decree_cmd(&dir)
+        .arg("approve")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("D0001-1200-ship-0"));
+
+    decree_cmd(&dir)
+        .args(["approve", "D0001-1200-ship-0"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("approved D0001-1200-ship-0"));
+
+    let content = fs::read_to_string(dir.path().join(".decree/inbox/D0001-1200-ship-0.md")).unwrap();
+    assert!(content.contains("approved:"));
+    assert!(!dir
+        .path()
+        .join(".decree/inbox/pending-approval/D0001-1200-ship-0.md")
+        .exists());
+
+    decree_cmd(&dir)
+        .args(["approve", "D0001-1200-ship-0"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_reject_dead_letters_with_reason() {
+    let dir = TempDir::new().unwrap();
+    decree_cmd(&dir).arg("init").assert().success();
+    hold_for_approval(&dir, "D0001-1200-ship-0");
+
+    decree_cmd(&dir)
+        .args(["reject", "D0001-1200-ship-0", "--reason", "freeze week"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("rejected D0001-1200-ship-0"));
+
+    let dead = fs::read_to_string(dir.path().join(".decree/inbox/dead/D0001-1200-ship-0.md")).unwrap();
+    assert!(dead.contains("rejected: freeze week"));
+    let record = fs::read_to_string(dir.path().join(".decree/runs/D0001-1200-ship-0/approval.json")).unwrap();
+    assert!(record.contains("\"rejection_reason\": \"freeze week\""));
+}
+
+// --- decree cron ---
+
+#[test]
+fn test_cron_list_shows_next_fire_times() {
+    let dir = TempDir::new().unwrap();
+    decree_cmd(&dir).arg("init").assert().success();
+
+    fs::write(
+        dir.path().join(".decree/cron/nightly.md"),
+        "---\ncron: \"0 2 * * *\"\nroutine: develop\ntimezone: America/Denver\n---\nNightly run.\n",
+    )
+    .unwrap();
+
+    decree_cmd(&dir)
+        .args(["cron", "list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("nightly"))
+        .stdout(predicate::str::contains("0 2 * * * (America/Denver)"))
+        .stdout(predicate::str::contains("routine:  develop"))
+        .stdout(predicate::str::contains("next:"))
+        .stdout(predicate::str::contains(" UTC)").count(3));
+}
+
+#[test]
+fn test_cron_list_every_and_at() {
+    let dir = TempDir::new().unwrap();
+    decree_cmd(&dir).arg("init").assert().success();
+
+    fs::write(
+        dir.path().join(".decree/cron/poll.md"),
+        "---\nevery: 1h30m\n---\nPoll.\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join(".decree/cron/launch.md"),
+        "---\nat: 2099-01-01T00:00:00Z\n---\nLaunch.\n",
+    )
+    .unwrap();
+
+    decree_cmd(&dir)
+        .args(["cron", "list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("every 1h30m"))
+        .stdout(predicate::str::contains("at 2099-01-01T00:00:00Z"))
+        .stdout(predicate::str::contains("2099-01-01 00:00 UTC"));
+}
+
+#[test]
+fn test_cron_check_reports_errors() {
+    let dir = TempDir::new().unwrap();
+    decree_cmd(&dir).arg("init").assert().success();
+
+    fs::write(
+        dir.path().join(".decree/cron/good.md"),
+        "---\ncron: \"0 * * * *\"\nroutine: develop\n---\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join(".decree/cron/typo.md"),
+        "---\ncron: \"0 * * *\"\n---\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join(".decree/cron/missing.md"),
+        "---\ncron: \"0 * * * *\"\nroutine: no-such-routine\n---\n",
+    )
+    .unwrap();
+
+    decree_cmd(&dir)
+        .args(["cron", "check"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("good.md"))
+        .stdout(predicate::str::contains("OK"))
+        .stdout(predicate::str::contains("typo.md"))
+        .stdout(predicate::str::contains("invalid cron expression"))
+        .stdout(predicate::str::contains("routine 'no-such-routine' not found"))
+        .stdout(predicate::str::contains("1 of 3 cron files valid"))
+        .stderr(predicate::str::contains("2 invalid cron files"));
+}
+
+#[test]
+fn test_cron_check_all_valid() {
+    let dir = TempDir::new().unwrap();
+    decree_cmd(&dir).arg("init").assert().success();
+
+    fs::write(
+        dir.path().join(".decree/cron/good.md"),
+        "---\ncron: \"*/15 * * * *\"\n---\n",
+    )
+    .unwrap();
+
+    decree_cmd(&dir)
+        .args(["cron", "check"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("1 of 1 cron files valid"));
+}
+
+#[test]
+fn test_cron_run_enqueues_message() {
+    let dir = TempDir::new().unwrap();
+    decree_cmd(&dir).arg("init").assert().success();
+
+    fs::write(
+        dir.path().join(".decree/cron/weekly.md"),
+        "---\ncron: \"0 9 * * 1\"\nroutine: develop\n---\nWeekly review.\n",
+    )
+    .unwrap();
+
+    decree_cmd(&dir)
+        .args(["cron", "run", "weekly"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Enqueued"))
+        .stdout(predicate::str::contains("weekly.md"));
+
+    let inbox: Vec<_> = fs::read_dir(dir.path().join(".decree/inbox"))
+        .unwrap()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_file())
+        .collect();
+    assert_eq!(inbox.len(), 1);
+    let name = inbox[0].file_name().into_string().unwrap();
+    assert!(name.contains("-weekly-0.md"));
+    let content = fs::read_to_string(inbox[0].path()).unwrap();
+    assert!(content.contains("routine: develop"));
+    assert!(content.contains("Weekly review."));
+}
+
+#[test]
+fn test_cron_run_unknown_job() {
+    let dir = TempDir::new().unwrap();
+    decree_cmd(&dir).arg("init").assert().success();
+
+    decree_cmd(&dir)
+        .args(["cron", "run", "nope"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("unknown cron job 'nope'"));
+}
+
 #[test]
 fn test_unknown_subcommand_exit_code_2() {
-    Command::from(cargo_bin_cmd!("decree"))
+    cargo_bin_cmd!("decree")
         .arg("nonexistent")
         .env("NO_COLOR", "1")
         .assert()